@@ -1,28 +1,216 @@
-use clap::{CommandFactory, Parser};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{Generator, Shell, generate};
+use rambo::{
+    Backend, ConflictStrategy, CounterReset, DateSource, DedupeAction, Fallback, FsCompat, InputSource, MediaKind, OutputFormat, UnicodeNormalization,
+};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
-#[command(version, about)]
+#[command(version, about, args_conflicts_with_subcommands = true)]
 pub(crate) struct RamboCli {
+    #[command(subcommand)]
+    pub(crate) command: Option<RamboCommand>,
+
+    /// Without a subcommand, the arguments of the 'rename' subcommand are accepted for backward compatibility.
+    #[clap(flatten)]
+    pub(crate) rename: RenameArgs,
+
+    #[clap(long, value_name = "SHELL", help = "Generate completion scripts for your shell.")]
+    pub(crate) completions: Option<Shell>,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum RamboCommand {
+    /// Rename media files based on their date/time of creation. This is the default when no subcommand is given.
+    Rename(RenameArgs),
+    /// Rename media files and move them into a directory tree based on their date/time of creation.
+    Organize(OrganizeArgs),
+    /// Print the date/time of creation of media files without renaming them.
+    Inspect(InspectArgs),
+    /// Write the renames to a plan file without renaming anything, so they can be reviewed or edited and applied later.
+    Plan(PlanArgs),
+    /// Apply the renames of a plan file created by the 'plan' subcommand.
+    Apply(ApplyArgs),
+    /// Rename the files of a rename map, as written with '--rename-map', back to their old names and remove the copies.
+    Undo(UndoArgs),
+    /// Watch for new media files and rename them as they arrive, like files that are synced from a phone.
+    Watch(WatchArgs),
+    /// Check whether the names of media files match their date/time of creation, without renaming anything. Exits with a failure if any file does not match.
+    Verify(VerifyArgs),
+    /// Find media files with the same content and report them, replace them with hard links or move them to the trash.
+    Dedupe(DedupeArgs),
+    /// Write the date/time of creation encoded in the names of media files to their EXIF data or XMP sidecars, the inverse of renaming.
+    WriteExif(WriteExifArgs),
+    /// Rename the objects of a bucket of an S3-compatible object storage, like AWS S3 or MinIO, based on their date/time of creation.
+    #[cfg(feature = "s3")]
+    S3(S3Args),
+    /// Rename media files on a remote server with SFTP, without mounting it, based on their date/time of creation.
+    #[cfg(feature = "sftp")]
+    Sftp(SftpArgs),
+    /// Rename media files on a WebDAV server, like the auto-upload folder of Nextcloud, based on their date/time of creation.
+    #[cfg(feature = "webdav")]
+    Webdav(WebDavArgs),
+    /// Import the media of a camera or phone connected with USB (PTP/MTP) into a directory, renamed based on their date/time of creation. Media which was imported before is skipped.
+    #[cfg(feature = "mtp")]
+    Import(ImportArgs),
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct InputArgs {
     #[clap(
         default_value = "*",
-        help = "The glob pattern to match the files that shall be renamed. Use '**/*' to match all files recursively. Provide the pattern in quotes to prevent your shell from expanding it."
+        help = "The glob patterns to match the files that shall be processed. A file matched by several patterns is processed once. A directory stands for all files in it and its subdirectories. Use '**/*' to match all files recursively. Provide the patterns in quotes to prevent your shell from expanding them."
     )]
-    pub(crate) pattern: String,
+    pub(crate) pattern: Vec<String>,
 
-    #[clap(long, default_value_t = false, help = "Apply the renaming. For safety, the default behavior is a dry run.")]
-    pub(crate) no_dry_run: bool,
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with = "pattern",
+        help = "Process the files in this directory instead of using a glob pattern. Use '--recursive' to include all subdirectories."
+    )]
+    pub(crate) dir: Option<PathBuf>,
+
+    #[clap(
+        long,
+        short = 'r',
+        default_value_t = false,
+        requires = "dir",
+        help = "Include the files in all subdirectories of '--dir'."
+    )]
+    pub(crate) recursive: bool,
+
+    #[clap(
+        long,
+        value_name = "REGEX",
+        conflicts_with_all = ["pattern", "dir"],
+        help = "Process the files below the current directory whose relative path, separated by '/', matches this regex instead of using a glob pattern. Anchor it with '^' and '$' to match the whole path."
+    )]
+    pub(crate) regex: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["pattern", "dir", "regex"],
+        help = "Process the files listed in this file instead of using a glob pattern, one path per line. Use '-' to read the list from stdin, e.g. from 'find' or 'fd'."
+    )]
+    pub(crate) files_from: Option<PathBuf>,
+
+    #[clap(
+        long,
+        short = '0',
+        default_value_t = false,
+        requires = "files_from",
+        help = "The paths in '--files-from' are separated by NUL characters instead of newlines, like the output of 'find -print0'."
+    )]
+    pub(crate) null: bool,
 
-    #[clap(long, short = 'i', default_value_t = false, help = "Match the pattern in a case insensitive way.")]
+    #[clap(long, short = 'i', default_value_t = false, help = "Match the pattern or regex in a case insensitive way.")]
     pub(crate) case_insensitive: bool,
 
+    #[clap(long, short = 's', default_value_t = false, help = "Include and follow symlinks.")]
+    pub(crate) include_symlinks: bool,
+
     #[clap(
         long,
-        short,
-        default_value = "%Y-%m-%d__%H-%M-%S",
-        help = "The format of the renamed file (without the extension). See: https://docs.rs/chrono/0.4.42/chrono/format/strftime/index.html#specifiers"
+        value_name = "N",
+        help = "Descend at most N levels, 1 meaning only the files directly inside. For a pattern like 'Pictures/**/*', the levels are counted below 'Pictures'. Also applies to directories, '--dir --recursive' and '--regex'."
     )]
-    pub(crate) format: String,
+    pub(crate) max_depth: Option<NonZeroUsize>,
+}
+
+impl InputArgs {
+    pub(crate) fn source(&self) -> InputSource {
+        if let Some(dir) = &self.dir {
+            InputSource::Directory {
+                path: dir.clone(),
+                recursive: self.recursive,
+            }
+        } else if let Some(regex) = &self.regex {
+            InputSource::Regex(regex.clone())
+        } else if let Some(files_from) = &self.files_from {
+            InputSource::FileList {
+                path: files_from.clone(),
+                null_separated: self.null,
+            }
+        } else {
+            InputSource::GlobPatterns(self.pattern.clone())
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct FilterArgs {
+    #[clap(
+        long,
+        value_name = "DATE",
+        help = "Only rename files created at or after DATE, like '2022-06-01' or '2022-06-01T18:30:00', in the time zone they are renamed in."
+    )]
+    pub(crate) after: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "DATE",
+        help = "Only rename files created before DATE, like '2022-09-01' or '2022-09-01T06:00:00', in the time zone they are renamed in."
+    )]
+    pub(crate) before: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "EXT",
+        value_delimiter = ',',
+        help = "Only rename files with one of these extensions, like 'jpg,heic,mp4'. The case of the extensions does not matter."
+    )]
+    pub(crate) ext: Option<Vec<String>>,
+
+    #[clap(
+        long,
+        value_name = "EXT",
+        value_delimiter = ',',
+        help = "Skip files with one of these extensions, like 'png,gif'. The case of the extensions does not matter."
+    )]
+    pub(crate) skip_ext: Vec<String>,
+
+    #[clap(
+        long,
+        value_name = "KIND",
+        help = "Only rename files of this kind, which is recognized by their content. Use two runs to give photos and videos different formats."
+    )]
+    pub(crate) only: Option<Only>,
+
+    #[clap(
+        long,
+        value_name = "N",
+        help = "Stop after processing N files, in the order of their paths, and leave the remaining ones untouched. Useful to try a new format on a large archive."
+    )]
+    pub(crate) limit: Option<NonZeroUsize>,
+
+    #[clap(
+        long,
+        value_name = "SIZE",
+        help = "Skip files smaller than SIZE, like '500', '10k', '5M' or '2G', e.g. thumbnails."
+    )]
+    pub(crate) min_size: Option<String>,
+
+    #[clap(
+        long,
+        value_name = "SIZE",
+        help = "Skip files larger than SIZE, like '500', '10k', '5M' or '2G', e.g. raw video captures."
+    )]
+    pub(crate) max_size: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct ExtractionArgs {
+    #[clap(
+        long,
+        short = 'j',
+        default_value = "1",
+        value_name = "N",
+        help = "The number of files to extract the date/time of creation from in parallel."
+    )]
+    pub(crate) jobs: NonZeroUsize,
 
     #[clap(
         long,
@@ -33,16 +221,841 @@ pub(crate) struct RamboCli {
     )]
     pub(crate) time_offset: Option<String>,
 
-    #[clap(long, short = 's', default_value_t = false, help = "Include and follow symlinks.")]
-    pub(crate) include_symlinks: bool,
+    #[clap(
+        long,
+        value_name = "ZONE",
+        conflicts_with = "time_offset",
+        help = "Render the creation datetimes in a time zone, like 'Europe/Berlin'. Unlike '--time-offset', the offset of each file respects daylight saving time. \
+        Use 'from-gps' for the time zone at the GPS coordinates of each file."
+    )]
+    pub(crate) timezone: Option<String>,
 
-    #[clap(long, value_name = "SHELL", help = "Generate completion scripts for your shell.")]
-    pub(crate) completions: Option<Shell>,
+    #[clap(
+        long,
+        allow_hyphen_values = true,
+        value_name = "DURATION",
+        help = "Shift the creation datetimes by a duration, like '-1h37m' or '+30s', to correct a camera clock that was wrong. The units are 'd', 'h', 'm' and 's'."
+    )]
+    pub(crate) shift: Option<String>,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "SOURCE",
+        value_delimiter = ',',
+        default_values_t = [Source::Metadata],
+        help = "Where the date/time of creation is taken from, tried in order, like 'DateTimeOriginal,CreateDate,ModifyDate,track,xmp,name,fs'. \
+        If none of the sources reads the embedded metadata, it is tried after them. With '--verbose', the source which won is logged for each file."
+    )]
+    pub(crate) date_source: Vec<Source>,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "BACKEND",
+        default_value_t = MetadataBackend::Native,
+        help = "What reads the metadata embedded in the files. Use '--fallback exiftool' instead to run exiftool only for the files that the built-in parser cannot read."
+    )]
+    pub(crate) backend: MetadataBackend,
+
+    #[clap(
+        long,
+        value_name = "CMD",
+        help = "An external command to run when the creation datetime cannot be extracted otherwise. The file path is appended as the last argument and the command must print an ISO 8601 datetime, like '2024-05-01T10:00:00+02:00'."
+    )]
+    pub(crate) extractor_cmd: Option<String>,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "SOURCE",
+        value_delimiter = ',',
+        help = "Sources of the date/time of creation, which are tried in order when it cannot be extracted from the metadata or with '--extractor-cmd'."
+    )]
+    pub(crate) fallback: Vec<FallbackSource>,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Also read the creation date/time of PDFs, from their '/CreationDate', and of office documents, like '.docx', '.xlsx' and '.pptx' files, from their core properties, \
+        so that scanned documents in the same folder are named like the photos."
+    )]
+    pub(crate) documents: bool,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        help = "Cache the metadata extracted from the files in FILE, or in '~/.cache/rambo/extractions.jsonl' if FILE is omitted, so that repeated runs over the same files, like when trying out formats, skip the files that did not change. \
+        A file counts as changed when its size or modification time changed."
+    )]
+    pub(crate) extraction_cache: Option<Option<PathBuf>>,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Log for each file which of the '--date-source' sources or fallbacks its date/time of creation was taken from."
+    )]
+    pub(crate) verbose: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct FormatArgs {
+    #[clap(
+        long,
+        short,
+        default_value = rambo::DEFAULT_FORMAT,
+        help = "The format of the renamed file (without the extension). See: https://docs.rs/chrono/0.4.42/chrono/format/strftime/index.html#specifiers \
+        It may also contain the tokens '{camera}' (or '{model}'), '{make}', '{serial}' and '{lens}' (or '{serial:NAME}' and '{lens:NAME}' to use 'NAME' if they are missing), '{iso}', '{fnumber}', '{shutter}', '{gps}', '{lat}' and '{lon}' (or '{lat:2}' for 2 decimal places), '{place}', '{original}', '{dir}' (the name of the parent directory), '{hash}' (or '{hash:8}' for the first 8 hex digits of the content hash), '{rating}' and '{keyword1}', '{keyword2}', ... from XMP data, and '{counter}' (or '{counter:03}' for padding), and optional segments in '[...]', like '%Y-%m-%d[_{camera}]'. \
+        '%3f' renders the milliseconds; files without sub-second data are numbered within their second instead."
+    )]
+    pub(crate) format: String,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with = "format",
+        help = "Read the format of the renamed file from a template file. Use '{date:FORMAT}' for the creation datetime, the tokens of '--format' and '[...]' for optional segments. Lines starting with '#' are ignored."
+    )]
+    pub(crate) template_file: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "SCOPE",
+        default_value_t = CounterResetScope::Never,
+        help = "When '{counter}' starts again at 1: never, for each directory of the files or for each day of their creation datetime."
+    )]
+    pub(crate) counter_reset: CounterResetScope,
+
+    #[clap(
+        long,
+        value_name = "CHAR",
+        default_value_t = rambo::DEFAULT_REPLACEMENT_CHAR,
+        help = "Replace the characters of the new file names which are not allowed in file names, like '/' or ':', with CHAR. Trailing dots and spaces are removed."
+    )]
+    pub(crate) replacement_char: char,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "FILE_SYSTEM",
+        help = "Restrict the new names to the limits of FILE_SYSTEM, e.g. when renaming files on a share which is used with Windows as well or on an SD card. \
+        Files whose new name does not fit fail."
+    )]
+    pub(crate) fs_compat: Option<FileSystem>,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "FORM",
+        default_value_t = Normalization::None,
+        help = "Normalize the Unicode characters of the new names to FORM. Names which differ only in their normalization count as the same, e.g. the decomposed names of macOS."
+    )]
+    pub(crate) normalize: Normalization,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct RenameArgs {
+    #[clap(flatten)]
+    pub(crate) input: InputArgs,
+
+    #[clap(flatten)]
+    pub(crate) extraction: ExtractionArgs,
+
+    #[clap(flatten)]
+    pub(crate) filter: FilterArgs,
+
+    #[clap(long, default_value_t = false, help = "Apply the renaming. For safety, the default behavior is a dry run.")]
+    pub(crate) no_dry_run: bool,
+
+    #[clap(
+        long,
+        value_name = "NAME",
+        help = "Use the settings of the profile '[profiles.NAME]' in 'rambo.toml' or '~/.config/rambo/config.toml'. Flags on the command line take precedence."
+    )]
+    pub(crate) profile: Option<String>,
+
+    #[clap(flatten)]
+    pub(crate) naming: FormatArgs,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "STRATEGY",
+        default_value_t = OnConflict::Suffix,
+        help = "What to do when the new name of a file is already taken by another file."
+    )]
+    pub(crate) on_conflict: OnConflict,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Move the files that would be overwritten with '--on-conflict overwrite' to the trash of the system instead, from where they can be restored."
+    )]
+    pub(crate) use_trash: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Check all renames before any file is renamed, like the permissions, the new names and their conflicts. If a file fails nonetheless, the renamed files are renamed back, so that either all files are renamed or none."
+    )]
+    pub(crate) atomic: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Continue the run that was interrupted in the current working directory. The files it already handled are skipped and the creation datetimes it already extracted are reused. Each run that is no dry run records its progress in '.rambo-resume.jsonl' until it completes."
+    )]
+    pub(crate) resume: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Skip the files whose names already match the format, optionally with a suffix like '-1', without reading them. This speeds up repeated runs over a mostly renamed archive a lot, \
+        but files which match the format with a wrong date are not corrected. Ignored with '--copy-to', '--move-to' and 'organize'."
+    )]
+    pub(crate) trust_existing_names: bool,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Copy the files into DIR with their new names instead of renaming them, leaving the originals untouched. Combine with '--on-conflict skip' for repeated imports."
+    )]
+    pub(crate) copy_to: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "DIR",
+        conflicts_with = "copy_to",
+        help = "Move the renamed files into DIR instead of keeping them next to the originals. Works across file systems."
+    )]
+    pub(crate) move_to: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "EXT",
+        value_delimiter = ',',
+        help = "Rename sidecar files with these extensions along with the media file of the same name, like 'xmp,aae,thm'. If a sidecar cannot be renamed, the media file keeps its name as well."
+    )]
+    pub(crate) sidecars: Vec<String>,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Give files with the same name in the same directory, like RAW+JPEG pairs, the same new name. They are renamed, skipped or failed together."
+    )]
+    pub(crate) keep_pairs: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Set the modification time of each file to its date/time of creation, also if it already has the correct name. Many gallery apps sort files by it."
+    )]
+    pub(crate) sync_mtime: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Replace extensions that do not fit the format of the file, like '.jpg' for a HEIC file. The format is recognized by the first bytes of the file."
+    )]
+    pub(crate) fix_extensions: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Report the processed files that have the same content, like the same photo imported twice. Nothing is deleted."
+    )]
+    pub(crate) detect_duplicates: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Show a progress bar with the number of processed files and the estimated remaining time."
+    )]
+    pub(crate) progress: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = Output::Human,
+        help = "The format of the output on stdout. Log messages are always written to stderr."
+    )]
+    pub(crate) output: Output,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Write a SHA256SUMS manifest of the renamed files to FILE, which can be verified with 'sha256sum -c'. Only written when not in dry-run mode."
+    )]
+    pub(crate) checksums: Option<PathBuf>,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Write the old and the new name of each renamed or copied file as CSV to FILE, so that the renaming can be undone with 'rambo undo FILE'. Only written when not in dry-run mode."
+    )]
+    pub(crate) rename_map: Option<PathBuf>,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Treat files that vanished between matching and renaming as failures. By default, they are only counted and reported."
+    )]
+    pub(crate) fail_on_vanished: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Do not write the old and the new name of each renamed file to 'rambo-renames-<TIMESTAMP>.csv' in the current working directory. By default, it is written unless it is a dry run."
+    )]
+    pub(crate) no_backup_map: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Hide the files that already have the correct name in the output of a dry run, so that it only shows what would change."
+    )]
+    pub(crate) dry_run_hide_unchanged: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Store the original file name in the extended attribute 'user.rambo.original' of each renamed file. It is kept when the file is renamed again, and '{original}' renders it."
+    )]
+    pub(crate) store_original_xattr: bool,
+
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Write a report of all files that collided on the same new name, and how each collision was resolved, to FILE."
+    )]
+    pub(crate) collisions_report: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct OrganizeArgs {
+    #[clap(flatten)]
+    pub(crate) rename: RenameArgs,
+
+    #[clap(
+        long,
+        value_name = "LAYOUT",
+        default_value = "%Y/%m",
+        help = "The directory layout relative to the current working directory, which the files are moved into. It uses the same specifiers as '--format'."
+    )]
+    pub(crate) into: String,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct PlanArgs {
+    #[clap(flatten)]
+    pub(crate) rename: RenameArgs,
+
+    #[clap(long, short = 'o', value_name = "FILE", help = "The file to write the plan to, in JSON format.")]
+    pub(crate) plan: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct WatchArgs {
+    #[clap(flatten)]
+    pub(crate) rename: RenameArgs,
+
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 2,
+        help = "How long the size of a new file must stay the same before it is renamed, so that files are not renamed while they are still being copied."
+    )]
+    pub(crate) settle_time: u64,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct ApplyArgs {
+    #[clap(value_name = "PLAN", help = "The plan file created by the 'plan' subcommand.")]
+    pub(crate) plan: PathBuf,
+
+    #[clap(long, default_value_t = false, help = "Apply the plan. For safety, the default behavior is a dry run.")]
+    pub(crate) no_dry_run: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "STRATEGY",
+        default_value_t = OnConflict::Suffix,
+        help = "What to do when the new name of a file has been taken by another file since the plan was created."
+    )]
+    pub(crate) on_conflict: OnConflict,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Move the files that would be overwritten with '--on-conflict overwrite' to the trash of the system instead, from where they can be restored."
+    )]
+    pub(crate) use_trash: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Treat files that vanished since the plan was created as failures. By default, they are only counted and reported."
+    )]
+    pub(crate) fail_on_vanished: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Do not write the old and the new name of each renamed file to 'rambo-renames-<TIMESTAMP>.csv' in the current working directory. By default, it is written unless it is a dry run."
+    )]
+    pub(crate) no_backup_map: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Store the original file name in the extended attribute 'user.rambo.original' of each renamed file. It is kept when the file is renamed again, and '{original}' renders it."
+    )]
+    pub(crate) store_original_xattr: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct InspectArgs {
+    #[clap(flatten)]
+    pub(crate) input: InputArgs,
+
+    #[clap(flatten)]
+    pub(crate) extraction: ExtractionArgs,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct UndoArgs {
+    #[clap(
+        value_name = "RENAME_MAP",
+        help = "The rename map which was written with '--rename-map', or a 'rambo-renames-<TIMESTAMP>.csv' of a run."
+    )]
+    pub(crate) rename_map: PathBuf,
+
+    #[clap(long, default_value_t = false, help = "Apply the undo. For safety, the default behavior is a dry run.")]
+    pub(crate) no_dry_run: bool,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Treat files that vanished since they were renamed as failures. By default, they are only counted and reported."
+    )]
+    pub(crate) fail_on_vanished: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct VerifyArgs {
+    #[clap(flatten)]
+    pub(crate) input: InputArgs,
+
+    #[clap(flatten)]
+    pub(crate) extraction: ExtractionArgs,
+
+    #[clap(flatten)]
+    pub(crate) naming: FormatArgs,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct DedupeArgs {
+    #[clap(flatten)]
+    pub(crate) input: InputArgs,
+
+    #[clap(
+        long,
+        value_enum,
+        default_value_t = DuplicateAction::Report,
+        help = "What to do with the duplicates. Of each group of identical files, the one with the first path in sort order is kept."
+    )]
+    pub(crate) action: DuplicateAction,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Move the duplicates to the trash of the system before they are replaced by hard links, so that they can be restored."
+    )]
+    pub(crate) use_trash: bool,
+
+    #[clap(long, default_value_t = false, help = "Apply the action. For safety, the default behavior is a dry run.")]
+    pub(crate) no_dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct WriteExifArgs {
+    #[clap(flatten)]
+    pub(crate) input: InputArgs,
+
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        default_value = rambo::DEFAULT_FORMAT,
+        help = "The format which the names of the files are parsed with, like '%Y-%m-%d_%H-%M-%S'. Anything after the date/time, like a suffix, is ignored. \
+        See: https://docs.rs/chrono/0.4.42/chrono/format/strftime/index.html#specifiers"
+    )]
+    pub(crate) from_name: String,
+
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Write the date/time to an XMP sidecar instead of the EXIF data of the file, e.g. for videos. An existing sidecar is updated, otherwise '<FILE NAME>.xmp' is created."
+    )]
+    pub(crate) xmp_sidecar: bool,
+
+    #[clap(long, default_value_t = false, help = "Write the date/time. For safety, the default behavior is a dry run.")]
+    pub(crate) no_dry_run: bool,
+}
+
+#[cfg(feature = "s3")]
+#[derive(Debug, Args)]
+pub(crate) struct S3Args {
+    #[clap(long, value_name = "BUCKET", help = "The bucket whose objects are renamed.")]
+    pub(crate) bucket: String,
+
+    #[clap(
+        long,
+        value_name = "PREFIX",
+        default_value = "",
+        help = "Only rename the objects whose key starts with this prefix, like 'photos/2023/'."
+    )]
+    pub(crate) prefix: String,
+
+    #[clap(
+        long,
+        value_name = "URL",
+        help = "The endpoint of an S3-compatible service, like 'http://localhost:9000' for MinIO. Without it, AWS S3 is used. \
+        The credentials are read from 'AWS_ACCESS_KEY_ID' and 'AWS_SECRET_ACCESS_KEY' or from '~/.aws/credentials'."
+    )]
+    pub(crate) endpoint: Option<String>,
+
+    #[clap(long, value_name = "REGION", default_value = "us-east-1", help = "The region of the bucket.")]
+    pub(crate) region: String,
+
+    #[clap(flatten)]
+    pub(crate) rename: RemoteRenameArgs,
+}
+
+#[cfg(feature = "sftp")]
+#[derive(Debug, Args)]
+pub(crate) struct SftpArgs {
+    #[clap(
+        value_name = "SOURCE",
+        help = "The files on the remote server, like 'sftp://user@nas/photos/**/*.jpg'. The host must be listed in '~/.ssh/known_hosts'. \
+        The user is authenticated with the SSH agent or with '~/.ssh/id_ed25519' or '~/.ssh/id_rsa'."
+    )]
+    pub(crate) source: rambo::SftpSource,
+
+    #[clap(flatten)]
+    pub(crate) rename: RemoteRenameArgs,
+}
+
+#[cfg(feature = "webdav")]
+#[derive(Debug, Args)]
+pub(crate) struct WebDavArgs {
+    #[clap(
+        long,
+        value_name = "URL",
+        help = "The URL of the WebDAV server, like 'https://cloud.example.com/remote.php/dav/files/USER' for Nextcloud."
+    )]
+    pub(crate) url: String,
+
+    #[clap(
+        long,
+        value_name = "PREFIX",
+        default_value = "",
+        help = "Only rename the files whose path relative to the URL starts with this prefix, like 'InstantUpload/Camera/'."
+    )]
+    pub(crate) prefix: String,
+
+    #[clap(
+        long,
+        value_name = "USER",
+        help = "The user to authenticate as. The password is read from the environment variable 'RAMBO_WEBDAV_PASSWORD', use an app password for Nextcloud."
+    )]
+    pub(crate) user: Option<String>,
+
+    #[clap(flatten)]
+    pub(crate) rename: RemoteRenameArgs,
+}
+
+#[cfg(feature = "mtp")]
+#[derive(Debug, Args)]
+pub(crate) struct ImportArgs {
+    #[clap(
+        value_name = "DESTINATION",
+        help = "The directory to import the media into. The imported media is listed in the file '.rambo-mtp-imported' in it."
+    )]
+    pub(crate) destination: PathBuf,
+
+    #[clap(flatten)]
+    pub(crate) rename: RemoteRenameArgs,
+}
+
+/// The settings of a run on an object storage, a remote server or a device, see [rambo::run_on_storage] and [rambo::import_from_mtp].
+#[cfg(any(feature = "s3", feature = "sftp", feature = "webdav", feature = "mtp"))]
+#[derive(Debug, Args)]
+pub(crate) struct RemoteRenameArgs {
+    #[clap(flatten)]
+    pub(crate) naming: FormatArgs,
+
+    #[clap(long, default_value_t = false, help = "Apply the renaming. For safety, the default behavior is a dry run.")]
+    pub(crate) no_dry_run: bool,
+
+    #[clap(
+        long,
+        value_enum,
+        value_name = "STRATEGY",
+        default_value_t = OnConflict::Suffix,
+        help = "What to do when the new name of a file or object is already taken by another one."
+    )]
+    pub(crate) on_conflict: OnConflict,
 }
 
 impl RamboCli {
     pub(crate) fn print_completions<G: Generator>(generator: G) {
-        let ref mut cmd = Self::command();
+        let cmd = &mut Self::command();
         generate(generator, cmd, cmd.get_name().to_string(), &mut std::io::stdout());
     }
+
+    /// Only the subcommands which extract the creation datetimes have the '--verbose' flag.
+    pub(crate) fn is_verbose(&self) -> bool {
+        let extraction = match &self.command {
+            None => Some(&self.rename.extraction),
+            Some(RamboCommand::Rename(rename_args)) => Some(&rename_args.extraction),
+            Some(
+                RamboCommand::Organize(OrganizeArgs { rename, .. })
+                | RamboCommand::Plan(PlanArgs { rename, .. })
+                | RamboCommand::Watch(WatchArgs { rename, .. }),
+            ) => Some(&rename.extraction),
+            Some(RamboCommand::Inspect(inspect_args)) => Some(&inspect_args.extraction),
+            Some(RamboCommand::Verify(verify_args)) => Some(&verify_args.extraction),
+            Some(_) => None,
+        };
+
+        extraction.is_some_and(|extraction| extraction.verbose)
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum Only {
+    /// Images, which may contain EXIF data.
+    Photos,
+    /// Videos.
+    Videos,
+    /// Audio recordings, like MP3, FLAC and M4A files.
+    Audio,
+    /// PDFs and office documents, like '.docx' files. Requires '--documents'.
+    Documents,
+}
+
+impl From<Only> for MediaKind {
+    fn from(only: Only) -> Self {
+        match only {
+            Only::Photos => MediaKind::Photos,
+            Only::Videos => MediaKind::Videos,
+            Only::Audio => MediaKind::Audio,
+            Only::Documents => MediaKind::Documents,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum DuplicateAction {
+    /// Only print the groups of identical files.
+    Report,
+    /// Replace the duplicates with hard links to the kept file.
+    Hardlink,
+    /// Move the duplicates to the trash of the system.
+    Trash,
+}
+
+impl From<DuplicateAction> for DedupeAction {
+    fn from(duplicate_action: DuplicateAction) -> Self {
+        match duplicate_action {
+            DuplicateAction::Report => DedupeAction::Report,
+            DuplicateAction::Hardlink => DedupeAction::Hardlink,
+            DuplicateAction::Trash => DedupeAction::Trash,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum OnConflict {
+    /// Keep the original name of the file.
+    Skip,
+    /// Append '-1', '-2', ... to the new name until it is free.
+    Suffix,
+    /// Replace the other file.
+    Overwrite,
+    /// Count the file as failed and keep its original name.
+    Fail,
+}
+
+impl From<OnConflict> for ConflictStrategy {
+    fn from(on_conflict: OnConflict) -> Self {
+        match on_conflict {
+            OnConflict::Skip => ConflictStrategy::Skip,
+            OnConflict::Suffix => ConflictStrategy::Suffix,
+            OnConflict::Overwrite => ConflictStrategy::Overwrite,
+            OnConflict::Fail => ConflictStrategy::Fail,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum FileSystem {
+    /// NTFS without long paths, which allows at most 259 characters per path.
+    Windows,
+    /// FAT32 or exFAT, like on SD cards. Characters other than ASCII are replaced.
+    Fat,
+}
+
+impl From<FileSystem> for FsCompat {
+    fn from(file_system: FileSystem) -> Self {
+        match file_system {
+            FileSystem::Windows => FsCompat::Windows,
+            FileSystem::Fat => FsCompat::Fat,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum Normalization {
+    /// Keep the names as they are.
+    None,
+    /// Composed characters, as usual on Linux and Windows.
+    Nfc,
+    /// Decomposed characters, as stored by macOS.
+    Nfd,
+}
+
+impl From<Normalization> for UnicodeNormalization {
+    fn from(normalization: Normalization) -> Self {
+        match normalization {
+            Normalization::None => UnicodeNormalization::None,
+            Normalization::Nfc => UnicodeNormalization::Nfc,
+            Normalization::Nfd => UnicodeNormalization::Nfd,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum CounterResetScope {
+    /// Count through the whole run.
+    Never,
+    /// Count separately for the files of each directory.
+    Directory,
+    /// Count separately for the files of each day of their creation datetime.
+    Day,
+}
+
+impl From<CounterResetScope> for CounterReset {
+    fn from(counter_reset_scope: CounterResetScope) -> Self {
+        match counter_reset_scope {
+            CounterResetScope::Never => CounterReset::Never,
+            CounterResetScope::Directory => CounterReset::Directory,
+            CounterResetScope::Day => CounterReset::Day,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum Output {
+    /// A summary for humans.
+    Human,
+    /// One JSON object per line for each file, followed by a summary object.
+    Json,
+}
+
+impl From<Output> for OutputFormat {
+    fn from(output: Output) -> Self {
+        match output {
+            Output::Human => OutputFormat::Human,
+            Output::Json => OutputFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum FallbackSource {
+    /// Parse the date/time from well-known file name patterns of cameras and phones, like 'IMG_20190704_183012.jpg', of screenshots, like 'Screenshot 2024-03-01 at 10.15.32.png', and of messenger exports, like 'signal-2023-01-05-101530.jpg', in the local time zone.
+    Name,
+    /// Read the date/time from the '*.supplemental-metadata.json' sidecar of a Google Takeout export, and rename the sidecar along with its media file.
+    Takeout,
+    /// Use the modification time of the file, in the local time zone. Copying a file often resets it, so it is only a guess.
+    Mtime,
+    /// Run exiftool, which reads many formats the built-in parser cannot, like obscure RAW formats or files with damaged headers. It must be installed.
+    Exiftool,
+    /// Run ffprobe, which reads the 'creation_time' of many video containers that the built-in parser cannot, like unusual MP4 variants. It is part of FFmpeg and must be installed.
+    Ffprobe,
+}
+
+impl From<FallbackSource> for Fallback {
+    fn from(fallback_source: FallbackSource) -> Self {
+        match fallback_source {
+            FallbackSource::Name => Fallback::Name,
+            FallbackSource::Takeout => Fallback::Takeout,
+            FallbackSource::Mtime => Fallback::Mtime,
+            FallbackSource::Exiftool => Fallback::Exiftool,
+            FallbackSource::Ffprobe => Fallback::Ffprobe,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum Source {
+    /// The metadata embedded in the file, like EXIF, trying the tags 'DateTimeOriginal' and 'CreateDate' and the track info of videos. 'ModifyDate' is only read when it is given itself.
+    Metadata,
+    /// Only the 'DateTimeOriginal' EXIF tag, which is when the photo was taken.
+    #[value(name = "DateTimeOriginal")]
+    DateTimeOriginal,
+    /// Only the 'CreateDate' EXIF tag, which is when the photo was digitized, e.g. scanned.
+    #[value(name = "CreateDate")]
+    CreateDate,
+    /// Only the 'ModifyDate' EXIF tag, which is when the file was last changed, e.g. by an image editor.
+    #[value(name = "ModifyDate")]
+    ModifyDate,
+    /// Only the track info of a video container, like MP4 or MOV.
+    Track,
+    /// The XMP sidecar of the file, like 'IMG_1234.xmp' or 'IMG_1234.CR2.xmp', reading 'photoshop:DateCreated', 'exif:DateTimeOriginal' or 'xmp:CreateDate'.
+    Xmp,
+    /// The telemetry in the SRT subtitles of a DJI drone video, like 'DJI_0001.SRT' of 'DJI_0001.MP4', which also has the position of the drone. The subtitles are renamed along with the video.
+    DjiSrt,
+    /// Well-known file name patterns, like '--fallback name'.
+    Name,
+    /// The modification time of the file in the file system, like '--fallback mtime'.
+    Fs,
+}
+
+impl From<Source> for DateSource {
+    fn from(source: Source) -> Self {
+        match source {
+            Source::Metadata => DateSource::Metadata,
+            Source::DateTimeOriginal => DateSource::DateTimeOriginal,
+            Source::CreateDate => DateSource::CreateDate,
+            Source::ModifyDate => DateSource::ModifyDate,
+            Source::Track => DateSource::Track,
+            Source::Xmp => DateSource::Xmp,
+            Source::DjiSrt => DateSource::DjiSrt,
+            Source::Name => DateSource::Name,
+            Source::Fs => DateSource::Fs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum MetadataBackend {
+    /// The built-in parser, which reads the common photo and video formats.
+    Native,
+    /// exiftool, which reads many more formats, but is much slower, as it is run once per file. It must be installed.
+    Exiftool,
+}
+
+impl From<MetadataBackend> for Backend {
+    fn from(metadata_backend: MetadataBackend) -> Self {
+        match metadata_backend {
+            MetadataBackend::Native => Backend::Native,
+            MetadataBackend::Exiftool => Backend::Exiftool,
+        }
+    }
 }
@@ -1,34 +1,375 @@
 #![forbid(unsafe_code)]
 
-use clap::Parser;
+use crate::cli::{ApplyArgs, DedupeArgs, InspectArgs, OrganizeArgs, PlanArgs, RamboCommand, RenameArgs, UndoArgs, VerifyArgs, WatchArgs, WriteExifArgs};
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
+use indicatif::MultiProgress;
+use indicatif_log_bridge::LogWrapper;
 use log::LevelFilter;
-use rambo::RamboOptions;
+use rambo::{
+    ApplyOptions, Config, DateSource, DedupeOptions, Fallback, FsCompat, InspectOptions, MediaKind, RamboOptions, UndoOptions, VerifyOptions, WriteExifOptions,
+};
+use std::num::NonZeroUsize;
+use std::ops::Not;
 use std::process::ExitCode;
+use std::time::Duration;
 
 mod cli;
 
 fn main() -> ExitCode {
-    env_logger::builder()
+    let matches = cli::RamboCli::command().get_matches();
+    let args = match cli::RamboCli::from_arg_matches(&matches) {
+        Ok(args) => args,
+        Err(error) => error.exit(),
+    };
+    // The flags of a subcommand are only part of its own matches.
+    let command_matches = matches.subcommand().map(|(_, command_matches)| command_matches).unwrap_or(&matches);
+
+    // With '--verbose', rambo logs the source of the creation datetime of each file.
+    let rambo_log_level = if args.is_verbose() {
+        LevelFilter::Debug
+    } else {
+        LevelFilter::Info
+    };
+    let logger = env_logger::builder()
         .format_timestamp(None)
         .format_target(false)
         .filter_level(LevelFilter::Info)
-        .init();
+        .filter_module("rambo", rambo_log_level)
+        // little_exif logs an error for every file without EXIF data, which 'write-exif' handles itself.
+        .filter_module("little_exif", LevelFilter::Off)
+        .build();
+    let max_log_level = logger.filter();
 
-    let args = cli::RamboCli::parse();
+    // All logging is routed through the MultiProgress, so that log lines do not break a progress bar.
+    let multi_progress = MultiProgress::new();
+    if LogWrapper::new(multi_progress.clone(), logger).try_init().is_ok() {
+        log::set_max_level(max_log_level);
+    }
 
     if let Some(completion_generator) = args.completions {
         cli::RamboCli::print_completions(completion_generator);
         return ExitCode::SUCCESS;
     }
 
-    let options = RamboOptions {
-        pattern: args.pattern,
+    match args.command.unwrap_or(RamboCommand::Rename(args.rename)) {
+        RamboCommand::Rename(rename_args) => match rename_options(rename_args, command_matches, &multi_progress) {
+            Ok(options) => run(options),
+            Err(exit_code) => exit_code,
+        },
+        RamboCommand::Organize(organize_args) => organize(organize_args, command_matches, &multi_progress),
+        RamboCommand::Inspect(inspect_args) => inspect(inspect_args),
+        RamboCommand::Plan(plan_args) => plan(plan_args, command_matches, &multi_progress),
+        RamboCommand::Apply(apply_args) => apply(apply_args),
+        RamboCommand::Undo(undo_args) => undo(undo_args),
+        RamboCommand::Watch(watch_args) => watch(watch_args, command_matches, &multi_progress),
+        RamboCommand::Verify(verify_args) => verify(verify_args),
+        RamboCommand::Dedupe(dedupe_args) => dedupe(dedupe_args),
+        RamboCommand::WriteExif(write_exif_args) => write_exif(write_exif_args),
+        #[cfg(feature = "s3")]
+        RamboCommand::S3(s3_args) => s3(s3_args),
+        #[cfg(feature = "sftp")]
+        RamboCommand::Sftp(sftp_args) => sftp(sftp_args),
+        #[cfg(feature = "webdav")]
+        RamboCommand::Webdav(webdav_args) => webdav(webdav_args),
+        #[cfg(feature = "mtp")]
+        RamboCommand::Import(import_args) => import(import_args),
+    }
+}
+
+/// Runs the renaming and maps its report to the exit code of the process.
+fn run(options: RamboOptions) -> ExitCode {
+    let fail_on_vanished = options.fail_on_vanished;
+
+    match rambo::run(options) {
+        Ok(report) if report.has_failures(fail_on_vanished) => ExitCode::FAILURE,
+        Ok(_) => ExitCode::SUCCESS,
+        Err(error) => {
+            log::error!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The settings of the config files are applied, unless they are overridden on the command line.
+fn rename_options(args: RenameArgs, matches: &ArgMatches, multi_progress: &MultiProgress) -> Result<RamboOptions, ExitCode> {
+    let profile = std::env::current_dir()
+        .map_err(anyhow::Error::from)
+        .and_then(|current_working_directory| Ok(Config::load(&current_working_directory)?))
+        .and_then(|config| Ok(config.profile(args.profile.as_deref())?));
+    let profile = match profile {
+        Ok(profile) => profile,
+        Err(error) => {
+            log::error!("{:#}", error);
+            return Err(ExitCode::FAILURE);
+        }
+    };
+
+    let extraction_cache = match args.extraction.extraction_cache {
+        Some(Some(extraction_cache_path)) => Some(extraction_cache_path),
+        Some(None) => {
+            let default_extraction_cache_path = rambo::default_extraction_cache_path();
+            if default_extraction_cache_path.is_none() {
+                log::warn!("The extraction cache is not used, because there is no home directory to keep it in. Pass the path of the cache instead.");
+            }
+            default_extraction_cache_path
+        }
+        None => None,
+    };
+
+    let options = RamboOptions::builder()
+        .input(args.input.source())
+        .no_dry_run(args.no_dry_run)
+        .case_insensitive(args.input.case_insensitive)
+        .format(args.naming.format)
+        .time_zone(args.extraction.timezone.or(args.extraction.time_offset))
+        .shift(args.extraction.shift)
+        .after(args.filter.after)
+        .before(args.filter.before)
+        .extensions(args.filter.ext)
+        .skipped_extensions(args.filter.skip_ext)
+        .only(args.filter.only.map(MediaKind::from))
+        .limit(args.filter.limit.map(NonZeroUsize::get))
+        .min_size(args.filter.min_size)
+        .max_size(args.filter.max_size)
+        .include_symlinks(args.input.include_symlinks)
+        .max_depth(args.input.max_depth.map(NonZeroUsize::get))
+        .checksums(args.checksums)
+        .rename_map(args.rename_map)
+        .backup_map(args.no_backup_map.not())
+        .fail_on_vanished(args.fail_on_vanished)
+        .dry_run_hide_unchanged(args.dry_run_hide_unchanged)
+        .date_sources(args.extraction.date_source.into_iter().map(DateSource::from).collect::<Vec<_>>())
+        .backend(args.extraction.backend)
+        .extractor_cmd(args.extraction.extractor_cmd)
+        .fallbacks(args.extraction.fallback.into_iter().map(Fallback::from).collect::<Vec<_>>())
+        .documents(args.extraction.documents)
+        .extraction_cache(extraction_cache)
+        .store_original_xattr(args.store_original_xattr)
+        .sidecar_extensions(args.sidecars)
+        .keep_pairs(args.keep_pairs)
+        .sync_mtime(args.sync_mtime)
+        .fix_extensions(args.fix_extensions)
+        .detect_duplicates(args.detect_duplicates)
+        .collisions_report(args.collisions_report)
+        .template_file(args.naming.template_file)
+        .counter_reset(args.naming.counter_reset)
+        .replacement_char(args.naming.replacement_char)
+        .fs_compat(args.naming.fs_compat.map(FsCompat::from))
+        .normalize(args.naming.normalize)
+        .on_conflict(args.on_conflict)
+        .use_trash(args.use_trash)
+        .atomic(args.atomic)
+        .resume(args.resume)
+        .trust_existing_names(args.trust_existing_names)
+        .copy_to(args.copy_to)
+        .move_to(args.move_to)
+        .jobs(args.extraction.jobs.get())
+        .progress(args.progress.then(|| multi_progress.clone()))
+        .output(args.output)
+        .build();
+
+    Ok(options.with_profile(&profile, |key| matches.value_source(&key.replace('-', "_")) == Some(ValueSource::CommandLine)))
+}
+
+fn organize(args: OrganizeArgs, matches: &ArgMatches, multi_progress: &MultiProgress) -> ExitCode {
+    let mut options = match rename_options(args.rename, matches, multi_progress) {
+        Ok(options) => options,
+        Err(exit_code) => return exit_code,
+    };
+    options.target_layout = Some(args.into);
+
+    run(options)
+}
+
+fn plan(args: PlanArgs, matches: &ArgMatches, multi_progress: &MultiProgress) -> ExitCode {
+    let mut options = match rename_options(args.rename, matches, multi_progress) {
+        Ok(options) => options,
+        Err(exit_code) => return exit_code,
+    };
+    options.plan_file = Some(args.plan);
+
+    run(options)
+}
+
+fn watch(args: WatchArgs, matches: &ArgMatches, multi_progress: &MultiProgress) -> ExitCode {
+    let options = match rename_options(args.rename, matches, multi_progress) {
+        Ok(options) => options,
+        Err(exit_code) => return exit_code,
+    };
+
+    rambo::watch(options, Duration::from_secs(args.settle_time))
+}
+
+fn apply(args: ApplyArgs) -> ExitCode {
+    let options = ApplyOptions {
+        plan_file: args.plan,
         no_dry_run: args.no_dry_run,
-        case_insensitive: args.case_insensitive,
-        format: args.format,
-        time_offset: args.time_offset,
-        include_symlinks: args.include_symlinks,
+        on_conflict: args.on_conflict.into(),
+        use_trash: args.use_trash,
+        store_original_xattr: args.store_original_xattr,
+        fail_on_vanished: args.fail_on_vanished,
+        backup_map: args.no_backup_map.not(),
     };
 
-    rambo::run(options)
+    rambo::apply(options)
+}
+
+fn inspect(args: InspectArgs) -> ExitCode {
+    let options = InspectOptions {
+        input: args.input.source(),
+        case_insensitive: args.input.case_insensitive,
+        include_symlinks: args.input.include_symlinks,
+        max_depth: args.input.max_depth.map(NonZeroUsize::get),
+        time_zone: args.extraction.timezone.or(args.extraction.time_offset),
+        shift: args.extraction.shift,
+        date_sources: args.extraction.date_source.into_iter().map(DateSource::from).collect(),
+        backend: args.extraction.backend.into(),
+        extractor_cmd: args.extraction.extractor_cmd,
+        fallbacks: args.extraction.fallback.into_iter().map(Fallback::from).collect(),
+        documents: args.extraction.documents,
+        jobs: args.extraction.jobs.get(),
+    };
+
+    rambo::inspect(options)
+}
+
+fn undo(args: UndoArgs) -> ExitCode {
+    let options = UndoOptions {
+        rename_map: args.rename_map,
+        no_dry_run: args.no_dry_run,
+        fail_on_vanished: args.fail_on_vanished,
+    };
+
+    rambo::undo(options)
+}
+
+fn verify(args: VerifyArgs) -> ExitCode {
+    let options = VerifyOptions {
+        input: args.input.source(),
+        case_insensitive: args.input.case_insensitive,
+        include_symlinks: args.input.include_symlinks,
+        max_depth: args.input.max_depth.map(NonZeroUsize::get),
+        format: args.naming.format,
+        template_file: args.naming.template_file,
+        counter_reset: args.naming.counter_reset.into(),
+        replacement_char: args.naming.replacement_char,
+        fs_compat: args.naming.fs_compat.map(FsCompat::from),
+        normalize: args.naming.normalize.into(),
+        time_zone: args.extraction.timezone.or(args.extraction.time_offset),
+        shift: args.extraction.shift,
+        date_sources: args.extraction.date_source.into_iter().map(DateSource::from).collect(),
+        backend: args.extraction.backend.into(),
+        extractor_cmd: args.extraction.extractor_cmd,
+        fallbacks: args.extraction.fallback.into_iter().map(Fallback::from).collect(),
+        documents: args.extraction.documents,
+        jobs: args.extraction.jobs.get(),
+    };
+
+    rambo::verify(options)
+}
+
+fn dedupe(args: DedupeArgs) -> ExitCode {
+    let options = DedupeOptions {
+        input: args.input.source(),
+        case_insensitive: args.input.case_insensitive,
+        include_symlinks: args.input.include_symlinks,
+        max_depth: args.input.max_depth.map(NonZeroUsize::get),
+        action: args.action.into(),
+        use_trash: args.use_trash,
+        no_dry_run: args.no_dry_run,
+    };
+
+    rambo::dedupe(options)
+}
+
+fn write_exif(args: WriteExifArgs) -> ExitCode {
+    let options = WriteExifOptions {
+        input: args.input.source(),
+        case_insensitive: args.input.case_insensitive,
+        include_symlinks: args.input.include_symlinks,
+        max_depth: args.input.max_depth.map(NonZeroUsize::get),
+        from_name: args.from_name,
+        xmp_sidecar: args.xmp_sidecar,
+        no_dry_run: args.no_dry_run,
+    };
+
+    rambo::write_exif(options)
+}
+
+#[cfg(feature = "s3")]
+fn s3(args: cli::S3Args) -> ExitCode {
+    match rambo::S3Storage::new(&args.bucket, &args.region, args.endpoint.as_deref()) {
+        Ok(storage) => run_on_storage(&storage, &args.prefix, args.rename),
+        Err(error) => {
+            log::error!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(feature = "sftp")]
+fn sftp(args: cli::SftpArgs) -> ExitCode {
+    match rambo::SftpStorage::connect(&args.source) {
+        Ok(storage) => run_on_storage(&storage, &args.source.root(), args.rename),
+        Err(error) => {
+            log::error!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(feature = "webdav")]
+fn webdav(args: cli::WebDavArgs) -> ExitCode {
+    let password = std::env::var("RAMBO_WEBDAV_PASSWORD").ok();
+    let storage = rambo::WebDavStorage::new(&args.url, args.user.as_deref(), password.as_deref());
+
+    run_on_storage(&storage, &args.prefix, args.rename)
+}
+
+#[cfg(feature = "mtp")]
+fn import(args: cli::ImportArgs) -> ExitCode {
+    let mut device = match rambo::MtpDevice::open() {
+        Ok(device) => device,
+        Err(error) => {
+            log::error!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match rambo::import_from_mtp(&mut device, &args.destination, remote_options(args.rename)) {
+        Ok(report) if report.has_failures(false) => ExitCode::FAILURE,
+        Ok(_) => ExitCode::SUCCESS,
+        Err(error) => {
+            log::error!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(any(feature = "s3", feature = "sftp", feature = "webdav"))]
+fn run_on_storage(storage: &dyn rambo::ObjectStorage, prefix: &str, args: cli::RemoteRenameArgs) -> ExitCode {
+    match rambo::run_on_storage(storage, prefix, remote_options(args)) {
+        Ok(report) if report.has_failures(false) => ExitCode::FAILURE,
+        Ok(_) => ExitCode::SUCCESS,
+        Err(error) => {
+            log::error!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(any(feature = "s3", feature = "sftp", feature = "webdav", feature = "mtp"))]
+fn remote_options(args: cli::RemoteRenameArgs) -> RamboOptions {
+    RamboOptions::builder()
+        .no_dry_run(args.no_dry_run)
+        .format(args.naming.format)
+        .template_file(args.naming.template_file)
+        .counter_reset(args.naming.counter_reset)
+        .replacement_char(args.naming.replacement_char)
+        .fs_compat(args.naming.fs_compat.map(FsCompat::from))
+        .normalize(args.naming.normalize)
+        .on_conflict(args.on_conflict)
+        .build()
 }
@@ -0,0 +1,51 @@
+use crate::cancel::CancellationToken;
+use crate::error::RamboError;
+use crate::report::RunReport;
+use crate::{RamboOptions, run, run_on_paths};
+
+use std::path::PathBuf;
+
+/// Like [run], but the run is carried out on the blocking thread pool of tokio, so that it does not block the tasks of the runtime.
+/// Dropping the future cancels the run between files, like [CancellationToken::cancel]. The files which were already renamed stay renamed.
+pub async fn run_async(options: RamboOptions) -> Result<RunReport, RamboError> {
+    spawn_run(options, run).await
+}
+
+/// Like [run_on_paths], but asynchronous, see [run_async].
+pub async fn run_on_paths_async(paths: Vec<PathBuf>, options: RamboOptions) -> Result<RunReport, RamboError> {
+    spawn_run(options, move |options| run_on_paths(paths, options)).await
+}
+
+async fn spawn_run<F>(mut options: RamboOptions, run: F) -> Result<RunReport, RamboError>
+where
+    F: FnOnce(RamboOptions) -> Result<RunReport, RamboError> + Send + 'static,
+{
+    let cancellation_token = options.cancellation_token.get_or_insert_with(CancellationToken::new).clone();
+    let cancel_on_drop = CancelOnDrop(Some(cancellation_token));
+
+    let result = tokio::task::spawn_blocking(move || run(options)).await;
+    cancel_on_drop.disarm();
+
+    match result {
+        Ok(result) => result,
+        Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+        Err(error) => panic!("The run was aborted by the runtime: {}", error),
+    }
+}
+
+/// Cancels the run if the future is dropped before the run finished.
+struct CancelOnDrop(Option<CancellationToken>);
+
+impl CancelOnDrop {
+    fn disarm(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(cancellation_token) = self.0.take() {
+            cancellation_token.cancel();
+        }
+    }
+}
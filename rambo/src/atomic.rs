@@ -0,0 +1,165 @@
+use crate::format_path_buf_without_prefix;
+use crate::naming::file_system_limit_violation;
+use crate::rename::{ConflictStrategy, RenameAction, RenameOutcome, RenameSettings, is_same_file, move_file, move_to_temporary_name};
+use crate::sidecar::transferred_sidecars;
+use crate::statistics::Statistics;
+
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// Checks the planned renames of an atomic run before any file is touched, so that a run which would fail midway is not started at all.
+/// Each file must still exist, the directories it is renamed from and to must be writable and its new path must fit the limits of the file system.
+/// With [ConflictStrategy::Fail], no new path may be taken either, by another file or by another rename of the run.
+/// Returns the problem of each file which cannot be renamed, by its current path.
+pub(crate) fn validate_renames<'a>(renames: impl Iterator<Item = &'a (PathBuf, PathBuf)> + Clone, settings: &RenameSettings) -> HashMap<PathBuf, String> {
+    let sources = renames.clone().map(|(source, _)| source.as_path()).collect::<HashSet<_>>();
+    let mut requested_targets = HashSet::<&Path>::new();
+    let mut problems = HashMap::<PathBuf, String>::new();
+
+    for (source, target) in renames {
+        let is_renamed = settings.normalization.is_same_path(source, target).not() && is_same_file(source, target).not();
+
+        let problem = if source.exists().not() {
+            Some("The file vanished".to_string())
+        } else if is_renamed.not() {
+            None
+        } else if let Some(violation) = file_system_limit_violation(target, settings.max_path_length) {
+            Some(format!("The new name is invalid, because {}", violation))
+        } else if settings.copy.not() && source.parent().is_some_and(|directory| is_writable_directory(directory).not()) {
+            Some("The directory of the file is not writable".to_string())
+        } else if target.parent().is_some_and(|directory| is_writable_directory(directory).not()) {
+            Some("The directory of the new name is not writable".to_string())
+        } else if settings.on_conflict == ConflictStrategy::Fail
+            && (requested_targets.insert(target).not() || (target.exists() && (settings.copy || sources.contains(target.as_path()).not())))
+        {
+            Some("The new name is already taken".to_string())
+        } else {
+            None
+        };
+
+        if let Some(problem) = problem {
+            problems.insert(source.clone(), problem);
+        }
+    }
+
+    problems
+}
+
+/// A directory which does not exist yet must be creatable in its closest existing parent.
+fn is_writable_directory(directory: &Path) -> bool {
+    directory
+        .ancestors()
+        .filter(|ancestor| ancestor.as_os_str().is_empty().not())
+        .find(|ancestor| ancestor.exists())
+        .is_none_or(|ancestor| {
+            ancestor
+                .metadata()
+                .is_ok_and(|metadata| metadata.is_dir() && metadata.permissions().readonly().not())
+        })
+}
+
+/// Renames the files of an atomic run back to their original names, after one of them failed. Copies are removed instead.
+/// The sidecars, which were transferred along with their media files, are rolled back as well.
+/// Files which take the original name of another file of the run, like two files which swapped their names, are given temporary names first.
+/// Each file which was rolled back counts as skipped, each file which cannot be rolled back as failed.
+pub(crate) fn roll_back(
+    rename_outcomes: &mut [RenameOutcome],
+    sidecars: &HashMap<PathBuf, Vec<PathBuf>>,
+    current_working_directory: &Path,
+    statistics: &mut Statistics,
+) {
+    // The media file of each outcome comes first, followed by its sidecars.
+    let transfers = rename_outcomes
+        .iter()
+        .map(|outcome| match outcome.action {
+            RenameAction::Renamed | RenameAction::Copied => {
+                let transferred_sidecars = sidecars
+                    .get(&outcome.source)
+                    .map(|sidecar_paths| transferred_sidecars(sidecar_paths, outcome))
+                    .unwrap_or_default();
+                std::iter::once((outcome.source.clone(), outcome.target.clone()))
+                    .chain(transferred_sidecars)
+                    .collect()
+            }
+            _ => Vec::new(),
+        })
+        .collect::<Vec<Vec<(PathBuf, PathBuf)>>>();
+
+    let original_paths = transfers
+        .iter()
+        .flatten()
+        .map(|(original_path, _)| original_path.clone())
+        .collect::<HashSet<_>>();
+
+    // First, the renamed files which took the original name of another file make room, so that no file is renamed onto another one afterwards.
+    let mut rollback_results = Vec::<std::io::Result<()>>::with_capacity(transfers.len());
+    let mut current_transfers = Vec::<Vec<(PathBuf, PathBuf)>>::with_capacity(transfers.len());
+    for (outcome, outcome_transfers) in rename_outcomes.iter().zip(transfers) {
+        let mut result = Ok(());
+        let mut current_outcome_transfers = Vec::with_capacity(outcome_transfers.len());
+
+        for (original_path, transferred_path) in outcome_transfers {
+            if outcome.action == RenameAction::Renamed && result.is_ok() && original_paths.contains(&transferred_path) {
+                match move_to_temporary_name(&transferred_path, false) {
+                    Ok(temporary_path) => current_outcome_transfers.push((original_path, temporary_path)),
+                    Err(error) => {
+                        result = Err(error);
+                        current_outcome_transfers.push((original_path, transferred_path));
+                    }
+                }
+            } else {
+                current_outcome_transfers.push((original_path, transferred_path));
+            }
+        }
+
+        rollback_results.push(result);
+        current_transfers.push(current_outcome_transfers);
+    }
+
+    for ((outcome, outcome_transfers), rollback_result) in rename_outcomes.iter_mut().zip(current_transfers).zip(rollback_results).rev() {
+        if outcome_transfers.is_empty() {
+            continue;
+        }
+        let is_copy = outcome.action == RenameAction::Copied;
+        let clean_file_name = format_path_buf_without_prefix(&outcome.source, current_working_directory);
+
+        let rollback_result = rollback_result.and_then(|_| {
+            outcome_transfers.iter().try_for_each(|(original_path, current_path)| {
+                if is_copy {
+                    std::fs::remove_file(current_path)
+                } else if original_path.exists() {
+                    Err(std::io::Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("The original name {} is taken", original_path.display()),
+                    ))
+                } else {
+                    move_file(current_path, original_path)
+                }
+            })
+        });
+
+        if is_copy {
+            statistics.copied_files -= 1;
+        } else {
+            statistics.renamed_files -= 1;
+        }
+        statistics.sidecar_files -= outcome_transfers.len() as u64 - 1;
+
+        match rollback_result {
+            Ok(_) => {
+                log::warn!("Rolled back {}, because another file of the atomic run failed", clean_file_name);
+                statistics.skipped_files += 1;
+                outcome.action = RenameAction::Skipped;
+                outcome.target = outcome.source.clone();
+            }
+            Err(error) => {
+                log::error!("Failed to roll back {}: {}", clean_file_name, error);
+                statistics.failed_files += 1;
+                outcome.action = RenameAction::Failed;
+                outcome.error = Some(format!("Failed to roll back: {}", error));
+            }
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use crate::error::ExtractError;
+use crate::extract::{DatetimeSource, ExtractedDatetime, MediaMetadata, in_local_time_zone, parse_xmp_datetime};
+use crate::raw::{find_box, read_box_content, read_up_to};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Not;
+
+/// ID3v2 frames and Vorbis comment blocks which are larger are not read, as dates are short.
+const MAX_TAG_LENGTH: u32 = 1 << 20;
+
+/// The major brands of the `ftyp` box of M4A audio, audiobooks and protected audio.
+const M4A_BRANDS: [&[u8; 4]; 3] = [b"M4A ", b"M4B ", b"M4P "];
+
+/// The `©day` atom of the iTunes metadata, whose first byte is not ASCII.
+const DAY_ATOM: [u8; 4] = [0xA9, b'd', b'a', b'y'];
+
+/// The seconds from 1904-01-01, the epoch of the times in the `mvhd` box, to the Unix epoch.
+const MP4_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+const FLAC_VORBIS_COMMENT_BLOCK: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AudioContainer {
+    /// An MP3 file, or any other file which starts with an ID3v2 tag.
+    Id3,
+    Flac,
+    M4a,
+}
+
+/// Whether the file is an MP3 file with an ID3v2 tag, a FLAC file or an M4A file, by its header. The reader is rewound afterwards.
+pub(crate) fn is_audio_container<R: Read + Seek>(reader: &mut R) -> bool {
+    detect_audio_container(reader).is_some()
+}
+
+/// Returns [None] if the file is no audio file, so that it is parsed with nom-exif instead.
+pub(crate) fn extract_creation_datetime_from_audio<R: Read + Seek>(reader: &mut R) -> Option<Result<ExtractedDatetime, ExtractError>> {
+    let audio_container = detect_audio_container(reader)?;
+
+    let extracted_datetime = match audio_container {
+        AudioContainer::Id3 => read_id3_creation_datetime(reader),
+        AudioContainer::Flac => read_flac_creation_datetime(reader, 4),
+        AudioContainer::M4a => read_m4a_creation_datetime(reader),
+    };
+
+    Some(match extracted_datetime {
+        Ok(Some(extracted_datetime)) => extracted_datetime,
+        Ok(None) => Err(ExtractError::MissingDatetime("audio")),
+        Err(error) => Err(ExtractError::io("Cannot read the file")(error)),
+    })
+}
+
+/// Returns [None] if the file is no audio file, so that it is parsed with nom-exif instead.
+/// Only the creation datetime is read, as audio files have no camera.
+pub(crate) fn extract_metadata_from_audio<R: Read + Seek>(reader: &mut R) -> Option<Result<MediaMetadata, ExtractError>> {
+    extract_creation_datetime_from_audio(reader)
+        .map(|extracted_datetime| extracted_datetime.map(|extracted_datetime| MediaMetadata::from(extracted_datetime.datetime)))
+}
+
+fn detect_audio_container<R: Read + Seek>(reader: &mut R) -> Option<AudioContainer> {
+    let mut header = [0u8; 12];
+    let header_length = read_up_to(reader, &mut header).unwrap_or_default();
+    let _ = reader.seek(SeekFrom::Start(0));
+
+    if header_length >= 10 && header.starts_with(b"ID3") {
+        Some(AudioContainer::Id3)
+    } else if header_length >= 4 && header.starts_with(b"fLaC") {
+        Some(AudioContainer::Flac)
+    } else if header_length == header.len() && &header[4..8] == b"ftyp" && M4A_BRANDS.iter().any(|brand| header[8..12] == **brand) {
+        Some(AudioContainer::M4a)
+    } else {
+        None
+    }
+}
+
+/// The datetimes of the tags are in the local time zone, unless they have an offset.
+/// Returns [None] if the value is not at least a date, like a plain year, which is no recording date.
+fn parse_recording_datetime(value: &str) -> Option<Result<DateTime<FixedOffset>, ExtractError>> {
+    let value = value.trim_end_matches('\0').trim().replacen(' ', "T", 1);
+    (value.len() >= "2024-05-01".len()).then(|| parse_xmp_datetime(&value))
+}
+
+/// ID3v2.4 has the recording time in `TDRC`, like `2024-05-01T10:00:00`.
+/// ID3v2.3 has the year in `TYER`, the day and month in `TDAT`, like `0105`, and the hours and minutes in `TIME`, like `1000`, though many taggers write `TDRC` anyway.
+/// A FLAC file may start with an ID3v2 tag, in which case its Vorbis comments are read, if the tag has no date.
+fn read_id3_creation_datetime<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<Result<ExtractedDatetime, ExtractError>>> {
+    let mut header = [0u8; 10];
+    reader.read_exact(&mut header)?;
+    let major_version = header[3];
+    let flags = header[5];
+    let tag_end = 10 + u64::from(syncsafe(&header[6..10]));
+
+    let mut position = 10;
+    if flags & 0x40 != 0 {
+        // The size of the extended header excludes itself in ID3v2.3.
+        let mut extended_header_size = [0u8; 4];
+        reader.read_exact(&mut extended_header_size)?;
+        position += match major_version {
+            3 => 4 + u64::from(u32::from_be_bytes(extended_header_size)),
+            _ => u64::from(syncsafe(&extended_header_size)),
+        };
+    }
+
+    let mut frames = Id3DateFrames::default();
+    // ID3v2.2 has 3 character frame IDs, which are not supported.
+    while matches!(major_version, 3 | 4) && position + 10 <= tag_end {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut frame_header = [0u8; 10];
+        reader.read_exact(&mut frame_header)?;
+        // The frames are followed by padding.
+        if frame_header[0] == 0 {
+            break;
+        }
+
+        let frame_size = match major_version {
+            4 => syncsafe(&frame_header[4..8]),
+            _ => u32::from_be_bytes([frame_header[4], frame_header[5], frame_header[6], frame_header[7]]),
+        };
+        let frame_id = [frame_header[0], frame_header[1], frame_header[2], frame_header[3]];
+        position += 10 + u64::from(frame_size);
+
+        if matches!(&frame_id, b"TDRC" | b"TYER" | b"TDAT" | b"TIME").not() || frame_size > MAX_TAG_LENGTH || position > tag_end {
+            continue;
+        }
+        let mut frame = vec![0u8; frame_size as usize];
+        reader.read_exact(&mut frame)?;
+        // ID3v2.4 frames with a data length indicator are preceded by the length.
+        if major_version == 4 && frame_header[9] & 0x01 != 0 {
+            frame.drain(..frame.len().min(4));
+        }
+
+        let text = decode_id3_text(&frame);
+        match &frame_id {
+            b"TDRC" => frames.recording_time = Some(text),
+            b"TYER" => frames.year = Some(text),
+            b"TDAT" => frames.date = Some(text),
+            _ => frames.time = Some(text),
+        }
+    }
+
+    if let Some(extracted_datetime) = frames.creation_datetime() {
+        return Ok(Some(extracted_datetime));
+    }
+
+    // A footer, which repeats the header, follows the frames.
+    let audio_start = tag_end + if flags & 0x10 != 0 { 10 } else { 0 };
+    reader.seek(SeekFrom::Start(audio_start))?;
+    let mut flac_marker = [0u8; 4];
+    if read_up_to(reader, &mut flac_marker)? == flac_marker.len() && &flac_marker == b"fLaC" {
+        return read_flac_creation_datetime(reader, audio_start + 4);
+    }
+
+    Ok(None)
+}
+
+#[derive(Debug, Default)]
+struct Id3DateFrames {
+    recording_time: Option<String>,
+    year: Option<String>,
+    date: Option<String>,
+    time: Option<String>,
+}
+
+impl Id3DateFrames {
+    fn creation_datetime(&self) -> Option<Result<ExtractedDatetime, ExtractError>> {
+        if let Some(datetime) = self.recording_time.as_deref().and_then(parse_recording_datetime) {
+            return Some(datetime.map(|datetime| ExtractedDatetime {
+                datetime,
+                source: DatetimeSource::AudioTag("TDRC"),
+            }));
+        }
+
+        let (year, date) = (self.year.as_deref()?.trim(), self.date.as_deref()?.trim());
+        let date = NaiveDate::parse_from_str(&format!("{}{}", year, date), "%Y%d%m").ok()?;
+        let time = self
+            .time
+            .as_deref()
+            .and_then(|time| NaiveTime::parse_from_str(time.trim(), "%H%M").ok())
+            .unwrap_or(NaiveTime::MIN);
+
+        Some(in_local_time_zone(date.and_time(time)).map(|datetime| ExtractedDatetime {
+            datetime,
+            source: DatetimeSource::AudioTag("TDAT"),
+        }))
+    }
+}
+
+/// The sizes of ID3v2 tags use only the lower 7 bits of each byte.
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |size, &byte| (size << 7) | u32::from(byte & 0x7F))
+}
+
+/// The first byte of a text frame is its encoding: ISO-8859-1, UTF-16 with a byte order mark, UTF-16BE or UTF-8.
+fn decode_id3_text(frame: &[u8]) -> String {
+    let Some((&encoding, text)) = frame.split_first() else {
+        return String::new();
+    };
+
+    let decoded_text = match encoding {
+        1 | 2 => {
+            let is_little_endian = encoding == 1 && text.starts_with(&[0xFF, 0xFE]);
+            let text = if encoding == 1 {
+                text.get(2..).unwrap_or_default()
+            } else {
+                text
+            };
+            let code_units = text.chunks_exact(2).map(|code_unit| {
+                if is_little_endian {
+                    u16::from_le_bytes([code_unit[0], code_unit[1]])
+                } else {
+                    u16::from_be_bytes([code_unit[0], code_unit[1]])
+                }
+            });
+            char::decode_utf16(code_units)
+                .map(|character| character.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => text.iter().map(|&byte| char::from(byte)).collect(),
+    };
+
+    // Frames may hold several values, which are separated by null characters.
+    decoded_text.split('\0').next().unwrap_or_default().to_string()
+}
+
+/// Each metadata block has a header with a flag for the last block, its type and its length, from which the `DATE` Vorbis comment is read.
+fn read_flac_creation_datetime<R: Read + Seek>(reader: &mut R, first_block_start: u64) -> std::io::Result<Option<Result<ExtractedDatetime, ExtractError>>> {
+    let mut position = first_block_start;
+
+    loop {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut block_header = [0u8; 4];
+        reader.read_exact(&mut block_header)?;
+        let is_last_block = block_header[0] & 0x80 != 0;
+        let block_length = u32::from_be_bytes([0, block_header[1], block_header[2], block_header[3]]);
+
+        if block_header[0] & 0x7F == FLAC_VORBIS_COMMENT_BLOCK && block_length <= MAX_TAG_LENGTH {
+            let mut block = vec![0u8; block_length as usize];
+            reader.read_exact(&mut block)?;
+            return Ok(find_vorbis_comment(&block, "DATE")
+                .and_then(|value| parse_recording_datetime(&value))
+                .map(|datetime| {
+                    datetime.map(|datetime| ExtractedDatetime {
+                        datetime,
+                        source: DatetimeSource::AudioTag("DATE"),
+                    })
+                }));
+        }
+
+        if is_last_block {
+            return Ok(None);
+        }
+        position += 4 + u64::from(block_length);
+    }
+}
+
+/// Vorbis comments are little endian: the length of the vendor string and the string, the number of comments and each comment with its length, like `DATE=2024-05-01`.
+/// The names of the comments are case-insensitive.
+fn find_vorbis_comment(block: &[u8], name: &str) -> Option<String> {
+    let le_u32 = |offset: usize| Some(u32::from_le_bytes(block.get(offset..offset + 4)?.try_into().ok()?) as usize);
+
+    let mut offset = 4 + le_u32(0)?;
+    let comment_count = le_u32(offset)?;
+    offset += 4;
+
+    for _ in 0..comment_count {
+        let comment_length = le_u32(offset)?;
+        let comment = block.get(offset + 4..offset + 4 + comment_length)?;
+        offset += 4 + comment_length;
+
+        let comment = String::from_utf8_lossy(comment);
+        if let Some((comment_name, value)) = comment.split_once('=')
+            && comment_name.eq_ignore_ascii_case(name)
+        {
+            return Some(value.to_string());
+        }
+    }
+
+    None
+}
+
+/// The `©day` atom of the iTunes metadata in `moov/udta/meta/ilst`, like `2024-05-01T08:00:00Z`, is preferred,
+/// as voice memos have it, with the creation time of the `mvhd` box, which is in UTC, as fallback.
+fn read_m4a_creation_datetime<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<Result<ExtractedDatetime, ExtractError>>> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    let Some((moov_start, moov_end)) = find_box(reader, 0, end, |box_type, _| box_type == b"moov")? else {
+        return Ok(None);
+    };
+
+    if let Some(day) = read_day_atom(reader, moov_start, moov_end)?
+        && let Some(datetime) = parse_recording_datetime(&day)
+    {
+        return Ok(Some(datetime.map(|datetime| ExtractedDatetime {
+            datetime,
+            source: DatetimeSource::AudioTag("©day"),
+        })));
+    }
+
+    let Some(mvhd) = read_box_content(reader, moov_start, moov_end, b"mvhd")? else {
+        return Ok(None);
+    };
+    // Version 1 has a 64 bit creation time, version 0 a 32 bit one, after the version and the flags.
+    let seconds_since_1904 = match mvhd.first() {
+        Some(&1) => mvhd.get(4..12).and_then(|bytes| bytes.try_into().ok()).map(u64::from_be_bytes),
+        _ => mvhd.get(4..8).and_then(|bytes| bytes.try_into().ok()).map(u32::from_be_bytes).map(u64::from),
+    };
+
+    // Many encoders leave the creation time unset.
+    Ok(seconds_since_1904
+        .filter(|&seconds_since_1904| seconds_since_1904 != 0)
+        .and_then(|seconds_since_1904| i64::try_from(seconds_since_1904).ok())
+        .and_then(|seconds_since_1904| DateTime::from_timestamp(seconds_since_1904 - MP4_EPOCH_OFFSET, 0))
+        .map(|utc_datetime| {
+            Ok(ExtractedDatetime {
+                datetime: utc_datetime.with_timezone(&Local).fixed_offset(),
+                source: DatetimeSource::AudioTag("mvhd"),
+            })
+        }))
+}
+
+/// The `meta` box is a full box with a version and flags in MP4 files, but not in QuickTime files, which is told by its first child, the `hdlr` box.
+fn read_day_atom<R: Read + Seek>(reader: &mut R, moov_start: u64, moov_end: u64) -> std::io::Result<Option<String>> {
+    let Some((udta_start, udta_end)) = find_box(reader, moov_start, moov_end, |box_type, _| box_type == b"udta")? else {
+        return Ok(None);
+    };
+    let Some((meta_start, meta_end)) = find_box(reader, udta_start, udta_end, |box_type, _| box_type == b"meta")? else {
+        return Ok(None);
+    };
+
+    reader.seek(SeekFrom::Start(meta_start))?;
+    let mut meta_header = [0u8; 8];
+    reader.read_exact(&mut meta_header)?;
+    let children_start = if &meta_header[4..8] == b"hdlr" {
+        meta_start
+    } else {
+        meta_start + 4
+    };
+
+    let Some((ilst_start, ilst_end)) = find_box(reader, children_start, meta_end, |box_type, _| box_type == b"ilst")? else {
+        return Ok(None);
+    };
+    let Some((day_start, day_end)) = find_box(reader, ilst_start, ilst_end, |box_type, _| box_type == &DAY_ATOM)? else {
+        return Ok(None);
+    };
+
+    // The value of the `data` box follows its type and its locale.
+    Ok(read_box_content(reader, day_start, day_end, b"data")?.and_then(|data| data.get(8..).map(|value| String::from_utf8_lossy(value).into_owned())))
+}
@@ -0,0 +1,140 @@
+use crate::error::ExtractError;
+use crate::extract::{DatetimeSource, ExtractedDatetime, MediaMetadata, in_local_time_zone};
+use crate::raw::read_up_to;
+
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Not;
+
+/// The recording datetime is in the first frames of the video stream, so only the start of the file is searched.
+const MDPM_SEARCH_LENGTH: usize = 4 * 1024 * 1024;
+
+/// The user data of the H.264 stream, which AVCHD camcorders write their metadata to, is marked by this UUID, followed by `MDPM`.
+const MDPM_MARKER: [u8; 20] = [
+    0x17, 0xEE, 0x8C, 0x60, 0xF8, 0x4D, 0x11, 0xD9, 0x8C, 0xD6, 0x08, 0x00, 0x20, 0x0C, 0x9A, 0x66, b'M', b'D', b'P', b'M',
+];
+
+/// The tag of the time zone and the year and month of the recording, and the one of its day and time.
+const MDPM_DATE_TAG: u8 = 0x18;
+const MDPM_TIME_TAG: u8 = 0x19;
+
+/// The size of the packets of an MPEG transport stream, which AVCHD precedes with a 4 byte timestamp.
+const TS_PACKET_LENGTH: usize = 188;
+const M2TS_PACKET_LENGTH: usize = 192;
+
+/// Whether the file is an MPEG transport stream, like the `.MTS` and `.M2TS` files of AVCHD camcorders, by the sync bytes of its first two packets.
+/// The reader is rewound afterwards.
+pub(crate) fn is_transport_stream<R: Read + Seek>(reader: &mut R) -> bool {
+    let mut header = [0u8; M2TS_PACKET_LENGTH + 5];
+    let header_length = read_up_to(reader, &mut header).unwrap_or_default();
+    let _ = reader.seek(SeekFrom::Start(0));
+
+    let has_sync_bytes = |first: usize, second: usize| header_length > second && header[first] == 0x47 && header[second] == 0x47;
+    has_sync_bytes(0, TS_PACKET_LENGTH) || has_sync_bytes(4, M2TS_PACKET_LENGTH + 4)
+}
+
+/// Returns [None] if the file is no transport stream, so that it is parsed with nom-exif instead.
+pub(crate) fn extract_creation_datetime_from_transport_stream<R: Read + Seek>(reader: &mut R) -> Option<Result<ExtractedDatetime, ExtractError>> {
+    if is_transport_stream(reader).not() {
+        return None;
+    }
+
+    let mut head = vec![0u8; MDPM_SEARCH_LENGTH];
+    let head_length = match read_up_to(reader, &mut head) {
+        Ok(head_length) => head_length,
+        Err(error) => return Some(Err(ExtractError::io("Cannot read the file")(error))),
+    };
+    head.truncate(head_length);
+
+    Some(
+        head.windows(MDPM_MARKER.len())
+            .position(|window| window == MDPM_MARKER)
+            .and_then(|marker_start| parse_mdpm_datetime(&head[marker_start + MDPM_MARKER.len()..]))
+            .ok_or(ExtractError::MissingDatetime("AVCHD"))
+            .and_then(|datetime| datetime)
+            .map(|datetime| ExtractedDatetime {
+                datetime,
+                source: DatetimeSource::AvchdTag("DateTimeOriginal"),
+            }),
+    )
+}
+
+/// Returns [None] if the file is no transport stream, so that it is parsed with nom-exif instead.
+/// Only the creation datetime is read, as the camera is stored as a code of its manufacturer.
+pub(crate) fn extract_metadata_from_transport_stream<R: Read + Seek>(reader: &mut R) -> Option<Result<MediaMetadata, ExtractError>> {
+    extract_creation_datetime_from_transport_stream(reader)
+        .map(|extracted_datetime| extracted_datetime.map(|extracted_datetime| MediaMetadata::from(extracted_datetime.datetime)))
+}
+
+/// The `MDPM` marker is followed by the number of entries, each of which is a tag and 4 bytes of data.
+/// The data is escaped like any H.264 data, so a `0x03` following two zero bytes is dropped.
+/// The date and the time are BCD encoded. The first byte of the date is the time zone: bit 6 is set during daylight saving time, bit 5 for negative offsets,
+/// bits 4 to 1 are the hours and bit 0 adds half an hour. A time zone of `0xFF` is unknown, so the local time zone is assumed.
+fn parse_mdpm_datetime(data: &[u8]) -> Option<Result<DateTime<FixedOffset>, ExtractError>> {
+    let data = remove_emulation_prevention_bytes(data.get(..1 + 255 * 5).unwrap_or(data));
+    let (&entry_count, entries) = data.split_first()?;
+
+    let mut date = None;
+    let mut time = None;
+    for entry in entries.chunks_exact(5).take(usize::from(entry_count)) {
+        match entry[0] {
+            MDPM_DATE_TAG => date = Some([entry[1], entry[2], entry[3], entry[4]]),
+            MDPM_TIME_TAG => time = Some([entry[1], entry[2], entry[3], entry[4]]),
+            _ => {}
+        }
+    }
+    let ([time_zone, year_high, year_low, month], [day, hour, minute, second]) = (date?, time?);
+
+    let naive_datetime = NaiveDate::from_ymd_opt(
+        i32::from(bcd(year_high)?) * 100 + i32::from(bcd(year_low)?),
+        u32::from(bcd(month)?),
+        u32::from(bcd(day)?),
+    )?
+    .and_hms_opt(u32::from(bcd(hour)?), u32::from(bcd(minute)?), u32::from(bcd(second)?))?;
+
+    if time_zone == 0xFF {
+        return Some(in_local_time_zone(naive_datetime));
+    }
+
+    let sign = if time_zone & 0x20 != 0 {
+        -1
+    } else {
+        1
+    };
+    let daylight_saving_seconds = if time_zone & 0x40 != 0 {
+        3600
+    } else {
+        0
+    };
+    let offset_seconds = sign * (i32::from((time_zone >> 1) & 0x0F) * 3600 + i32::from(time_zone & 0x01) * 1800) + daylight_saving_seconds;
+
+    Some(
+        FixedOffset::east_opt(offset_seconds)
+            .and_then(|offset| naive_datetime.and_local_timezone(offset).single())
+            .ok_or_else(|| ExtractError::InvalidDatetime(format!("The AVCHD datetime {} has an invalid time zone!", naive_datetime))),
+    )
+}
+
+fn remove_emulation_prevention_bytes(data: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(data.len());
+    let mut zero_bytes = 0;
+    for &byte in data {
+        if byte == 0x03 && zero_bytes >= 2 {
+            zero_bytes = 0;
+            continue;
+        }
+        zero_bytes = if byte == 0x00 {
+            zero_bytes + 1
+        } else {
+            0
+        };
+        unescaped.push(byte);
+    }
+    unescaped
+}
+
+/// Returns [None] if the byte is no valid BCD, like the `0xFF` of unset values.
+fn bcd(byte: u8) -> Option<u8> {
+    let (tens, ones) = (byte >> 4, byte & 0x0F);
+    (tens < 10 && ones < 10).then_some(tens * 10 + ones)
+}
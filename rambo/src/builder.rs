@@ -0,0 +1,209 @@
+use crate::RamboOptions;
+use crate::cancel::CancellationToken;
+use crate::counter::CounterReset;
+use crate::event::EventHandler;
+use crate::extract::{Backend, DateSource, Fallback};
+use crate::extractor::ExtractorChain;
+use crate::filter::MediaKind;
+use crate::input::InputSource;
+use crate::naming::{FsCompat, UnicodeNormalization};
+use crate::output::OutputFormat;
+use crate::progress::ProgressUpdate;
+use crate::rename::ConflictStrategy;
+use crate::renamer::Renamer;
+
+use indicatif::MultiProgress;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+
+/// The format of the new file names, if none is set.
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d__%H-%M-%S";
+
+/// The replacement of the characters which are not allowed in file names, if none is set, like the one of the values of tokens.
+pub const DEFAULT_REPLACEMENT_CHAR: char = '-';
+
+/// Sets the value of the option.
+macro_rules! setters {
+    ($($name:ident: $value_type:ty),* $(,)?) => {
+        $(
+            #[doc = concat!("Sets [RamboOptions::", stringify!($name), "].")]
+            pub fn $name(&mut self, $name: impl Into<$value_type>) -> &mut Self {
+                self.options.$name = $name.into();
+                self
+            }
+        )*
+    };
+}
+
+/// Sets the value of the optional option, which is unset by default. It can be unset again with `None`.
+macro_rules! optional_setters {
+    ($($name:ident: $value_type:ty),* $(,)?) => {
+        $(
+            #[doc = concat!("Sets [RamboOptions::", stringify!($name), "].")]
+            pub fn $name(&mut self, $name: impl Into<Option<$value_type>>) -> &mut Self {
+                self.options.$name = $name.into();
+                self
+            }
+        )*
+    };
+}
+
+/// Builds [RamboOptions], starting with the same defaults as the command line, so that a dry run of the files matching `*` is carried out.
+/// The setters can be called on a builder which is kept around, and [RamboOptionsBuilder::build] can be called several times.
+#[derive(Clone)]
+pub struct RamboOptionsBuilder {
+    options: RamboOptions,
+}
+
+impl RamboOptions {
+    pub fn builder() -> RamboOptionsBuilder {
+        RamboOptionsBuilder::default()
+    }
+}
+
+impl Default for RamboOptionsBuilder {
+    fn default() -> Self {
+        Self {
+            options: RamboOptions {
+                input: InputSource::GlobPatterns(vec!["*".to_string()]),
+                no_dry_run: false,
+                case_insensitive: false,
+                format: DEFAULT_FORMAT.to_string(),
+                time_zone: None,
+                shift: None,
+                camera_shifts: BTreeMap::new(),
+                after: None,
+                before: None,
+                extensions: None,
+                skipped_extensions: Vec::new(),
+                only: None,
+                limit: None,
+                min_size: None,
+                max_size: None,
+                include_symlinks: false,
+                max_depth: None,
+                checksums: None,
+                rename_map: None,
+                backup_map: true,
+                fail_on_vanished: false,
+                dry_run_hide_unchanged: false,
+                date_sources: vec![DateSource::default()],
+                backend: Backend::default(),
+                extractor_cmd: None,
+                fallbacks: Vec::new(),
+                documents: false,
+                extractor_chain: None,
+                extraction_cache: None,
+                store_original_xattr: false,
+                sidecar_extensions: Vec::new(),
+                keep_pairs: false,
+                sync_mtime: false,
+                fix_extensions: false,
+                detect_duplicates: false,
+                collisions_report: None,
+                template_file: None,
+                renamer: None,
+                on_conflict: ConflictStrategy::Suffix,
+                use_trash: false,
+                counter_reset: CounterReset::Never,
+                replacement_char: DEFAULT_REPLACEMENT_CHAR,
+                fs_compat: None,
+                normalize: UnicodeNormalization::None,
+                target_layout: None,
+                copy_to: None,
+                move_to: None,
+                jobs: 1,
+                progress: None,
+                output: OutputFormat::default(),
+                plan_file: None,
+                atomic: false,
+                resume: false,
+                trust_existing_names: false,
+                event_handler: None,
+                cancellation_token: None,
+                progress_sender: None,
+            },
+        }
+    }
+}
+
+impl RamboOptionsBuilder {
+    setters! {
+        input: InputSource,
+        no_dry_run: bool,
+        case_insensitive: bool,
+        format: String,
+        camera_shifts: BTreeMap<String, String>,
+        skipped_extensions: Vec<String>,
+        include_symlinks: bool,
+        backup_map: bool,
+        fail_on_vanished: bool,
+        dry_run_hide_unchanged: bool,
+        date_sources: Vec<DateSource>,
+        backend: Backend,
+        fallbacks: Vec<Fallback>,
+        documents: bool,
+        store_original_xattr: bool,
+        sidecar_extensions: Vec<String>,
+        keep_pairs: bool,
+        sync_mtime: bool,
+        fix_extensions: bool,
+        detect_duplicates: bool,
+        on_conflict: ConflictStrategy,
+        use_trash: bool,
+        counter_reset: CounterReset,
+        replacement_char: char,
+        normalize: UnicodeNormalization,
+        jobs: usize,
+        output: OutputFormat,
+        atomic: bool,
+        resume: bool,
+        trust_existing_names: bool,
+    }
+
+    optional_setters! {
+        time_zone: String,
+        shift: String,
+        after: String,
+        before: String,
+        extensions: Vec<String>,
+        only: MediaKind,
+        limit: usize,
+        min_size: String,
+        max_size: String,
+        max_depth: usize,
+        checksums: PathBuf,
+        rename_map: PathBuf,
+        extractor_cmd: String,
+        collisions_report: PathBuf,
+        template_file: PathBuf,
+        target_layout: String,
+        copy_to: PathBuf,
+        move_to: PathBuf,
+        progress: MultiProgress,
+        plan_file: PathBuf,
+        cancellation_token: CancellationToken,
+        extractor_chain: ExtractorChain,
+        extraction_cache: PathBuf,
+        fs_compat: FsCompat,
+        progress_sender: Sender<ProgressUpdate>,
+    }
+
+    /// Sets [RamboOptions::event_handler], which can also be a closure.
+    pub fn event_handler(&mut self, event_handler: impl EventHandler + 'static) -> &mut Self {
+        self.options.event_handler = Some(Arc::new(event_handler));
+        self
+    }
+
+    /// Sets [RamboOptions::renamer].
+    pub fn renamer(&mut self, renamer: impl Renamer + 'static) -> &mut Self {
+        self.options.renamer = Some(Arc::new(renamer));
+        self
+    }
+
+    pub fn build(&self) -> RamboOptions {
+        self.options.clone()
+    }
+}
@@ -0,0 +1,158 @@
+use crate::error::RamboError;
+use crate::extract::MediaMetadata;
+use crate::rename::{RenameAction, RenameOutcome};
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The default location of the extraction cache, `$XDG_CACHE_HOME/rambo/extractions.jsonl` or `~/.cache/rambo/extractions.jsonl`.
+pub fn default_extraction_cache_path() -> Option<PathBuf> {
+    let cache_directory = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|cache_directory| cache_directory.is_empty().not())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(cache_directory.join("rambo").join("extractions.jsonl"))
+}
+
+/// A line of the cache file.
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+    /// How the metadata was extracted, like the fallbacks, as the same file may yield other metadata with other settings.
+    settings: String,
+    metadata: MediaMetadata,
+}
+
+/// The metadata of the files of earlier runs, so that repeated runs over the same files, like when trying out formats, skip the files which did not change.
+/// A file counts as changed when its size or its modification time changed. The cache is read once and written once per run.
+pub(crate) struct ExtractionCache {
+    cache_path: PathBuf,
+    settings: String,
+    /// By the absolute path of each file.
+    entries: HashMap<PathBuf, CacheEntry>,
+    is_changed: bool,
+}
+
+impl ExtractionCache {
+    /// A cache which does not exist yet or cannot be read is started anew. Lines which cannot be read are dropped.
+    pub(crate) fn load(cache_path: &Path, settings: String) -> Self {
+        let mut entries = HashMap::<PathBuf, CacheEntry>::new();
+
+        match File::open(cache_path) {
+            Ok(cache_file) => {
+                for line in BufReader::new(cache_file).lines().map_while(Result::ok) {
+                    if let Ok(entry) = serde_json::from_str::<CacheEntry>(&line) {
+                        entries.insert(entry.path.clone(), entry);
+                    }
+                }
+            }
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            Err(error) => log::warn!("Failed to read extraction cache '{}', it is started anew: {}", cache_path.display(), error),
+        }
+
+        Self {
+            cache_path: cache_path.to_path_buf(),
+            settings,
+            entries,
+            is_changed: false,
+        }
+    }
+
+    /// The cached metadata of the files which did not change since it was extracted with the same settings.
+    pub(crate) fn known_extractions(&self, paths: &[PathBuf]) -> HashMap<PathBuf, MediaMetadata> {
+        paths
+            .iter()
+            .filter_map(|path_buf| {
+                let entry = self.entries.get(path_buf).filter(|entry| entry.settings == self.settings)?;
+                let (size, modified) = size_and_modification_time(path_buf)?;
+
+                (entry.size == size && entry.modified == modified).then(|| (path_buf.clone(), entry.metadata.clone()))
+            })
+            .collect()
+    }
+
+    /// Files which cannot be read anymore are not cached.
+    pub(crate) fn insert(&mut self, path: &Path, metadata: &MediaMetadata) {
+        let Some((size, modified)) = size_and_modification_time(path) else {
+            return;
+        };
+
+        self.entries.insert(
+            path.to_path_buf(),
+            CacheEntry {
+                path: path.to_path_buf(),
+                size,
+                modified,
+                settings: self.settings.clone(),
+                metadata: metadata.clone(),
+            },
+        );
+        self.is_changed = true;
+    }
+
+    /// Renamed files keep their entry under their new path and copied files get an entry under their new path as well, as renaming and copying keep the modification time.
+    pub(crate) fn apply_renames(&mut self, rename_outcomes: &[RenameOutcome]) {
+        for outcome in rename_outcomes {
+            let entry = match outcome.action {
+                RenameAction::Renamed => self.entries.remove(&outcome.source),
+                RenameAction::Copied => self.entries.get(&outcome.source).cloned(),
+                _ => None,
+            };
+
+            if let Some(entry) = entry {
+                self.entries.insert(
+                    outcome.target.clone(),
+                    CacheEntry {
+                        path: outcome.target.clone(),
+                        ..entry
+                    },
+                );
+                self.is_changed = true;
+            }
+        }
+    }
+
+    /// The cache is written to a temporary file first, which replaces the cache, so that an interrupted write does not lose the cache.
+    /// Paths which are not valid UTF-8 cannot be cached.
+    pub(crate) fn save(&self) -> Result<(), RamboError> {
+        if self.is_changed.not() {
+            return Ok(());
+        }
+
+        let write_error_description = || format!("Failed to write extraction cache '{}'", self.cache_path.display());
+        let temporary_path = self.cache_path.with_extension("jsonl.tmp");
+
+        if let Some(cache_directory) = self.cache_path.parent()
+            && cache_directory.as_os_str().is_empty().not()
+        {
+            std::fs::create_dir_all(cache_directory).map_err(RamboError::io(write_error_description()))?;
+        }
+
+        let cache_file = File::create(&temporary_path).map_err(RamboError::io(write_error_description()))?;
+        let mut writer = BufWriter::new(cache_file);
+        for entry in self.entries.values() {
+            let Ok(line) = serde_json::to_string(entry) else {
+                continue;
+            };
+            writeln!(writer, "{}", line).map_err(RamboError::io(write_error_description()))?;
+        }
+        writer.flush().map_err(RamboError::io(write_error_description()))?;
+        drop(writer);
+
+        std::fs::rename(&temporary_path, &self.cache_path).map_err(RamboError::io(write_error_description()))
+    }
+}
+
+fn size_and_modification_time(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = std::fs::metadata(path).ok()?;
+
+    Some((metadata.len(), metadata.modified().ok()?))
+}
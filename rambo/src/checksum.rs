@@ -0,0 +1,39 @@
+use crate::error::RamboError;
+use crate::strip_path_prefix;
+
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes a manifest in the format of `sha256sum`, so it can be verified with `sha256sum -c <FILE>`.
+/// The paths are written relative to the current working directory, if possible.
+/// They are written as raw bytes, so paths which are not valid UTF-8 can still be verified.
+pub fn write_checksum_manifest(manifest_path: &Path, file_paths: &[PathBuf], current_working_directory: &Path) -> Result<(), RamboError> {
+    let manifest_file = File::create(manifest_path).map_err(RamboError::io(format!("Failed to create checksum manifest '{}'", manifest_path.display())))?;
+    let mut writer = BufWriter::new(manifest_file);
+
+    for file_path in file_paths {
+        let checksum = compute_sha256(file_path).map_err(RamboError::io(format!("Failed to compute checksum of '{}'", file_path.display())))?;
+
+        let relative_file_path = strip_path_prefix(file_path, current_working_directory);
+
+        write!(writer, "{}  ", checksum)
+            .and_then(|_| writer.write_all(relative_file_path.as_os_str().as_encoded_bytes()))
+            .and_then(|_| writeln!(writer))
+            .map_err(RamboError::io(format!("Failed to write checksum manifest '{}'", manifest_path.display())))?;
+    }
+
+    writer
+        .flush()
+        .map_err(RamboError::io(format!("Failed to write checksum manifest '{}'", manifest_path.display())))
+}
+
+fn compute_sha256(file_path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(file_path)?;
+    let mut hasher = Sha256::new();
+
+    std::io::copy(&mut file, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,76 @@
+use crate::error::RamboError;
+use crate::format_path_buf_without_prefix;
+use crate::rename::{ConflictStrategy, RenameAction, RenameOutcome};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Files collide when more than one of them should be renamed to the same target, or when the target was already taken.
+/// Returns the number of collisions that were written to the report.
+pub fn write_collisions_report(
+    report_path: &Path,
+    outcomes: &[RenameOutcome],
+    is_dry_run: bool,
+    current_working_directory: &Path,
+) -> Result<usize, RamboError> {
+    let collisions = find_collisions(outcomes);
+
+    let report_file = File::create(report_path).map_err(RamboError::io(format!("Failed to create collisions report '{}'", report_path.display())))?;
+    let mut writer = BufWriter::new(report_file);
+
+    let write_error_description = || format!("Failed to write collisions report '{}'", report_path.display());
+
+    if is_dry_run {
+        writeln!(writer, "# DRY RUN: nothing was renamed, the resolutions describe what would have happened.")
+            .map_err(RamboError::io(write_error_description()))?;
+    }
+
+    for (target, colliding_outcomes) in collisions.iter() {
+        writeln!(writer, "{}", format_path_buf_without_prefix(target, current_working_directory)).map_err(RamboError::io(write_error_description()))?;
+
+        for outcome in colliding_outcomes {
+            let resolution = match (outcome.conflict, outcome.action) {
+                (_, RenameAction::Vanished) => "vanished".to_string(),
+                (Some(ConflictStrategy::Suffix), _) => format!("counter: {}", format_path_buf_without_prefix(&outcome.target, current_working_directory)),
+                (Some(ConflictStrategy::Skip), _) => "skip".to_string(),
+                (Some(ConflictStrategy::Overwrite), _) => "overwrite".to_string(),
+                (Some(ConflictStrategy::Fail), _) => "fail".to_string(),
+                (None, RenameAction::AlreadyCorrect) => "already had this name".to_string(),
+                (None, RenameAction::Renamed) => "renamed".to_string(),
+                (None, RenameAction::Copied) => "copied".to_string(),
+                (None, RenameAction::Skipped) => "skipped".to_string(),
+                (None, RenameAction::Failed) => "failed".to_string(),
+            };
+
+            writeln!(
+                writer,
+                "    {} ({})",
+                format_path_buf_without_prefix(&outcome.source, current_working_directory),
+                resolution
+            )
+            .map_err(RamboError::io(write_error_description()))?;
+        }
+    }
+
+    writer.flush().map_err(RamboError::io(write_error_description()))?;
+
+    Ok(collisions.len())
+}
+
+fn find_collisions(outcomes: &[RenameOutcome]) -> Vec<(&PathBuf, Vec<&RenameOutcome>)> {
+    let mut outcomes_by_target = HashMap::<&PathBuf, Vec<&RenameOutcome>>::new();
+    for outcome in outcomes {
+        outcomes_by_target.entry(&outcome.requested_target).or_default().push(outcome);
+    }
+
+    let mut collisions = outcomes_by_target
+        .into_iter()
+        .filter(|(_, outcomes)| outcomes.len() > 1 || outcomes.iter().any(|outcome| outcome.conflict.is_some()))
+        .collect::<Vec<_>>();
+
+    collisions.sort_by_key(|(target, _)| *target);
+
+    collisions
+}
@@ -0,0 +1,259 @@
+use crate::RamboOptions;
+use crate::counter::CounterReset;
+use crate::error::RamboError;
+use crate::extract::{Backend, DateSource, Fallback};
+use crate::input::InputSource;
+use crate::naming::{FsCompat, UnicodeNormalization};
+use crate::rename::ConflictStrategy;
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE_NAME: &str = "rambo.toml";
+
+/// The settings at the top level of a config file apply to every run. The settings of a named profile in `[profiles.<NAME>]` take precedence over them.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(flatten)]
+    pub defaults: Profile,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// The keys are the names of the command line flags, except for `camera-shifts`, which maps the serial number or the model of a camera to a shift like `-1h37m`.
+/// Settings which are not set are left as they are.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Profile {
+    /// Either a single glob pattern or a list of them.
+    #[serde(default, deserialize_with = "deserialize_one_or_many")]
+    pub pattern: Option<Vec<String>>,
+    pub format: Option<String>,
+    pub time_offset: Option<String>,
+    pub timezone: Option<String>,
+    pub shift: Option<String>,
+    pub camera_shifts: Option<BTreeMap<String, String>>,
+    pub case_insensitive: Option<bool>,
+    pub include_symlinks: Option<bool>,
+    /// Either a single source or a list of them, which are tried in order.
+    #[serde(default, deserialize_with = "deserialize_one_or_many")]
+    pub date_source: Option<Vec<DateSource>>,
+    pub backend: Option<Backend>,
+    pub extractor_cmd: Option<String>,
+    pub fallback: Option<Vec<Fallback>>,
+    pub documents: Option<bool>,
+    pub extraction_cache: Option<PathBuf>,
+    pub on_conflict: Option<ConflictStrategy>,
+    pub use_trash: Option<bool>,
+    pub atomic: Option<bool>,
+    pub trust_existing_names: Option<bool>,
+    pub counter_reset: Option<CounterReset>,
+    pub replacement_char: Option<char>,
+    pub fs_compat: Option<FsCompat>,
+    pub normalize: Option<UnicodeNormalization>,
+    pub jobs: Option<NonZeroUsize>,
+    pub sidecars: Option<Vec<String>>,
+    pub keep_pairs: Option<bool>,
+    pub sync_mtime: Option<bool>,
+    pub fix_extensions: Option<bool>,
+    pub detect_duplicates: Option<bool>,
+    pub dry_run_hide_unchanged: Option<bool>,
+    pub store_original_xattr: Option<bool>,
+    pub fail_on_vanished: Option<bool>,
+    pub no_backup_map: Option<bool>,
+}
+
+impl Config {
+    /// Reads the user config from `$XDG_CONFIG_HOME/rambo/config.toml` (or `~/.config/rambo/config.toml`) and `rambo.toml` in the current working directory.
+    /// The settings of the latter take precedence. Missing files are ignored.
+    pub fn load(current_working_directory: &Path) -> Result<Self, RamboError> {
+        let config_paths = [user_config_path(), Some(current_working_directory.join(CONFIG_FILE_NAME))];
+
+        let mut config = Config::default();
+        for config_path in config_paths.into_iter().flatten().filter(|config_path| config_path.is_file()) {
+            log::debug!("Reading config file {}", config_path.display());
+            config.merge(Self::from_file(&config_path)?);
+        }
+
+        Ok(config)
+    }
+
+    pub fn from_file(config_path: &Path) -> Result<Self, RamboError> {
+        let content = std::fs::read_to_string(config_path).map_err(RamboError::io(format!("Failed to read config file '{}'", config_path.display())))?;
+
+        toml::from_str(&content).map_err(RamboError::invalid_file(format!("Invalid config file '{}'", config_path.display())))
+    }
+
+    /// Returns the defaults merged with the named profile, which must exist.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, RamboError> {
+        let mut profile = self.defaults.clone();
+
+        if let Some(name) = name {
+            let named_profile = self
+                .profiles
+                .get(name)
+                .ok_or_else(|| RamboError::InvalidSetting(format!("There is no profile named '{}' in the config files", name)))?;
+            profile.merge(named_profile.clone());
+        }
+
+        Ok(profile)
+    }
+
+    fn merge(&mut self, other: Config) {
+        self.defaults.merge(other.defaults);
+        for (name, other_profile) in other.profiles {
+            self.profiles.entry(name).or_default().merge(other_profile);
+        }
+    }
+}
+
+fn deserialize_one_or_many<'de, D: serde::Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Option<Vec<T>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(Option::<OneOrMany<T>>::deserialize(deserializer)?.map(|values| match values {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    }))
+}
+
+impl Profile {
+    /// The settings of `other` take precedence.
+    fn merge(&mut self, other: Profile) {
+        self.pattern = other.pattern.or(self.pattern.take());
+        self.format = other.format.or(self.format.take());
+        self.time_offset = other.time_offset.or(self.time_offset.take());
+        self.timezone = other.timezone.or(self.timezone.take());
+        self.shift = other.shift.or(self.shift.take());
+        self.camera_shifts = other.camera_shifts.or(self.camera_shifts.take());
+        self.case_insensitive = other.case_insensitive.or(self.case_insensitive);
+        self.include_symlinks = other.include_symlinks.or(self.include_symlinks);
+        self.date_source = other.date_source.or(self.date_source.take());
+        self.backend = other.backend.or(self.backend);
+        self.extractor_cmd = other.extractor_cmd.or(self.extractor_cmd.take());
+        self.fallback = other.fallback.or(self.fallback.take());
+        self.documents = other.documents.or(self.documents);
+        self.extraction_cache = other.extraction_cache.or(self.extraction_cache.take());
+        self.on_conflict = other.on_conflict.or(self.on_conflict);
+        self.use_trash = other.use_trash.or(self.use_trash);
+        self.atomic = other.atomic.or(self.atomic);
+        self.trust_existing_names = other.trust_existing_names.or(self.trust_existing_names);
+        self.counter_reset = other.counter_reset.or(self.counter_reset);
+        self.replacement_char = other.replacement_char.or(self.replacement_char);
+        self.fs_compat = other.fs_compat.or(self.fs_compat);
+        self.normalize = other.normalize.or(self.normalize);
+        self.jobs = other.jobs.or(self.jobs);
+        self.sidecars = other.sidecars.or(self.sidecars.take());
+        self.keep_pairs = other.keep_pairs.or(self.keep_pairs);
+        self.sync_mtime = other.sync_mtime.or(self.sync_mtime);
+        self.fix_extensions = other.fix_extensions.or(self.fix_extensions);
+        self.detect_duplicates = other.detect_duplicates.or(self.detect_duplicates);
+        self.dry_run_hide_unchanged = other.dry_run_hide_unchanged.or(self.dry_run_hide_unchanged);
+        self.store_original_xattr = other.store_original_xattr.or(self.store_original_xattr);
+        self.fail_on_vanished = other.fail_on_vanished.or(self.fail_on_vanished);
+        self.no_backup_map = other.no_backup_map.or(self.no_backup_map);
+    }
+}
+
+impl RamboOptions {
+    /// Applies the settings of the profile, except for the ones for which `is_set_explicitly` returns `true`, e.g. because they were given on the command line.
+    /// `is_set_explicitly` is called with the key of the setting, like `time-offset`.
+    pub fn with_profile(mut self, profile: &Profile, is_set_explicitly: impl Fn(&str) -> bool) -> Self {
+        fn apply<T: Clone>(target: &mut T, value: &Option<T>, key: &str, is_set_explicitly: &impl Fn(&str) -> bool) {
+            if let Some(value) = value
+                && is_set_explicitly(key).not()
+            {
+                *target = value.clone();
+            }
+        }
+
+        // The patterns only replace other patterns, not a directory or a file list.
+        if let InputSource::GlobPatterns(patterns) = &mut self.input {
+            apply(patterns, &profile.pattern, "pattern", &is_set_explicitly);
+        }
+        apply(&mut self.format, &profile.format, "format", &is_set_explicitly);
+        apply(&mut self.case_insensitive, &profile.case_insensitive, "case-insensitive", &is_set_explicitly);
+        apply(&mut self.include_symlinks, &profile.include_symlinks, "include-symlinks", &is_set_explicitly);
+        apply(&mut self.on_conflict, &profile.on_conflict, "on-conflict", &is_set_explicitly);
+        apply(&mut self.use_trash, &profile.use_trash, "use-trash", &is_set_explicitly);
+        apply(&mut self.atomic, &profile.atomic, "atomic", &is_set_explicitly);
+        apply(
+            &mut self.trust_existing_names,
+            &profile.trust_existing_names,
+            "trust-existing-names",
+            &is_set_explicitly,
+        );
+        apply(&mut self.counter_reset, &profile.counter_reset, "counter-reset", &is_set_explicitly);
+        apply(&mut self.replacement_char, &profile.replacement_char, "replacement-char", &is_set_explicitly);
+        apply(&mut self.fs_compat, &profile.fs_compat.map(Some), "fs-compat", &is_set_explicitly);
+        apply(&mut self.normalize, &profile.normalize, "normalize", &is_set_explicitly);
+        apply(
+            &mut self.dry_run_hide_unchanged,
+            &profile.dry_run_hide_unchanged,
+            "dry-run-hide-unchanged",
+            &is_set_explicitly,
+        );
+        apply(
+            &mut self.store_original_xattr,
+            &profile.store_original_xattr,
+            "store-original-xattr",
+            &is_set_explicitly,
+        );
+        apply(&mut self.fail_on_vanished, &profile.fail_on_vanished, "fail-on-vanished", &is_set_explicitly);
+        apply(&mut self.backup_map, &profile.no_backup_map.map(Not::not), "no-backup-map", &is_set_explicitly);
+        apply(&mut self.jobs, &profile.jobs.map(NonZeroUsize::get), "jobs", &is_set_explicitly);
+        // Both keys set the same option, so neither is applied if one of them was set explicitly.
+        if is_set_explicitly("time-offset").not() {
+            apply(
+                &mut self.time_zone,
+                &profile.timezone.clone().or(profile.time_offset.clone()).map(Some),
+                "timezone",
+                &is_set_explicitly,
+            );
+        }
+        apply(
+            &mut self.extractor_cmd,
+            &profile.extractor_cmd.clone().map(Some),
+            "extractor-cmd",
+            &is_set_explicitly,
+        );
+        apply(&mut self.shift, &profile.shift.clone().map(Some), "shift", &is_set_explicitly);
+        // There is no command line flag for the camera shifts, so they cannot be set explicitly.
+        if let Some(camera_shifts) = &profile.camera_shifts {
+            self.camera_shifts = camera_shifts.clone();
+        }
+        apply(&mut self.sidecar_extensions, &profile.sidecars, "sidecars", &is_set_explicitly);
+        apply(&mut self.keep_pairs, &profile.keep_pairs, "keep-pairs", &is_set_explicitly);
+        apply(&mut self.sync_mtime, &profile.sync_mtime, "sync-mtime", &is_set_explicitly);
+        apply(&mut self.fix_extensions, &profile.fix_extensions, "fix-extensions", &is_set_explicitly);
+        apply(&mut self.detect_duplicates, &profile.detect_duplicates, "detect-duplicates", &is_set_explicitly);
+        apply(&mut self.date_sources, &profile.date_source, "date-source", &is_set_explicitly);
+        apply(&mut self.backend, &profile.backend, "backend", &is_set_explicitly);
+        apply(&mut self.fallbacks, &profile.fallback, "fallback", &is_set_explicitly);
+        apply(&mut self.documents, &profile.documents, "documents", &is_set_explicitly);
+        apply(
+            &mut self.extraction_cache,
+            &profile.extraction_cache.clone().map(Some),
+            "extraction-cache",
+            &is_set_explicitly,
+        );
+
+        self
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let config_directory = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|config_directory| config_directory.is_empty().not())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_directory.join("rambo").join("config.toml"))
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// When the `{counter}` token starts again at 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CounterReset {
+    /// Count through the whole run.
+    #[default]
+    Never,
+    /// Count separately for the files of each directory.
+    Directory,
+    /// Count separately for the files of each day of their creation datetime.
+    Day,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum CounterScope {
+    Run,
+    Directory(PathBuf),
+    Day(NaiveDate),
+}
+
+/// Numbers the files in processing order, starting at 1 in each scope of the [CounterReset].
+#[derive(Debug)]
+pub(crate) struct Counter {
+    reset: CounterReset,
+    counts: HashMap<CounterScope, u64>,
+}
+
+impl Counter {
+    pub(crate) fn new(reset: CounterReset) -> Self {
+        Self { reset, counts: HashMap::new() }
+    }
+
+    /// The directory is the one the file is in, not the one it is moved to. The day is the one of the datetime in its own offset.
+    pub(crate) fn next(&mut self, file_path: &Path, datetime: &DateTime<FixedOffset>) -> u64 {
+        let scope = match self.reset {
+            CounterReset::Never => CounterScope::Run,
+            CounterReset::Directory => CounterScope::Directory(file_path.parent().map(Path::to_path_buf).unwrap_or_default()),
+            CounterReset::Day => CounterScope::Day(datetime.date_naive()),
+        };
+
+        let count = self.counts.entry(scope).or_default();
+        *count += 1;
+        *count
+    }
+}
@@ -0,0 +1,136 @@
+use crate::duplicates::find_duplicate_groups;
+use crate::input::InputSource;
+use crate::rename::{is_same_file, move_to_trash};
+use crate::statistics::Statistics;
+use crate::{collect_paths_or_exit, current_working_directory, format_path_buf_without_prefix, log_error};
+
+use std::ops::Not;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// What to do with the files that have the same content as another file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupeAction {
+    /// Only print the groups of identical files.
+    #[default]
+    Report,
+    /// Replace the duplicates with hard links to the kept file, which frees their space but keeps their paths.
+    Hardlink,
+    /// Move the duplicates to the trash of the system, from where they can be restored.
+    Trash,
+}
+
+pub struct DedupeOptions {
+    pub input: InputSource,
+    pub case_insensitive: bool,
+    pub include_symlinks: bool,
+    /// The maximum depth of the walked directories, 1 meaning only the files directly inside. `None` for no limit.
+    pub max_depth: Option<usize>,
+    pub action: DedupeAction,
+    /// Move the duplicates to the trash before they are replaced by a hard link, instead of replacing them right away.
+    pub use_trash: bool,
+    pub no_dry_run: bool,
+}
+
+/// Groups the files by their content. Of each group, the file with the first path in sort order is kept and the action is applied to the others.
+pub fn dedupe(options: DedupeOptions) -> ExitCode {
+    let mut statistics = Statistics::new();
+
+    let current_working_directory = match current_working_directory() {
+        Ok(current_working_directory) => current_working_directory,
+        Err(error) => return log_error(error),
+    };
+
+    let paths = match collect_paths_or_exit(
+        &options.input,
+        options.case_insensitive,
+        options.include_symlinks,
+        options.max_depth,
+        &mut statistics,
+    ) {
+        Ok(paths) => paths,
+        Err(exit_code) => return exit_code,
+    };
+
+    let is_dry_run = options.no_dry_run.not();
+    let duplicate_groups = find_duplicate_groups(&paths, &current_working_directory);
+    let mut processed_files = 0;
+
+    for duplicate_group in &duplicate_groups {
+        let Some((kept_path, duplicate_paths)) = duplicate_group.paths.split_first() else {
+            continue;
+        };
+        let clean_kept_file_name = format_path_buf_without_prefix(kept_path, &current_working_directory);
+
+        println!("{}", clean_kept_file_name);
+        for duplicate_path in duplicate_paths {
+            println!("  = {}", format_path_buf_without_prefix(duplicate_path, &current_working_directory));
+        }
+        statistics.duplicate_files += duplicate_paths.len() as u64;
+
+        for duplicate_path in duplicate_paths {
+            let clean_file_name = format_path_buf_without_prefix(duplicate_path, &current_working_directory);
+
+            let result = match options.action {
+                DedupeAction::Report => continue,
+                DedupeAction::Hardlink if is_same_file(kept_path, duplicate_path) => continue,
+                DedupeAction::Hardlink if is_dry_run => {
+                    log::info!("[DRY RUN] Hardlinking: {} ==> {}", clean_file_name, clean_kept_file_name);
+                    Ok(())
+                }
+                DedupeAction::Hardlink => replace_with_hard_link(kept_path, duplicate_path, options.use_trash)
+                    .inspect(|_| log::info!("Hardlinking: {} ==> {}", clean_file_name, clean_kept_file_name)),
+                DedupeAction::Trash if is_dry_run => {
+                    log::info!("[DRY RUN] Moving to trash: {}", clean_file_name);
+                    Ok(())
+                }
+                DedupeAction::Trash => move_to_trash(duplicate_path).inspect(|_| log::info!("Moving to trash: {}", clean_file_name)),
+            };
+
+            match result {
+                Ok(_) => processed_files += 1,
+                Err(error) => {
+                    log::warn!("Failed to deduplicate {}: {}", clean_file_name, error);
+                    statistics.failed_files += 1;
+                }
+            }
+        }
+    }
+
+    println!("==============================");
+    println!("Failed files:     {}", statistics.failed_files);
+    println!("Duplicate groups: {}", duplicate_groups.len());
+    println!("Duplicates:       {}", statistics.duplicate_files);
+    match options.action {
+        DedupeAction::Report => {}
+        DedupeAction::Hardlink => println!("Hardlinked files: {}", processed_files),
+        DedupeAction::Trash => println!("Trashed files:    {}", processed_files),
+    }
+
+    if is_dry_run && options.action != DedupeAction::Report {
+        log::warn!("This was just a dry run. To actually deduplicate the files, use the '--no-dry-run' flag.")
+    }
+
+    if statistics.failed_files > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// The hard link is created next to the duplicate first and then renamed over it, so the duplicate is never lost.
+/// With `use_trash`, the duplicate is moved to the trash right before the hard link takes its place.
+fn replace_with_hard_link(kept_path: &Path, duplicate_path: &Path, use_trash: bool) -> std::io::Result<()> {
+    let mut temporary_file_name = duplicate_path.file_name().unwrap_or_default().to_os_string();
+    temporary_file_name.push(".rambo-link");
+    let temporary_path = duplicate_path.with_file_name(temporary_file_name);
+
+    std::fs::hard_link(kept_path, &temporary_path)?;
+    if use_trash && let Err(error) = move_to_trash(duplicate_path) {
+        let _ = std::fs::remove_file(&temporary_path);
+        return Err(error);
+    }
+    std::fs::rename(&temporary_path, duplicate_path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temporary_path);
+    })
+}
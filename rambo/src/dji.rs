@@ -0,0 +1,125 @@
+use crate::error::ExtractError;
+use crate::extract::{GpsCoordinates, in_local_time_zone};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// The position is taken from the first subtitles with a GPS fix, as the drone may not have one when the recording starts.
+const MAX_SEARCHED_SUBTITLES: usize = 300;
+
+/// The capture time and position of a DJI drone video, read from the telemetry in its SRT subtitles.
+pub(crate) struct DjiTelemetry {
+    /// The clock of the drone is in local time, so the datetime is in the local time zone.
+    pub(crate) datetime: DateTime<FixedOffset>,
+    pub(crate) gps: Option<GpsCoordinates>,
+}
+
+/// DJI drones name the subtitles of `DJI_0001.MP4` `DJI_0001.SRT`.
+pub(crate) fn find_srt_sidecar(media_file_path: &Path) -> Option<PathBuf> {
+    let media_file_name = media_file_path.file_name()?;
+
+    ["SRT", "srt"]
+        .map(|extension| media_file_path.with_extension(extension))
+        .into_iter()
+        .find(|sidecar_path| sidecar_path.file_name() != Some(media_file_name) && sidecar_path.is_file())
+}
+
+/// Returns [None] if the file has no SRT sidecar.
+/// The datetime of the first subtitle is moved back by its start within the video, so it is the start of the recording.
+pub(crate) fn read_dji_telemetry(media_file_path: &Path) -> Option<Result<DjiTelemetry, ExtractError>> {
+    let sidecar_path = find_srt_sidecar(media_file_path)?;
+
+    let result = std::fs::read(&sidecar_path)
+        .map_err(ExtractError::io(format!("Failed to read the SRT sidecar '{}'", sidecar_path.display())))
+        .and_then(|content| parse_dji_telemetry(&String::from_utf8_lossy(&content)));
+
+    Some(result)
+}
+
+/// Each subtitle is a number, a time range like `00:00:00,000 --> 00:00:00,033` and the telemetry, which depends on the model:
+/// newer drones write `2024-05-01 10:00:00.123` and `[latitude: 47.123456] [longitude: 8.123456]` (some `longtitude`),
+/// older ones `GPS(8.123456,47.123456,19)` and `2017.08.05 14:11:51`.
+fn parse_dji_telemetry(content: &str) -> Result<DjiTelemetry, ExtractError> {
+    let subtitles = content
+        .split("\n\n")
+        .flat_map(|subtitles| subtitles.split("\r\n\r\n"))
+        .filter(|subtitle| subtitle.trim().is_empty().not())
+        .take(MAX_SEARCHED_SUBTITLES)
+        .collect::<Vec<_>>();
+
+    let (start, naive_datetime) = subtitles
+        .iter()
+        .find_map(|subtitle| parse_datetime(subtitle).map(|naive_datetime| (parse_start(subtitle).unwrap_or_default(), naive_datetime)))
+        .ok_or(ExtractError::MissingDatetime("DJI SRT"))?;
+
+    Ok(DjiTelemetry {
+        datetime: in_local_time_zone(naive_datetime - start)?,
+        gps: subtitles.iter().find_map(|subtitle| parse_gps(subtitle)),
+    })
+}
+
+/// The start of the subtitle within the video, like `00:00:01,500` of `00:00:01,500 --> 00:00:02,000`.
+fn parse_start(subtitle: &str) -> Option<TimeDelta> {
+    let (start, _) = subtitle.lines().find_map(|line| line.split_once("-->"))?;
+    let start = NaiveTime::parse_from_str(start.trim(), "%H:%M:%S,%3f").ok()?;
+
+    Some(start - NaiveTime::MIN)
+}
+
+/// The date and the time are the first two words which parse as such, ignoring markup like `<font size="28">`.
+/// Some models write microseconds, like `10:00:00,123,456`, of which only the milliseconds are kept.
+fn parse_datetime(subtitle: &str) -> Option<NaiveDateTime> {
+    let words = subtitle
+        .split_whitespace()
+        .map(|word| word.rsplit('>').next().unwrap_or(word))
+        .collect::<Vec<_>>();
+
+    words.windows(2).find_map(|words| {
+        let date = NaiveDate::parse_from_str(words[0], "%Y-%m-%d")
+            .or_else(|_| NaiveDate::parse_from_str(words[0], "%Y.%m.%d"))
+            .ok()?;
+
+        let (time, fraction) = words[1].split_once(['.', ',']).unwrap_or((words[1], ""));
+        let time = NaiveTime::parse_from_str(time, "%H:%M:%S").ok()?;
+        let milliseconds = fraction
+            .get(..3)
+            .filter(|milliseconds| milliseconds.bytes().all(|byte| byte.is_ascii_digit()))
+            .and_then(|milliseconds| milliseconds.parse::<i64>().ok())
+            .unwrap_or_default();
+
+        Some(date.and_time(time) + TimeDelta::milliseconds(milliseconds))
+    })
+}
+
+/// Returns [None] without a GPS fix, for which the drone writes a position of `0, 0`.
+fn parse_gps(subtitle: &str) -> Option<GpsCoordinates> {
+    let (latitude, longitude) = match (
+        value_after(subtitle, "latitude"),
+        value_after(subtitle, "longitude").or_else(|| value_after(subtitle, "longtitude")),
+    ) {
+        (Some(latitude), Some(longitude)) => (latitude, longitude),
+        _ => {
+            let (_, rest) = subtitle.split_once("GPS")?;
+            let (values, _) = rest.trim_start().strip_prefix('(')?.split_once(')')?;
+            let mut values = values.split(',').map(|value| value.trim().parse::<f64>().ok());
+            let longitude = values.next()??;
+            let latitude = values.next()??;
+            (latitude, longitude)
+        }
+    };
+
+    let is_valid = (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude) && (latitude != 0.0 || longitude != 0.0);
+    is_valid.then_some(GpsCoordinates { latitude, longitude })
+}
+
+/// The number following the key, like `47.123456` of `[latitude: 47.123456]` or `[latitude : 47.123456]`.
+fn value_after(subtitle: &str, key: &str) -> Option<f64> {
+    let (_, rest) = subtitle.split_once(key)?;
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let value_length = rest
+        .find(|character: char| (character.is_ascii_digit() || character == '.' || character == '-').not())
+        .unwrap_or(rest.len());
+
+    rest[..value_length].parse().ok()
+}
@@ -0,0 +1,313 @@
+use crate::error::ExtractError;
+use crate::extract::{DatetimeSource, ExtractedDatetime, extract_creation_datetime_from_xmp, in_local_time_zone, parse_xmp_datetime};
+use crate::raw::{invalid_data, read_up_to};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate};
+use flate2::read::DeflateDecoder;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Not;
+
+/// The document information dictionary of a PDF is usually near its end, where the trailer refers to it, or near its start in linearized files,
+/// so only these parts are searched, so that large scans are not read entirely.
+const PDF_SEARCH_LENGTH: u64 = 1024 * 1024;
+
+/// The core properties of an office document are a few hundred bytes, so larger entries are not read.
+const MAX_ZIP_ENTRY_LENGTH: u32 = 1 << 20;
+
+/// The end of central directory record of a ZIP file is followed by a comment of at most 65535 bytes.
+const MAX_END_OF_CENTRAL_DIRECTORY_LENGTH: u64 = 22 + 0xFFFF;
+
+const MAX_CENTRAL_DIRECTORY_LENGTH: u32 = 16 << 20;
+
+/// The part of an OOXML document, like a `.docx`, `.xlsx` or `.pptx` file, with its core properties.
+const OOXML_CORE_PROPERTIES: &str = "docProps/core.xml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DocumentContainer {
+    Pdf,
+    /// A ZIP file, which is only an OOXML document if it has core properties.
+    Zip,
+}
+
+/// Whether the file is a PDF or an OOXML document, by its magic bytes and, for ZIP files, its core properties. The reader is rewound afterwards.
+pub(crate) fn is_document<R: Read + Seek>(reader: &mut R) -> bool {
+    let is_document = match detect_document_container(reader) {
+        Some(DocumentContainer::Pdf) => true,
+        Some(DocumentContainer::Zip) => find_zip_entry(reader, OOXML_CORE_PROPERTIES).is_ok_and(|entry| entry.is_some()),
+        None => false,
+    };
+    let _ = reader.seek(SeekFrom::Start(0));
+
+    is_document
+}
+
+/// Returns [None] if the file is neither a PDF nor an OOXML document.
+pub(crate) fn extract_creation_datetime_from_document<R: Read + Seek>(reader: &mut R) -> Option<Result<ExtractedDatetime, ExtractError>> {
+    let extracted_datetime = match detect_document_container(reader)? {
+        DocumentContainer::Pdf => read_pdf_creation_datetime(reader),
+        DocumentContainer::Zip => match read_zip_entry(reader, OOXML_CORE_PROPERTIES) {
+            Ok(Some(core_properties)) => Ok(read_ooxml_creation_datetime(&String::from_utf8_lossy(&core_properties))),
+            Ok(None) => return None,
+            Err(error) => Err(error),
+        },
+    };
+
+    Some(
+        extracted_datetime
+            .map_err(ExtractError::io("Cannot read the document"))
+            .and_then(|extracted_datetime| extracted_datetime),
+    )
+}
+
+fn detect_document_container<R: Read + Seek>(reader: &mut R) -> Option<DocumentContainer> {
+    let mut header = [0u8; 5];
+    let header_length = read_up_to(reader, &mut header).unwrap_or_default();
+    let _ = reader.seek(SeekFrom::Start(0));
+
+    if header_length == header.len() && &header == b"%PDF-" {
+        Some(DocumentContainer::Pdf)
+    } else if header_length >= 4 && header.starts_with(b"PK\x03\x04") {
+        Some(DocumentContainer::Zip)
+    } else {
+        None
+    }
+}
+
+/// Documents in UTC, like most office documents, are converted to the local time zone, like the creation datetimes of videos.
+fn utc_to_local(datetime: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+    if datetime.offset().local_minus_utc() == 0 {
+        datetime.with_timezone(&Local).fixed_offset()
+    } else {
+        datetime
+    }
+}
+
+/// The `/CreationDate` of the document information dictionary is preferred, with the `xmp:CreateDate` of the XMP metadata of the document as fallback.
+fn read_pdf_creation_datetime<R: Read + Seek>(reader: &mut R) -> std::io::Result<Result<ExtractedDatetime, ExtractError>> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    let tail_start = end.saturating_sub(PDF_SEARCH_LENGTH);
+
+    let mut tail = Vec::new();
+    reader.seek(SeekFrom::Start(tail_start))?;
+    reader.by_ref().take(PDF_SEARCH_LENGTH).read_to_end(&mut tail)?;
+
+    let mut head = Vec::new();
+    if tail_start > 0 {
+        reader.seek(SeekFrom::Start(0))?;
+        reader.by_ref().take(PDF_SEARCH_LENGTH.min(tail_start)).read_to_end(&mut head)?;
+    }
+
+    // Incremental updates append a new dictionary, so the last one is the current one.
+    let creation_date = [&tail, &head].into_iter().find_map(|part| {
+        let marker_start = part.windows(b"/CreationDate".len()).rposition(|window| window == b"/CreationDate")?;
+        read_pdf_string(&part[marker_start + b"/CreationDate".len()..])
+    });
+    if let Some(creation_date) = creation_date {
+        return Ok(parse_pdf_date(&creation_date)
+            .map(|datetime| ExtractedDatetime {
+                datetime,
+                source: DatetimeSource::DocumentProperty("CreationDate"),
+            })
+            .ok_or_else(|| ExtractError::InvalidDatetime(format!("The PDF creation date '{}' is not a date!", creation_date))));
+    }
+
+    let head_and_tail = format!("{}{}", String::from_utf8_lossy(&head), String::from_utf8_lossy(&tail));
+    Ok(extract_creation_datetime_from_xmp(&head_and_tail)
+        .map(|datetime| ExtractedDatetime {
+            datetime: utc_to_local(datetime),
+            source: DatetimeSource::DocumentProperty("xmp:CreateDate"),
+        })
+        .map_err(|_| ExtractError::MissingDatetime("PDF")))
+}
+
+/// A PDF string is either literal, like `(D:20240501100000+02'00')`, or hexadecimal, like `<FEFF0044003A...>`, which may be UTF-16 with a byte order mark.
+fn read_pdf_string(data: &[u8]) -> Option<String> {
+    let data = &data[data.iter().position(|byte| byte.is_ascii_whitespace().not())?..];
+
+    match data.first()? {
+        b'(' => {
+            let length = data.iter().position(|&byte| byte == b')')?;
+            Some(String::from_utf8_lossy(&data[1..length]).into_owned())
+        }
+        b'<' => {
+            let length = data.iter().position(|&byte| byte == b'>')?;
+            let hex_digits = data[1..length].iter().filter(|byte| byte.is_ascii_hexdigit()).copied().collect::<Vec<_>>();
+            let bytes = hex_digits
+                .chunks(2)
+                .map(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok())
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(match bytes.strip_prefix(&[0xFE, 0xFF]) {
+                Some(utf16) => {
+                    let code_units = utf16.chunks_exact(2).map(|code_unit| u16::from_be_bytes([code_unit[0], code_unit[1]]));
+                    char::decode_utf16(code_units)
+                        .map(|character| character.unwrap_or(char::REPLACEMENT_CHARACTER))
+                        .collect()
+                }
+                None => bytes.iter().map(|&byte| char::from(byte)).collect(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Like `D:20240501100000+02'00'`, where everything after the year is optional and the time zone may be `Z` for UTC.
+/// Without a time zone, the local time zone is assumed. Dates without at least a day are no creation dates.
+fn parse_pdf_date(value: &str) -> Option<DateTime<FixedOffset>> {
+    let value = value.trim();
+    let value = value.strip_prefix("D:").unwrap_or(value);
+    let digits_length = value.find(|character: char| character.is_ascii_digit().not()).unwrap_or(value.len());
+    let (digits, time_zone) = value.split_at(digits_length);
+    if digits.len() < 8 || digits.len() > 14 || digits.len() % 2 != 0 {
+        return None;
+    }
+
+    let number = |range: std::ops::Range<usize>| digits.get(range).map_or(Some(0), |number| number.parse::<u32>().ok());
+    let naive_datetime = NaiveDate::from_ymd_opt(i32::try_from(number(0..4)?).ok()?, number(4..6)?, number(6..8)?)?.and_hms_opt(
+        number(8..10)?,
+        number(10..12)?,
+        number(12..14)?,
+    )?;
+
+    let time_zone = time_zone.replace('\'', "");
+    let offset_seconds = match time_zone.chars().next() {
+        None => return in_local_time_zone(naive_datetime).ok(),
+        Some('Z') => 0,
+        Some(sign @ ('+' | '-')) => {
+            let hours = time_zone.get(1..3)?.parse::<i32>().ok()?;
+            let minutes = time_zone.get(3..5).map_or(Some(0), |minutes| minutes.parse::<i32>().ok())?;
+            let offset_seconds = hours * 3600 + minutes * 60;
+            if sign == '-' {
+                -offset_seconds
+            } else {
+                offset_seconds
+            }
+        }
+        Some(_) => return None,
+    };
+
+    FixedOffset::east_opt(offset_seconds)
+        .and_then(|offset| naive_datetime.and_local_timezone(offset).single())
+        .map(utc_to_local)
+}
+
+/// The core properties have the creation datetime in `dcterms:created`, like `<dcterms:created xsi:type="dcterms:W3CDTF">2024-05-01T08:00:00Z</dcterms:created>`.
+fn read_ooxml_creation_datetime(core_properties: &str) -> Result<ExtractedDatetime, ExtractError> {
+    let (_, rest) = core_properties.split_once("<dcterms:created").ok_or(ExtractError::MissingDatetime("OOXML"))?;
+    let value = rest
+        .split_once('>')
+        .and_then(|(_, rest)| rest.split_once('<'))
+        .map(|(value, _)| value.trim())
+        .ok_or(ExtractError::MissingDatetime("OOXML"))?;
+
+    parse_xmp_datetime(value)
+        .map(|datetime| ExtractedDatetime {
+            datetime: utc_to_local(datetime),
+            source: DatetimeSource::DocumentProperty("dcterms:created"),
+        })
+        .map_err(|error| ExtractError::InvalidDatetime(format!("The OOXML creation datetime '{}' is not a datetime!: {}", value, error)))
+}
+
+/// An entry of the central directory of a ZIP file.
+struct ZipEntry {
+    compression_method: u16,
+    compressed_length: u32,
+    uncompressed_length: u32,
+    local_header_offset: u32,
+}
+
+impl ZipEntry {
+    /// The fields of the entry follow its signature, the versions, the flags and the modification time.
+    fn read(entry: &[u8]) -> Option<Self> {
+        Some(Self {
+            compression_method: le_u16(entry, 10)?,
+            compressed_length: le_u32(entry, 20)?,
+            uncompressed_length: le_u32(entry, 24)?,
+            local_header_offset: le_u32(entry, 42)?,
+        })
+    }
+}
+
+/// The end of central directory record, which is at the end of the file before the comment, has the length and the offset of the central directory,
+/// which lists the entries with their names. ZIP64 files are not supported, as office documents are far smaller.
+fn find_zip_entry<R: Read + Seek>(reader: &mut R, name: &str) -> std::io::Result<Option<ZipEntry>> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    let tail_start = end.saturating_sub(MAX_END_OF_CENTRAL_DIRECTORY_LENGTH);
+    let mut tail = Vec::new();
+    reader.seek(SeekFrom::Start(tail_start))?;
+    reader.by_ref().take(MAX_END_OF_CENTRAL_DIRECTORY_LENGTH).read_to_end(&mut tail)?;
+
+    let Some(record_start) = tail.windows(4).rposition(|window| window == b"PK\x05\x06") else {
+        return Ok(None);
+    };
+    let record = &tail[record_start..];
+    let (Some(central_directory_length), Some(central_directory_offset)) = (le_u32(record, 12), le_u32(record, 16)) else {
+        return Ok(None);
+    };
+    if central_directory_length > MAX_CENTRAL_DIRECTORY_LENGTH {
+        return Err(invalid_data("The central directory of the ZIP file is too large"));
+    }
+
+    let mut central_directory = vec![0u8; central_directory_length as usize];
+    reader.seek(SeekFrom::Start(u64::from(central_directory_offset)))?;
+    reader.read_exact(&mut central_directory)?;
+
+    let mut offset = 0;
+    while central_directory.get(offset..offset + 4) == Some(&b"PK\x01\x02"[..]) {
+        let field = |position: usize| le_u16(&central_directory, offset + position).map(usize::from);
+        let (Some(name_length), Some(extra_length), Some(comment_length)) = (field(28), field(30), field(32)) else {
+            break;
+        };
+
+        if central_directory.get(offset + 46..offset + 46 + name_length) == Some(name.as_bytes()) {
+            return Ok(ZipEntry::read(&central_directory[offset..]));
+        }
+        offset += 46 + name_length + extra_length + comment_length;
+    }
+
+    Ok(None)
+}
+
+/// The data of an entry follows its local header, whose name and extra field may differ from the ones of the central directory.
+/// Entries are either stored or deflated.
+fn read_zip_entry<R: Read + Seek>(reader: &mut R, name: &str) -> std::io::Result<Option<Vec<u8>>> {
+    let Some(entry) = find_zip_entry(reader, name)? else {
+        return Ok(None);
+    };
+    if entry.compressed_length > MAX_ZIP_ENTRY_LENGTH || entry.uncompressed_length > MAX_ZIP_ENTRY_LENGTH {
+        return Err(invalid_data("The ZIP entry is too large"));
+    }
+
+    let mut local_header = [0u8; 30];
+    reader.seek(SeekFrom::Start(u64::from(entry.local_header_offset)))?;
+    reader.read_exact(&mut local_header)?;
+    if local_header.starts_with(b"PK\x03\x04").not() {
+        return Err(invalid_data("The local header of the ZIP entry is invalid"));
+    }
+    let name_length = u64::from(u16::from_le_bytes([local_header[26], local_header[27]]));
+    let extra_length = u64::from(u16::from_le_bytes([local_header[28], local_header[29]]));
+
+    let mut compressed_data = vec![0u8; entry.compressed_length as usize];
+    reader.seek(SeekFrom::Current((name_length + extra_length) as i64))?;
+    reader.read_exact(&mut compressed_data)?;
+
+    match entry.compression_method {
+        0 => Ok(Some(compressed_data)),
+        8 => {
+            let mut data = Vec::with_capacity(entry.uncompressed_length as usize);
+            DeflateDecoder::new(compressed_data.as_slice())
+                .take(u64::from(MAX_ZIP_ENTRY_LENGTH))
+                .read_to_end(&mut data)?;
+            Ok(Some(data))
+        }
+        _ => Err(invalid_data("The ZIP entry is compressed with an unsupported method")),
+    }
+}
+
+fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
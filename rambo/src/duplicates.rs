@@ -0,0 +1,79 @@
+use crate::format_path_buf_without_prefix;
+use crate::output::serialize_paths_lossy;
+use crate::rename::{RenameAction, RenameOutcome};
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Files of a run with the same content.
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    #[serde(serialize_with = "serialize_paths_lossy")]
+    pub paths: Vec<PathBuf>,
+}
+
+/// Finds the files of the run with the same content, at the paths where they are after the run (or still are in a dry run).
+pub(crate) fn find_duplicates(rename_outcomes: &[RenameOutcome], is_dry_run: bool, current_working_directory: &Path) -> Vec<DuplicateGroup> {
+    let paths = rename_outcomes
+        .iter()
+        .filter_map(|rename_outcome| match rename_outcome.action {
+            RenameAction::Vanished => None,
+            RenameAction::Renamed | RenameAction::Copied if is_dry_run.not() => Some(rename_outcome.target.clone()),
+            _ => Some(rename_outcome.source.clone()),
+        })
+        .collect::<Vec<_>>();
+
+    find_duplicate_groups(&paths, current_working_directory)
+}
+
+/// Only files of the same size are hashed. The paths of each group are sorted.
+pub(crate) fn find_duplicate_groups(paths: &[PathBuf], current_working_directory: &Path) -> Vec<DuplicateGroup> {
+    let mut paths_by_size = BTreeMap::<u64, Vec<PathBuf>>::new();
+    for path in paths {
+        match path.metadata() {
+            Ok(metadata) => paths_by_size.entry(metadata.len()).or_default().push(path.clone()),
+            Err(error) => log::warn!(
+                "Cannot check {} for duplicates: {}",
+                format_path_buf_without_prefix(path, current_working_directory),
+                error
+            ),
+        }
+    }
+
+    let mut paths_by_hash = BTreeMap::<u128, Vec<PathBuf>>::new();
+    for path in paths_by_size.into_values().filter(|paths| paths.len() > 1).flatten() {
+        match compute_xxh3(&path) {
+            Ok(hash) => paths_by_hash.entry(hash).or_default().push(path),
+            Err(error) => log::warn!(
+                "Cannot check {} for duplicates: {}",
+                format_path_buf_without_prefix(&path, current_working_directory),
+                error
+            ),
+        }
+    }
+
+    let mut duplicate_groups = paths_by_hash
+        .into_values()
+        .filter(|paths| paths.len() > 1)
+        .map(|mut paths| {
+            paths.sort();
+            DuplicateGroup { paths }
+        })
+        .collect::<Vec<_>>();
+    duplicate_groups.sort_by(|group, other_group| group.paths.cmp(&other_group.paths));
+
+    duplicate_groups
+}
+
+pub(crate) fn compute_xxh3(file_path: &Path) -> std::io::Result<u128> {
+    let mut file = File::open(file_path)?;
+    let mut hasher = Xxh3::new();
+
+    std::io::copy(&mut file, &mut hasher)?;
+
+    Ok(hasher.digest128())
+}
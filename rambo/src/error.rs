@@ -0,0 +1,116 @@
+use crate::storage::StorageError;
+
+/// Why a run cannot be carried out at all. Problems with single files are reported in the [crate::RunReport] instead.
+#[derive(Debug, thiserror::Error)]
+pub enum RamboError {
+    /// The current working directory cannot be determined.
+    #[error("Cannot determine current working directory: {0}")]
+    CurrentWorkingDirectory(#[source] std::io::Error),
+    /// A setting, like the format, the time zone or a date, is invalid.
+    #[error("{0}")]
+    InvalidSetting(String),
+    /// The files cannot be collected, e.g. because of a malformed glob pattern.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// A file, like the config file, a template or a report, cannot be read or written.
+    #[error("{description}: {source}")]
+    Io {
+        description: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The content of a file, like the config file or a plan, is invalid.
+    #[error("{description}: {source}")]
+    InvalidFile {
+        description: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The object storage cannot be accessed, e.g. because the bucket does not exist or the credentials are invalid.
+    #[error("{description}: {source}")]
+    Storage {
+        description: String,
+        #[source]
+        source: StorageError,
+    },
+    /// The camera or phone cannot be accessed, e.g. because it is not connected or its storage is locked.
+    #[error("{description}: {source}")]
+    Device {
+        description: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+impl RamboError {
+    pub(crate) fn io(description: impl Into<String>) -> impl FnOnce(std::io::Error) -> Self {
+        let description = description.into();
+        move |source| RamboError::Io { description, source }
+    }
+
+    pub(crate) fn invalid_file<E: std::error::Error + Send + Sync + 'static>(description: impl Into<String>) -> impl FnOnce(E) -> Self {
+        let description = description.into();
+        move |source| RamboError::InvalidFile {
+            description,
+            source: Box::new(source),
+        }
+    }
+}
+
+/// Why the creation datetime of a file could not be extracted.
+#[derive(Debug, thiserror::Error)]
+pub enum ExtractError {
+    /// The file has neither EXIF data nor tracks.
+    #[error("The media source has no EXIF or track data!")]
+    NoMetadata,
+    /// The metadata of the file, like its EXIF data, contains no creation datetime.
+    #[error("Could not get the creation datetime from {0} data!")]
+    MissingDatetime(&'static str),
+    /// The source has no creation datetime at all, e.g. because there is no sidecar or the file name matches no known pattern.
+    #[error("{0}")]
+    Unavailable(String),
+    /// A value, like the output of the external extractor, is no valid datetime.
+    #[error("{0}")]
+    InvalidDatetime(String),
+    /// The external extractor could not be run or failed.
+    #[error("{0}")]
+    ExternalCommand(String),
+    /// The file or its sidecar cannot be read.
+    #[error("{description}: {source}")]
+    Io {
+        description: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The file or its sidecar cannot be parsed.
+    #[error("{description}: {source}")]
+    Parse {
+        description: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The error of a custom [crate::DatetimeExtractor].
+    #[error("{0}")]
+    Other(Box<dyn std::error::Error + Send + Sync>),
+    /// All extractors of an [crate::ExtractorChain] failed. The errors of the extractors after the first one are listed with their description.
+    #[error("{first}{}", others.iter().map(|(description, error)| format!(" ({}: {})", description, error)).collect::<String>())]
+    Chain {
+        first: Box<ExtractError>,
+        others: Vec<(String, ExtractError)>,
+    },
+}
+
+impl ExtractError {
+    pub(crate) fn io(description: impl Into<String>) -> impl FnOnce(std::io::Error) -> Self {
+        let description = description.into();
+        move |source| ExtractError::Io { description, source }
+    }
+
+    pub(crate) fn parse<E: std::error::Error + Send + Sync + 'static>(description: impl Into<String>) -> impl FnOnce(E) -> Self {
+        let description = description.into();
+        move |source| ExtractError::Parse {
+            description,
+            source: Box::new(source),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::RamboOptions;
+use crate::output::FileOutcome;
+use crate::rename::RenameAction;
+
+use chrono::{DateTime, FixedOffset};
+use std::path::Path;
+
+/// What happens to a single file during a run, in the order the files are processed.
+#[derive(Debug, Clone, Copy)]
+pub enum RunEvent<'a> {
+    /// The file was collected from the input and will be processed, unless it is skipped by the filters.
+    FileMatched { path: &'a Path },
+    /// The creation datetime was extracted, before it is shifted or converted into the target time zone.
+    DatetimeExtracted { path: &'a Path, datetime: DateTime<FixedOffset> },
+    /// The file was renamed, copied or already had the correct name, see [FileOutcome::action]. In a dry run, nothing was actually changed.
+    Renamed(&'a FileOutcome),
+    /// The file was skipped, e.g. because of the filters, the date range or a conflict.
+    Skipped(&'a FileOutcome),
+    /// The file vanished between matching and renaming.
+    Vanished(&'a FileOutcome),
+    /// The creation datetime could not be extracted or the file could not be renamed, see [FileOutcome::error].
+    Failed(&'a FileOutcome),
+}
+
+impl<'a> RunEvent<'a> {
+    pub(crate) fn from_file_outcome(file_outcome: &'a FileOutcome) -> Self {
+        match file_outcome.action {
+            RenameAction::AlreadyCorrect | RenameAction::Renamed | RenameAction::Copied => RunEvent::Renamed(file_outcome),
+            RenameAction::Skipped => RunEvent::Skipped(file_outcome),
+            RenameAction::Vanished => RunEvent::Vanished(file_outcome),
+            RenameAction::Failed => RunEvent::Failed(file_outcome),
+        }
+    }
+}
+
+/// Receives the [RunEvent]s of a run, e.g. to show the progress of each file in a GUI. The events are handled on the thread of the run.
+/// Closures which take a [RunEvent] are handlers as well.
+pub trait EventHandler: Send + Sync {
+    fn handle_event(&self, event: &RunEvent);
+}
+
+impl<F: Fn(&RunEvent) + Send + Sync> EventHandler for F {
+    fn handle_event(&self, event: &RunEvent) {
+        self(event)
+    }
+}
+
+impl RamboOptions {
+    pub(crate) fn emit_event(&self, event: RunEvent) {
+        if let Some(event_handler) = &self.event_handler {
+            event_handler.handle_event(&event);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::error::ExtractError;
+use crate::extract::{GpsCoordinates, MediaMetadata, in_local_time_zone};
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Timelike};
+use serde_json::{Map, Value};
+use std::ops::Not;
+use std::path::Path;
+use std::process::Command;
+
+/// The program which is run, if no other one is configured. It must be installed by the user.
+pub const DEFAULT_EXIFTOOL_PROGRAM: &str = "exiftool";
+
+/// The tags which may hold the creation datetime, in the order they are tried.
+/// The `SubSec*` tags are composites of exiftool, which include the fraction of a second and the offset, if the file has them.
+const DATETIME_TAGS: [&str; 6] = [
+    "SubSecDateTimeOriginal",
+    "DateTimeOriginal",
+    "SubSecCreateDate",
+    "CreateDate",
+    "MediaCreateDate",
+    "TrackCreateDate",
+];
+
+/// Applied to a creation datetime without an offset, in this order. Without any of them, it is interpreted in the local time zone.
+const OFFSET_TAGS: [&str; 2] = ["OffsetTimeOriginal", "OffsetTime"];
+
+const OTHER_TAGS: [&str; 8] = ["Make", "Model", "SerialNumber", "LensModel", "ISO", "FNumber", "ExposureTime", "GPSPosition"];
+
+/// Runs exiftool on the file and reads the creation datetime and the other metadata from its JSON output.
+/// exiftool reads many formats which the built-in parser cannot, like obscure RAW formats or files with damaged headers, but it is run once per file, so it is much slower.
+/// The dates of QuickTime videos, which are stored in UTC, are converted into the local time zone, like cameras and phones show them.
+pub(crate) fn extract_metadata_with_exiftool(program: &str, file_path: &Path) -> Result<MediaMetadata, ExtractError> {
+    let output = Command::new(program)
+        .args(["-json", "-n", "-api", "QuickTimeUTC=1"])
+        .args(DATETIME_TAGS.iter().chain(&OFFSET_TAGS).chain(&OTHER_TAGS).map(|tag| format!("-{}", tag)))
+        .arg("--")
+        .arg(file_path)
+        .output()
+        .map_err(ExtractError::io(format!("Failed to run '{}', make sure exiftool is installed!", program)))?;
+
+    if output.status.success().not() {
+        return Err(ExtractError::ExternalCommand(format!(
+            "exiftool exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let mut files = serde_json::from_slice::<Vec<Map<String, Value>>>(&output.stdout).map_err(ExtractError::parse("The output of exiftool is invalid!"))?;
+    let tags = files
+        .pop()
+        .ok_or_else(|| ExtractError::ExternalCommand("exiftool returned no metadata!".to_string()))?;
+
+    let offset = OFFSET_TAGS
+        .iter()
+        .filter_map(|tag| text_tag(&tags, tag))
+        .find_map(|offset| DateTime::parse_from_rfc3339(&format!("2000-01-01T00:00:00{}", offset)).ok())
+        .map(|datetime| *datetime.offset());
+    let datetime = DATETIME_TAGS
+        .iter()
+        .filter_map(|tag| text_tag(&tags, tag))
+        .find_map(|value| parse_exiftool_datetime(&value, offset))
+        .ok_or(ExtractError::MissingDatetime("exiftool"))??;
+
+    Ok(MediaMetadata {
+        datetime,
+        camera_make: text_tag(&tags, "Make"),
+        camera_model: text_tag(&tags, "Model"),
+        camera_serial: text_tag(&tags, "SerialNumber"),
+        lens_model: text_tag(&tags, "LensModel"),
+        iso: tags.get("ISO").and_then(Value::as_f64).map(|iso| iso.round() as u32).filter(|iso| *iso > 0),
+        f_number: tags.get("FNumber").and_then(Value::as_f64).filter(|f_number| *f_number > 0.0),
+        exposure_time: tags.get("ExposureTime").and_then(Value::as_f64).filter(|exposure_time| *exposure_time > 0.0),
+        gps: text_tag(&tags, "GPSPosition").and_then(|position| parse_gps_position(&position)),
+        has_subseconds: datetime.nanosecond() != 0,
+        extractor: None,
+    })
+}
+
+/// Like `2024:05:01 18:30:00`, optionally followed by a fraction of a second and an offset like `+02:00` or `Z`.
+/// Returns [None] for values which are no datetime, like the `0000:00:00 00:00:00` of unset tags, so that the next tag is tried.
+fn parse_exiftool_datetime(value: &str, offset: Option<FixedOffset>) -> Option<Result<DateTime<FixedOffset>, ExtractError>> {
+    let value = value.trim().replace('Z', "+00:00");
+
+    if let Ok(datetime) = DateTime::parse_from_str(&value, "%Y:%m:%d %H:%M:%S%.f%:z") {
+        return Some(Ok(datetime));
+    }
+
+    let naive_datetime = NaiveDateTime::parse_from_str(&value, "%Y:%m:%d %H:%M:%S%.f").ok()?;
+    Some(match offset {
+        Some(offset) => naive_datetime
+            .and_local_timezone(offset)
+            .single()
+            .ok_or_else(|| ExtractError::InvalidDatetime(format!("The datetime {} of exiftool is invalid!", value))),
+        None => in_local_time_zone(naive_datetime),
+    })
+}
+
+/// With `-n`, the position is the signed latitude and longitude in decimal degrees, separated by a space.
+fn parse_gps_position(position: &str) -> Option<GpsCoordinates> {
+    let (latitude, longitude) = position.split_once(' ')?;
+
+    Some(GpsCoordinates {
+        latitude: latitude.trim().parse().ok()?,
+        longitude: longitude.trim().parse().ok()?,
+    })
+}
+
+/// exiftool writes values which look like numbers as numbers, like some serial numbers.
+fn text_tag(tags: &Map<String, Value>, tag: &str) -> Option<String> {
+    match tags.get(tag)? {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+    .filter(|text| text.is_empty().not())
+}
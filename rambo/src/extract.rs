@@ -1,46 +1,702 @@
-use anyhow::Context;
-use chrono::{DateTime, FixedOffset};
-use nom_exif::{Exif, ExifIter, ExifTag, MediaParser, MediaSource, TrackInfo, TrackInfoTag};
+use crate::audio::{extract_creation_datetime_from_audio, extract_metadata_from_audio};
+use crate::avchd::{extract_creation_datetime_from_transport_stream, extract_metadata_from_transport_stream};
+use crate::error::ExtractError;
+use crate::gpmf::read_gpmf_capture;
+use crate::image::{extract_creation_datetime_from_image, extract_exif_tag_datetime_from_image, extract_metadata_from_image};
+use crate::raw::{extract_creation_datetime_from_raw, extract_exif_tag_datetime_from_raw, extract_metadata_from_raw};
+use crate::sidecar::find_xmp_sidecar;
+
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Timelike};
+use nom_exif::{EntryValue, Exif, ExifIter, ExifTag, GPSInfo, LatLng, MediaParser, MediaSource, TrackInfo, TrackInfoTag};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
 use std::fs::File;
+use std::io::{Read, Seek};
+use std::ops::Not;
+use std::path::Path;
+use std::process::Command;
+
+/// Sources of the creation datetime, which are used when it cannot be extracted from the metadata of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fallback {
+    /// Parse the creation datetime from well-known file name patterns of cameras and phones, of the screenshots of macOS, Windows, Android and GNOME
+    /// and of the exports of WhatsApp, Signal and Telegram.
+    Name,
+    /// Read the creation datetime from the JSON sidecar of a Google Takeout export. The sidecar is renamed along with its media file.
+    Takeout,
+    /// Use the modification time of the file, in the local time zone.
+    Mtime,
+    /// Run exiftool, which reads many formats the built-in parser cannot. It must be installed.
+    Exiftool,
+    /// Run ffprobe, which reads the creation datetime of many video containers the built-in parser cannot. It is part of FFmpeg and must be installed.
+    Ffprobe,
+}
+
+/// Where the creation datetime is taken from. The sources are tried in the given order, before the fallbacks.
+/// If none of them reads the metadata embedded in the file, it is tried after them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateSource {
+    /// The metadata embedded in the file, like EXIF, whose datetime tags are tried in the order `DateTimeOriginal` and `CreateDate`. `ModifyDate` is only read as [DateSource::ModifyDate].
+    #[default]
+    Metadata,
+    /// Only the `DateTimeOriginal` tag of the EXIF data, which is when the photo was taken.
+    #[serde(rename = "DateTimeOriginal", alias = "date-time-original")]
+    DateTimeOriginal,
+    /// Only the `CreateDate` tag of the EXIF data, which is when the photo was digitized, e.g. scanned.
+    #[serde(rename = "CreateDate", alias = "create-date")]
+    CreateDate,
+    /// Only the `ModifyDate` tag of the EXIF data, which is when the file was last changed, e.g. by an image editor.
+    #[serde(rename = "ModifyDate", alias = "modify-date")]
+    ModifyDate,
+    /// Only the creation datetime of the tracks of a video container, like MP4 or MOV.
+    Track,
+    /// The XMP sidecar of the file, like `IMG_1234.xmp` or `IMG_1234.CR2.xmp`.
+    Xmp,
+    /// The telemetry in the SRT subtitles of a DJI drone video, like `DJI_0001.SRT` of `DJI_0001.MP4`, which also has the position of the drone.
+    DjiSrt,
+    /// The file name, like [Fallback::Name].
+    Name,
+    /// The modification time of the file in the file system, like [Fallback::Mtime].
+    Fs,
+}
+
+/// What reads the metadata embedded in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// The built-in parser, which reads the common photo and video formats.
+    #[default]
+    Native,
+    /// exiftool, which reads many more formats, like obscure RAW formats, but is much slower, as it is run once per file. It must be installed.
+    Exiftool,
+}
+
+/// How the creation datetime is extracted, besides from the metadata of a file. It configures the default [crate::ExtractorChain].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExtractionSettings {
+    /// Tried in order, before the documents.
+    pub(crate) date_sources: Vec<DateSource>,
+    pub(crate) backend: Backend,
+    pub(crate) extractor_cmd: Option<String>,
+    /// Tried in order, after the external extractor.
+    pub(crate) fallbacks: Vec<Fallback>,
+    /// Whether the creation datetimes of PDFs and office documents are read, after the metadata.
+    pub(crate) documents: bool,
+}
+
+/// The metadata of a media file, which is used to derive its new name.
+/// Only the creation datetime is required; the other values are only known if the metadata of the file contains them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaMetadata {
+    pub datetime: DateTime<FixedOffset>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub camera_serial: Option<String>,
+    /// From the `LensModel` EXIF tag, as the maker notes, where some cameras keep the lens instead, are not parsed.
+    pub lens_model: Option<String>,
+    pub iso: Option<u32>,
+    pub f_number: Option<f64>,
+    /// In seconds.
+    pub exposure_time: Option<f64>,
+    pub gps: Option<GpsCoordinates>,
+    /// Whether the datetime has a fraction of a second, e.g. from `SubSecTimeOriginal`.
+    pub has_subseconds: bool,
+    /// The description of the [crate::DatetimeExtractor] which extracted the metadata, like `exiftool`. It is set by the [crate::ExtractorChain].
+    #[serde(default)]
+    pub extractor: Option<String>,
+}
+
+/// In decimal degrees, negative for south and west.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GpsCoordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl From<DateTime<FixedOffset>> for MediaMetadata {
+    fn from(datetime: DateTime<FixedOffset>) -> Self {
+        Self {
+            datetime,
+            camera_make: None,
+            camera_model: None,
+            camera_serial: None,
+            lens_model: None,
+            iso: None,
+            f_number: None,
+            exposure_time: None,
+            gps: None,
+            has_subseconds: datetime.nanosecond() != 0,
+            extractor: None,
+        }
+    }
+}
+
+impl From<&GPSInfo> for GpsCoordinates {
+    fn from(gps_info: &GPSInfo) -> Self {
+        let degrees = |lat_lng: &LatLng| lat_lng.0.as_float() + lat_lng.1.as_float() / 60.0 + lat_lng.2.as_float() / 3600.0;
+        let sign = |reference: char, negative_reference: char| {
+            if reference == negative_reference {
+                -1.0
+            } else {
+                1.0
+            }
+        };
+
+        Self {
+            latitude: sign(gps_info.latitude_ref, 'S') * degrees(&gps_info.latitude),
+            longitude: sign(gps_info.longitude_ref, 'W') * degrees(&gps_info.longitude),
+        }
+    }
+}
+
+/// A creation datetime with the tag of the metadata it was extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractedDatetime {
+    pub datetime: DateTime<FixedOffset>,
+    pub source: DatetimeSource,
+}
+
+/// The tag of the metadata which provided a creation datetime, like `DateTimeOriginal` of the EXIF data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatetimeSource {
+    ExifTag(&'static str),
+    TrackTag(&'static str),
+    /// The XMP packet embedded in the file, like in a PNG or WebP file.
+    Xmp,
+    /// A chunk of a PNG file, like `tIME`, or one of its text chunks, like `Creation Time`.
+    PngChunk(&'static str),
+    /// A tag of the metadata which AVCHD camcorders write into the video stream of their `.MTS` files.
+    AvchdTag(&'static str),
+    /// A tag of the GPMF telemetry of a GoPro clip, like `GPSU`, whose GPS time is more precise than the clock of the camera.
+    GpmfTag(&'static str),
+    /// A tag of an audio file, like the `TDRC` frame of ID3v2, the `DATE` Vorbis comment of FLAC or the `©day` atom of M4A.
+    AudioTag(&'static str),
+    /// A property of a document, like the `CreationDate` of a PDF or the `dcterms:created` of an OOXML document.
+    DocumentProperty(&'static str),
+}
+
+/// Extracts the creation datetime from the metadata embedded in a photo, video or audio file, like a run does before any fallback.
+/// Use it to get the creation datetime of a file without renaming it.
+/// RAW files, like CR2, CR3, NEF, ARW, DNG and RAF, PNG, WebP and AVIF files, AVCHD videos, MP3, FLAC and M4A files and the GPMF telemetry of GoPro clips
+/// are read natively, the other formats with nom-exif.
+pub fn extract_creation_datetime(file_path: &Path) -> Result<ExtractedDatetime, ExtractError> {
+    let mut file = File::open(file_path).map_err(ExtractError::io("Cannot read the file"))?;
+    if let Some(extracted_datetime) = extract_creation_datetime_from_raw(&mut file)
+        .or_else(|| extract_creation_datetime_from_image(&mut file))
+        .or_else(|| extract_creation_datetime_from_transport_stream(&mut file))
+        .or_else(|| extract_creation_datetime_from_audio(&mut file))
+    {
+        return extracted_datetime;
+    }
+    if let Some(gpmf_capture) = read_gpmf_capture(&mut file) {
+        return Ok(ExtractedDatetime {
+            datetime: gpmf_capture.datetime,
+            source: DatetimeSource::GpmfTag(gpmf_capture.tag),
+        });
+    }
+
+    let media_source = open_media_source(file_path)?;
+    let mut media_parser = MediaParser::new();
 
-pub fn extract_creation_datetime_from_media_source(media_source: MediaSource<File>, media_parser: &mut MediaParser) -> anyhow::Result<DateTime<FixedOffset>> {
     if media_source.has_exif() {
-        let exif_iter: ExifIter = media_parser.parse(media_source).context("Failed to parse EXIF data!")?;
+        extract_creation_datetime_from_exif(&parse_exif(media_source, &mut media_parser)?)
+    } else if media_source.has_track() {
+        extract_creation_datetime_from_track_info(&parse_track_info(media_source, &mut media_parser)?)
+    } else {
+        Err(ExtractError::NoMetadata)
+    }
+}
+
+/// RAW files, like CR2, CR3, NEF, ARW, DNG and RAF, PNG, WebP and AVIF files, AVCHD videos and MP3, FLAC and M4A files are read natively,
+/// the other formats with nom-exif. The creation datetime and the position of GoPro clips are taken from their GPMF telemetry, as the clock of the camera is often wrong.
+pub(crate) fn extract_metadata_from_file(file_path: &Path, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+    let mut file = File::open(file_path).map_err(ExtractError::io("Cannot read the file"))?;
+    if let Some(metadata) = extract_metadata_from_raw(&mut file)
+        .or_else(|| extract_metadata_from_image(&mut file))
+        .or_else(|| extract_metadata_from_transport_stream(&mut file))
+        .or_else(|| extract_metadata_from_audio(&mut file))
+    {
+        return metadata;
+    }
+
+    let gpmf_capture = read_gpmf_capture(&mut file);
+    file.rewind().map_err(ExtractError::io("Cannot read the file"))?;
+    let metadata = MediaSource::file(file)
+        .map_err(ExtractError::parse("Cannot process the file"))
+        .and_then(|media_source| extract_metadata_from_media_source(media_source, media_parser));
+
+    match (gpmf_capture, metadata) {
+        (Some(gpmf_capture), Ok(metadata)) => Ok(MediaMetadata {
+            datetime: gpmf_capture.datetime,
+            gps: Some(gpmf_capture.gps),
+            has_subseconds: gpmf_capture.datetime.nanosecond() != 0,
+            ..metadata
+        }),
+        (Some(gpmf_capture), Err(_)) => Ok(MediaMetadata {
+            camera_make: Some("GoPro".to_string()),
+            gps: Some(gpmf_capture.gps),
+            ..MediaMetadata::from(gpmf_capture.datetime)
+        }),
+        (None, metadata) => metadata,
+    }
+}
+
+/// Extracts the creation datetime from a single datetime tag of the EXIF data, like `DateTimeOriginal`, instead of the first one which is set.
+/// RAW files, PNG, WebP and AVIF files are read natively, the other formats with nom-exif.
+pub(crate) fn extract_creation_datetime_from_exif_tag(
+    file_path: &Path,
+    tag_name: &'static str,
+    media_parser: &mut MediaParser,
+) -> Result<ExtractedDatetime, ExtractError> {
+    let mut file = File::open(file_path).map_err(ExtractError::io("Cannot read the file"))?;
+    if let Some(extracted_datetime) =
+        extract_exif_tag_datetime_from_raw(&mut file, tag_name).or_else(|| extract_exif_tag_datetime_from_image(&mut file, tag_name))
+    {
+        return extracted_datetime;
+    }
+
+    let media_source = MediaSource::file(file).map_err(ExtractError::parse("Cannot process the file"))?;
+    if media_source.has_exif().not() {
+        return Err(ExtractError::MissingDatetime("EXIF"));
+    }
+    let exif = parse_exif(media_source, media_parser)?;
+
+    EXIF_DATETIME_TAGS
+        .into_iter()
+        .find(|(.., name)| *name == tag_name)
+        .and_then(|(exif_tag, subsec_exif_tag, _)| exif_tag_datetime(&exif, exif_tag, subsec_exif_tag))
+        .map(|datetime| ExtractedDatetime {
+            datetime,
+            source: DatetimeSource::ExifTag(tag_name),
+        })
+        .ok_or(ExtractError::MissingDatetime(tag_name))
+}
+
+/// Extracts the creation datetime from the track info of a video container, like MP4 or MOV, even if it also has EXIF data.
+pub(crate) fn extract_creation_datetime_from_track(file_path: &Path, media_parser: &mut MediaParser) -> Result<ExtractedDatetime, ExtractError> {
+    let media_source = open_media_source(file_path)?;
+    if media_source.has_track().not() {
+        return Err(ExtractError::MissingDatetime("track info"));
+    }
+
+    extract_creation_datetime_from_track_info(&parse_track_info(media_source, media_parser)?)
+}
 
-        let exif: Exif = exif_iter.into();
-        extract_creation_datetime_from_exif(&exif)
+/// Errors which occur while reading the file are [ExtractError::Io], so that they can be told apart from files which cannot be parsed.
+pub(crate) fn open_media_source(file_path: &Path) -> Result<MediaSource<File>, ExtractError> {
+    let file = File::open(file_path).map_err(ExtractError::io("Cannot read the file"))?;
+
+    MediaSource::file(file).map_err(ExtractError::parse("Cannot process the file"))
+}
+
+fn parse_exif<R: Read + Seek>(media_source: MediaSource<R>, media_parser: &mut MediaParser) -> Result<Exif, ExtractError> {
+    let exif_iter: ExifIter = media_parser.parse(media_source).map_err(ExtractError::parse("Failed to parse EXIF data!"))?;
+
+    Ok(exif_iter.into())
+}
+
+fn parse_track_info<R: Read + Seek>(media_source: MediaSource<R>, media_parser: &mut MediaParser) -> Result<TrackInfo, ExtractError> {
+    media_parser
+        .parse(media_source)
+        .map_err(ExtractError::parse("Failed to parse track info data!"))
+}
+
+pub fn extract_metadata_from_media_source<R: Read + Seek>(media_source: MediaSource<R>, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+    if media_source.has_exif() {
+        let exif = parse_exif(media_source, media_parser)?;
+        let datetime = extract_creation_datetime_from_exif(&exif)?.datetime;
+        Ok(MediaMetadata {
+            datetime,
+            camera_make: exif.get(ExifTag::Make).and_then(text_value),
+            camera_model: exif.get(ExifTag::Model).and_then(text_value),
+            camera_serial: exif.get_by_ifd_tag_code(0, BODY_SERIAL_NUMBER_TAG_CODE).and_then(text_value),
+            lens_model: exif.get(ExifTag::LensModel).and_then(text_value),
+            iso: exif.get(ExifTag::ISOSpeedRatings).and_then(integer_value),
+            f_number: exif.get(ExifTag::FNumber).and_then(rational_value),
+            exposure_time: exif.get(ExifTag::ExposureTime).and_then(rational_value),
+            gps: exif.get_gps_info().ok().flatten().as_ref().map(GpsCoordinates::from),
+            has_subseconds: datetime.nanosecond() != 0,
+            extractor: None,
+        })
     } else if media_source.has_track() {
-        let track_info: TrackInfo = media_parser.parse(media_source)?;
-        extract_creation_datetime_from_track_info(&track_info)
+        let track_info = parse_track_info(media_source, media_parser)?;
+        Ok(MediaMetadata {
+            datetime: extract_creation_datetime_from_track_info(&track_info)?.datetime,
+            camera_make: track_info.get(TrackInfoTag::Make).and_then(text_value),
+            camera_model: track_info.get(TrackInfoTag::Model).and_then(text_value),
+            camera_serial: None,
+            lens_model: None,
+            iso: None,
+            f_number: None,
+            exposure_time: None,
+            gps: track_info.get_gps_info().map(GpsCoordinates::from),
+            has_subseconds: false,
+            extractor: None,
+        })
     } else {
-        Err(anyhow::anyhow!("The media source has no EXIF or track data!"))
+        Err(ExtractError::NoMetadata)
     }
 }
 
-const EXIF_TAGS_FOR_CREATION_DATETIME: [ExifTag; 3] = [ExifTag::DateTimeOriginal, ExifTag::OffsetTimeOriginal, ExifTag::CreateDate];
+/// nom-exif has no [ExifTag] for the serial number of the camera body.
+const BODY_SERIAL_NUMBER_TAG_CODE: u16 = 0xA431;
 
-fn extract_creation_datetime_from_exif(exif: &Exif) -> anyhow::Result<DateTime<FixedOffset>> {
-    for exif_tag in EXIF_TAGS_FOR_CREATION_DATETIME {
-        if let Some(exif_value) = exif.get(exif_tag) {
-            if let Some(datetime) = exif_value.as_time() {
-                return Ok(datetime);
-            }
+/// Each datetime tag with the tag holding its fraction of a second and the name of the datetime tag.
+const EXIF_TAGS_FOR_CREATION_DATETIME: [(ExifTag, ExifTag, &str); 3] = [
+    (ExifTag::DateTimeOriginal, ExifTag::SubSecTimeOriginal, "DateTimeOriginal"),
+    (ExifTag::OffsetTimeOriginal, ExifTag::SubSecTimeOriginal, "OffsetTimeOriginal"),
+    (ExifTag::CreateDate, ExifTag::SubSecTimeDigitized, "CreateDate"),
+];
+
+/// The datetime tags which can be chosen as a [DateSource], with the tag holding their fraction of a second and the name of the datetime tag.
+const EXIF_DATETIME_TAGS: [(ExifTag, ExifTag, &str); 3] = [
+    (ExifTag::DateTimeOriginal, ExifTag::SubSecTimeOriginal, "DateTimeOriginal"),
+    (ExifTag::CreateDate, ExifTag::SubSecTimeDigitized, "CreateDate"),
+    (ExifTag::ModifyDate, ExifTag::SubSecTime, "ModifyDate"),
+];
+
+fn extract_creation_datetime_from_exif(exif: &Exif) -> Result<ExtractedDatetime, ExtractError> {
+    for (exif_tag, subsec_exif_tag, tag_name) in EXIF_TAGS_FOR_CREATION_DATETIME {
+        if let Some(datetime) = exif_tag_datetime(exif, exif_tag, subsec_exif_tag) {
+            return Ok(ExtractedDatetime {
+                datetime,
+                source: DatetimeSource::ExifTag(tag_name),
+            });
         }
     }
 
-    Err(anyhow::anyhow!("Could not get the creation datetime from EXIF data!"))
+    Err(ExtractError::MissingDatetime("EXIF"))
 }
 
-const TRACK_INFO_TAGS_FOR_CREATION_DATETIME: [TrackInfoTag; 1] = [TrackInfoTag::CreateDate];
+/// Returns [None] if the datetime tag is not set.
+fn exif_tag_datetime(exif: &Exif, exif_tag: ExifTag, subsec_exif_tag: ExifTag) -> Option<DateTime<FixedOffset>> {
+    let datetime = exif.get(exif_tag)?.as_time()?;
+    let nanoseconds = exif.get(subsec_exif_tag).and_then(text_value).and_then(|subsec| subsec_to_nanoseconds(&subsec));
 
-fn extract_creation_datetime_from_track_info(track_info: &TrackInfo) -> anyhow::Result<DateTime<FixedOffset>> {
-    for track_info_tag in TRACK_INFO_TAGS_FOR_CREATION_DATETIME {
-        if let Some(exif_value) = track_info.get(track_info_tag) {
-            if let Some(datetime) = exif_value.as_time() {
-                return Ok(datetime);
-            }
+    Some(nanoseconds.and_then(|nanoseconds| datetime.with_nanosecond(nanoseconds)).unwrap_or(datetime))
+}
+
+/// The digits of a `SubSecTime*` tag are the decimal places of the second, so `"5"` is half a second and `"005"` five milliseconds.
+pub(crate) fn subsec_to_nanoseconds(subsec: &str) -> Option<u32> {
+    if subsec.is_empty() || subsec.chars().all(|char| char.is_ascii_digit()).not() {
+        return None;
+    }
+
+    let digits = subsec.chars().take(9).collect::<String>();
+    format!("{:0<9}", digits).parse().ok()
+}
+
+/// Text values are often padded with spaces or NUL characters.
+fn text_value(entry_value: &EntryValue) -> Option<String> {
+    entry_value
+        .as_str()
+        .map(|text| text.trim_matches(|char: char| char.is_whitespace() || char == '\0').to_string())
+        .filter(|text| text.is_empty().not())
+}
+
+/// The ISO speed is a single short, but some cameras write a long or several values, of which the first one counts.
+fn integer_value(entry_value: &EntryValue) -> Option<u32> {
+    match entry_value {
+        EntryValue::U16(value) => Some(u32::from(*value)),
+        EntryValue::U32(value) => Some(*value),
+        EntryValue::U16Array(values) => values.first().copied().map(u32::from),
+        _ => None,
+    }
+    .filter(|value| *value > 0)
+}
+
+/// A zero denominator marks an unknown value.
+fn rational_value(entry_value: &EntryValue) -> Option<f64> {
+    entry_value
+        .as_urational()
+        .filter(|rational| rational.1 != 0)
+        .map(|rational| rational.as_float())
+}
+
+const TRACK_INFO_TAGS_FOR_CREATION_DATETIME: [(TrackInfoTag, &str); 1] = [(TrackInfoTag::CreateDate, "CreateDate")];
+
+fn extract_creation_datetime_from_track_info(track_info: &TrackInfo) -> Result<ExtractedDatetime, ExtractError> {
+    for (track_info_tag, tag_name) in TRACK_INFO_TAGS_FOR_CREATION_DATETIME {
+        if let Some(exif_value) = track_info.get(track_info_tag)
+            && let Some(datetime) = exif_value.as_time()
+        {
+            return Ok(ExtractedDatetime {
+                datetime,
+                source: DatetimeSource::TrackTag(tag_name),
+            });
         }
     }
 
-    Err(anyhow::anyhow!("Could not get the creation datetime from track info data!"))
+    Err(ExtractError::MissingDatetime("track info"))
+}
+
+/// Runs the given command with the file path appended as the last argument.
+/// The command is split at whitespace, so arguments can be passed, e.g. `exiftool -s3 -DateTimeOriginal -d %Y-%m-%dT%H:%M:%S%:z`.
+/// The first line of its standard output must be a datetime in the ISO 8601 / RFC 3339 format, e.g. `2024-05-01T10:00:00+02:00`.
+pub fn extract_creation_datetime_with_external_command(command: &str, file_path: &Path) -> Result<DateTime<FixedOffset>, ExtractError> {
+    let mut command_parts = command.split_whitespace();
+    let program = command_parts
+        .next()
+        .ok_or_else(|| ExtractError::ExternalCommand("The external extractor command is empty!".to_string()))?;
+
+    let output = Command::new(program)
+        .args(command_parts)
+        .arg(file_path)
+        .output()
+        .map_err(ExtractError::io(format!("Failed to run the external extractor '{}'!", program)))?;
+
+    if output.status.success().not() {
+        return Err(ExtractError::ExternalCommand(format!(
+            "The external extractor exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let stdout = String::from_utf8(output.stdout).map_err(ExtractError::parse("The output of the external extractor is not valid UTF-8!"))?;
+    let first_line = stdout.lines().next().unwrap_or_default().trim();
+
+    DateTime::parse_from_rfc3339(first_line).map_err(ExtractError::parse(format!(
+        "The output of the external extractor '{}' is not an ISO 8601 datetime!",
+        first_line
+    )))
+}
+
+/// Formats of datetimes in the file names of cameras and phones. Trailing characters, like the milliseconds of Pixel phones, are ignored.
+const FILE_NAME_DATETIME_FORMATS: [&str; 6] = [
+    // IMG_20190704_183012.jpg, PXL_20230101_120000123.mp4
+    "%Y%m%d_%H%M%S",
+    // IMG-20190704-183012.jpg
+    "%Y%m%d-%H%M%S",
+    // 2019-07-04__18-30-12.jpg, the default format of rambo itself
+    "%Y-%m-%d__%H-%M-%S",
+    // 2019-07-04 18.30.12.jpg, from Dropbox camera uploads
+    "%Y-%m-%d %H.%M.%S",
+    // Screenshot_2019-07-04-18-30-12.png
+    "%Y-%m-%d-%H-%M-%S",
+    // Screenshot_2019-07-04_18-30-12.png
+    "%Y-%m-%d_%H-%M-%S",
+];
+
+/// Formats of the file names of messenger exports, which strip the EXIF data: the messenger, the prefix which precedes the date and the format.
+/// Formats without a time only contain the date, so the time is set to midnight.
+const MESSENGER_FILE_NAME_FORMATS: [(&str, &str, &str); 12] = [
+    // IMG-20230105-WA0012.jpg, VID-20230105-WA0012.mp4, AUD-20230105-WA0012.opus and PTT-20230105-WA0012.opus from WhatsApp
+    ("WhatsApp", "IMG-", "%Y%m%d-WA"),
+    ("WhatsApp", "VID-", "%Y%m%d-WA"),
+    ("WhatsApp", "AUD-", "%Y%m%d-WA"),
+    ("WhatsApp", "PTT-", "%Y%m%d-WA"),
+    // WhatsApp Image 2023-01-05 at 10.15.30.jpeg from WhatsApp Web and Desktop
+    ("WhatsApp", "WhatsApp Image ", "%Y-%m-%d at %H.%M.%S"),
+    ("WhatsApp", "WhatsApp Video ", "%Y-%m-%d at %H.%M.%S"),
+    // signal-2023-01-05-101530.jpg, and signal-2023-01-05-10-15-30-123.jpg from older versions
+    ("Signal", "signal-", "%Y-%m-%d-%H%M%S"),
+    ("Signal", "signal-", "%Y-%m-%d-%H-%M-%S"),
+    // photo_2023-01-05_10-15-30.jpg, and photo_2023-01-05 10.15.30.jpeg from newer versions of Telegram Desktop
+    ("Telegram", "photo_", "%Y-%m-%d_%H-%M-%S"),
+    ("Telegram", "photo_", "%Y-%m-%d %H.%M.%S"),
+    ("Telegram", "video_", "%Y-%m-%d_%H-%M-%S"),
+    ("Telegram", "video_", "%Y-%m-%d %H.%M.%S"),
+];
+
+/// File names contain no time zone, so the datetime is interpreted in the local time zone.
+pub fn extract_creation_datetime_from_file_name(file_path: &Path) -> Result<DateTime<FixedOffset>, ExtractError> {
+    let file_stem = file_path
+        .file_stem()
+        .and_then(OsStr::to_str)
+        .ok_or_else(|| ExtractError::Unavailable("The file name is not valid UTF-8!".to_string()))?;
+
+    let naive_datetime = parse_messenger_file_name(file_stem)
+        .or_else(|| datetime_candidates(file_stem).find_map(parse_file_name_datetime))
+        .ok_or_else(|| ExtractError::Unavailable("The file name does not match any known pattern!".to_string()))?;
+
+    in_local_time_zone(naive_datetime)
+}
+
+/// File names which only contain the date are logged, as the files of a day are then ordered by their names instead of their times.
+fn parse_messenger_file_name(file_stem: &str) -> Option<NaiveDateTime> {
+    MESSENGER_FILE_NAME_FORMATS.iter().find_map(|&(messenger, prefix, format)| {
+        let (_, rest) = file_stem.split_once(prefix)?;
+        if format.contains("%H") {
+            return NaiveDateTime::parse_and_remainder(rest, format)
+                .ok()
+                .map(|(datetime, _)| datetime)
+                .filter(|datetime| datetime.year() >= 1970);
+        }
+
+        let (date, _) = NaiveDate::parse_and_remainder(rest, format).ok().filter(|(date, _)| date.year() >= 1970)?;
+        log::info!(
+            "Only the day of '{}' could be recovered from its {} file name, so its time is set to midnight",
+            file_stem,
+            messenger
+        );
+        Some(date.and_time(NaiveTime::MIN))
+    })
+}
+
+pub(crate) fn in_local_time_zone(naive_datetime: NaiveDateTime) -> Result<DateTime<FixedOffset>, ExtractError> {
+    Local
+        .from_local_datetime(&naive_datetime)
+        .earliest()
+        .map(|datetime| datetime.fixed_offset())
+        .ok_or_else(|| ExtractError::InvalidDatetime(format!("The datetime {} does not exist in the local time zone!", naive_datetime)))
+}
+
+/// A datetime can start at each digit which does not follow another digit.
+fn datetime_candidates(file_stem: &str) -> impl Iterator<Item = &str> {
+    file_stem
+        .char_indices()
+        .filter(|(index, character)| {
+            character.is_ascii_digit()
+                && file_stem[..*index]
+                    .chars()
+                    .next_back()
+                    .is_none_or(|previous_character| previous_character.is_ascii_digit().not())
+        })
+        .map(|(index, _)| &file_stem[index..])
+}
+
+fn parse_file_name_datetime(candidate: &str) -> Option<NaiveDateTime> {
+    FILE_NAME_DATETIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_and_remainder(candidate, format).ok())
+        .map(|(datetime, _)| datetime)
+        .or_else(|| parse_screenshot_datetime(candidate))
+        // Other numbers in file names, like counters, are unlikely to be plausible dates.
+        .filter(|datetime| datetime.year() >= 1970)
+}
+
+/// The formats of the time in the names of screenshots, which follows the date and a space.
+const SCREENSHOT_TIME_FORMATS: [&str; 3] = [
+    "%H.%M.%S", // Screenshot 2024-03-01 at 10.15.32.png from macOS
+    "%H%M%S",   // Screenshot 2024-03-01 101532.png from the Snipping Tool of Windows
+    "%H-%M-%S", // Screenshot from 2024-03-01 10-15-32.png from GNOME
+];
+
+/// Screenshots of macOS put a word before the time, which depends on the language, like `at` or `um` in `Bildschirmfoto 2024-03-01 um 10.15.32`,
+/// and older versions use the 12 hour clock, like `Screen Shot 2019-07-04 at 6.30.12 PM`, with a narrow no-break space before `PM` since macOS 14.
+fn parse_screenshot_datetime(candidate: &str) -> Option<NaiveDateTime> {
+    let (date, rest) = NaiveDate::parse_and_remainder(candidate, "%Y-%m-%d").ok()?;
+    let mut rest = rest.strip_prefix(' ')?;
+    // Some languages use more than one word, like `a las` in Spanish.
+    while let Some((word, after_word)) = rest.split_once(' ')
+        && word.is_empty().not()
+        && word.chars().all(char::is_alphabetic)
+    {
+        rest = after_word;
+    }
+
+    let (time, rest) = SCREENSHOT_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_and_remainder(rest, format).ok())?;
+    let meridiem = rest.trim_start_matches([' ', '\u{00A0}', '\u{202F}']);
+    let time = if meridiem.starts_with("PM") && time.hour() < 12 {
+        time + TimeDelta::hours(12)
+    } else if meridiem.starts_with("AM") && time.hour() == 12 {
+        time - TimeDelta::hours(12)
+    } else {
+        time
+    };
+
+    Some(date.and_time(time))
+}
+
+/// The XMP properties for the creation datetime, in the order of their precedence.
+pub(crate) const XMP_PROPERTIES_FOR_CREATION_DATETIME: [&str; 3] = ["photoshop:DateCreated", "exif:DateTimeOriginal", "xmp:CreateDate"];
+
+/// Reads the creation datetime from an XMP packet. XMP dates may omit the seconds or the time zone; without a time zone, the local time zone is assumed.
+pub fn extract_creation_datetime_from_xmp(xmp: &str) -> Result<DateTime<FixedOffset>, ExtractError> {
+    let (property, value) = XMP_PROPERTIES_FOR_CREATION_DATETIME
+        .iter()
+        .find_map(|property| find_xmp_property_value(xmp, property).map(|value| (property, value)))
+        .ok_or_else(|| ExtractError::Unavailable("Could not find the creation datetime in the XMP data!".to_string()))?;
+
+    parse_xmp_datetime(value).map_err(|error| {
+        ExtractError::InvalidDatetime(format!(
+            "The value '{}' of the XMP property '{}' is not a datetime!: {}",
+            value, property, error
+        ))
+    })
+}
+
+/// Embedded XMP packets are searched for only at the start of a file, where they are in JPEG files, so that large videos are not read entirely.
+const EMBEDDED_XMP_SEARCH_LENGTH: u64 = 1024 * 1024;
+
+/// Reads the XMP sidecar of the file or, if it has none, the XMP packet embedded in it. Returns [None] if there is neither.
+pub(crate) fn read_xmp(file_path: &Path) -> Option<String> {
+    if let Some(sidecar_path) = find_xmp_sidecar(file_path) {
+        return std::fs::read_to_string(sidecar_path).ok();
+    }
+
+    let mut head = Vec::new();
+    File::open(file_path).ok()?.take(EMBEDDED_XMP_SEARCH_LENGTH).read_to_end(&mut head).ok()?;
+    let head = String::from_utf8_lossy(&head);
+
+    let start = head.find("<x:xmpmeta")?;
+    let length = head[start..].find("</x:xmpmeta>")? + "</x:xmpmeta>".len();
+    Some(head[start..start + length].to_string())
+}
+
+/// The `xmp:Rating` of the XMP packet, from -1 for rejected to 5 stars.
+pub(crate) fn xmp_rating(xmp: &str) -> Option<i8> {
+    find_xmp_property_value(xmp, "xmp:Rating")?
+        .parse::<f64>()
+        .ok()
+        .map(|rating| rating.round() as i8)
+}
+
+/// The items of the `dc:subject` bag of the XMP packet, in their order.
+pub(crate) fn xmp_keywords(xmp: &str) -> Vec<String> {
+    let Some((_, subject)) = xmp.split_once("<dc:subject>") else {
+        return Vec::new();
+    };
+    let subject = subject.split_once("</dc:subject>").map_or(subject, |(subject, _)| subject);
+
+    subject
+        .split("<rdf:li")
+        .skip(1)
+        .filter_map(|item| item.split_once('>').and_then(|(_, rest)| rest.split_once("</rdf:li>")))
+        .map(|(keyword, _)| unescape_xml(keyword.trim()))
+        .filter(|keyword| keyword.is_empty().not())
+        .collect()
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The property can either be an attribute, like `xmp:CreateDate="..."`, or an element, like `<xmp:CreateDate>...</xmp:CreateDate>`.
+fn find_xmp_property_value<'a>(xmp: &'a str, property: &str) -> Option<&'a str> {
+    for quote in ['"', '\''] {
+        if let Some((_, rest)) = xmp.split_once(&format!("{}={}", property, quote))
+            && let Some((value, _)) = rest.split_once(quote)
+        {
+            return Some(value.trim());
+        }
+    }
+
+    let (_, rest) = xmp.split_once(&format!("<{}>", property))?;
+    let (value, _) = rest.split_once('<')?;
+    Some(value.trim())
+}
+
+pub(crate) fn parse_xmp_datetime(value: &str) -> Result<DateTime<FixedOffset>, ExtractError> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value).or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M%:z")) {
+        return Ok(datetime);
+    }
+
+    let naive_datetime = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().map(|date| date.and_time(NaiveTime::MIN)))
+        .ok_or_else(|| ExtractError::InvalidDatetime("Unknown datetime format!".to_string()))?;
+
+    in_local_time_zone(naive_datetime)
 }
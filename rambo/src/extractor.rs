@@ -0,0 +1,397 @@
+use crate::dji::read_dji_telemetry;
+use crate::document::extract_creation_datetime_from_document;
+use crate::error::ExtractError;
+use crate::exiftool::{DEFAULT_EXIFTOOL_PROGRAM, extract_metadata_with_exiftool};
+use crate::extract::{
+    Backend, DateSource, ExtractionSettings, Fallback, MediaMetadata, extract_creation_datetime_from_exif_tag, extract_creation_datetime_from_file_name,
+    extract_creation_datetime_from_track, extract_creation_datetime_with_external_command, extract_metadata_from_file,
+};
+use crate::ffprobe::{DEFAULT_FFPROBE_PROGRAM, extract_metadata_with_ffprobe, is_ffprobe_available};
+use crate::sidecar::{extract_creation_datetime_from_takeout_sidecar, extract_creation_datetime_from_xmp_sidecar};
+
+use chrono::{DateTime, FixedOffset, Local, Timelike};
+use nom_exif::MediaParser;
+use std::fs::File;
+use std::ops::Not;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// Extracts the creation datetime of a file from a single source, like its EXIF data or its file name.
+/// Implement it to add another source to an [ExtractorChain].
+pub trait DatetimeExtractor: Send + Sync {
+    /// Names the source in the error of a file whose creation datetime could not be extracted, like `file name`.
+    fn description(&self) -> &str;
+
+    /// The media parser is shared by the files which are processed on the same thread.
+    fn extract(&self, file_path: &Path, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError>;
+}
+
+/// The extractors which are tried in order, until one of them succeeds.
+/// By default, the chain is configured with [crate::RamboOptions::date_sources], [crate::RamboOptions::backend], [crate::RamboOptions::extractor_cmd] and [crate::RamboOptions::fallbacks].
+#[derive(Clone, Default)]
+pub struct ExtractorChain {
+    extractors: Vec<Arc<dyn DatetimeExtractor>>,
+}
+
+impl ExtractorChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the extractor to the end of the chain, so it is tried after all others.
+    pub fn push(&mut self, extractor: impl DatetimeExtractor + 'static) -> &mut Self {
+        self.extractors.push(Arc::new(extractor));
+        self
+    }
+
+    /// Adds the extractor at the index of the chain, e.g. `0` to try it before all others.
+    pub fn insert(&mut self, index: usize, extractor: impl DatetimeExtractor + 'static) -> &mut Self {
+        self.extractors.insert(index.min(self.extractors.len()), Arc::new(extractor));
+        self
+    }
+
+    /// Keeps only the extractors for which the predicate is true, e.g. to remove one of the default chain by its description.
+    pub fn retain(&mut self, predicate: impl Fn(&dyn DatetimeExtractor) -> bool) -> &mut Self {
+        self.extractors.retain(|extractor| predicate(extractor.as_ref()));
+        self
+    }
+
+    pub fn extractors(&self) -> impl Iterator<Item = &dyn DatetimeExtractor> {
+        self.extractors.iter().map(Arc::as_ref)
+    }
+
+    pub(crate) fn from_settings(extraction_settings: &ExtractionSettings) -> Self {
+        let mut chain = Self::new();
+
+        for date_source in &extraction_settings.date_sources {
+            match date_source {
+                DateSource::Metadata => chain.push_backend(extraction_settings.backend),
+                DateSource::DateTimeOriginal => chain.push(ExifTagExtractor::new("DateTimeOriginal")),
+                DateSource::CreateDate => chain.push(ExifTagExtractor::new("CreateDate")),
+                DateSource::ModifyDate => chain.push(ExifTagExtractor::new("ModifyDate")),
+                DateSource::Track => chain.push(TrackExtractor),
+                DateSource::Xmp => chain.push(XmpSidecarExtractor),
+                DateSource::DjiSrt => chain.push(DjiSrtExtractor),
+                DateSource::Name => chain.push(FileNameExtractor),
+                DateSource::Fs => chain.push(ModificationTimeExtractor),
+            };
+        }
+        // Sources like the XMP sidecar only take precedence over the embedded metadata, which is still read for files without them.
+        let reads_embedded_metadata = extraction_settings.date_sources.iter().any(|date_source| {
+            matches!(
+                date_source,
+                DateSource::Metadata | DateSource::DateTimeOriginal | DateSource::CreateDate | DateSource::ModifyDate | DateSource::Track
+            )
+        });
+        if reads_embedded_metadata.not() {
+            chain.push_backend(extraction_settings.backend);
+        }
+        if extraction_settings.documents {
+            chain.push(DocumentExtractor);
+        }
+        if let Some(extractor_cmd) = &extraction_settings.extractor_cmd {
+            chain.push(ExternalCommandExtractor::new(extractor_cmd));
+        }
+        for fallback in &extraction_settings.fallbacks {
+            match fallback {
+                Fallback::Name => chain.push(FileNameExtractor),
+                Fallback::Takeout => chain.push(TakeoutSidecarExtractor),
+                Fallback::Mtime => chain.push(ModificationTimeExtractor),
+                Fallback::Exiftool => chain.push(ExiftoolExtractor::default()),
+                Fallback::Ffprobe => chain.push(FfprobeExtractor::default()),
+            };
+        }
+
+        chain
+    }
+
+    fn push_backend(&mut self, backend: Backend) -> &mut Self {
+        match backend {
+            Backend::Native => self.push(MetadataExtractor),
+            Backend::Exiftool => self.push(ExiftoolExtractor::default()),
+        }
+    }
+
+    /// The error of the first extractor is followed by the errors of the others, like `... (file name: ...)`.
+    /// The metadata names the extractor which succeeded in [MediaMetadata::extractor].
+    pub(crate) fn extract(&self, file_path: &Path, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        let mut first_error: Option<ExtractError> = None;
+        let mut other_errors = Vec::new();
+
+        for extractor in &self.extractors {
+            match extractor.extract(file_path, media_parser) {
+                Ok(metadata) => {
+                    return Ok(MediaMetadata {
+                        extractor: Some(extractor.description().to_string()),
+                        ..metadata
+                    });
+                }
+                Err(error) if first_error.is_none() => first_error = Some(error),
+                Err(error) => other_errors.push((extractor.description().to_string(), error)),
+            }
+        }
+
+        match first_error {
+            None => Err(ExtractError::Unavailable("There is no extractor for the creation datetime!".to_string())),
+            Some(first_error) if other_errors.is_empty() => Err(first_error),
+            Some(first_error) => Err(ExtractError::Chain {
+                first: Box::new(first_error),
+                others: other_errors,
+            }),
+        }
+    }
+}
+
+/// The metadata embedded in the file, either EXIF data, including the EXIF data of RAW files, or the track info of a video.
+pub struct MetadataExtractor;
+
+impl DatetimeExtractor for MetadataExtractor {
+    fn description(&self) -> &str {
+        "metadata"
+    }
+
+    fn extract(&self, file_path: &Path, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        extract_metadata_from_file(file_path, media_parser)
+    }
+}
+
+/// A single datetime tag of the EXIF data, like `DateTimeOriginal`. The other metadata, like the camera, is still taken from the file.
+pub struct ExifTagExtractor {
+    tag_name: &'static str,
+}
+
+impl ExifTagExtractor {
+    /// The tag is one of `DateTimeOriginal`, `CreateDate` and `ModifyDate`.
+    pub fn new(tag_name: &'static str) -> Self {
+        Self { tag_name }
+    }
+}
+
+impl DatetimeExtractor for ExifTagExtractor {
+    fn description(&self) -> &str {
+        self.tag_name
+    }
+
+    fn extract(&self, file_path: &Path, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        let extracted_datetime = extract_creation_datetime_from_exif_tag(file_path, self.tag_name, media_parser)?;
+
+        Ok(with_metadata_of_file(extracted_datetime.datetime, file_path, media_parser))
+    }
+}
+
+/// The creation datetime of the tracks of a video container, even if it also has EXIF data. The other metadata is still taken from the file.
+pub struct TrackExtractor;
+
+impl DatetimeExtractor for TrackExtractor {
+    fn description(&self) -> &str {
+        "track"
+    }
+
+    fn extract(&self, file_path: &Path, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        let extracted_datetime = extract_creation_datetime_from_track(file_path, media_parser)?;
+
+        Ok(with_metadata_of_file(extracted_datetime.datetime, file_path, media_parser))
+    }
+}
+
+/// The metadata of the file, like the camera, with the datetime which was extracted instead of its own.
+fn with_metadata_of_file(datetime: DateTime<FixedOffset>, file_path: &Path, media_parser: &mut MediaParser) -> MediaMetadata {
+    match MetadataExtractor.extract(file_path, media_parser) {
+        Ok(metadata) => MediaMetadata {
+            datetime,
+            has_subseconds: datetime.nanosecond() != 0,
+            ..metadata
+        },
+        Err(_) => MediaMetadata::from(datetime),
+    }
+}
+
+/// The XMP sidecar of the file. Its creation datetime takes precedence, but the other metadata, like the camera, is still taken from the file.
+pub struct XmpSidecarExtractor;
+
+impl DatetimeExtractor for XmpSidecarExtractor {
+    fn description(&self) -> &str {
+        "XMP sidecar"
+    }
+
+    fn extract(&self, file_path: &Path, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        let datetime =
+            extract_creation_datetime_from_xmp_sidecar(file_path).ok_or_else(|| ExtractError::Unavailable("There is no XMP sidecar!".to_string()))??;
+
+        Ok(with_metadata_of_file(datetime, file_path, media_parser))
+    }
+}
+
+/// The creation datetime of a PDF, from its `/CreationDate`, or of an OOXML document, like a `.docx` file, from its core properties.
+/// Other files are not read, so that it can follow the other extractors.
+pub struct DocumentExtractor;
+
+impl DatetimeExtractor for DocumentExtractor {
+    fn description(&self) -> &str {
+        "document"
+    }
+
+    fn extract(&self, file_path: &Path, _media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        let mut file = File::open(file_path).map_err(ExtractError::io("Cannot read the file"))?;
+
+        extract_creation_datetime_from_document(&mut file)
+            .ok_or_else(|| ExtractError::Unavailable("The file is no PDF or office document!".to_string()))?
+            .map(|extracted_datetime| MediaMetadata::from(extracted_datetime.datetime))
+    }
+}
+
+/// The telemetry in the SRT subtitles of a DJI drone video. Its capture time and position take precedence, but the other metadata is still taken from the video.
+pub struct DjiSrtExtractor;
+
+impl DatetimeExtractor for DjiSrtExtractor {
+    fn description(&self) -> &str {
+        "DJI SRT telemetry"
+    }
+
+    fn extract(&self, file_path: &Path, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        let telemetry = read_dji_telemetry(file_path).ok_or_else(|| ExtractError::Unavailable("There are no SRT subtitles!".to_string()))??;
+
+        Ok(match MetadataExtractor.extract(file_path, media_parser) {
+            Ok(metadata) => MediaMetadata {
+                datetime: telemetry.datetime,
+                gps: telemetry.gps.or(metadata.gps),
+                has_subseconds: telemetry.datetime.nanosecond() != 0,
+                ..metadata
+            },
+            Err(_) => MediaMetadata {
+                camera_make: Some("DJI".to_string()),
+                gps: telemetry.gps,
+                ..MediaMetadata::from(telemetry.datetime)
+            },
+        })
+    }
+}
+
+/// Runs exiftool on each file, which reads many formats the built-in parser cannot. It must be installed.
+pub struct ExiftoolExtractor {
+    program: String,
+}
+
+impl ExiftoolExtractor {
+    /// The program may be a path, if exiftool is not on the `PATH`.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self { program: program.into() }
+    }
+}
+
+impl Default for ExiftoolExtractor {
+    fn default() -> Self {
+        Self::new(DEFAULT_EXIFTOOL_PROGRAM)
+    }
+}
+
+impl DatetimeExtractor for ExiftoolExtractor {
+    fn description(&self) -> &str {
+        "exiftool"
+    }
+
+    fn extract(&self, file_path: &Path, _media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        extract_metadata_with_exiftool(&self.program, file_path)
+    }
+}
+
+/// Runs ffprobe on each file to read the creation datetime from the tags of its container, like `creation_time`. It is part of FFmpeg and must be installed.
+/// Whether ffprobe can be run is checked once, when it is needed first, so a missing ffprobe is skipped without trying it for each file.
+pub struct FfprobeExtractor {
+    program: String,
+    is_available: OnceLock<bool>,
+}
+
+impl FfprobeExtractor {
+    /// The program may be a path, if ffprobe is not on the `PATH`.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            is_available: OnceLock::new(),
+        }
+    }
+}
+
+impl Default for FfprobeExtractor {
+    fn default() -> Self {
+        Self::new(DEFAULT_FFPROBE_PROGRAM)
+    }
+}
+
+impl DatetimeExtractor for FfprobeExtractor {
+    fn description(&self) -> &str {
+        "ffprobe"
+    }
+
+    fn extract(&self, file_path: &Path, _media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        if *self.is_available.get_or_init(|| is_ffprobe_available(&self.program)) {
+            extract_metadata_with_ffprobe(&self.program, file_path)
+        } else {
+            Err(ExtractError::Unavailable(format!("'{}' cannot be run!", self.program)))
+        }
+    }
+}
+
+/// See [extract_creation_datetime_with_external_command].
+pub struct ExternalCommandExtractor {
+    command: String,
+}
+
+impl ExternalCommandExtractor {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self { command: command.into() }
+    }
+}
+
+impl DatetimeExtractor for ExternalCommandExtractor {
+    fn description(&self) -> &str {
+        "external extractor"
+    }
+
+    fn extract(&self, file_path: &Path, _media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        extract_creation_datetime_with_external_command(&self.command, file_path).map(MediaMetadata::from)
+    }
+}
+
+/// See [extract_creation_datetime_from_file_name].
+pub struct FileNameExtractor;
+
+impl DatetimeExtractor for FileNameExtractor {
+    fn description(&self) -> &str {
+        "file name"
+    }
+
+    fn extract(&self, file_path: &Path, _media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        extract_creation_datetime_from_file_name(file_path).map(MediaMetadata::from)
+    }
+}
+
+/// The JSON sidecar of a Google Takeout export.
+pub struct TakeoutSidecarExtractor;
+
+impl DatetimeExtractor for TakeoutSidecarExtractor {
+    fn description(&self) -> &str {
+        "Google Takeout sidecar"
+    }
+
+    fn extract(&self, file_path: &Path, _media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        extract_creation_datetime_from_takeout_sidecar(file_path).map(MediaMetadata::from)
+    }
+}
+
+/// The modification time of the file in the file system, in the local time zone. It is only a guess, because copying a file often resets it.
+pub struct ModificationTimeExtractor;
+
+impl DatetimeExtractor for ModificationTimeExtractor {
+    fn description(&self) -> &str {
+        "modification time"
+    }
+
+    fn extract(&self, file_path: &Path, _media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+        let modified = std::fs::metadata(file_path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(ExtractError::io("Failed to read the modification time!"))?;
+
+        Ok(MediaMetadata::from(DateTime::<Local>::from(modified).fixed_offset()))
+    }
+}
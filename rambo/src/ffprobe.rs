@@ -0,0 +1,105 @@
+use crate::error::ExtractError;
+use crate::extract::MediaMetadata;
+
+use chrono::{DateTime, FixedOffset, Local};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ops::Not;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// The program which is run, if no other one is configured. It is part of FFmpeg and must be installed by the user.
+pub const DEFAULT_FFPROBE_PROGRAM: &str = "ffprobe";
+
+/// The tags which may hold the creation datetime, in the order they are tried.
+/// The QuickTime tag of Apple devices keeps the local offset, whereas `creation_time` is in UTC.
+const DATETIME_TAGS: [&str; 2] = ["com.apple.quicktime.creationdate", "creation_time"];
+
+/// The relevant part of the output of `ffprobe -print_format json -show_format -show_streams`.
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    format: Option<ProbeSection>,
+    #[serde(default)]
+    streams: Vec<ProbeSection>,
+}
+
+#[derive(Deserialize)]
+struct ProbeSection {
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Whether the program can be run at all, so that a missing ffprobe is reported once instead of failing for each file.
+pub(crate) fn is_ffprobe_available(program: &str) -> bool {
+    let is_available = Command::new(program)
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+
+    if is_available.not() {
+        log::warn!(
+            "'{}' cannot be run, so the ffprobe fallback is not used. Make sure FFmpeg is installed!",
+            program
+        );
+    }
+    is_available
+}
+
+/// Runs ffprobe on the video and reads the creation datetime from the tags of its container, or else from the tags of its streams.
+/// It reads many containers whose tracks the built-in parser cannot, like unusual MP4 variants.
+/// A creation datetime in UTC is converted into the local time zone, like cameras and phones show it.
+pub(crate) fn extract_metadata_with_ffprobe(program: &str, file_path: &Path) -> Result<MediaMetadata, ExtractError> {
+    let output = Command::new(program)
+        .args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams", "--"])
+        .arg(file_path)
+        .output()
+        .map_err(ExtractError::io(format!("Failed to run '{}', make sure FFmpeg is installed!", program)))?;
+
+    if output.status.success().not() {
+        return Err(ExtractError::ExternalCommand(format!(
+            "ffprobe exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let probe_output = serde_json::from_slice::<ProbeOutput>(&output.stdout).map_err(ExtractError::parse("The output of ffprobe is invalid!"))?;
+    let sections = probe_output.format.iter().chain(&probe_output.streams).collect::<Vec<_>>();
+
+    let (tag, value) = DATETIME_TAGS
+        .iter()
+        .find_map(|tag| sections.iter().find_map(|section| section.tags.get(*tag)).map(|value| (tag, value)))
+        .ok_or(ExtractError::MissingDatetime("ffprobe"))?;
+    let datetime = parse_ffprobe_datetime(value)
+        .ok_or_else(|| ExtractError::InvalidDatetime(format!("The value '{}' of the ffprobe tag '{}' is not a datetime!", value, tag)))?;
+
+    let text_tag = |tag: &str| {
+        sections
+            .iter()
+            .find_map(|section| section.tags.get(tag))
+            .map(|value| value.trim().to_string())
+            .filter(|value| value.is_empty().not())
+    };
+
+    Ok(MediaMetadata {
+        camera_make: text_tag("com.apple.quicktime.make").or_else(|| text_tag("make")),
+        camera_model: text_tag("com.apple.quicktime.model").or_else(|| text_tag("model")),
+        ..MediaMetadata::from(datetime)
+    })
+}
+
+/// Like `2024-05-01T10:00:00.000000Z` or `2024-05-01T12:00:00+0200`. Dates in UTC are converted into the local time zone.
+fn parse_ffprobe_datetime(value: &str) -> Option<DateTime<FixedOffset>> {
+    let datetime = DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f%z"))
+        .ok()?;
+
+    if datetime.offset().local_minus_utc() == 0 {
+        Some(datetime.with_timezone(&Local).fixed_offset())
+    } else {
+        Some(datetime)
+    }
+}
@@ -0,0 +1,187 @@
+use crate::audio::is_audio_container;
+use crate::avchd::is_transport_stream;
+use crate::document::is_document;
+use crate::error::RamboError;
+use crate::image::is_image_container;
+use crate::raw::is_raw_container;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use nom_exif::MediaSource;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The kind of a media file, as it is recognized by its MIME type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    /// Images, which may contain EXIF data.
+    Photos,
+    /// Videos and other files with tracks.
+    Videos,
+    /// Audio recordings, like MP3, FLAC and M4A files.
+    Audio,
+    /// PDFs and OOXML documents, like `.docx` files.
+    Documents,
+}
+
+/// Decides which of the matched files are processed at all, before their creation datetime is extracted.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FileFilter {
+    /// When set, only files with one of these extensions pass. The extensions are compared case-insensitively and without a leading dot.
+    pub(crate) extensions: Option<Vec<String>>,
+    /// Files with one of these extensions do not pass.
+    pub(crate) skipped_extensions: Vec<String>,
+    pub(crate) media_kind: Option<MediaKind>,
+    /// Inclusive, in bytes.
+    pub(crate) min_size: Option<FileSize>,
+    /// Inclusive, in bytes.
+    pub(crate) max_size: Option<FileSize>,
+}
+
+impl FileFilter {
+    /// Removes the files which do not pass the filter and returns them.
+    /// Files which do not exist anymore are kept, so that they are reported as vanished.
+    pub(crate) fn apply(&self, paths: &mut Vec<PathBuf>) -> Vec<PathBuf> {
+        let mut filtered_paths = Vec::new();
+
+        paths.retain(|path| {
+            let passes = self.passes_extension(path) && self.passes_size(path) && self.passes_media_kind(path);
+            if passes.not() {
+                filtered_paths.push(path.clone());
+            }
+            passes
+        });
+
+        filtered_paths
+    }
+
+    pub(crate) fn passes_extension(&self, path: &Path) -> bool {
+        let extension = path.extension().map(OsStr::to_string_lossy).unwrap_or_default();
+        let is_listed = |extensions: &[String]| {
+            extensions
+                .iter()
+                .any(|listed_extension| listed_extension.trim_start_matches('.').eq_ignore_ascii_case(&extension))
+        };
+
+        self.extensions.as_deref().is_none_or(is_listed) && is_listed(&self.skipped_extensions).not()
+    }
+
+    fn passes_size(&self, path: &Path) -> bool {
+        if self.min_size.is_none() && self.max_size.is_none() {
+            return true;
+        }
+
+        match std::fs::metadata(path) {
+            Ok(metadata) => {
+                self.min_size.is_none_or(|FileSize(min_size)| metadata.len() >= min_size)
+                    && self.max_size.is_none_or(|FileSize(max_size)| metadata.len() <= max_size)
+            }
+            // The file is reported when its creation datetime cannot be extracted.
+            Err(_) => true,
+        }
+    }
+
+    fn passes_media_kind(&self, path: &Path) -> bool {
+        match self.media_kind {
+            Some(media_kind) => media_kind_of(path).is_none_or(|kind| kind == Some(media_kind)),
+            None => true,
+        }
+    }
+}
+
+/// A size in bytes, optionally with a binary unit like `10k`, `5M` or `2G`, where `1k` is 1024 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct FileSize(u64);
+
+impl FromStr for FileSize {
+    type Err = RamboError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let digits_end = value.find(|char: char| char.is_ascii_digit().not()).unwrap_or(value.len());
+        let (number, unit) = value.split_at(digits_end);
+
+        let number = number
+            .parse::<u64>()
+            .map_err(|_| RamboError::InvalidSetting("Expected a size like '500', '10k', '5M' or '2G'".to_string()))?;
+        let factor: u64 = match unit.to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kb" => 1 << 10,
+            "m" | "mb" => 1 << 20,
+            "g" | "gb" => 1 << 30,
+            "t" | "tb" => 1 << 40,
+            _ => return Err(RamboError::InvalidSetting(format!("Unknown unit '{}', expected 'k', 'M', 'G' or 'T'", unit))),
+        };
+
+        number
+            .checked_mul(factor)
+            .map(FileSize)
+            .ok_or_else(|| RamboError::InvalidSetting("The size is too large".to_string()))
+    }
+}
+
+/// `None` if the file does not exist anymore and `Some(None)` if it is no media file which can be processed.
+/// RAW files and images, which are read natively, are photos, transport streams, like AVCHD videos, are videos, MP3, FLAC and M4A files are audio,
+/// and PDFs and OOXML documents are documents.
+fn media_kind_of(path: &Path) -> Option<Option<MediaKind>> {
+    if File::open(path).is_ok_and(|mut file| is_raw_container(&mut file) || is_image_container(&mut file)) {
+        return Some(Some(MediaKind::Photos));
+    }
+    if File::open(path).is_ok_and(|mut file| is_transport_stream(&mut file)) {
+        return Some(Some(MediaKind::Videos));
+    }
+    if File::open(path).is_ok_and(|mut file| is_audio_container(&mut file)) {
+        return Some(Some(MediaKind::Audio));
+    }
+    if File::open(path).is_ok_and(|mut file| is_document(&mut file)) {
+        return Some(Some(MediaKind::Documents));
+    }
+
+    match MediaSource::file_path(path) {
+        Ok(media_source) if media_source.has_exif() => Some(Some(MediaKind::Photos)),
+        Ok(media_source) if media_source.has_track() => Some(Some(MediaKind::Videos)),
+        Err(nom_exif::Error::IOError(error)) if error.kind() == ErrorKind::NotFound => None,
+        Ok(_) | Err(_) => Some(None),
+    }
+}
+
+/// A date like `2022-06-01`, which stands for its midnight, or a datetime like `2022-06-01T18:30:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct DateBound(NaiveDateTime);
+
+impl FromStr for DateBound {
+    type Err = RamboError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            return Ok(DateBound(date.and_time(Default::default())));
+        }
+
+        ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M"]
+            .into_iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .map(DateBound)
+            .ok_or_else(|| RamboError::InvalidSetting("Expected a date like '2022-06-01' or a datetime like '2022-06-01T18:30:00'".to_string()))
+    }
+}
+
+/// The range of creation datetimes of the files that are processed.
+/// The bounds are compared with the local time of each creation datetime, after it was shifted and converted into the target time zone.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct DateRange {
+    /// Inclusive.
+    pub(crate) after: Option<DateBound>,
+    /// Exclusive, so that `--after 2022-06-01 --before 2022-07-01` selects June.
+    pub(crate) before: Option<DateBound>,
+}
+
+impl DateRange {
+    pub(crate) fn contains(&self, datetime: &DateTime<FixedOffset>) -> bool {
+        let local_datetime = datetime.naive_local();
+
+        self.after.is_none_or(|DateBound(after)| local_datetime >= after) && self.before.is_none_or(|DateBound(before)| local_datetime < before)
+    }
+}
@@ -1,4 +1,9 @@
-use glob::{GlobError, MatchOptions};
+use crate::error::RamboError;
+use crate::walk::evaluate_files_from_directory;
+
+use glob::{GlobError, MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
 use std::ffi::OsString;
 use std::fmt::Display;
 use std::ops::Not;
@@ -25,33 +30,48 @@ impl Display for GlobEvaluationError {
             GlobEvaluationError::GlobError(glob_error) => {
                 write!(f, "Failed to evaluate glob: {}", glob_error)
             }
+            // The description already names the failed operation, which is not necessarily a glob evaluation.
             GlobEvaluationError::Other { description, .. } => {
-                write!(f, "Failed to evaluate glob: {}", description)
+                write!(f, "{}", description)
             }
         }
     }
 }
 
-pub fn evaluate_files_from_glob_pattern(pattern: &str, case_insensitive: bool, include_symlinks: bool) -> Option<(Vec<PathBuf>, Vec<GlobEvaluationError>)> {
+/// A file which is matched by several patterns is only returned once.
+/// `max_depth` limits how deep a match may be below the leading components of its pattern which contain no wildcards, 1 meaning only the files directly inside.
+/// Note that the `glob` crate silently skips paths which are not valid UTF-8. Use [crate::walk::evaluate_files_from_directory] for those.
+pub fn evaluate_files_from_glob_patterns(
+    patterns: &[String],
+    case_insensitive: bool,
+    include_symlinks: bool,
+    max_depth: Option<usize>,
+) -> Result<(Vec<PathBuf>, Vec<GlobEvaluationError>), RamboError> {
     let match_options = MatchOptions {
         case_sensitive: case_insensitive.not(),
         ..Default::default()
     };
 
-    let glob_results = match glob::glob_with(pattern, match_options) {
-        Ok(paths) => paths,
-        Err(error) => {
-            log::error!("Failed to interpret glob pattern: {}", error);
-            return None;
-        }
-    };
+    let mut glob_results = Vec::with_capacity(patterns.len());
+    for pattern in patterns {
+        let paths = glob::glob_with(pattern, match_options)
+            .map_err(|error| RamboError::InvalidInput(format!("Failed to interpret glob pattern '{}': {}", pattern, error)))?;
+        glob_results.push((glob_root(pattern), paths));
+    }
+
+    let glob_results = glob_results.into_iter().flat_map(|(root, paths)| {
+        paths.map(move |glob_result| {
+            let depth = depth_below(&root, &glob_result);
+            (glob_result, depth)
+        })
+    });
 
     let (mut paths, mut errors) = glob_results.fold(
         (Vec::<PathBuf>::new(), Vec::<GlobEvaluationError>::new()),
-        |(mut paths, mut errors), glob_result| {
+        |(mut paths, mut errors), (glob_result, depth)| {
             match glob_result {
                 Ok(path) => {
-                    if include_symlinks || path.is_symlink().not() {
+                    if (include_symlinks || path.is_symlink().not()) && max_depth.is_none_or(|max_depth| depth <= max_depth) {
                         match path.canonicalize() {
                             Ok(path) => paths.push(path),
                             Err(error) => {
@@ -71,14 +91,124 @@ pub fn evaluate_files_from_glob_pattern(pattern: &str, case_insensitive: bool, i
         },
     );
 
-    paths.sort_by_key(lowercase_os_str_from_path_buf);
-    errors.sort_by_key(lowercase_os_str_from_glob_evaluation_error);
+    remove_duplicate_paths(&mut paths);
+    sort_paths_and_errors(&mut paths, &mut errors);
+
+    Ok((paths, errors))
+}
+
+/// Decides whether a file is selected by its path relative to the current working directory.
+pub trait PathMatcher {
+    fn matches_path(&self, relative_path: &Path) -> bool;
+}
+
+/// Matches like the evaluation of a glob pattern, so '*' does not match the separators of subdirectories.
+pub struct GlobMatcher {
+    pattern: Pattern,
+    match_options: MatchOptions,
+}
+
+impl GlobMatcher {
+    pub fn new(pattern: &str, case_insensitive: bool) -> Result<Self, RamboError> {
+        Ok(Self {
+            pattern: Pattern::new(pattern).map_err(|error| RamboError::InvalidInput(format!("Failed to interpret glob pattern '{}': {}", pattern, error)))?,
+            match_options: MatchOptions {
+                case_sensitive: case_insensitive.not(),
+                require_literal_separator: true,
+                ..Default::default()
+            },
+        })
+    }
+}
 
-    Some((paths, errors))
+impl PathMatcher for GlobMatcher {
+    fn matches_path(&self, relative_path: &Path) -> bool {
+        self.pattern.matches_path_with(relative_path, self.match_options)
+    }
+}
+
+/// Matches anywhere in the path, unless the regex is anchored with '^' and '$'. The components of the path are always separated by '/'.
+pub struct RegexMatcher {
+    regex: Regex,
+}
+
+impl RegexMatcher {
+    pub fn new(pattern: &str, case_insensitive: bool) -> Result<Self, RamboError> {
+        Ok(Self {
+            regex: RegexBuilder::new(pattern)
+                .case_insensitive(case_insensitive)
+                .build()
+                .map_err(|error| RamboError::InvalidInput(format!("Failed to interpret regex '{}': {}", pattern, error)))?,
+        })
+    }
+}
+
+impl PathMatcher for RegexMatcher {
+    fn matches_path(&self, relative_path: &Path) -> bool {
+        let components = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>();
+
+        self.regex.is_match(&components.join("/"))
+    }
+}
+
+/// An alternative to [evaluate_files_from_glob_patterns] that collects the files below the current working directory whose relative path is matched by the matcher.
+pub fn evaluate_files_from_matcher(
+    matcher: &dyn PathMatcher,
+    max_depth: Option<usize>,
+    include_symlinks: bool,
+) -> Result<(Vec<PathBuf>, Vec<GlobEvaluationError>), RamboError> {
+    let current_working_directory = std::env::current_dir()
+        .and_then(|current_working_directory| current_working_directory.canonicalize())
+        .map_err(RamboError::CurrentWorkingDirectory)?;
+
+    let (mut paths, errors) = evaluate_files_from_directory(&current_working_directory, true, max_depth, include_symlinks);
+    paths.retain(|path| matcher.matches_path(path.strip_prefix(&current_working_directory).unwrap_or(path)));
+
+    Ok((paths, errors))
+}
+
+/// The directory of the leading components of a glob pattern which contain no wildcards.
+pub(crate) fn glob_root(pattern: &str) -> PathBuf {
+    let pattern_path = Path::new(pattern);
+    let root = pattern_path
+        .components()
+        .take_while(|component| component.as_os_str().to_string_lossy().contains(['*', '?', '[']).not())
+        .collect::<PathBuf>();
+
+    // A pattern without wildcards matches a single file.
+    if root == pattern_path {
+        return root.parent().map(Path::to_path_buf).unwrap_or_default();
+    }
+
+    root
+}
+
+/// The number of components of the matched path below the root of its pattern.
+fn depth_below(root: &Path, glob_result: &Result<PathBuf, GlobError>) -> usize {
+    let path = match glob_result {
+        Ok(path) => path.as_path(),
+        Err(error) => error.path(),
+    };
+
+    path.strip_prefix(root).unwrap_or(path).components().count()
+}
+
+/// Keeps the first occurrence of each path.
+pub(crate) fn remove_duplicate_paths(paths: &mut Vec<PathBuf>) {
+    let mut seen_paths = HashSet::new();
+    paths.retain(|path| seen_paths.insert(path.clone()));
+}
+
+pub(crate) fn sort_paths_and_errors(paths: &mut [PathBuf], errors: &mut [GlobEvaluationError]) {
+    paths.sort_by_key(|path_buf| lowercase_os_str_from_path(path_buf));
+    errors.sort_by_key(lowercase_os_str_from_glob_evaluation_error);
 }
 
-fn lowercase_os_str_from_path_buf(path_buf: &PathBuf) -> OsString {
-    path_buf.as_os_str().to_ascii_lowercase()
+fn lowercase_os_str_from_path(path: &Path) -> OsString {
+    path.as_os_str().to_ascii_lowercase()
 }
 
 fn lowercase_os_str_from_glob_evaluation_error(glob_error: &GlobEvaluationError) -> OsString {
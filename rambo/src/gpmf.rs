@@ -0,0 +1,306 @@
+use crate::extract::GpsCoordinates;
+use crate::raw::{find_box, read_box_content, read_up_to};
+
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime};
+use std::io::{Read, Seek, SeekFrom};
+
+/// The GPS of GoPro cameras often needs a while to get a fix, so the samples of the first minutes are searched for one.
+const MAX_SEARCHED_SAMPLES: usize = 300;
+
+/// Samples which are larger are not read, as the telemetry of a second is far smaller.
+const MAX_SAMPLE_LENGTH: u32 = 1 << 20;
+
+/// The capture time and the position of a GoPro clip, from the GPS data of its GPMF telemetry.
+pub(crate) struct GpmfCapture {
+    /// In the local time zone, like the creation datetimes of other videos.
+    pub(crate) datetime: DateTime<FixedOffset>,
+    pub(crate) gps: GpsCoordinates,
+    /// `GPSU` or `GPS9`, whichever the time was taken from.
+    pub(crate) tag: &'static str,
+}
+
+/// A GPS sample of the telemetry, with its time in UTC.
+struct GpsSample {
+    utc_datetime: NaiveDateTime,
+    gps: GpsCoordinates,
+    tag: &'static str,
+}
+
+/// Reads the GPMF telemetry of a GoPro clip, i.e. the `gpmd` track of an MP4 file. The time of the first sample with a GPS fix is moved back
+/// by the time of the sample within the clip, so that it is the start of the recording, which is precise, unlike the clock of the camera.
+/// Returns [None] if the file has no GPMF telemetry or its GPS never got a fix.
+pub(crate) fn read_gpmf_capture<R: Read + Seek>(reader: &mut R) -> Option<GpmfCapture> {
+    let mut header = [0u8; 8];
+    let header_length = read_up_to(reader, &mut header).ok()?;
+    if header_length < header.len() || &header[4..8] != b"ftyp" {
+        return None;
+    }
+
+    let end = reader.seek(SeekFrom::End(0)).ok()?;
+    let (moov_start, moov_end) = find_box(reader, 0, end, |box_type, _| box_type == b"moov").ok()??;
+
+    let mut position = moov_start;
+    while let Some((trak_start, trak_end)) = find_box(reader, position, moov_end, |box_type, _| box_type == b"trak").ok()? {
+        position = trak_end;
+
+        let Some(track) = read_gpmd_track(reader, trak_start, trak_end) else {
+            continue;
+        };
+        for (sample_offset, sample_length, elapsed) in track.samples().take(MAX_SEARCHED_SAMPLES) {
+            if sample_length > MAX_SAMPLE_LENGTH {
+                continue;
+            }
+            let mut sample = vec![0u8; sample_length as usize];
+            if reader.seek(SeekFrom::Start(sample_offset)).is_err() || reader.read_exact(&mut sample).is_err() {
+                return None;
+            }
+
+            if let Some(gps_sample) = find_gps_sample(&sample) {
+                let utc_datetime = gps_sample.utc_datetime - elapsed;
+                return Some(GpmfCapture {
+                    datetime: utc_datetime.and_utc().with_timezone(&Local).fixed_offset(),
+                    gps: gps_sample.gps,
+                    tag: gps_sample.tag,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// The sample tables of the `gpmd` track, from which the offset, the length and the time of each sample are derived.
+struct GpmdTrack {
+    timescale: u32,
+    /// The number of samples and the duration of each of them, in the timescale.
+    time_to_sample: Vec<(u32, u32)>,
+    /// The first chunk, counted from 1, and the number of samples of it and of the following chunks.
+    sample_to_chunk: Vec<(u32, u32)>,
+    sample_lengths: Vec<u32>,
+    chunk_offsets: Vec<u64>,
+}
+
+/// Returns [None] if the track is no `gpmd` track or its sample tables are invalid.
+fn read_gpmd_track<R: Read + Seek>(reader: &mut R, trak_start: u64, trak_end: u64) -> Option<GpmdTrack> {
+    let (mdia_start, mdia_end) = find_box(reader, trak_start, trak_end, |box_type, _| box_type == b"mdia").ok()??;
+    let hdlr = read_box_content(reader, mdia_start, mdia_end, b"hdlr").ok()??;
+    // After the version, the flags and the predefined value comes the handler type.
+    if hdlr.get(8..12)? != b"meta" {
+        return None;
+    }
+
+    let mdhd = read_box_content(reader, mdia_start, mdia_end, b"mdhd").ok()??;
+    // Version 1 has 64 bit creation and modification times before the timescale.
+    let timescale = if mdhd.first()? == &1 {
+        be_u32(&mdhd, 20)?
+    } else {
+        be_u32(&mdhd, 12)?
+    };
+
+    let (minf_start, minf_end) = find_box(reader, mdia_start, mdia_end, |box_type, _| box_type == b"minf").ok()??;
+    let (stbl_start, stbl_end) = find_box(reader, minf_start, minf_end, |box_type, _| box_type == b"stbl").ok()??;
+    let table = |reader: &mut R, box_type: &[u8; 4]| read_box_content(reader, stbl_start, stbl_end, box_type).ok().flatten();
+
+    // The format of the first sample description follows the version, the flags, the number of descriptions and the size of the first one.
+    let stsd = table(reader, b"stsd")?;
+    if stsd.get(12..16)? != b"gpmd" {
+        return None;
+    }
+
+    let stts = table(reader, b"stts")?;
+    let time_to_sample = (0..be_u32(&stts, 4)?)
+        .map(|index| Some((be_u32(&stts, 8 + index as usize * 8)?, be_u32(&stts, 12 + index as usize * 8)?)))
+        .collect::<Option<Vec<_>>>()?;
+
+    let stsc = table(reader, b"stsc")?;
+    let sample_to_chunk = (0..be_u32(&stsc, 4)?)
+        .map(|index| Some((be_u32(&stsc, 8 + index as usize * 12)?, be_u32(&stsc, 12 + index as usize * 12)?)))
+        .collect::<Option<Vec<_>>>()?;
+
+    let stsz = table(reader, b"stsz")?;
+    let (uniform_length, sample_count) = (be_u32(&stsz, 4)?, be_u32(&stsz, 8)?);
+    let sample_lengths = (0..sample_count)
+        .map(|index| {
+            if uniform_length != 0 {
+                Some(uniform_length)
+            } else {
+                be_u32(&stsz, 12 + index as usize * 4)
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let chunk_offsets = match table(reader, b"stco") {
+        Some(stco) => (0..be_u32(&stco, 4)?)
+            .map(|index| be_u32(&stco, 8 + index as usize * 4).map(u64::from))
+            .collect::<Option<Vec<_>>>()?,
+        None => {
+            let co64 = table(reader, b"co64")?;
+            (0..be_u32(&co64, 4)?)
+                .map(|index| be_u64(&co64, 8 + index as usize * 8))
+                .collect::<Option<Vec<_>>>()?
+        }
+    };
+
+    (timescale != 0).then_some(GpmdTrack {
+        timescale,
+        time_to_sample,
+        sample_to_chunk,
+        sample_lengths,
+        chunk_offsets,
+    })
+}
+
+impl GpmdTrack {
+    /// The offset and the length of each sample in the file and the time at which it starts within the clip.
+    fn samples(&self) -> impl Iterator<Item = (u64, u32, Duration)> + '_ {
+        let sample_durations = self
+            .time_to_sample
+            .iter()
+            .flat_map(|(count, duration)| std::iter::repeat_n(*duration, *count as usize));
+        let sample_starts = sample_durations.scan(0u64, |start, duration| {
+            let sample_start = *start;
+            *start = start.saturating_add(u64::from(duration));
+            let milliseconds = sample_start.saturating_mul(1000) / u64::from(self.timescale);
+            Some(Duration::milliseconds(i64::try_from(milliseconds).unwrap_or(i64::MAX)))
+        });
+
+        let sample_positions = self.chunk_offsets.iter().enumerate().flat_map(move |(chunk_index, chunk_offset)| {
+            let chunk_number = chunk_index as u32 + 1;
+            let samples_per_chunk = self
+                .sample_to_chunk
+                .iter()
+                .rev()
+                .find(|(first_chunk, _)| *first_chunk <= chunk_number)
+                .map_or(0, |(_, samples_per_chunk)| *samples_per_chunk);
+            std::iter::repeat_n(*chunk_offset, samples_per_chunk as usize)
+        });
+
+        let mut offset_in_chunk = 0;
+        let mut current_chunk_offset = None;
+        sample_positions
+            .zip(&self.sample_lengths)
+            .map(move |(chunk_offset, sample_length)| {
+                if current_chunk_offset != Some(chunk_offset) {
+                    current_chunk_offset = Some(chunk_offset);
+                    offset_in_chunk = 0;
+                }
+                let sample_offset = chunk_offset + offset_in_chunk;
+                offset_in_chunk += u64::from(*sample_length);
+                (sample_offset, *sample_length)
+            })
+            .zip(sample_starts)
+            .map(|((sample_offset, sample_length), sample_start)| (sample_offset, sample_length, sample_start))
+    }
+}
+
+/// A GPMF entry has a key, a type, the size of each value and the number of values, followed by the values, which are padded to 4 bytes.
+/// Entries of the type 0 contain other entries, like the `DEVC` of each device and the `STRM` of each of its streams.
+fn gpmf_entries(data: &[u8]) -> impl Iterator<Item = (&[u8], u8, usize, &[u8])> {
+    let mut position = 0;
+    std::iter::from_fn(move || {
+        let header = data.get(position..position + 8)?;
+        let (key, value_type, value_size) = (&header[..4], header[4], usize::from(header[5]));
+        let repeat = usize::from(u16::from_be_bytes([header[6], header[7]]));
+        let length = value_size * repeat;
+        let value = data.get(position + 8..position + 8 + length)?;
+
+        position += 8 + length.div_ceil(4) * 4;
+        Some((key, value_type, value_size, value))
+    })
+}
+
+fn find_gps_sample(data: &[u8]) -> Option<GpsSample> {
+    gpmf_entries(data).find_map(|(key, value_type, _, value)| match (key, value_type) {
+        (b"STRM", 0) => read_gps_stream(value),
+        (_, 0) => find_gps_sample(value),
+        _ => None,
+    })
+}
+
+/// Newer cameras write `GPS9`, whose samples contain their time, older ones `GPS5` along with `GPSU`, the time of the first sample.
+/// The values are integers, which are divided by the scales of `SCAL`. A fix of 0 means the GPS has no fix, so its time and position are unknown.
+fn read_gps_stream(stream: &[u8]) -> Option<GpsSample> {
+    let mut scales = Vec::<f64>::new();
+    let mut fix = None;
+    let mut gpsu = None;
+    let mut gps5 = None;
+    let mut gps9 = None;
+
+    for (key, value_type, value_size, value) in gpmf_entries(stream) {
+        match key {
+            b"SCAL" => scales = integers(value_type, value).collect(),
+            b"GPSF" => fix = integers(value_type, value).next(),
+            b"GPSU" if value_type == b'U' => gpsu = Some(String::from_utf8_lossy(value).into_owned()),
+            b"GPS5" if value_type == b'l' && value_size >= 20 => gps5 = Some(&value[..20]),
+            // The type of `GPS9` is a structure, whose first 7 values are 32 bit integers, followed by 2 16 bit integers.
+            b"GPS9" if value_size >= 32 => gps9 = Some(&value[..32]),
+            _ => {}
+        }
+    }
+    let scale = |index: usize| scales.get(index).or(scales.last()).copied().filter(|scale| *scale != 0.0).unwrap_or(1.0);
+
+    let (utc_datetime, values, tag) = if let Some(gps9) = gps9 {
+        if u16::from_be_bytes([gps9[30], gps9[31]]) == 0 {
+            return None;
+        }
+        let days = f64::from(be_i32(gps9, 20)?) / scale(5);
+        let seconds = f64::from(be_i32(gps9, 24)?) / scale(6);
+        // Garbage values would overflow the datetime.
+        let utc_datetime = NaiveDate::from_ymd_opt(2000, 1, 1)?
+            .and_hms_opt(0, 0, 0)?
+            .checked_add_signed(Duration::try_days(days as i64)?)?
+            .checked_add_signed(Duration::try_milliseconds((seconds * 1000.0) as i64)?)?;
+        (utc_datetime, gps9, "GPS9")
+    } else {
+        if fix.is_some_and(|fix| fix == 0.0) {
+            return None;
+        }
+        // Like `240501183012.345`, in UTC.
+        let utc_datetime = NaiveDateTime::parse_from_str(&format!("20{}", gpsu?.trim_end_matches('\0')), "%Y%m%d%H%M%S%.f").ok()?;
+        (utc_datetime, gps5?, "GPSU")
+    };
+
+    let gps = GpsCoordinates {
+        latitude: f64::from(be_i32(values, 0)?) / scale(0),
+        longitude: f64::from(be_i32(values, 4)?) / scale(1),
+    };
+    // Without a fix, some cameras write zeros instead of setting `GPSF`.
+    if gps.latitude == 0.0 && gps.longitude == 0.0 {
+        return None;
+    }
+
+    Some(GpsSample { utc_datetime, gps, tag })
+}
+
+/// The values of an entry of 8, 16 or 32 bit integers.
+fn integers(value_type: u8, value: &[u8]) -> Box<dyn Iterator<Item = f64> + '_> {
+    match value_type {
+        b'b' => Box::new(value.iter().map(|byte| f64::from(*byte as i8))),
+        b'B' => Box::new(value.iter().map(|byte| f64::from(*byte))),
+        b's' => Box::new(value.chunks_exact(2).map(|bytes| f64::from(i16::from_be_bytes([bytes[0], bytes[1]])))),
+        b'S' => Box::new(value.chunks_exact(2).map(|bytes| f64::from(u16::from_be_bytes([bytes[0], bytes[1]])))),
+        b'l' => Box::new(
+            value
+                .chunks_exact(4)
+                .map(|bytes| f64::from(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))),
+        ),
+        b'L' => Box::new(
+            value
+                .chunks_exact(4)
+                .map(|bytes| f64::from(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))),
+        ),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+fn be_u32(bytes: &[u8], index: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(index..index + 4)?.try_into().ok()?))
+}
+
+fn be_i32(bytes: &[u8], index: usize) -> Option<i32> {
+    Some(i32::from_be_bytes(bytes.get(index..index + 4)?.try_into().ok()?))
+}
+
+fn be_u64(bytes: &[u8], index: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(bytes.get(index..index + 8)?.try_into().ok()?))
+}
@@ -0,0 +1,70 @@
+use crate::rename_map::is_rename_map;
+use crate::resume::RESUME_STATE_FILE_NAME;
+
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// The name of the files which list the paths that are never processed, with the syntax of `.gitignore` files.
+pub const IGNORE_FILE_NAME: &str = ".ramboignore";
+
+/// The ignore files in the directories of the paths. Each directory is only read once.
+#[derive(Default)]
+pub(crate) struct IgnoreFiles {
+    /// `None` if the directory has no ignore file or it cannot be read.
+    ignore_files_by_directory: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl IgnoreFiles {
+    /// Removes the paths which are ignored, as well as the ignore files themselves, the rename maps and the resume state.
+    pub(crate) fn remove_ignored_paths(&mut self, paths: &mut Vec<PathBuf>) {
+        paths.retain(|path| self.is_ignored(path).not());
+    }
+
+    /// Whether the path is ignored by an ignore file in its directory or in one of the parent directories.
+    /// Like with `.gitignore` files, the patterns of a deeper directory take precedence, so they can re-include paths with `!`.
+    /// The path must be absolute, like the canonical paths of the evaluated input.
+    pub(crate) fn is_ignored(&mut self, path: &Path) -> bool {
+        if path.file_name() == Some(OsStr::new(IGNORE_FILE_NAME)) || path.file_name() == Some(OsStr::new(RESUME_STATE_FILE_NAME)) || is_rename_map(path) {
+            return true;
+        }
+
+        for directory in path.ancestors().skip(1) {
+            let ignore_file = self
+                .ignore_files_by_directory
+                .entry(directory.to_path_buf())
+                .or_insert_with(|| read_ignore_file(directory));
+
+            match ignore_file.as_ref().map(|ignore_file| ignore_file.matched_path_or_any_parents(path, false)) {
+                Some(Match::Ignore(_)) => return true,
+                Some(Match::Whitelist(_)) => return false,
+                Some(Match::None) | None => {}
+            }
+        }
+
+        false
+    }
+}
+
+fn read_ignore_file(directory: &Path) -> Option<Gitignore> {
+    let ignore_file_path = directory.join(IGNORE_FILE_NAME);
+    if ignore_file_path.is_file().not() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(directory);
+    if let Some(error) = builder.add(&ignore_file_path) {
+        log::warn!("Failed to read {}: {}", ignore_file_path.display(), error);
+    }
+
+    match builder.build() {
+        Ok(ignore_file) => Some(ignore_file),
+        Err(error) => {
+            log::warn!("Failed to read {}: {}", ignore_file_path.display(), error);
+            None
+        }
+    }
+}
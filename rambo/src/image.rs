@@ -0,0 +1,380 @@
+use crate::error::ExtractError;
+use crate::extract::{DatetimeSource, ExtractedDatetime, MediaMetadata, extract_creation_datetime_from_xmp, in_local_time_zone};
+use crate::raw::{RawIfds, find_box, invalid_data, read_box_content, read_tiff_ifds, read_u32_be, read_up_to};
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, Timelike};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+/// The image formats which nom-exif cannot read, but whose metadata is read natively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageContainer {
+    Png,
+    WebP,
+    Avif,
+}
+
+/// Chunks which are larger, like the image data, are not read, as no metadata is that large.
+const MAX_METADATA_LENGTH: u32 = 1 << 20;
+
+/// The keyword of the PNG text chunk with the XMP packet.
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+
+/// The keyword of the PNG text chunk with the creation time, which is free-form text, but mostly RFC 2822 or EXIF.
+const PNG_CREATION_TIME_KEYWORD: &[u8] = b"Creation Time";
+
+/// Whether the file is an image which is read natively, by its magic bytes. The reader is rewound afterwards.
+pub(crate) fn is_image_container<R: Read + Seek>(reader: &mut R) -> bool {
+    detect_image_container(reader).is_ok_and(|image_container| image_container.is_some())
+}
+
+/// Returns [None] if the file is no PNG, WebP or AVIF file, so that it is parsed with nom-exif instead.
+pub(crate) fn extract_creation_datetime_from_image<R: Read + Seek>(reader: &mut R) -> Option<Result<ExtractedDatetime, ExtractError>> {
+    read_image_metadata(reader).map(|image_metadata| image_metadata.and_then(|image_metadata| image_metadata.creation_datetime()))
+}
+
+/// Returns [None] if the file is no PNG, WebP or AVIF file, so that it is parsed with nom-exif instead.
+/// Only the EXIF data is read, as the other places of the creation datetime have no equivalent of the tag.
+pub(crate) fn extract_exif_tag_datetime_from_image<R: Read + Seek>(reader: &mut R, tag_name: &'static str) -> Option<Result<ExtractedDatetime, ExtractError>> {
+    read_image_metadata(reader).map(|image_metadata| {
+        image_metadata.and_then(|image_metadata| {
+            image_metadata
+                .exif
+                .ok_or(ExtractError::MissingDatetime("image EXIF"))?
+                .exif_tag_datetime(tag_name)
+        })
+    })
+}
+
+/// Returns [None] if the file is no PNG, WebP or AVIF file, so that it is parsed with nom-exif instead.
+pub(crate) fn extract_metadata_from_image<R: Read + Seek>(reader: &mut R) -> Option<Result<MediaMetadata, ExtractError>> {
+    read_image_metadata(reader).map(|image_metadata| image_metadata.and_then(|image_metadata| image_metadata.metadata()))
+}
+
+fn detect_image_container<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<ImageContainer>> {
+    let mut header = [0u8; 12];
+    let header_length = read_up_to(reader, &mut header)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    Ok(match &header[..header_length] {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some(ImageContainer::Png),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P'] => Some(ImageContainer::WebP),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f' | b's'] => Some(ImageContainer::Avif),
+        _ => None,
+    })
+}
+
+fn read_image_metadata<R: Read + Seek>(reader: &mut R) -> Option<Result<ImageMetadata, ExtractError>> {
+    let image_container = match detect_image_container(reader) {
+        Ok(image_container) => image_container?,
+        Err(error) => return Some(Err(ExtractError::io("Cannot read the file")(error))),
+    };
+
+    let image_metadata = match image_container {
+        ImageContainer::Png => read_png_metadata(reader),
+        ImageContainer::WebP => read_webp_metadata(reader),
+        ImageContainer::Avif => read_avif_metadata(reader),
+    };
+
+    Some(image_metadata.map_err(ExtractError::io(format!("Failed to parse the {:?} file", image_container))))
+}
+
+/// The metadata of an image, which may be in several places. Any of them may be missing.
+#[derive(Default)]
+struct ImageMetadata {
+    exif: Option<RawIfds>,
+    xmp: Option<String>,
+    /// From the `Creation Time` text chunk of a PNG file.
+    text_creation_time: Option<String>,
+    /// From the `tIME` chunk of a PNG file, which is the time of the last modification of the image, in UTC.
+    modification_time: Option<DateTime<FixedOffset>>,
+}
+
+impl ImageMetadata {
+    /// The EXIF data takes precedence over the XMP packet and the text chunks. The last modification of a PNG file is the last resort.
+    fn creation_datetime(&self) -> Result<ExtractedDatetime, ExtractError> {
+        if let Some(exif) = &self.exif
+            && let Ok(extracted_datetime) = exif.creation_datetime()
+        {
+            return Ok(extracted_datetime);
+        }
+
+        if let Some(xmp) = &self.xmp
+            && let Ok(datetime) = extract_creation_datetime_from_xmp(xmp)
+        {
+            return Ok(ExtractedDatetime {
+                datetime,
+                source: DatetimeSource::Xmp,
+            });
+        }
+
+        if let Some(datetime) = self.text_creation_time.as_deref().and_then(parse_text_creation_time) {
+            return Ok(ExtractedDatetime {
+                datetime,
+                source: DatetimeSource::PngChunk("Creation Time"),
+            });
+        }
+
+        self.modification_time
+            .map(|datetime| ExtractedDatetime {
+                datetime,
+                source: DatetimeSource::PngChunk("tIME"),
+            })
+            .ok_or(ExtractError::MissingDatetime("image"))
+    }
+
+    /// The other metadata, like the camera, is only known from the EXIF data.
+    fn metadata(&self) -> Result<MediaMetadata, ExtractError> {
+        let datetime = self.creation_datetime()?.datetime;
+
+        Ok(match self.exif.as_ref().map(RawIfds::metadata) {
+            Some(Ok(metadata)) => MediaMetadata {
+                datetime,
+                has_subseconds: datetime.nanosecond() != 0,
+                ..metadata
+            },
+            Some(Err(_)) | None => MediaMetadata::from(datetime),
+        })
+    }
+}
+
+/// Each chunk has its length, its type, its data and a CRC. The metadata chunks may come before or after the image data.
+fn read_png_metadata<R: Read + Seek>(reader: &mut R) -> std::io::Result<ImageMetadata> {
+    let mut image_metadata = ImageMetadata::default();
+    let mut position = 8;
+
+    loop {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut chunk_header = [0u8; 8];
+        // A file without an `IEND` chunk, like the head of an object, ends after its last complete chunk.
+        if read_up_to(reader, &mut chunk_header)? < chunk_header.len() {
+            break;
+        }
+        let chunk_length = u32::from_be_bytes([chunk_header[0], chunk_header[1], chunk_header[2], chunk_header[3]]);
+        let chunk_type = [chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7]];
+        let data_start = position + 8;
+        position = data_start + u64::from(chunk_length) + 4;
+
+        match &chunk_type {
+            b"IEND" => break,
+            b"eXIf" => image_metadata.exif = Some(read_exif_payload(reader, data_start, chunk_length)?),
+            b"tIME" if chunk_length == 7 => {
+                let mut time = [0u8; 7];
+                reader.read_exact(&mut time)?;
+                image_metadata.modification_time = NaiveDate::from_ymd_opt(i32::from(u16::from_be_bytes([time[0], time[1]])), time[2].into(), time[3].into())
+                    .and_then(|date| date.and_hms_opt(time[4].into(), time[5].into(), time[6].into()))
+                    .map(|naive_datetime| naive_datetime.and_utc().with_timezone(&Local).fixed_offset());
+            }
+            b"tEXt" | b"iTXt" if chunk_length <= MAX_METADATA_LENGTH => {
+                let mut data = vec![0u8; chunk_length as usize];
+                reader.read_exact(&mut data)?;
+                read_png_text_chunk(&chunk_type, &data, &mut image_metadata);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(image_metadata)
+}
+
+/// The keyword is followed by a NUL byte. In `iTXt` chunks, it is followed by the compression flag and method, the language tag and the translated keyword.
+/// Compressed text is skipped.
+fn read_png_text_chunk(chunk_type: &[u8; 4], data: &[u8], image_metadata: &mut ImageMetadata) {
+    let Some(keyword_end) = data.iter().position(|byte| *byte == 0) else {
+        return;
+    };
+    let (keyword, rest) = (&data[..keyword_end], &data[keyword_end + 1..]);
+
+    let text = if chunk_type == b"iTXt" {
+        let [0, _, rest @ ..] = rest else {
+            return;
+        };
+        let mut fields = rest.splitn(3, |byte| *byte == 0);
+        let (Some(_), Some(_), Some(text)) = (fields.next(), fields.next(), fields.next()) else {
+            return;
+        };
+        text
+    } else {
+        rest
+    };
+    let text = String::from_utf8_lossy(text).trim().to_string();
+
+    if keyword == PNG_XMP_KEYWORD {
+        image_metadata.xmp = Some(text);
+    } else if keyword == PNG_CREATION_TIME_KEYWORD {
+        image_metadata.text_creation_time = Some(text);
+    }
+}
+
+/// Like `Wed, 01 May 2024 10:00:00 +0200`, `2024-05-01T10:00:00+02:00` or `2024:05:01 10:00:00`, which is in the local time zone.
+fn parse_text_creation_time(text: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc2822(text)
+        .or_else(|_| DateTime::parse_from_rfc3339(text))
+        .ok()
+        .or_else(|| {
+            ["%Y:%m:%d %H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+                .and_then(|naive_datetime| in_local_time_zone(naive_datetime).ok())
+        })
+}
+
+/// Each chunk has its type, its length and its data, which is padded to an even length.
+fn read_webp_metadata<R: Read + Seek>(reader: &mut R) -> std::io::Result<ImageMetadata> {
+    let mut image_metadata = ImageMetadata::default();
+    let end = reader.seek(SeekFrom::End(0))?;
+    let mut position = 12;
+
+    while position + 8 <= end {
+        reader.seek(SeekFrom::Start(position))?;
+        let mut chunk_header = [0u8; 8];
+        reader.read_exact(&mut chunk_header)?;
+        let chunk_length = u32::from_le_bytes([chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7]]);
+        let data_start = position + 8;
+        position = data_start + u64::from(chunk_length) + u64::from(chunk_length % 2);
+
+        match &chunk_header[..4] {
+            b"EXIF" => image_metadata.exif = Some(read_exif_payload(reader, data_start, chunk_length)?),
+            b"XMP " if chunk_length <= MAX_METADATA_LENGTH => {
+                let mut data = vec![0u8; chunk_length as usize];
+                reader.read_exact(&mut data)?;
+                image_metadata.xmp = Some(String::from_utf8_lossy(&data).into_owned());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(image_metadata)
+}
+
+/// The EXIF data of PNG and WebP files is a TIFF structure, but some writers put the `Exif\0\0` identifier of JPEG files in front of it.
+fn read_exif_payload<R: Read + Seek>(reader: &mut R, data_start: u64, data_length: u32) -> std::io::Result<RawIfds> {
+    let mut identifier = [0u8; 6];
+    reader.seek(SeekFrom::Start(data_start))?;
+    let identifier_length = read_up_to(reader, &mut identifier)?;
+
+    if data_length >= 6 && identifier_length == 6 && &identifier == b"Exif\0\0" {
+        read_tiff_ifds(reader, data_start + 6)
+    } else {
+        read_tiff_ifds(reader, data_start)
+    }
+}
+
+/// The `meta` box lists the items of the file in its `iinf` box and their location in its `iloc` box. The EXIF data is the item of the type `Exif`.
+fn read_avif_metadata<R: Read + Seek>(reader: &mut R) -> std::io::Result<ImageMetadata> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    let (meta_start, meta_end) = find_box(reader, 0, end, |box_type, _| box_type == b"meta")?.ok_or_else(|| invalid_data("There is no 'meta' box"))?;
+    // The `meta` box is a full box, whose content starts after its version and flags.
+    let meta_start = meta_start + 4;
+
+    let Some(iinf) = read_box_content(reader, meta_start, meta_end, b"iinf")? else {
+        return Ok(ImageMetadata::default());
+    };
+    let Some(exif_item_id) = find_exif_item_id(&iinf)? else {
+        return Ok(ImageMetadata::default());
+    };
+    let iloc = read_box_content(reader, meta_start, meta_end, b"iloc")?.ok_or_else(|| invalid_data("There is no 'iloc' box"))?;
+    let Some(exif_offset) = find_item_offset(&iloc, exif_item_id)? else {
+        return Ok(ImageMetadata::default());
+    };
+
+    // The EXIF item starts with the offset of the TIFF header, which follows the identifier `Exif\0\0`.
+    reader.seek(SeekFrom::Start(exif_offset))?;
+    let tiff_header_offset = read_u32_be(reader)?;
+
+    let tiff_header_start = exif_offset
+        .checked_add(4 + u64::from(tiff_header_offset))
+        .ok_or_else(|| invalid_data("The offset of the EXIF item is invalid"))?;
+
+    Ok(ImageMetadata {
+        exif: Some(read_tiff_ifds(reader, tiff_header_start)?),
+        ..ImageMetadata::default()
+    })
+}
+
+/// The `iinf` box contains an `infe` box for each item, with its ID and its type.
+fn find_exif_item_id(iinf: &[u8]) -> std::io::Result<Option<u32>> {
+    let mut cursor = Cursor::new(iinf);
+    let version = read_uint(&mut cursor, 4)? >> 24;
+    let entry_count = read_uint(&mut cursor, if version == 0 { 2 } else { 4 })?;
+
+    for _ in 0..entry_count {
+        let infe_start = cursor.position();
+        let infe_size = read_uint(&mut cursor, 4)?;
+        let mut infe_type = [0u8; 4];
+        cursor.read_exact(&mut infe_type)?;
+        if &infe_type != b"infe" || infe_size < 8 {
+            return Err(invalid_data("The 'iinf' box is invalid"));
+        }
+
+        // Only the versions 2 and 3 of the `infe` box have an item type.
+        let infe_version = read_uint(&mut cursor, 4)? >> 24;
+        if infe_version >= 2 {
+            let item_id = read_uint(&mut cursor, if infe_version == 2 { 2 } else { 4 })?;
+            let _protection_index = read_uint(&mut cursor, 2)?;
+            let mut item_type = [0u8; 4];
+            cursor.read_exact(&mut item_type)?;
+            if &item_type == b"Exif" {
+                return Ok(Some(item_id as u32));
+            }
+        }
+
+        cursor.set_position(infe_start + infe_size);
+    }
+
+    Ok(None)
+}
+
+/// Returns the offset of the first extent of the item in the file. Items which are constructed otherwise, like from other items, are not supported.
+fn find_item_offset(iloc: &[u8], item_id: u32) -> std::io::Result<Option<u64>> {
+    let mut cursor = Cursor::new(iloc);
+    let version = read_uint(&mut cursor, 4)? >> 24;
+    let sizes = read_uint(&mut cursor, 2)?;
+    let (offset_size, length_size, base_offset_size) = ((sizes >> 12) & 0xF, (sizes >> 8) & 0xF, (sizes >> 4) & 0xF);
+    let index_size = if version == 1 || version == 2 {
+        sizes & 0xF
+    } else {
+        0
+    };
+    let item_count = read_uint(&mut cursor, if version < 2 { 2 } else { 4 })?;
+
+    for _ in 0..item_count {
+        let current_item_id = read_uint(&mut cursor, if version < 2 { 2 } else { 4 })?;
+        let construction_method = if version == 1 || version == 2 {
+            read_uint(&mut cursor, 2)? & 0xF
+        } else {
+            0
+        };
+        let _data_reference_index = read_uint(&mut cursor, 2)?;
+        let base_offset = read_uint(&mut cursor, base_offset_size)?;
+        let extent_count = read_uint(&mut cursor, 2)?;
+
+        let mut first_extent_offset = None;
+        for _ in 0..extent_count {
+            let _extent_index = read_uint(&mut cursor, index_size)?;
+            let extent_offset = read_uint(&mut cursor, offset_size)?;
+            let _extent_length = read_uint(&mut cursor, length_size)?;
+            first_extent_offset = first_extent_offset.or(Some(extent_offset));
+        }
+
+        if current_item_id == u64::from(item_id) {
+            return Ok((construction_method == 0)
+                .then_some(first_extent_offset)
+                .flatten()
+                .and_then(|extent_offset| base_offset.checked_add(extent_offset)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reads a big-endian unsigned integer of 0, 2, 4 or 8 bytes.
+fn read_uint(cursor: &mut Cursor<&[u8]>, size: u64) -> std::io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    let size = usize::try_from(size)
+        .ok()
+        .filter(|size| matches!(size, 0 | 2 | 4 | 8))
+        .ok_or_else(|| invalid_data("An integer has an invalid size"))?;
+    cursor.read_exact(&mut bytes[8 - size..])?;
+
+    Ok(u64::from_be_bytes(bytes))
+}
@@ -0,0 +1,163 @@
+use crate::RamboError;
+use crate::glob::{
+    GlobEvaluationError, RegexMatcher, evaluate_files_from_glob_patterns, evaluate_files_from_matcher, remove_duplicate_paths, sort_paths_and_errors,
+};
+use crate::ignore_file::IgnoreFiles;
+use crate::walk::evaluate_files_from_directory;
+
+use std::io::Read;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// The path of a file list which stands for stdin.
+pub const STDIN_FILE_LIST: &str = "-";
+
+/// Where the files that are processed come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputSource {
+    /// The files matching any of the glob patterns.
+    GlobPatterns(Vec<String>),
+    /// The files in the directory, including the ones in all subdirectories if `recursive` is set.
+    Directory { path: PathBuf, recursive: bool },
+    /// The files below the current working directory whose relative path is matched by the regex.
+    Regex(String),
+    /// The files listed in a file, or in stdin if the path is [STDIN_FILE_LIST].
+    /// The paths are separated by newlines, or by NUL characters if `null_separated` is set, like the output of `find -print0`.
+    FileList { path: PathBuf, null_separated: bool },
+}
+
+impl InputSource {
+    /// `case_insensitive` only applies to glob patterns and regexes, `max_depth` does not apply to file lists.
+    /// A glob pattern which names an existing directory, like `Pictures/2023`, stands for all files in it and its subdirectories.
+    /// Paths which are ignored by a [crate::IGNORE_FILE_NAME] file are never returned.
+    /// Returns an error if the source itself is invalid, like a malformed glob pattern or a file list that cannot be read.
+    pub fn evaluate(
+        &self,
+        case_insensitive: bool,
+        include_symlinks: bool,
+        max_depth: Option<usize>,
+    ) -> Result<(Vec<PathBuf>, Vec<GlobEvaluationError>), RamboError> {
+        let evaluation = match self {
+            InputSource::GlobPatterns(patterns) => evaluate_glob_patterns_and_directories(patterns, case_insensitive, include_symlinks, max_depth),
+            InputSource::Directory { path, recursive } => Ok(evaluate_files_from_directory(path, *recursive, max_depth, include_symlinks)),
+            InputSource::Regex(pattern) => {
+                RegexMatcher::new(pattern, case_insensitive).and_then(|matcher| evaluate_files_from_matcher(&matcher, max_depth, include_symlinks))
+            }
+            InputSource::FileList { path, null_separated } => evaluate_files_from_list(path, *null_separated, include_symlinks),
+        };
+        let (mut paths, errors) = evaluation?;
+
+        IgnoreFiles::default().remove_ignored_paths(&mut paths);
+
+        Ok((paths, errors))
+    }
+
+    /// Describes the source for log messages, like "directory 'Pictures'".
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            InputSource::GlobPatterns(patterns) => {
+                let quoted_patterns = patterns.iter().map(|pattern| format!("'{}'", pattern)).collect::<Vec<_>>();
+
+                match quoted_patterns.as_slice() {
+                    [quoted_pattern] => format!("glob pattern {}", quoted_pattern),
+                    _ => format!("glob patterns {}", quoted_patterns.join(", ")),
+                }
+            }
+            InputSource::Directory { path, .. } => format!("directory '{}'", path.display()),
+            InputSource::Regex(pattern) => format!("regex '{}'", pattern),
+            InputSource::FileList { path, .. } if path.as_os_str() == STDIN_FILE_LIST => "file list from stdin".to_string(),
+            InputSource::FileList { path, .. } => format!("file list '{}'", path.display()),
+        }
+    }
+}
+
+/// The patterns which name an existing directory are walked instead of evaluated.
+fn evaluate_glob_patterns_and_directories(
+    patterns: &[String],
+    case_insensitive: bool,
+    include_symlinks: bool,
+    max_depth: Option<usize>,
+) -> Result<(Vec<PathBuf>, Vec<GlobEvaluationError>), RamboError> {
+    let (directories, patterns) = patterns.iter().cloned().partition::<Vec<_>, _>(|pattern| Path::new(pattern).is_dir());
+
+    let (mut paths, mut errors) = evaluate_files_from_glob_patterns(&patterns, case_insensitive, include_symlinks, max_depth)?;
+    for directory in directories {
+        let (directory_paths, directory_errors) = evaluate_files_from_directory(Path::new(&directory), true, max_depth, include_symlinks);
+        paths.extend(directory_paths);
+        errors.extend(directory_errors);
+    }
+
+    remove_duplicate_paths(&mut paths);
+    sort_paths_and_errors(&mut paths, &mut errors);
+
+    Ok((paths, errors))
+}
+
+/// An alternative to [crate::glob::evaluate_files_from_glob_patterns] that collects the files listed in a file or in stdin.
+/// Relative paths are relative to the current working directory. Empty lines are skipped and a file which is listed several times is only returned once.
+pub fn evaluate_files_from_list(
+    list_path: &Path,
+    null_separated: bool,
+    include_symlinks: bool,
+) -> Result<(Vec<PathBuf>, Vec<GlobEvaluationError>), RamboError> {
+    let content = if list_path.as_os_str() == STDIN_FILE_LIST {
+        let mut content = Vec::new();
+        std::io::stdin().lock().read_to_end(&mut content).map(|_| content)
+    } else {
+        std::fs::read(list_path)
+    };
+    let content = content.map_err(RamboError::io(format!("Failed to read the file list '{}'", list_path.display())))?;
+
+    let separator = if null_separated {
+        b'\0'
+    } else {
+        b'\n'
+    };
+    let mut paths = Vec::new();
+    let mut errors = Vec::new();
+
+    for entry in content.split(|&byte| byte == separator) {
+        let entry = if null_separated {
+            entry
+        } else {
+            entry.strip_suffix(b"\r").unwrap_or(entry)
+        };
+        if entry.is_empty() {
+            continue;
+        }
+
+        let path = path_from_bytes(entry);
+        if include_symlinks.not() && path.is_symlink() {
+            continue;
+        }
+
+        match path.canonicalize() {
+            Ok(canonical_path) => paths.push(canonical_path),
+            Err(error) => {
+                let error_description = format!("Failed to canonicalize path '{}': {}", path.display(), error);
+                errors.push(GlobEvaluationError::Other {
+                    path_buf: path,
+                    description: error_description,
+                });
+            }
+        }
+    }
+
+    remove_duplicate_paths(&mut paths);
+    sort_paths_and_errors(&mut paths, &mut errors);
+
+    Ok((paths, errors))
+}
+
+#[cfg(unix)]
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
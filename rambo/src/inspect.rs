@@ -0,0 +1,97 @@
+use crate::extract::{Backend, DateSource, ExtractionSettings, Fallback};
+use crate::extractor::ExtractorChain;
+use crate::input::InputSource;
+use crate::sidecar::remove_xmp_sidecars;
+use crate::statistics::Statistics;
+use crate::{
+    collect_paths_or_exit, current_working_directory, datetime_transform, extract_creation_datetimes, format_path_buf_without_prefix, log_error,
+    record_extraction,
+};
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+
+pub struct InspectOptions {
+    pub input: InputSource,
+    pub case_insensitive: bool,
+    pub include_symlinks: bool,
+    /// The maximum depth of the walked directories, 1 meaning only the files directly inside. `None` for no limit.
+    pub max_depth: Option<usize>,
+    pub time_zone: Option<String>,
+    pub shift: Option<String>,
+    /// Where the creation datetime is taken from, tried in order.
+    pub date_sources: Vec<DateSource>,
+    pub backend: Backend,
+    pub extractor_cmd: Option<String>,
+    pub fallbacks: Vec<Fallback>,
+    /// Whether the creation datetimes of PDFs and OOXML documents are read, from their document properties.
+    pub documents: bool,
+    pub jobs: usize,
+}
+
+/// Prints the creation datetime of each file, without renaming anything.
+pub fn inspect(options: InspectOptions) -> ExitCode {
+    let mut statistics = Statistics::new();
+
+    let current_working_directory = match current_working_directory() {
+        Ok(current_working_directory) => current_working_directory,
+        Err(error) => return log_error(error),
+    };
+
+    let datetime_transform = match datetime_transform(options.time_zone.as_deref(), options.shift.as_deref(), &BTreeMap::new()) {
+        Ok(datetime_transform) => datetime_transform,
+        Err(error) => return log_error(error),
+    };
+
+    let mut paths = match collect_paths_or_exit(
+        &options.input,
+        options.case_insensitive,
+        options.include_symlinks,
+        options.max_depth,
+        &mut statistics,
+    ) {
+        Ok(paths) => paths,
+        Err(exit_code) => return exit_code,
+    };
+
+    if options.date_sources.contains(&DateSource::Xmp) {
+        remove_xmp_sidecars(&mut paths);
+    }
+
+    let extractor_chain = ExtractorChain::from_settings(&ExtractionSettings {
+        date_sources: options.date_sources,
+        backend: options.backend,
+        extractor_cmd: options.extractor_cmd,
+        fallbacks: options.fallbacks,
+        documents: options.documents,
+    });
+
+    for extraction in extract_creation_datetimes(paths, options.jobs, extractor_chain) {
+        let Ok((path_buf, metadata)) = record_extraction(extraction, &current_working_directory, &mut statistics) else {
+            continue;
+        };
+
+        let datetime = datetime_transform.apply(&metadata);
+
+        // Creation datetimes which were not taken from the metadata, e.g. from a fallback, name the extractor, like `(exiftool)`.
+        let extractor = metadata
+            .extractor
+            .as_deref()
+            .filter(|extractor| *extractor != "metadata")
+            .map(|extractor| format!(" ({})", extractor))
+            .unwrap_or_default();
+
+        println!(
+            "{}: {}{}",
+            format_path_buf_without_prefix(&path_buf, &current_working_directory),
+            datetime.format("%Y-%m-%d %H:%M:%S %:z"),
+            extractor
+        );
+    }
+
+    if statistics.failed_files > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
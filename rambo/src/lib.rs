@@ -1,165 +1,1387 @@
 #![forbid(unsafe_code)]
 
-use crate::extract::extract_creation_datetime_from_media_source;
-use crate::glob::evaluate_files_from_glob_pattern;
-use crate::rename::rename_file;
-use crate::statistics::Statistics;
-
-use chrono::FixedOffset;
-use nom_exif::{MediaParser, MediaSource};
-use std::fs::File;
+use crate::atomic::{roll_back, validate_renames};
+use crate::cache::ExtractionCache;
+use crate::checksum::write_checksum_manifest;
+use crate::collisions::write_collisions_report;
+use crate::counter::Counter;
+use crate::duplicates::find_duplicates;
+use crate::extract::ExtractionSettings;
+use crate::filter::{DateBound, DateRange, FileFilter, FileSize};
+use crate::glob::remove_duplicate_paths;
+use crate::naming::{is_legal_file_name_char, sanitize_file_name};
+use crate::order::{RenameOrder, order_renames};
+use crate::output::{print_duplicate_group, print_file_outcome, print_summary};
+use crate::pairs::{PairedExtractions, sort_paths_into_pairs};
+use crate::parallel::extract_creation_datetimes_in_parallel;
+use crate::rename::{
+    ClaimedTargets, RenameOutcome, RenameSettings, move_to_temporary_names, original_file_stem, rename_file, rename_files_as_unit, requested_file_path,
+    restore_from_temporary_name, sync_modification_time,
+};
+use crate::rename_map::{write_backup_rename_map, write_rename_map};
+use crate::resume::{ResumeJournal, ResumeState};
+use crate::sidecar::{remove_xmp_sidecars, rename_sidecars, separate_sidecars};
+use crate::template::{NamePattern, validate_date_format};
+use crate::transform::{DatetimeTransform, TimeShift};
+
+use chrono::{DateTime, FixedOffset, Timelike};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use nom_exif::MediaParser;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
 use std::ops::Not;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
 
+#[cfg(feature = "tokio")]
+mod r#async;
+mod atomic;
+mod audio;
+mod avchd;
+mod builder;
+mod cache;
+mod cancel;
+mod checksum;
+mod collisions;
+mod config;
+mod counter;
+mod dedupe;
+mod dji;
+mod document;
+mod duplicates;
+mod error;
+mod event;
+mod exiftool;
 mod extract;
+mod extractor;
+mod ffprobe;
+mod filter;
 mod glob;
+mod gpmf;
+mod ignore_file;
+mod image;
+mod input;
+mod inspect;
+mod metadata;
+#[cfg(feature = "mtp")]
+mod mtp;
+mod naming;
+mod order;
+mod output;
+mod pairs;
+mod parallel;
+mod place;
+mod plan;
+mod progress;
+mod raw;
 mod rename;
+mod rename_map;
+mod renamer;
+mod report;
+mod resume;
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "sftp")]
+mod sftp;
+mod sidecar;
+mod sniff;
 mod statistics;
+mod storage;
+mod template;
+mod timezone;
+mod transform;
+mod undo;
+mod verify;
+mod walk;
+mod watch;
+#[cfg(feature = "webdav")]
+mod webdav;
+mod write_exif;
+
+#[cfg(feature = "tokio")]
+pub use crate::r#async::{run_async, run_on_paths_async};
+pub use crate::builder::{DEFAULT_FORMAT, DEFAULT_REPLACEMENT_CHAR, RamboOptionsBuilder};
+pub use crate::cache::default_extraction_cache_path;
+pub use crate::cancel::CancellationToken;
+pub use crate::config::{CONFIG_FILE_NAME, Config, Profile};
+pub use crate::counter::CounterReset;
+pub use crate::dedupe::{DedupeAction, DedupeOptions, dedupe};
+pub use crate::duplicates::DuplicateGroup;
+pub use crate::error::{ExtractError, RamboError};
+pub use crate::event::{EventHandler, RunEvent};
+pub use crate::extract::{Backend, DateSource, DatetimeSource, ExtractedDatetime, Fallback, GpsCoordinates, MediaMetadata, extract_creation_datetime};
+pub use crate::extractor::{
+    DatetimeExtractor, DjiSrtExtractor, DocumentExtractor, ExifTagExtractor, ExiftoolExtractor, ExternalCommandExtractor, ExtractorChain, FfprobeExtractor,
+    FileNameExtractor, MetadataExtractor, ModificationTimeExtractor, TakeoutSidecarExtractor, TrackExtractor, XmpSidecarExtractor,
+};
+pub use crate::filter::MediaKind;
+pub use crate::ignore_file::IGNORE_FILE_NAME;
+pub use crate::input::{InputSource, STDIN_FILE_LIST};
+pub use crate::inspect::{InspectOptions, inspect};
+#[cfg(feature = "mtp")]
+pub use crate::mtp::{MTP_IMPORTED_FILE_NAME, MtpDevice, import_from_mtp};
+pub use crate::naming::{FsCompat, UnicodeNormalization};
+pub use crate::output::{FileOutcome, OutputFormat};
+pub use crate::plan::{ApplyOptions, PlannedRename, RenamePlan, apply};
+pub use crate::progress::ProgressUpdate;
+pub use crate::rename::{ConflictStrategy, RenameAction};
+pub use crate::rename_map::RENAME_MAP_PREFIX;
+pub use crate::renamer::Renamer;
+pub use crate::report::RunReport;
+pub use crate::resume::RESUME_STATE_FILE_NAME;
+#[cfg(feature = "s3")]
+pub use crate::s3::S3Storage;
+#[cfg(feature = "sftp")]
+pub use crate::sftp::{SftpSource, SftpStorage};
+pub use crate::statistics::Statistics;
+pub use crate::storage::{OBJECT_HEAD_LENGTH, ObjectStorage, StorageError, run_on_storage};
+pub use crate::template::{Template, TemplateContext};
+pub use crate::timezone::TargetTimeZone;
+pub use crate::undo::{UndoOptions, undo};
+pub use crate::verify::{VerifyOptions, verify};
+pub use crate::watch::watch;
+#[cfg(feature = "webdav")]
+pub use crate::webdav::WebDavStorage;
+pub use crate::write_exif::{WriteExifOptions, write_exif};
 
+/// The options of a run. Create them with [RamboOptions::builder], new options are added without breaking changes.
+#[derive(Clone)]
+#[non_exhaustive]
 pub struct RamboOptions {
-    pub pattern: String,
+    pub input: InputSource,
     pub no_dry_run: bool,
     pub case_insensitive: bool,
+    /// The chrono specifiers in the format are replaced by the creation datetime. It may also contain the tokens of a template, like `{camera}`.
     pub format: String,
-    pub time_offset: Option<String>,
+    /// An offset like `+02:00` or a time zone name like `Europe/Berlin`, in which the creation datetimes are rendered. `from-gps` uses the time zone at the GPS coordinates of each file.
+    pub time_zone: Option<String>,
+    /// A duration like `-1h37m` or `+30s` by which the creation datetimes are shifted, to correct the clock of the camera.
+    pub shift: Option<String>,
+    /// Shifts like `shift` for the files of single cameras, keyed by the serial number or the model of the camera.
+    pub camera_shifts: BTreeMap<String, String>,
+    /// A date like `2022-06-01` or a datetime like `2022-06-01T18:30:00`. Only files created at or after it are renamed, the others are skipped.
+    pub after: Option<String>,
+    /// Like [RamboOptions::after], but only files created before it are renamed.
+    pub before: Option<String>,
+    /// When set, only files with one of these extensions, like `jpg`, are renamed. The extensions are compared case-insensitively.
+    pub extensions: Option<Vec<String>>,
+    /// Files with one of these extensions are skipped.
+    pub skipped_extensions: Vec<String>,
+    /// When set, only files of this kind are renamed, the others are skipped.
+    pub only: Option<MediaKind>,
+    /// When set, the run stops after this many files, in the order of their paths. The remaining files are left untouched.
+    /// Files which are renamed together, like pairs, are not split up, so slightly more files may be processed.
+    pub limit: Option<usize>,
+    /// A size like `10k`, `5M` or `2G`. Smaller files are skipped, like thumbnails.
+    pub min_size: Option<String>,
+    /// Like [RamboOptions::min_size], but larger files are skipped.
+    pub max_size: Option<String>,
     pub include_symlinks: bool,
+    /// The maximum depth of the files below a walked directory or the leading components of a glob pattern which contain no wildcards,
+    /// 1 meaning only the files directly inside. `None` for no limit.
+    pub max_depth: Option<usize>,
+    pub checksums: Option<PathBuf>,
+    /// Write the old and the new path of each renamed or copied file to this file, so that the renaming can be undone with [undo].
+    pub rename_map: Option<PathBuf>,
+    /// Write the old and the new path of each renamed file to a new `rambo-renames-<TIMESTAMP>.csv` in the current working directory, unless it is a dry run.
+    pub backup_map: bool,
+    pub fail_on_vanished: bool,
+    /// Do not log the files that already have the correct name in a dry run, so that it only shows what would change.
+    pub dry_run_hide_unchanged: bool,
+    /// Where the creation datetime is taken from, tried in order, like `[DateSource::DateTimeOriginal, DateSource::Xmp, DateSource::Name]`.
+    pub date_sources: Vec<DateSource>,
+    /// What reads the metadata embedded in the files. [Fallback::Exiftool] uses exiftool only for the files which the [Backend::Native] parser cannot read.
+    pub backend: Backend,
+    pub extractor_cmd: Option<String>,
+    /// Tried in order, when the creation datetime cannot be extracted from the metadata or with the [RamboOptions::extractor_cmd].
+    pub fallbacks: Vec<Fallback>,
+    /// Whether the creation datetimes of PDFs and OOXML documents, like `.docx` files, are read from their document properties,
+    /// so that scanned documents are named like the photos among them.
+    pub documents: bool,
+    /// When set, it extracts the creation datetimes instead of the chain of [RamboOptions::date_sources], [RamboOptions::backend], [RamboOptions::extractor_cmd] and [RamboOptions::fallbacks].
+    pub extractor_chain: Option<ExtractorChain>,
+    /// When set, the metadata extracted from the files is cached in this file, like [default_extraction_cache_path], so that repeated runs skip the files
+    /// which did not change since, by their size and modification time. It is not used with an [RamboOptions::extractor_chain].
+    pub extraction_cache: Option<PathBuf>,
+    pub store_original_xattr: bool,
+    /// The extensions of sidecar files, like `xmp`, which are renamed along with the media file of the same name.
+    pub sidecar_extensions: Vec<String>,
+    /// Files with the same stem in the same directory, like RAW+JPEG pairs, get the same new name. They are renamed, skipped or failed together.
+    pub keep_pairs: bool,
+    /// Set the modification time of each file to its creation datetime, whether it is renamed or not.
+    pub sync_mtime: bool,
+    /// Replace extensions that do not fit the format of the file, which is recognized by its magic bytes, like `.jpg` for a HEIC file.
+    pub fix_extensions: bool,
+    /// Report the files of the run with the same content.
+    pub detect_duplicates: bool,
+    pub collisions_report: Option<PathBuf>,
+    /// When set, the template in this file is used for the new file names instead of [RamboOptions::format].
+    pub template_file: Option<PathBuf>,
+    /// When set, it decides the new file names instead of [RamboOptions::format] and [RamboOptions::template_file].
+    pub renamer: Option<Arc<dyn Renamer>>,
+    pub on_conflict: ConflictStrategy,
+    /// Move the files which are overwritten because of [ConflictStrategy::Overwrite] to the trash of the system instead, from where they can be restored.
+    pub use_trash: bool,
+    /// Check all renames before any file is renamed, and rename the files back if one of them fails nonetheless, so that either all files are renamed or none.
+    pub atomic: bool,
+    /// Skip the files whose names already match the format, without extracting their creation datetime, like in runs over a mostly renamed archive.
+    /// A suffix like `-1` of a previous conflict is allowed. This is ignored with a [RamboOptions::renamer] and when the files are moved or copied.
+    pub trust_existing_names: bool,
+    /// Continue the run which was interrupted in the current working directory. The files it handled are skipped and the metadata it extracted is reused.
+    /// Each run which is no dry run records its progress in [RESUME_STATE_FILE_NAME] in the current working directory, which is removed once the run is completed.
+    pub resume: bool,
+    /// When the `{counter}` token of the format starts again at 1.
+    pub counter_reset: CounterReset,
+    /// When set, the new names must fit the limits of this file system as well, like the length of the paths of Windows. Files whose new name does not fit fail.
+    pub fs_compat: Option<FsCompat>,
+    /// The Unicode normalization of the new names. Names which differ only in their normalization count as the same, so that such files keep their names.
+    pub normalize: UnicodeNormalization,
+    /// Replaces the characters of the new file names which are not allowed in file names, like `/` or `:`. It must be allowed itself.
+    pub replacement_char: char,
+    /// When set, the files are moved into directories relative to the current working directory, which are derived from their creation datetime.
+    /// The layout uses the same specifiers as [RamboOptions::format], e.g. `%Y/%m` for per-year/per-month directories.
+    pub target_layout: Option<String>,
+    /// When set, the files are copied into this directory with their new names, instead of being renamed in place.
+    pub copy_to: Option<PathBuf>,
+    /// When set, the renamed files are moved into this directory. This is ignored if [RamboOptions::copy_to] is set.
+    pub move_to: Option<PathBuf>,
+    /// The number of files to extract the creation datetime from in parallel. The renaming itself always happens sequentially.
+    pub jobs: usize,
+    /// When set, a progress bar is added to it. Route the logging through the [MultiProgress] as well, so that log lines do not break the bar.
+    pub progress: Option<MultiProgress>,
+    pub output: OutputFormat,
+    /// When set, nothing is renamed and the renames are written to this file as a [RenamePlan] instead, which can be applied with [apply].
+    pub plan_file: Option<PathBuf>,
+    /// When set, it receives the [RunEvent] of each file, in addition to the logging and the output.
+    pub event_handler: Option<Arc<dyn EventHandler>>,
+    /// When set, the run stops before the next file once the token is cancelled. The remaining files are left untouched.
+    pub cancellation_token: Option<CancellationToken>,
+    /// When set, it receives a [ProgressUpdate] before each file is renamed and after the last one. The sender is dropped with the options.
+    pub progress_sender: Option<Sender<ProgressUpdate>>,
 }
 
-pub fn run(options: RamboOptions) -> ExitCode {
+/// Renames the files of the input and prints the outcome of each file as well as a summary.
+/// Returns an error only if the run cannot be carried out at all. Failures of single files are counted in the [RunReport].
+pub fn run(options: RamboOptions) -> Result<RunReport, RamboError> {
     let mut statistics = Statistics::new();
 
-    let current_working_directory = match std::env::current_dir() {
-        Ok(working_directory) => format!("{}{}", working_directory.display(), std::path::MAIN_SEPARATOR),
-        Err(error) => {
-            log::error!("Cannot determine current working directory: {}", error);
-            return ExitCode::FAILURE;
+    let run_context = RunContext::new(&options)?;
+
+    let paths = collect_paths(
+        &options.input,
+        options.case_insensitive,
+        options.include_symlinks,
+        options.max_depth,
+        &mut statistics,
+    )?;
+    if paths.is_empty() {
+        return Ok(RunReport {
+            statistics,
+            ..Default::default()
+        });
+    }
+
+    Ok(rename_paths(&options, &run_context, paths, statistics))
+}
+
+/// Like [run], but processes exactly the given files instead of evaluating the [RamboOptions::input], so the options of the input do not apply.
+/// Relative paths are relative to the current working directory. Paths which do not exist are kept, so that they are reported, other paths which are no files are ignored.
+pub fn run_on_paths(paths: Vec<PathBuf>, options: RamboOptions) -> Result<RunReport, RamboError> {
+    let run_context = RunContext::new(&options)?;
+
+    let mut paths = paths
+        .into_iter()
+        .map(|path_buf| path_buf.canonicalize().unwrap_or_else(|_| run_context.current_working_directory.join(path_buf)))
+        .filter(|path_buf| path_buf.is_file() || path_buf.exists().not())
+        .collect::<Vec<_>>();
+    remove_duplicate_paths(&mut paths);
+
+    Ok(rename_paths(&options, &run_context, paths, Statistics::new()))
+}
+
+/// Everything which is prepared once for a run, before any file is processed.
+pub(crate) struct RunContext {
+    pub(crate) current_working_directory: PathBuf,
+    datetime_transform: DatetimeTransform,
+    date_range: DateRange,
+    file_filter: FileFilter,
+    renamer: Arc<dyn Renamer>,
+    /// Recognizes the names which already match the format, if they are trusted.
+    existing_name_pattern: Option<NamePattern>,
+    store_original_xattr: bool,
+    /// The progress of the interrupted run, if it is resumed.
+    resume_state: Option<ResumeState>,
+}
+
+impl RunContext {
+    pub(crate) fn new(options: &RamboOptions) -> Result<Self, RamboError> {
+        let current_working_directory = current_working_directory()?;
+
+        let datetime_transform = datetime_transform(options.time_zone.as_deref(), options.shift.as_deref(), &options.camera_shifts)?;
+        let date_range = DateRange {
+            after: parse_setting::<DateBound>(options.after.as_deref(), "Date")?,
+            before: parse_setting::<DateBound>(options.before.as_deref(), "Date")?,
+        };
+        let file_filter = FileFilter {
+            extensions: options.extensions.clone(),
+            skipped_extensions: options.skipped_extensions.clone(),
+            media_kind: options.only,
+            min_size: parse_setting::<FileSize>(options.min_size.as_deref(), "Size")?,
+            max_size: parse_setting::<FileSize>(options.max_size.as_deref(), "Size")?,
+        };
+
+        let template = match (&options.renamer, &options.template_file) {
+            (Some(_), _) => None,
+            (None, None) => Some(Template::parse_format(&options.format)?),
+            (None, Some(template_file)) => Some(Template::from_file(template_file)?),
+        };
+        let existing_name_pattern = if options.trust_existing_names {
+            existing_name_pattern(template.as_ref(), options)?
+        } else {
+            None
+        };
+        let renamer: Arc<dyn Renamer> = match (template, &options.renamer) {
+            (Some(template), _) => Arc::new(template),
+            (None, Some(renamer)) => Arc::clone(renamer),
+            (None, None) => unreachable!("the template is parsed unless there is a renamer"),
+        };
+
+        if let Some(target_layout) = &options.target_layout {
+            validate_date_format(target_layout)?;
         }
-    };
 
-    let time_offset = match options.time_offset {
-        None => None,
-        Some(time_offset_string) => match FixedOffset::from_str(&time_offset_string) {
-            Ok(time_offset) => Some(time_offset),
-            Err(error) => {
-                log::error!("Time offset '{}' is invalid: {}", time_offset_string, error);
-                return ExitCode::FAILURE;
+        if is_legal_file_name_char(options.replacement_char, options.fs_compat).not() {
+            return Err(RamboError::InvalidSetting(format!(
+                "The replacement character '{}' is not allowed in file names itself",
+                options.replacement_char.escape_default()
+            )));
+        }
+
+        if options.fs_compat == Some(FsCompat::Fat) && (options.sync_mtime || options.fallbacks.contains(&Fallback::Mtime)) {
+            log::warn!(
+                "FAT file systems store modification times in steps of 2 seconds and without a time zone, so they may be off by a second or by the offset."
+            );
+        }
+
+        let store_original_xattr = if options.store_original_xattr && xattr::SUPPORTED_PLATFORM.not() {
+            log::warn!("Extended attributes are not supported on this platform. The original file names will not be stored.");
+            false
+        } else {
+            options.store_original_xattr
+        };
+
+        let resume_state = if options.resume {
+            let resume_state = ResumeState::read(&current_working_directory)?;
+            if resume_state.is_none() {
+                log::warn!("There is no interrupted run to resume in the current working directory, so all files are processed.");
             }
-        },
+            resume_state
+        } else {
+            None
+        };
+
+        Ok(Self {
+            current_working_directory,
+            datetime_transform,
+            date_range,
+            file_filter,
+            renamer,
+            existing_name_pattern,
+            store_original_xattr,
+            resume_state,
+        })
+    }
+}
+
+/// Only the names of files which are renamed in place can be trusted, as the files of the other modes must be moved or copied as well.
+fn existing_name_pattern(template: Option<&Template>, options: &RamboOptions) -> Result<Option<NamePattern>, RamboError> {
+    let Some(template) = template else {
+        log::warn!("The existing names cannot be trusted with a custom renamer, so all files are processed.");
+        return Ok(None);
     };
+    if options.copy_to.is_some() || options.move_to.is_some() || options.target_layout.is_some() {
+        log::warn!("The existing names cannot be trusted when the files are moved or copied, so all files are processed.");
+        return Ok(None);
+    }
+
+    let name_pattern = template.name_pattern(|literal| {
+        let sanitized_literal = literal
+            .chars()
+            .map(|char| {
+                if is_legal_file_name_char(char, options.fs_compat) {
+                    char
+                } else {
+                    options.replacement_char
+                }
+            })
+            .collect::<String>();
+        options.normalize.normalize(&sanitized_literal)
+    })?;
+
+    Ok(Some(name_pattern))
+}
+
+/// Renames the given files and prints the summary.
+pub(crate) fn rename_paths(options: &RamboOptions, run_context: &RunContext, mut paths: Vec<PathBuf>, mut statistics: Statistics) -> RunReport {
+    let RunContext {
+        current_working_directory,
+        datetime_transform,
+        date_range,
+        file_filter,
+        renamer,
+        existing_name_pattern,
+        store_original_xattr,
+        resume_state,
+    } = run_context;
+    let store_original_xattr = *store_original_xattr;
+
+    // A plan is always created in a dry run, so that it can be reviewed before anything is renamed.
+    let is_dry_run = options.no_dry_run.not() || options.plan_file.is_some();
+
+    if options.date_sources.contains(&DateSource::Xmp) {
+        remove_xmp_sidecars(&mut paths);
+    }
+
+    // The SRT subtitles of DJI videos are renamed along with them, so that they stay paired.
+    let mut sidecar_extensions = options.sidecar_extensions.clone();
+    if options.date_sources.contains(&DateSource::DjiSrt) {
+        sidecar_extensions.push("srt".to_string());
+    }
+
+    let sidecars = separate_sidecars(&mut paths, &sidecar_extensions, options.fallbacks.contains(&Fallback::Takeout));
+
+    if let Some(resume_state) = resume_state {
+        let resumed_files = resume_state.remove_handled_paths(&mut paths);
+        if resumed_files > 0 {
+            log::info!("Skipping {} files, which the interrupted run already handled", resumed_files);
+            statistics.resumed_files += resumed_files as u64;
+        }
+    }
 
-    let Some((paths, errors)) = evaluate_files_from_glob_pattern(&options.pattern, options.case_insensitive, options.include_symlinks) else {
-        return ExitCode::FAILURE;
+    // Nothing needs to be resumed after a dry run.
+    let mut resume_journal = if is_dry_run {
+        ResumeJournal::disabled()
+    } else {
+        ResumeJournal::open(current_working_directory, resume_state.is_some())
     };
 
-    if errors.is_empty().not() {
-        statistics.failed_files += errors.len() as u64;
+    for path_buf in &paths {
+        options.emit_event(RunEvent::FileMatched { path: path_buf });
+    }
 
-        log::warn!(
-            "Some paths could not be read to determine if their contents match the given glob pattern '{}'. \
-            Make sure you have the permissions for these paths and symlinks are not broken.",
-            options.pattern
-        );
+    let mut file_outcomes = Vec::<FileOutcome>::new();
 
-        for error in errors.iter() {
-            log::warn!("{}", error);
+    let filtered_paths = file_filter.apply(&mut paths);
+    if filtered_paths.is_empty().not() {
+        log::info!("Skipping {} files, which do not pass the filters", filtered_paths.len());
+        statistics.filtered_files += filtered_paths.len() as u64;
+
+        for path_buf in filtered_paths {
+            record_file_outcome(
+                FileOutcome {
+                    path: path_buf,
+                    new_path: None,
+                    datetime: None,
+                    action: RenameAction::Skipped,
+                    conflict: None,
+                    error: None,
+                },
+                options,
+                &mut file_outcomes,
+            );
         }
     }
 
-    if paths.is_empty() && errors.is_empty() {
-        log::warn!("No media files will be processed. Make sure the glob pattern '{}' is correct.", options.pattern);
+    if let Some(existing_name_pattern) = existing_name_pattern {
+        let mut trusted_paths = Vec::new();
+        paths.retain(|path_buf| {
+            let is_trusted = path_buf
+                .file_stem()
+                .and_then(OsStr::to_str)
+                .is_some_and(|file_stem| existing_name_pattern.matches(&options.normalize.normalize(file_stem)));
+            if is_trusted {
+                trusted_paths.push(path_buf.clone());
+            }
+            is_trusted.not()
+        });
 
-        return ExitCode::SUCCESS;
-    } else if paths.is_empty() && errors.is_empty().not() {
-        log::warn!(
-            "No media files will be processed. Make sure the glob pattern '{}' is correct and you have adequate permissions.",
-            options.pattern
-        );
+        if trusted_paths.is_empty().not() {
+            log::info!("Skipping {} files, whose names already match the format", trusted_paths.len());
+            statistics.skipped_files += trusted_paths.len() as u64;
 
-        return ExitCode::FAILURE;
+            for path_buf in trusted_paths {
+                record_file_outcome(
+                    FileOutcome {
+                        path: path_buf.clone(),
+                        new_path: Some(path_buf),
+                        datetime: None,
+                        action: RenameAction::AlreadyCorrect,
+                        conflict: None,
+                        error: None,
+                    },
+                    options,
+                    &mut file_outcomes,
+                );
+            }
+        }
     }
 
-    let media_assets = get_media_assets_from_path_bufs(paths);
+    if options.keep_pairs {
+        sort_paths_into_pairs(&mut paths);
+    }
 
-    let mut media_parser = MediaParser::new();
+    let total_files = paths.len();
+    let files_to_rename = options.limit.unwrap_or(total_files).min(total_files);
+    let progress_bar = match &options.progress {
+        Some(multi_progress) => multi_progress.add(create_progress_bar(files_to_rename as u64)),
+        None => ProgressBar::hidden(),
+    };
 
-    for media_asset in media_assets {
-        let media_asset = match media_asset {
-            Ok(media_asset) => media_asset,
-            Err((path_buf, error)) => {
-                statistics.failed_files += 1;
+    let extraction_settings = ExtractionSettings {
+        date_sources: options.date_sources.clone(),
+        backend: options.backend,
+        extractor_cmd: options.extractor_cmd.clone(),
+        fallbacks: options.fallbacks.clone(),
+        documents: options.documents,
+    };
+    let mut extraction_cache = match (&options.extraction_cache, &options.extractor_chain) {
+        (Some(_), Some(_)) => {
+            log::warn!("The extraction cache is not used with a custom extractor chain, as the settings of its extractors are unknown.");
+            None
+        }
+        (Some(extraction_cache_path), None) => Some(ExtractionCache::load(extraction_cache_path, format!("{:?}", extraction_settings))),
+        (None, _) => None,
+    };
+    let extractor_chain = options
+        .extractor_chain
+        .clone()
+        .unwrap_or_else(|| ExtractorChain::from_settings(&extraction_settings));
+    let first_extractor = extractor_chain.extractors().next().map(|extractor| extractor.description().to_string());
+
+    let mut known_extractions = HashMap::<PathBuf, MediaMetadata>::new();
+    if let Some(extraction_cache) = &extraction_cache {
+        known_extractions.extend(extraction_cache.known_extractions(&paths));
+        if known_extractions.is_empty().not() {
+            log::info!("Reusing the cached metadata of {} unchanged files", known_extractions.len());
+        }
+    }
+    if let Some(resume_state) = resume_state {
+        known_extractions.extend(resume_state.known_extractions(&paths));
+    }
+    let reused_paths = known_extractions.keys().cloned().collect::<HashSet<_>>();
+    let extractions = extract_creation_datetimes_reusing(paths, known_extractions, options.jobs, extractor_chain);
+
+    let mut rename_outcomes = Vec::<RenameOutcome>::new();
+    // The creation datetime of each of the rename outcomes.
+    let mut rename_datetimes = Vec::<DateTime<FixedOffset>>::new();
+    let mut counter = Counter::new(options.counter_reset);
+    let mut files_per_second = HashMap::<DateTime<FixedOffset>, u32>::new();
+    let mut claimed_targets = ClaimedTargets::default();
+    let rename_settings = RenameSettings {
+        is_dry_run,
+        show_unchanged_in_dry_run: options.dry_run_hide_unchanged.not(),
+        store_original_xattr,
+        on_conflict: options.on_conflict,
+        copy: options.copy_to.is_some(),
+        fix_extensions: options.fix_extensions,
+        max_path_length: options.fs_compat.and_then(FsCompat::max_path_length),
+        normalization: options.normalize,
+        use_trash: options.use_trash,
+    };
+
+    let mut planned_groups = Vec::<PlannedGroup>::new();
+    let mut processed_files = 0;
+    let mut done_files = 0;
+    let first_renamed_outcome = file_outcomes.len();
+    let mut is_cancelled = false;
+    let mut groups = PairedExtractions::new(extractions, options.keep_pairs);
+    while options.limit.is_none_or(|limit| processed_files < limit)
+        && let Some(group) = groups.next()
+    {
+        resume_journal.record_new_outcomes(&file_outcomes);
+
+        if options.cancellation_token.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            is_cancelled = true;
+            break;
+        }
+
+        processed_files += group.len();
+        let mut extracted_files = Vec::<(PathBuf, MediaMetadata)>::with_capacity(group.len());
+        let mut has_failed_file = false;
+
+        for extraction in group {
+            progress_bar.inc(1);
+            progress_bar.set_message(format_path_buf_without_prefix(extraction.path(), current_working_directory));
+            options.send_progress(|| ProgressUpdate {
+                total: files_to_rename,
+                done: done_files,
+                current_path: Some(extraction.path().to_path_buf()),
+                last_outcome: file_outcomes[first_renamed_outcome..].last().cloned(),
+            });
+            done_files += 1;
+
+            match record_extraction(extraction, current_working_directory, &mut statistics) {
+                Ok((path_buf, metadata)) => {
+                    if let Some(extractor) = &metadata.extractor {
+                        // Only the files whose creation datetime was not taken from the first source, e.g. from a fallback, are logged by default.
+                        let log_level = if Some(extractor) == first_extractor.as_ref() {
+                            log::Level::Debug
+                        } else {
+                            log::Level::Info
+                        };
+                        log::log!(
+                            log_level,
+                            "Extracted the creation datetime of {} with the {} extractor",
+                            format_path_buf_without_prefix(&path_buf, current_working_directory),
+                            extractor
+                        );
+                    }
+                    options.emit_event(RunEvent::DatetimeExtracted {
+                        path: &path_buf,
+                        datetime: metadata.datetime,
+                    });
+                    if resume_state
+                        .as_ref()
+                        .is_none_or(|resume_state| resume_state.extractions.contains_key(&path_buf).not())
+                    {
+                        resume_journal.record_extraction(&path_buf, &metadata);
+                    }
+                    if let Some(extraction_cache) = &mut extraction_cache
+                        && reused_paths.contains(&path_buf).not()
+                    {
+                        extraction_cache.insert(&path_buf, &metadata);
+                    }
+                    extracted_files.push((path_buf, metadata));
+                }
+                Err(file_outcome) => {
+                    record_file_outcome(file_outcome, options, &mut file_outcomes);
+                    has_failed_file = true;
+                }
+            }
+        }
+
+        if has_failed_file {
+            // The other files of the pair are not renamed, so that the pair is not split up.
+            for (path_buf, metadata) in extracted_files {
                 log::warn!(
-                    "Cannot process {}: {}",
-                    format_path_buf_without_prefix(&path_buf, &current_working_directory),
-                    error
+                    "Not renaming {}, because a file with the same name could not be processed",
+                    format_path_buf_without_prefix(&path_buf, current_working_directory)
+                );
+                statistics.failed_files += 1;
+
+                record_file_outcome(
+                    FileOutcome {
+                        path: path_buf,
+                        new_path: None,
+                        datetime: Some(metadata.datetime),
+                        action: RenameAction::Failed,
+                        conflict: None,
+                        error: Some("A file with the same name could not be processed".to_string()),
+                    },
+                    options,
+                    &mut file_outcomes,
                 );
-                continue;
             }
+            continue;
+        }
+
+        // All files of a pair get the name derived from the metadata of the first one.
+        let Some((first_path_buf, metadata)) = extracted_files.first() else {
+            continue;
         };
 
-        let datetime = match extract_creation_datetime_from_media_source(media_asset.media_source, &mut media_parser) {
-            Ok(datetime) => datetime,
-            Err(error) => {
-                statistics.failed_files += 1;
-                log::warn!(
-                    "Cannot extract creation datetime from {}: {}",
-                    format_path_buf_without_prefix(&media_asset.path_buf, &current_working_directory),
-                    error
+        let datetime = datetime_transform.apply(metadata);
+
+        if date_range.contains(&datetime).not() {
+            for (path_buf, _) in extracted_files {
+                log::info!(
+                    "Skipping {}, because its creation datetime {} is outside of the date range",
+                    format_path_buf_without_prefix(&path_buf, current_working_directory),
+                    datetime
+                );
+                statistics.skipped_files += 1;
+
+                record_file_outcome(
+                    FileOutcome {
+                        path: path_buf,
+                        new_path: None,
+                        datetime: Some(datetime),
+                        action: RenameAction::Skipped,
+                        conflict: None,
+                        error: None,
+                    },
+                    options,
+                    &mut file_outcomes,
                 );
-                continue;
             }
+            continue;
+        }
+
+        let original_file_stem = original_file_stem(first_path_buf);
+        let new_file_name = renamer.new_file_name(
+            first_path_buf,
+            &TemplateContext {
+                datetime: &datetime_with_sequence(&datetime, metadata.has_subseconds, &mut files_per_second),
+                metadata,
+                original_file_name: &original_file_stem,
+                directory_name: first_path_buf.parent().and_then(Path::file_name).unwrap_or_default(),
+                file_path: Some(first_path_buf),
+                counter: counter.next(first_path_buf, &datetime),
+            },
+        );
+        let datetime_formatted = options
+            .normalize
+            .normalize(&sanitize_file_name(&new_file_name, options.replacement_char, options.fs_compat));
+
+        let destination_directory = options.copy_to.as_ref().or(options.move_to.as_ref());
+        let target_directory = match (&options.target_layout, destination_directory) {
+            (Some(target_layout), destination_directory) => destination_directory
+                .map(PathBuf::as_path)
+                .unwrap_or(current_working_directory)
+                .join(datetime.format(target_layout).to_string()),
+            (None, Some(destination_directory)) => destination_directory.clone(),
+            (None, None) => first_path_buf.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+
+        planned_groups.push(PlannedGroup {
+            extracted_files,
+            datetime,
+            new_file_name: datetime_formatted,
+            target_directory,
+        });
+    }
+
+    // The parallel extraction stops once nobody receives its results anymore.
+    drop(groups);
+    progress_bar.finish_and_clear();
+    resume_journal.record_new_outcomes(&file_outcomes);
+
+    // All new names are determined before any file is renamed, so that files which take each other's names, like two files which swap their names, are renamed in the right order.
+    let mut requested_renames = planned_groups
+        .iter()
+        .map(|planned_group| {
+            planned_group
+                .extracted_files
+                .iter()
+                .map(|(path_buf, _)| {
+                    let requested_target = requested_file_path(path_buf, &planned_group.target_directory, &planned_group.new_file_name, options.fix_extensions);
+                    (path_buf.clone(), requested_target)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    if options.atomic {
+        let invalid_renames = validate_renames(requested_renames.iter().flatten(), &rename_settings);
+        if invalid_renames.is_empty().not() {
+            log::warn!(
+                "Not renaming any file, because {} files of the atomic run cannot be renamed",
+                invalid_renames.len()
+            );
+            for planned_group in planned_groups.drain(..) {
+                for (path_buf, _) in planned_group.extracted_files {
+                    let error = invalid_renames.get(&path_buf).cloned();
+                    let action = if let Some(error) = &error {
+                        log::warn!(
+                            "Cannot rename {}: {}",
+                            format_path_buf_without_prefix(&path_buf, current_working_directory),
+                            error
+                        );
+                        statistics.failed_files += 1;
+                        RenameAction::Failed
+                    } else {
+                        statistics.skipped_files += 1;
+                        RenameAction::Skipped
+                    };
+
+                    record_file_outcome(
+                        FileOutcome {
+                            path: path_buf,
+                            new_path: None,
+                            datetime: Some(planned_group.datetime),
+                            action,
+                            conflict: None,
+                            error,
+                        },
+                        options,
+                        &mut file_outcomes,
+                    );
+                }
+            }
+            requested_renames.clear();
+        }
+    }
+
+    let rename_order = if rename_settings.copy {
+        RenameOrder::unchanged(planned_groups.len())
+    } else {
+        order_renames(&requested_renames)
+    };
+
+    let temporary_paths = move_to_temporary_names(
+        rename_order
+            .via_temporary_names
+            .iter()
+            .flat_map(|&index| planned_groups[index].extracted_files.iter().map(|(path_buf, _)| path_buf.as_path())),
+        &rename_settings,
+        &mut claimed_targets,
+        current_working_directory,
+    );
+
+    let mut planned_groups = planned_groups.into_iter().map(Some).collect::<Vec<_>>();
+    for index in rename_order.indices {
+        let Some(PlannedGroup {
+            extracted_files,
+            datetime,
+            new_file_name,
+            target_directory,
+        }) = planned_groups[index].take()
+        else {
+            continue;
         };
 
-        let datetime_formatted = time_offset
-            .map(|time_offset| datetime.with_timezone(&time_offset))
-            .unwrap_or(datetime)
-            .format(&options.format)
-            .to_string();
-
-        rename_file(
-            &media_asset.path_buf,
-            &datetime_formatted,
-            options.no_dry_run.not(),
-            &current_working_directory,
-            &mut statistics,
+        let current_path_bufs = extracted_files
+            .iter()
+            .map(|(path_buf, _)| temporary_paths.get(path_buf).unwrap_or(path_buf).clone())
+            .collect::<Vec<_>>();
+
+        let group_outcomes = if let [path_buf] = current_path_bufs.as_slice() {
+            vec![rename_file(
+                path_buf,
+                &target_directory,
+                &new_file_name,
+                &rename_settings,
+                &mut claimed_targets,
+                current_working_directory,
+                &mut statistics,
+            )]
+        } else {
+            rename_files_as_unit(
+                &current_path_bufs,
+                &target_directory,
+                &new_file_name,
+                &rename_settings,
+                &mut claimed_targets,
+                current_working_directory,
+                &mut statistics,
+            )
+        };
+
+        for (mut rename_outcome, (path_buf, _)) in group_outcomes.into_iter().zip(&extracted_files) {
+            if rename_outcome.source != *path_buf {
+                restore_from_temporary_name(&mut rename_outcome, path_buf, current_working_directory);
+            }
+
+            if let Some(sidecar_paths) = sidecars.get(&rename_outcome.source) {
+                rename_sidecars(sidecar_paths, &mut rename_outcome, is_dry_run, current_working_directory, &mut statistics);
+            }
+
+            // The outcomes of an atomic run are only final once all files are renamed.
+            if options.atomic.not() {
+                if options.sync_mtime {
+                    sync_modification_time(&rename_outcome, &datetime, is_dry_run, current_working_directory, &mut statistics);
+                }
+                record_file_outcome(FileOutcome::from_rename_outcome(&rename_outcome, datetime), options, &mut file_outcomes);
+            }
+
+            rename_outcomes.push(rename_outcome);
+            rename_datetimes.push(datetime);
+        }
+
+        resume_journal.record_new_outcomes(&file_outcomes);
+    }
+
+    if options.atomic {
+        let failed_files = rename_outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome.action, RenameAction::Failed | RenameAction::Vanished))
+            .count();
+        let is_rolled_back = is_dry_run.not() && failed_files > 0;
+        if is_rolled_back {
+            log::warn!("Rolling back the atomic run, because {} files could not be renamed", failed_files);
+            roll_back(&mut rename_outcomes, &sidecars, current_working_directory, &mut statistics);
+        }
+
+        for (rename_outcome, datetime) in rename_outcomes.iter().zip(&rename_datetimes) {
+            if options.sync_mtime && is_rolled_back.not() {
+                sync_modification_time(rename_outcome, datetime, is_dry_run, current_working_directory, &mut statistics);
+            }
+            record_file_outcome(FileOutcome::from_rename_outcome(rename_outcome, *datetime), options, &mut file_outcomes);
+        }
+        resume_journal.record_new_outcomes(&file_outcomes);
+    }
+
+    options.send_progress(|| ProgressUpdate {
+        total: files_to_rename,
+        done: done_files,
+        current_path: None,
+        last_outcome: file_outcomes[first_renamed_outcome..].last().cloned(),
+    });
+
+    if is_cancelled {
+        statistics.untouched_files = (total_files - processed_files) as u64;
+        log::warn!(
+            "The run was cancelled after {} files, {} files were left untouched",
+            processed_files,
+            statistics.untouched_files
         );
+        if is_dry_run.not() {
+            log::info!("To continue the run, use the '--resume' flag.");
+        }
+    } else if let Some(limit) = options.limit
+        && processed_files >= limit
+        && total_files > processed_files
+    {
+        statistics.untouched_files = (total_files - processed_files) as u64;
+        log::info!(
+            "Stopped after {} files because of the limit, {} files were left untouched",
+            processed_files,
+            statistics.untouched_files
+        );
+    }
+
+    if is_cancelled.not() {
+        resume_journal.finish(current_working_directory);
+    }
+
+    if let Some(mut extraction_cache) = extraction_cache {
+        if is_dry_run.not() {
+            extraction_cache.apply_renames(&rename_outcomes);
+        }
+        if let Err(error) = extraction_cache.save() {
+            log::warn!("{}", error);
+        }
+    }
+
+    let mut has_failed_outputs = false;
+    if let Some(collisions_report_path) = &options.collisions_report {
+        match write_collisions_report(collisions_report_path, &rename_outcomes, is_dry_run, current_working_directory) {
+            Ok(collisions) => log::info!("Wrote report of {} collisions to {}", collisions, collisions_report_path.display()),
+            Err(error) => {
+                log::error!("{}", error);
+                has_failed_outputs = true;
+            }
+        }
+    }
+
+    if let Some(checksums_path) = &options.checksums {
+        let renamed_file_paths = rename_outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome.action, RenameAction::Renamed | RenameAction::Copied))
+            .map(|outcome| outcome.target.clone())
+            .collect::<Vec<_>>();
+
+        if is_dry_run {
+            log::info!(
+                "[DRY RUN] A checksum manifest of the renamed files would be written to {}",
+                checksums_path.display()
+            );
+        } else if let Err(error) = write_checksum_manifest(checksums_path, &renamed_file_paths, current_working_directory) {
+            log::error!("{}", error);
+            has_failed_outputs = true;
+        } else {
+            log::info!(
+                "Wrote checksum manifest of {} renamed files to {}",
+                renamed_file_paths.len(),
+                checksums_path.display()
+            );
+        }
+    }
+
+    if let Some(rename_map_path) = &options.rename_map {
+        if is_dry_run {
+            log::info!(
+                "[DRY RUN] A rename map of the renamed and copied files would be written to {}",
+                rename_map_path.display()
+            );
+        } else {
+            match write_rename_map(rename_map_path, &rename_outcomes, current_working_directory) {
+                Ok(mapped_files) => log::info!("Wrote rename map of {} files to {}", mapped_files, rename_map_path.display()),
+                Err(error) => {
+                    log::error!("{}", error);
+                    has_failed_outputs = true;
+                }
+            }
+        }
+    }
+
+    if options.backup_map
+        && is_dry_run.not()
+        && rename_outcomes
+            .iter()
+            .any(|outcome| matches!(outcome.action, RenameAction::Renamed | RenameAction::Copied))
+    {
+        match write_backup_rename_map(current_working_directory, &rename_outcomes, current_working_directory) {
+            Ok(map_path) => log::info!("Wrote rename map to {}", format_path_buf_without_prefix(&map_path, current_working_directory)),
+            Err(error) => {
+                log::error!("{}", error);
+                has_failed_outputs = true;
+            }
+        }
+    }
+
+    if let Some(plan_path) = &options.plan_file {
+        let plan = RenamePlan::from_rename_outcomes(&rename_outcomes, options.copy_to.is_some(), current_working_directory);
+        match plan.write_to_file(plan_path) {
+            Ok(_) => log::info!("Wrote plan of {} renames to {}", plan.renames.len(), plan_path.display()),
+            Err(error) => {
+                log::error!("{}", error);
+                has_failed_outputs = true;
+            }
+        }
+    }
+
+    if options.detect_duplicates {
+        for duplicate_group in find_duplicates(&rename_outcomes, is_dry_run, current_working_directory) {
+            statistics.duplicate_files += duplicate_group.paths.len() as u64 - 1;
+
+            if options.output == OutputFormat::Json {
+                print_duplicate_group(&duplicate_group);
+            } else {
+                let clean_file_names = duplicate_group
+                    .paths
+                    .iter()
+                    .map(|path| format_path_buf_without_prefix(path, current_working_directory))
+                    .collect::<Vec<_>>();
+                log::warn!("Identical files: {}", clean_file_names.join(", "));
+            }
+        }
+    }
+
+    if options.output == OutputFormat::Json {
+        print_summary(&statistics);
+    } else {
+        print_human_summary(&statistics, options.copy_to.is_some());
+    }
+
+    if is_dry_run && options.plan_file.is_none() {
+        log::warn!("This was just a dry run. To actually apply the renaming, use the '--no-dry-run' flag.")
+    }
+
+    RunReport {
+        statistics,
+        file_outcomes,
+        has_failed_outputs,
+        is_cancelled,
     }
+}
 
+/// A group of files, like a RAW+JPEG pair, whose new name is determined, but which is not renamed yet.
+struct PlannedGroup {
+    extracted_files: Vec<(PathBuf, MediaMetadata)>,
+    datetime: DateTime<FixedOffset>,
+    new_file_name: String,
+    target_directory: PathBuf,
+}
+
+/// Keeps the outcome for the [RunReport] and prints it right away for the JSON output, so that it can be consumed while the run is going on.
+fn record_file_outcome(file_outcome: FileOutcome, options: &RamboOptions, file_outcomes: &mut Vec<FileOutcome>) {
+    if options.output == OutputFormat::Json {
+        print_file_outcome(&file_outcome);
+    }
+    options.emit_event(RunEvent::from_file_outcome(&file_outcome));
+    file_outcomes.push(file_outcome);
+}
+
+fn print_human_summary(statistics: &Statistics, is_copy: bool) {
     println!("==============================");
     println!("Failed files:  {}", statistics.failed_files);
     println!("Skipped files: {}", statistics.skipped_files);
+    if statistics.filtered_files > 0 {
+        println!("Filtered files: {}", statistics.filtered_files);
+    }
     println!("Renamed files: {}", statistics.renamed_files);
+    if is_copy {
+        println!("Copied files:  {}", statistics.copied_files);
+    }
+    if statistics.untouched_files > 0 {
+        println!("Untouched files: {}", statistics.untouched_files);
+    }
+    if statistics.resumed_files > 0 {
+        println!("Resumed files: {}", statistics.resumed_files);
+    }
+    if statistics.vanished_files > 0 {
+        println!("Vanished files: {}", statistics.vanished_files);
+    }
+    if statistics.conflicting_files > 0 {
+        println!("Conflicts:     {}", statistics.conflicting_files);
+    }
+    if statistics.invalid_names > 0 {
+        println!("Invalid names: {}", statistics.invalid_names);
+    }
+    if statistics.sidecar_files > 0 {
+        println!("Sidecar files: {}", statistics.sidecar_files);
+    }
+    if statistics.synced_modification_times > 0 {
+        println!("Synced mtimes: {}", statistics.synced_modification_times);
+    }
+    if statistics.fixed_extensions > 0 {
+        println!("Fixed extensions: {}", statistics.fixed_extensions);
+    }
+    if statistics.trashed_files > 0 {
+        println!("Trashed files: {}", statistics.trashed_files);
+    }
+    if statistics.duplicate_files > 0 {
+        println!("Duplicates:    {}", statistics.duplicate_files);
+    }
+}
 
-    if options.no_dry_run.not() {
-        log::warn!("This was just a dry run. To actually apply the renaming, use the '--no-dry-run' flag.")
+fn current_working_directory() -> Result<PathBuf, RamboError> {
+    std::env::current_dir().map_err(RamboError::CurrentWorkingDirectory)
+}
+
+/// For the commands which end with an [ExitCode] instead of returning a [RunReport].
+fn log_error(error: RamboError) -> ExitCode {
+    log::error!("{}", error);
+    ExitCode::FAILURE
+}
+
+/// Files without a fraction of a second are numbered within their second instead, so that `%3f` renders `000`, `001`, ... for burst shots.
+/// The numbering only changes the rendered name, not the datetime which is reported.
+fn datetime_with_sequence(
+    datetime: &DateTime<FixedOffset>,
+    has_subseconds: bool,
+    files_per_second: &mut HashMap<DateTime<FixedOffset>, u32>,
+) -> DateTime<FixedOffset> {
+    if has_subseconds {
+        return *datetime;
     }
 
-    if statistics.failed_files > 0 {
-        ExitCode::FAILURE
-    } else {
-        ExitCode::SUCCESS
+    let Some(second) = datetime.with_nanosecond(0) else {
+        return *datetime;
+    };
+    let files_in_second = files_per_second.entry(second).or_default();
+    let sequence = (*files_in_second).min(999);
+    *files_in_second += 1;
+
+    second.with_nanosecond(sequence * 1_000_000).unwrap_or(second)
+}
+
+fn datetime_transform(time_zone: Option<&str>, shift: Option<&str>, camera_shifts: &BTreeMap<String, String>) -> Result<DatetimeTransform, RamboError> {
+    let mut parsed_camera_shifts = BTreeMap::new();
+    for (camera, camera_shift) in camera_shifts {
+        if let Some(camera_shift) = parse_setting::<TimeShift>(Some(camera_shift), &format!("Shift of camera '{}'", camera))? {
+            parsed_camera_shifts.insert(camera.clone(), camera_shift);
+        }
     }
+
+    Ok(DatetimeTransform {
+        shift: parse_setting::<TimeShift>(shift, "Shift")?,
+        camera_shifts: parsed_camera_shifts,
+        time_zone: parse_setting::<TargetTimeZone>(time_zone, "Time zone")?,
+    })
 }
 
-struct MediaAsset {
-    media_source: MediaSource<File>,
-    path_buf: PathBuf,
+fn parse_setting<T: FromStr<Err = RamboError>>(value: Option<&str>, name: &str) -> Result<Option<T>, RamboError> {
+    match value {
+        None => Ok(None),
+        Some(value_string) => match T::from_str(value_string) {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => Err(RamboError::InvalidSetting(format!("{} '{}' is invalid: {}", name, value_string, error))),
+        },
+    }
+}
+
+/// The paths which cannot be read are counted as failed. Returns no paths, if there is nothing to process.
+fn collect_paths(
+    input: &InputSource,
+    case_insensitive: bool,
+    include_symlinks: bool,
+    max_depth: Option<usize>,
+    statistics: &mut Statistics,
+) -> Result<Vec<PathBuf>, RamboError> {
+    let input_description = input.describe();
+    let (mut paths, errors) = input.evaluate(case_insensitive, include_symlinks, max_depth)?;
+
+    // Paths that do not exist anymore are kept, so that files which vanished since the evaluation are reported.
+    paths.retain(|path_buf| path_buf.is_file() || path_buf.exists().not());
+
+    if errors.is_empty().not() {
+        statistics.failed_files += errors.len() as u64;
+
+        log::warn!(
+            "Some paths could not be read to determine if their contents match the given {}. \
+            Make sure you have the permissions for these paths and symlinks are not broken.",
+            input_description
+        );
+
+        for error in errors.iter() {
+            log::warn!("{}", error);
+        }
+    }
+
+    if paths.is_empty() && errors.is_empty() {
+        log::warn!("No media files will be processed. Make sure the {} is correct.", input_description);
+    } else if paths.is_empty() && errors.is_empty().not() {
+        log::warn!(
+            "No media files will be processed. Make sure the {} is correct and you have adequate permissions.",
+            input_description
+        );
+    }
+
+    Ok(paths)
+}
+
+/// Like [collect_paths], but returns the [ExitCode] with which a command ends early, if there is nothing to process.
+fn collect_paths_or_exit(
+    input: &InputSource,
+    case_insensitive: bool,
+    include_symlinks: bool,
+    max_depth: Option<usize>,
+    statistics: &mut Statistics,
+) -> Result<Vec<PathBuf>, ExitCode> {
+    match collect_paths(input, case_insensitive, include_symlinks, max_depth, statistics) {
+        Ok(paths) if paths.is_empty() && statistics.failed_files > 0 => Err(ExitCode::FAILURE),
+        Ok(paths) if paths.is_empty() => Err(ExitCode::SUCCESS),
+        Ok(paths) => Ok(paths),
+        Err(error) => Err(log_error(error)),
+    }
+}
+
+/// The result of extracting the creation datetime of a single file.
+/// It is only logged and counted by [record_extraction], so that the extraction itself can run on other threads.
+pub(crate) enum Extraction {
+    Extracted(PathBuf, MediaMetadata),
+    Vanished(PathBuf),
+    Failed(PathBuf, ExtractError),
+}
+
+impl Extraction {
+    fn path(&self) -> &Path {
+        match self {
+            Extraction::Extracted(path_buf, _) | Extraction::Vanished(path_buf) | Extraction::Failed(path_buf, _) => path_buf,
+        }
+    }
 }
 
 /// We return the iterator which will create (and clean up!) the [MediaSource]s on-demand when it gets iterated over, i.e. in a for-loop.
 /// Returning a vector for example, will create all [MediaSource]s at once, which will result in all respective files being opened and kept open at once.
 /// This could cause a _Too many files open_ error.
-fn get_media_assets_from_path_bufs(path_bufs: Vec<PathBuf>) -> impl Iterator<Item = Result<MediaAsset, (PathBuf, nom_exif::Error)>> {
-    path_bufs.into_iter().filter(|path_buf| path_buf.is_file()).map(|path_buf| {
-        MediaSource::file_path(&path_buf)
-            .map_err(|error| (path_buf.clone(), error))
-            .map(|media_source| MediaAsset { media_source, path_buf })
-    })
+///
+/// With more than one job, the files are processed on worker threads, but the results are still returned in the order of the paths.
+pub(crate) fn extract_creation_datetimes(paths: Vec<PathBuf>, jobs: usize, extractor_chain: ExtractorChain) -> Box<dyn Iterator<Item = Extraction>> {
+    if jobs > 1 {
+        return Box::new(extract_creation_datetimes_in_parallel(paths, jobs, extractor_chain));
+    }
+
+    let mut media_parser = MediaParser::new();
+    Box::new(
+        paths
+            .into_iter()
+            .filter_map(move |path_buf| extract_creation_datetime_from_path(path_buf, &mut media_parser, &extractor_chain)),
+    )
+}
+
+/// Like [extract_creation_datetimes], but the known metadata of some files, like from the extraction cache, is used instead of extracting it again.
+/// Only the other files are passed to the extraction. The results are still returned in the order of the paths.
+pub(crate) fn extract_creation_datetimes_reusing(
+    paths: Vec<PathBuf>,
+    mut known_extractions: HashMap<PathBuf, MediaMetadata>,
+    jobs: usize,
+    extractor_chain: ExtractorChain,
+) -> Box<dyn Iterator<Item = Extraction>> {
+    if known_extractions.is_empty() {
+        return extract_creation_datetimes(paths, jobs, extractor_chain);
+    }
+
+    let unknown_paths = paths
+        .iter()
+        .filter(|path_buf| known_extractions.contains_key(*path_buf).not())
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut extractions = extract_creation_datetimes(unknown_paths, jobs, extractor_chain).peekable();
+
+    Box::new(paths.into_iter().filter_map(move |path_buf| match known_extractions.remove(&path_buf) {
+        Some(_) if path_buf.exists().not() => Some(Extraction::Vanished(path_buf)),
+        Some(metadata) => Some(Extraction::Extracted(path_buf, metadata)),
+        // The extraction skips paths which are no files, so it may have no result for the path.
+        None => extractions.next_if(|extraction| extraction.path() == path_buf),
+    }))
+}
+
+/// Returns [None] for paths which are not files, like directories.
+/// Paths that do not exist anymore are not filtered out, so that files which vanished since the glob evaluation are reported.
+pub(crate) fn extract_creation_datetime_from_path(path_buf: PathBuf, media_parser: &mut MediaParser, extractor_chain: &ExtractorChain) -> Option<Extraction> {
+    if path_buf.is_file().not() && path_buf.exists() {
+        return None;
+    }
+    if path_buf.exists().not() {
+        return Some(Extraction::Vanished(path_buf));
+    }
+
+    match extractor_chain.extract(&path_buf, media_parser) {
+        Ok(metadata) => Some(Extraction::Extracted(path_buf, metadata)),
+        Err(_) if path_buf.exists().not() => Some(Extraction::Vanished(path_buf)),
+        Err(error) => Some(Extraction::Failed(path_buf, error)),
+    }
 }
 
-pub(crate) fn format_path_buf_without_prefix(path_buf: &PathBuf, prefix: &str) -> String {
-    let path_string = path_buf.display().to_string();
+/// Returns the [FileOutcome] if the creation datetime could not be extracted. This is logged and counted in the [Statistics].
+pub(crate) fn record_extraction(
+    extraction: Extraction,
+    current_working_directory: &Path,
+    statistics: &mut Statistics,
+) -> Result<(PathBuf, MediaMetadata), FileOutcome> {
+    let path_buf = extraction.path();
+
+    if path_buf.to_str().is_none() {
+        log::warn!(
+            "The path {} is not valid UTF-8. It is processed nonetheless, but displayed with replacement characters.",
+            format_path_buf_without_prefix(path_buf, current_working_directory)
+        );
+    }
+
+    match extraction {
+        Extraction::Extracted(path_buf, metadata) => Ok((path_buf, metadata)),
+        Extraction::Vanished(path_buf) => {
+            statistics.vanished_files += 1;
+            log::warn!(
+                "The file vanished before it could be processed: {}",
+                format_path_buf_without_prefix(&path_buf, current_working_directory)
+            );
+            Err(FileOutcome {
+                path: path_buf,
+                new_path: None,
+                datetime: None,
+                action: RenameAction::Vanished,
+                conflict: None,
+                error: None,
+            })
+        }
+        Extraction::Failed(path_buf, error) => {
+            statistics.failed_files += 1;
+            log::warn!(
+                "Cannot extract creation datetime from {}: {}",
+                format_path_buf_without_prefix(&path_buf, current_working_directory),
+                error
+            );
+            Err(FileOutcome {
+                path: path_buf,
+                new_path: None,
+                datetime: None,
+                action: RenameAction::Failed,
+                conflict: None,
+                error: Some(error.to_string()),
+            })
+        }
+    }
+}
+
+fn create_progress_bar(total: u64) -> ProgressBar {
+    let style = ProgressStyle::with_template("{bar:40} {pos}/{len} files, ETA {eta} {wide_msg}").unwrap_or_else(|_| ProgressStyle::default_bar());
+
+    ProgressBar::new(total).with_style(style)
+}
+
+/// The prefix is stripped component-wise on the [Path] itself, so that paths which are not valid UTF-8 are handled correctly.
+/// Only the returned [String] is lossy, as it is meant for display purposes.
+pub(crate) fn format_path_buf_without_prefix(path_buf: &Path, prefix: &Path) -> String {
+    strip_path_prefix(path_buf, prefix).display().to_string()
+}
 
-    path_string.strip_prefix(prefix).map(String::from).unwrap_or(path_string)
+pub(crate) fn strip_path_prefix<'a>(path: &'a Path, prefix: &Path) -> &'a Path {
+    path.strip_prefix(prefix).unwrap_or(path)
 }
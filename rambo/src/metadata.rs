@@ -0,0 +1,65 @@
+use std::fs::{File, FileTimes, Metadata};
+use std::ops::Not;
+use std::path::Path;
+
+/// Applies the metadata of the file system of the original to its copy: the permissions, the owner and the group where permitted, the extended attributes and the timestamps.
+/// On macOS, the resource fork is an extended attribute as well. Ownership and extended attributes are preserved on a best-effort basis,
+/// as they need privileges or support of the target file system, while failing to set the permissions or the timestamps is an error.
+pub(crate) fn preserve_metadata(source: &Path, target: &Path) -> std::io::Result<()> {
+    let source_metadata = std::fs::metadata(source)?;
+
+    std::fs::set_permissions(target, source_metadata.permissions())?;
+    preserve_ownership(&source_metadata, target);
+    preserve_extended_attributes(source, target);
+    preserve_timestamps(&source_metadata, target)
+}
+
+#[cfg(unix)]
+fn preserve_ownership(source_metadata: &Metadata, target: &Path) {
+    use std::os::unix::fs::MetadataExt;
+
+    // Only root may change the owner, but the group may be changed to any group the user is a member of.
+    if let Err(error) = std::os::unix::fs::chown(target, Some(source_metadata.uid()), Some(source_metadata.gid()))
+        && let Err(group_error) = std::os::unix::fs::chown(target, None, Some(source_metadata.gid()))
+    {
+        log::debug!("Cannot preserve the owner and the group of {}: {} ({})", target.display(), error, group_error);
+    }
+}
+
+#[cfg(not(unix))]
+fn preserve_ownership(_source_metadata: &Metadata, _target: &Path) {}
+
+fn preserve_extended_attributes(source: &Path, target: &Path) {
+    if xattr::SUPPORTED_PLATFORM.not() {
+        return;
+    }
+
+    let names = match xattr::list(source) {
+        Ok(names) => names,
+        Err(error) => {
+            log::debug!("Cannot list the extended attributes of {}: {}", source.display(), error);
+            return;
+        }
+    };
+
+    for name in names {
+        if let Err(error) = xattr::get(source, &name).and_then(|value| xattr::set(target, &name, &value.unwrap_or_default())) {
+            log::debug!(
+                "Cannot preserve the extended attribute '{}' of {}: {}",
+                name.to_string_lossy(),
+                target.display(),
+                error
+            );
+        }
+    }
+}
+
+/// The access time is only preserved if the file system of the original records it.
+fn preserve_timestamps(source_metadata: &Metadata, target: &Path) -> std::io::Result<()> {
+    let mut file_times = FileTimes::new().set_modified(source_metadata.modified()?);
+    if let Ok(accessed) = source_metadata.accessed() {
+        file_times = file_times.set_accessed(accessed);
+    }
+
+    File::options().write(true).open(target)?.set_times(file_times)
+}
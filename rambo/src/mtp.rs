@@ -0,0 +1,400 @@
+use crate::error::RamboError;
+use crate::output::FileOutcome;
+use crate::rename::RenameAction;
+use crate::report::RunReport;
+use crate::{CancellationToken, RamboOptions, RunContext, run_on_paths};
+
+use nusb::io::{EndpointRead, EndpointWrite};
+use nusb::transfer::{Bulk, Direction, In, Out};
+use nusb::{InterfaceInfo, MaybeFuture};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Write};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The file in the destination directory which lists the objects that were already imported, one `<DEVICE> <OBJECT HANDLE>` per line.
+pub const MTP_IMPORTED_FILE_NAME: &str = ".rambo-mtp-imported";
+
+/// The directory in the destination directory where the objects are downloaded to, before they are renamed.
+const STAGING_DIRECTORY_NAME: &str = ".rambo-mtp-import";
+
+const TRANSFER_SIZE: usize = 1 << 16;
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(30);
+
+const CONTAINER_COMMAND: u16 = 0x0001;
+const CONTAINER_DATA: u16 = 0x0002;
+const CONTAINER_RESPONSE: u16 = 0x0003;
+
+const OPERATION_OPEN_SESSION: u16 = 0x1002;
+const OPERATION_CLOSE_SESSION: u16 = 0x1003;
+const OPERATION_GET_STORAGE_IDS: u16 = 0x1004;
+const OPERATION_GET_OBJECT_HANDLES: u16 = 0x1007;
+const OPERATION_GET_OBJECT_INFO: u16 = 0x1008;
+const OPERATION_GET_OBJECT: u16 = 0x1009;
+
+const RESPONSE_OK: u16 = 0x2001;
+const RESPONSE_SESSION_ALREADY_OPEN: u16 = 0x201E;
+
+/// The format of an object which is a folder.
+const FORMAT_ASSOCIATION: u16 = 0x3001;
+
+/// A camera or phone connected with USB, whose objects are read with PTP, which MTP is based on.
+/// Phones must be unlocked and set to transfer files.
+pub struct MtpDevice {
+    /// Identifies the device in [MTP_IMPORTED_FILE_NAME], like `04a9:3218:<SERIAL>`.
+    id: String,
+    name: String,
+    reader: EndpointRead<Bulk>,
+    writer: EndpointWrite<Bulk>,
+    transaction_id: u32,
+}
+
+/// What is needed of the info of an object to import it.
+struct ObjectInfo {
+    format: u16,
+    file_name: String,
+}
+
+impl MtpDevice {
+    /// Opens the first connected device which offers PTP or MTP and starts a session with it.
+    /// Desktop environments often claim the device as soon as it is connected, so it may have to be unmounted first.
+    pub fn open() -> Result<Self, RamboError> {
+        let (device_info, interface_number) = nusb::list_devices()
+            .wait()
+            .map_err(device_error("Failed to list the USB devices"))?
+            .find_map(|device_info| {
+                let interface_number = device_info.interfaces().find(|interface| is_ptp_interface(interface))?.interface_number();
+                Some((device_info, interface_number))
+            })
+            .ok_or_else(|| RamboError::InvalidInput("No camera or phone with PTP or MTP is connected".to_string()))?;
+
+        let id = format!(
+            "{:04x}:{:04x}:{}",
+            device_info.vendor_id(),
+            device_info.product_id(),
+            device_info.serial_number().unwrap_or_default()
+        );
+        let name = device_info
+            .product_string()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{:04x}:{:04x}", device_info.vendor_id(), device_info.product_id()));
+
+        let interface = device_info
+            .open()
+            .wait()
+            .and_then(|device| device.detach_and_claim_interface(interface_number).wait())
+            .map_err(device_error(format!("Failed to open {}", name)))?;
+
+        let endpoint_address = |direction: Direction| {
+            interface.descriptor().and_then(|descriptor| {
+                descriptor
+                    .endpoints()
+                    .find(|endpoint| endpoint.transfer_type() == nusb::descriptors::TransferType::Bulk && endpoint.direction() == direction)
+                    .map(|endpoint| endpoint.address())
+            })
+        };
+        let (Some(in_address), Some(out_address)) = (endpoint_address(Direction::In), endpoint_address(Direction::Out)) else {
+            return Err(RamboError::InvalidInput(format!("{} has no bulk endpoints for PTP", name)));
+        };
+
+        let reader = interface
+            .endpoint::<Bulk, In>(in_address)
+            .map_err(device_error(format!("Failed to open {}", name)))?
+            .reader(TRANSFER_SIZE)
+            .with_read_timeout(TRANSFER_TIMEOUT);
+        let writer = interface
+            .endpoint::<Bulk, Out>(out_address)
+            .map_err(device_error(format!("Failed to open {}", name)))?
+            .writer(TRANSFER_SIZE)
+            .with_write_timeout(TRANSFER_TIMEOUT);
+
+        let mut device = Self {
+            id,
+            name,
+            reader,
+            writer,
+            transaction_id: 0,
+        };
+
+        match device.transaction(OPERATION_OPEN_SESSION, &[1], &mut std::io::sink()) {
+            Ok(RESPONSE_OK | RESPONSE_SESSION_ALREADY_OPEN) => Ok(device),
+            Ok(code) => Err(RamboError::Device {
+                description: format!("Failed to start a session with {}", device.name),
+                source: format!("The device responded with code {:#06x}", code).into(),
+            }),
+            Err(error) => Err(device_error(format!("Failed to start a session with {}", device.name))(error)),
+        }
+    }
+
+    /// The product name of the device, like `Canon EOS R6`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the handles of all objects on all available storages, including folders.
+    fn object_handles(&mut self) -> std::io::Result<Vec<u32>> {
+        let mut storage_ids = Vec::new();
+        self.request(OPERATION_GET_STORAGE_IDS, &[], &mut storage_ids)?;
+
+        let mut object_handles = Vec::new();
+        // A storage whose lower half is zero is not available, like an empty card slot.
+        for storage_id in parse_u32_array(&storage_ids).into_iter().filter(|storage_id| storage_id & 0xFFFF != 0) {
+            let mut handles = Vec::new();
+            // No format and no parent request all objects of the storage, including the ones in folders.
+            self.request(OPERATION_GET_OBJECT_HANDLES, &[storage_id, 0, 0], &mut handles)?;
+            object_handles.extend(parse_u32_array(&handles));
+        }
+
+        Ok(object_handles)
+    }
+
+    fn object_info(&mut self, handle: u32) -> std::io::Result<ObjectInfo> {
+        let mut data = Vec::new();
+        self.request(OPERATION_GET_OBJECT_INFO, &[handle], &mut data)?;
+
+        // The format follows the storage ID, the file name follows 15 fields of fixed size.
+        let format = data.get(4..6).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]));
+        let file_name = data.get(52..).and_then(parse_string);
+        match (format, file_name) {
+            (Some(format), Some(file_name)) => Ok(ObjectInfo { format, file_name }),
+            _ => Err(std::io::Error::other("The device sent an invalid object info")),
+        }
+    }
+
+    fn download(&mut self, handle: u32, file_path: &Path) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(file_path)?);
+        self.request(OPERATION_GET_OBJECT, &[handle], &mut file)?;
+        file.flush()
+    }
+
+    /// Like [Self::transaction], but fails if the response is not OK.
+    fn request(&mut self, operation: u16, parameters: &[u32], data: &mut dyn Write) -> std::io::Result<()> {
+        match self.transaction(operation, parameters, data)? {
+            RESPONSE_OK => Ok(()),
+            code => Err(std::io::Error::other(format!("The device responded with code {:#06x}", code))),
+        }
+    }
+
+    /// Sends the command and writes the payload of the data phase, if any, to `data`. Returns the code of the response.
+    /// Every container ends with a short packet, so it is read until then.
+    fn transaction(&mut self, operation: u16, parameters: &[u32], data: &mut dyn Write) -> std::io::Result<u16> {
+        let length = 12 + 4 * parameters.len() as u32;
+        let mut command = Vec::with_capacity(length as usize);
+        command.extend(length.to_le_bytes());
+        command.extend(CONTAINER_COMMAND.to_le_bytes());
+        command.extend(operation.to_le_bytes());
+        command.extend(self.transaction_id.to_le_bytes());
+        for parameter in parameters {
+            command.extend(parameter.to_le_bytes());
+        }
+        self.writer.write_all(&command)?;
+        self.writer.flush_end()?;
+
+        loop {
+            let mut reader = self.reader.until_short_packet();
+            let mut header = [0; 12];
+            reader.read_exact(&mut header)?;
+            let container_type = u16::from_le_bytes([header[4], header[5]]);
+            let code = u16::from_le_bytes([header[6], header[7]]);
+
+            match container_type {
+                CONTAINER_DATA => std::io::copy(&mut reader, data)?,
+                _ => std::io::copy(&mut reader, &mut std::io::sink())?,
+            };
+            reader.consume_end().map_err(std::io::Error::other)?;
+
+            if container_type == CONTAINER_RESPONSE {
+                self.transaction_id += 1;
+                return Ok(code);
+            }
+        }
+    }
+}
+
+impl Drop for MtpDevice {
+    fn drop(&mut self) {
+        if let Err(error) = self.request(OPERATION_CLOSE_SESSION, &[], &mut std::io::sink()) {
+            log::debug!("Failed to close the session with {}: {}", self.name, error);
+        }
+    }
+}
+
+/// Copies the objects of the device which were not imported before into the destination directory and renames them like [crate::run] with [RamboOptions::move_to].
+/// The imported objects are listed in [MTP_IMPORTED_FILE_NAME] in the destination directory, so that repeated imports only copy new objects. Nothing on the device is changed.
+/// The objects are downloaded even in a dry run, to extract their creation datetime, but nothing is kept. [RamboOptions::input] and [RamboOptions::plan_file] do not apply.
+pub fn import_from_mtp(device: &mut MtpDevice, destination: &Path, options: RamboOptions) -> Result<RunReport, RamboError> {
+    let run_context = RunContext::new(&options)?;
+
+    std::fs::create_dir_all(destination).map_err(RamboError::io(format!("Failed to create the directory {}", destination.display())))?;
+    let destination = destination
+        .canonicalize()
+        .map_err(RamboError::io(format!("Failed to resolve the directory {}", destination.display())))?;
+    let imported_file_path = destination.join(MTP_IMPORTED_FILE_NAME);
+    let imported_handles = read_imported_handles(&imported_file_path, &device.id)?;
+
+    let handles = device
+        .object_handles()
+        .map_err(device_error(format!("Failed to list the objects of {}", device.name)))?;
+    let new_handles = handles.into_iter().filter(|handle| imported_handles.contains(handle).not()).collect::<Vec<_>>();
+    log::info!("Found {} objects on {}, which were not imported yet", new_handles.len(), device.name);
+
+    let staging_directory = destination.join(STAGING_DIRECTORY_NAME);
+    let mut downloads = HashMap::<PathBuf, u32>::new();
+    let mut failed_outcomes = Vec::<FileOutcome>::new();
+
+    for handle in new_handles {
+        if options.cancellation_token.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            break;
+        }
+
+        let object_info = match device.object_info(handle) {
+            Ok(object_info) => object_info,
+            Err(error) => {
+                log::warn!("Cannot read the info of object {:#010x}: {}", handle, error);
+                failed_outcomes.push(failed_download(PathBuf::from(format!("{:#010x}", handle)), error));
+                continue;
+            }
+        };
+        if object_info.format == FORMAT_ASSOCIATION {
+            continue;
+        }
+
+        // Each object gets its own directory, as file names are not unique across the folders of a device.
+        let file_name = Path::new(&object_info.file_name)
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| format!("{:#010x}", handle).into());
+        let file_path = staging_directory.join(format!("{:08x}", handle)).join(file_name);
+        if run_context.file_filter.passes_extension(&file_path).not() {
+            continue;
+        }
+
+        log::info!("Downloading {}", object_info.file_name);
+        let download = file_path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| device.download(handle, &file_path));
+        match download {
+            Ok(()) => {
+                downloads.insert(file_path, handle);
+            }
+            Err(error) => {
+                log::warn!("Failed to download {}: {}", object_info.file_name, error);
+                failed_outcomes.push(failed_download(PathBuf::from(object_info.file_name), error));
+            }
+        }
+    }
+
+    let report = run_on_paths(
+        downloads.keys().cloned().collect(),
+        RamboOptions {
+            copy_to: None,
+            move_to: Some(destination.clone()),
+            plan_file: None,
+            ..options
+        },
+    );
+    if staging_directory.exists()
+        && let Err(error) = std::fs::remove_dir_all(&staging_directory)
+    {
+        log::warn!("Failed to remove the directory {}: {}", staging_directory.display(), error);
+    }
+    let mut report = report?;
+
+    let newly_imported_handles = report
+        .file_outcomes
+        .iter()
+        .filter(|file_outcome| file_outcome.action == RenameAction::Renamed)
+        .filter_map(|file_outcome| downloads.get(&file_outcome.path))
+        .collect::<Vec<_>>();
+    if newly_imported_handles.is_empty().not() {
+        append_imported_handles(&imported_file_path, &device.id, &newly_imported_handles)?;
+        log::info!("Imported {} objects from {}", newly_imported_handles.len(), device.name);
+    }
+
+    report.statistics.failed_files += failed_outcomes.len() as u64;
+    report.file_outcomes.extend(failed_outcomes);
+
+    Ok(report)
+}
+
+fn is_ptp_interface(interface: &InterfaceInfo) -> bool {
+    // Cameras use the still image class, phones mostly use a vendor specific class named MTP.
+    (interface.class() == 0x06 && interface.subclass() == 0x01) || (interface.class() == 0xFF && interface.interface_string() == Some("MTP"))
+}
+
+fn device_error<E: std::error::Error + Send + Sync + 'static>(description: impl Into<String>) -> impl FnOnce(E) -> RamboError {
+    let description = description.into();
+    move |source| RamboError::Device {
+        description,
+        source: Box::new(source),
+    }
+}
+
+fn failed_download(path: PathBuf, error: std::io::Error) -> FileOutcome {
+    FileOutcome {
+        path,
+        new_path: None,
+        datetime: None,
+        action: RenameAction::Failed,
+        conflict: None,
+        error: Some(format!("Failed to download: {}", error)),
+    }
+}
+
+/// A missing file means that nothing was imported yet.
+fn read_imported_handles(file_path: &Path, device_id: &str) -> Result<HashSet<u32>, RamboError> {
+    let content = match std::fs::read_to_string(file_path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(error) => return Err(RamboError::io(format!("Failed to read {}", file_path.display()))(error)),
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(id, _)| *id == device_id)
+        .filter_map(|(_, handle)| u32::from_str_radix(handle, 16).ok())
+        .collect())
+}
+
+fn append_imported_handles(file_path: &Path, device_id: &str, handles: &[&u32]) -> Result<(), RamboError> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file_path)
+        .map_err(RamboError::io(format!("Failed to open {}", file_path.display())))?;
+
+    let lines = handles.iter().map(|handle| format!("{} {:08x}\n", device_id, handle)).collect::<String>();
+    file.write_all(lines.as_bytes())
+        .map_err(RamboError::io(format!("Failed to write {}", file_path.display())))
+}
+
+/// An array of PTP is prefixed with the number of its elements.
+fn parse_u32_array(data: &[u8]) -> Vec<u32> {
+    let count = data
+        .get(..4)
+        .map_or(0, |bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize);
+
+    data.get(4..)
+        .unwrap_or_default()
+        .chunks_exact(4)
+        .take(count)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+/// A string of PTP is prefixed with the number of its UTF-16 code units, including the terminating null.
+fn parse_string(data: &[u8]) -> Option<String> {
+    let (&length, code_units) = data.split_first()?;
+    let code_units = code_units
+        .get(..2 * length as usize)?
+        .chunks_exact(2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .take_while(|code_unit| *code_unit != 0)
+        .collect::<Vec<_>>();
+
+    Some(String::from_utf16_lossy(&code_units))
+}
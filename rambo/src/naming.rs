@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Not;
+use std::path::Path;
+use unicode_normalization::UnicodeNormalization as _;
+
+/// The file system whose limits the new names must fit in, in addition to the ones of the file system they are renamed on.
+/// Use it when renaming files on a share or a drive which is also used with another operating system or device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FsCompat {
+    /// NTFS without long paths, whose paths have at most 259 characters.
+    Windows,
+    /// FAT32 or exFAT, like on SD cards. The names only contain ASCII characters, which devices like cameras can show.
+    Fat,
+}
+
+impl FsCompat {
+    pub(crate) fn max_path_length(self) -> Option<usize> {
+        match self {
+            FsCompat::Windows => Some(MAX_WINDOWS_PATH_LENGTH),
+            FsCompat::Fat => None,
+        }
+    }
+}
+
+/// The Unicode normalization form of the new names. macOS stores names decomposed (NFD), while Linux keeps them as they are, which are usually composed (NFC).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnicodeNormalization {
+    /// Keep the names as they are rendered and compare them byte by byte.
+    #[default]
+    None,
+    /// Compose the characters, like `é` as a single code point.
+    Nfc,
+    /// Decompose the characters, like `é` as `e` and a combining accent.
+    Nfd,
+}
+
+impl UnicodeNormalization {
+    pub(crate) fn normalize(self, value: &str) -> String {
+        match self {
+            UnicodeNormalization::None => value.to_string(),
+            UnicodeNormalization::Nfc => value.nfc().collect(),
+            UnicodeNormalization::Nfd => value.nfd().collect(),
+        }
+    }
+
+    /// Paths which differ only in their normalization are the same, so that a file whose name has another normalization is not renamed to the name it already has.
+    pub(crate) fn is_same_path(self, path: &Path, other_path: &Path) -> bool {
+        if path == other_path {
+            return true;
+        }
+
+        match (self, path.to_str(), other_path.to_str()) {
+            (UnicodeNormalization::None, _, _) | (_, None, _) | (_, _, None) => false,
+            (normalization, Some(path), Some(other_path)) => normalization.normalize(path) == normalization.normalize(other_path),
+        }
+    }
+}
+
+/// The limit of Windows without the long path opt-in is 260 characters, including the terminating null character.
+const MAX_WINDOWS_PATH_LENGTH: usize = 259;
+
+/// The limit of the names of files on most file systems, in bytes on Linux and in UTF-16 code units on Windows, which are never more than the bytes.
+const MAX_FILE_NAME_LENGTH: usize = 255;
+
+/// Characters which are not allowed in file names on Linux, macOS or Windows. Control characters are not allowed either.
+const ILLEGAL_FILE_NAME_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Names which Windows reserves for devices, with any extension.
+const RESERVED_FILE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7",
+    "LPT8", "LPT9",
+];
+
+/// Describes why the new path does not fit the limits of the file systems, if it does not.
+pub(crate) fn file_system_limit_violation(new_file_path: &Path, max_path_length: Option<usize>) -> Option<String> {
+    let file_name_length = new_file_path.file_name().map_or(0, |file_name| file_name.len());
+    if file_name_length > MAX_FILE_NAME_LENGTH {
+        return Some(format!(
+            "the name has {} bytes, but at most {} are allowed",
+            file_name_length, MAX_FILE_NAME_LENGTH
+        ));
+    }
+
+    let path_length = new_file_path.to_string_lossy().encode_utf16().count();
+    if let Some(max_path_length) = max_path_length
+        && path_length > max_path_length
+    {
+        return Some(format!("the path has {} characters, but at most {} are allowed", path_length, max_path_length));
+    }
+
+    None
+}
+
+pub(crate) fn is_legal_file_name_char(char: char, fs_compat: Option<FsCompat>) -> bool {
+    let is_legal = ILLEGAL_FILE_NAME_CHARS.contains(&char).not() && char.is_control().not();
+
+    match fs_compat {
+        Some(FsCompat::Fat) => is_legal && char.is_ascii(),
+        Some(FsCompat::Windows) | None => is_legal,
+    }
+}
+
+/// Replaces the characters of a new file name (without the extension) which are illegal on any common file system, or the one of the [FsCompat], with the replacement,
+/// e.g. the `/` of `%D` or the `:` of `%T`. Trailing dots and spaces, which Windows drops, are removed, and names reserved by Windows get the replacement appended.
+pub(crate) fn sanitize_file_name(file_name: &str, replacement: char, fs_compat: Option<FsCompat>) -> String {
+    let mut sanitized = file_name
+        .chars()
+        .map(|char| {
+            if is_legal_file_name_char(char, fs_compat) {
+                char
+            } else {
+                replacement
+            }
+        })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string();
+
+    if sanitized.is_empty() || RESERVED_FILE_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(&sanitized)) {
+        sanitized.push(replacement);
+    }
+    sanitized
+}
@@ -0,0 +1,93 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// The order in which the renames of a run are carried out, so that no file is renamed to the current name of a file which is renamed later in the run.
+pub(crate) struct RenameOrder {
+    /// The indices of the renames in the order they are carried out.
+    pub(crate) indices: Vec<usize>,
+    /// The renames which wait for each other in a cycle, like two files which swap their names.
+    /// Their files are given temporary names before any file is renamed, and they are carried out last.
+    pub(crate) via_temporary_names: HashSet<usize>,
+}
+
+impl RenameOrder {
+    /// The renames are carried out in the given order, like when the files are copied and their current names never become free.
+    pub(crate) fn unchanged(renames: usize) -> Self {
+        Self {
+            indices: (0..renames).collect(),
+            via_temporary_names: HashSet::new(),
+        }
+    }
+}
+
+/// Each rename consists of the current and the requested path of each of its files, e.g. of a RAW+JPEG pair.
+/// A rename whose requested path is the current path of another rename waits for the other one. Otherwise, the renames keep their order.
+/// Renames which wait for each other in a cycle are broken up by giving the files of one of them a temporary name, so that its current paths are free.
+pub(crate) fn order_renames(renames: &[Vec<(PathBuf, PathBuf)>]) -> RenameOrder {
+    let renames_by_source = renames
+        .iter()
+        .enumerate()
+        .flat_map(|(index, files)| files.iter().map(move |(source, _)| (source.as_path(), index)))
+        .collect::<HashMap<&Path, usize>>();
+
+    let mut waits_for = vec![Vec::<usize>::new(); renames.len()];
+    let mut awaited_by = vec![Vec::<usize>::new(); renames.len()];
+    for (index, files) in renames.iter().enumerate() {
+        for (_, target) in files {
+            if let Some(&other_index) = renames_by_source.get(target.as_path())
+                && other_index != index
+            {
+                waits_for[index].push(other_index);
+                awaited_by[other_index].push(index);
+            }
+        }
+    }
+
+    let mut pending_dependencies = waits_for.iter().map(Vec::len).collect::<Vec<_>>();
+    let mut is_ordered = vec![false; renames.len()];
+    let mut ready = (0..renames.len()).filter(|&index| pending_dependencies[index] == 0).collect::<BTreeSet<_>>();
+    let mut indices = Vec::<usize>::with_capacity(renames.len());
+    let mut via_temporary_names = Vec::<usize>::new();
+
+    let mut release = |index: usize, is_ordered: &[bool], ready: &mut BTreeSet<usize>| {
+        for &waiting_index in &awaited_by[index] {
+            pending_dependencies[waiting_index] -= 1;
+            if pending_dependencies[waiting_index] == 0 && is_ordered[waiting_index].not() {
+                ready.insert(waiting_index);
+            }
+        }
+    };
+
+    loop {
+        while let Some(index) = ready.pop_first() {
+            is_ordered[index] = true;
+            indices.push(index);
+            release(index, &is_ordered, &mut ready);
+        }
+
+        // All remaining renames wait for another remaining rename, so following the waits leads into a cycle eventually.
+        let Some(mut index) = (0..renames.len()).find(|&index| is_ordered[index].not()) else {
+            break;
+        };
+        let mut visited = HashSet::<usize>::new();
+        while visited.insert(index) {
+            index = waits_for[index]
+                .iter()
+                .copied()
+                .find(|&other_index| is_ordered[other_index].not())
+                .expect("a remaining rename waits for another remaining rename");
+        }
+
+        is_ordered[index] = true;
+        via_temporary_names.push(index);
+        release(index, &is_ordered, &mut ready);
+    }
+
+    indices.extend(&via_temporary_names);
+
+    RenameOrder {
+        indices,
+        via_temporary_names: via_temporary_names.into_iter().collect(),
+    }
+}
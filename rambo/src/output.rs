@@ -0,0 +1,96 @@
+use crate::duplicates::DuplicateGroup;
+use crate::rename::{ConflictStrategy, RenameAction, RenameOutcome};
+use crate::statistics::Statistics;
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Serialize, Serializer};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Log lines and a summary for humans.
+    #[default]
+    Human,
+    /// One JSON object per line for each file and each group of duplicates, followed by a summary object.
+    Json,
+}
+
+/// The outcome of processing a single file.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileOutcome {
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub path: PathBuf,
+    /// The path the file was (or would be in a dry run) renamed or copied to.
+    #[serde(serialize_with = "serialize_optional_path_lossy")]
+    pub new_path: Option<PathBuf>,
+    pub datetime: Option<DateTime<FixedOffset>>,
+    pub action: RenameAction,
+    pub conflict: Option<ConflictStrategy>,
+    pub error: Option<String>,
+}
+
+impl FileOutcome {
+    pub(crate) fn from_rename_outcome(rename_outcome: &RenameOutcome, datetime: DateTime<FixedOffset>) -> Self {
+        let new_path = match rename_outcome.action {
+            RenameAction::AlreadyCorrect | RenameAction::Renamed | RenameAction::Copied => Some(rename_outcome.target.clone()),
+            RenameAction::Skipped | RenameAction::Vanished | RenameAction::Failed => None,
+        };
+
+        Self {
+            path: rename_outcome.source.clone(),
+            new_path,
+            datetime: Some(datetime),
+            action: rename_outcome.action,
+            conflict: rename_outcome.conflict,
+            error: rename_outcome.error.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonRecord<'a> {
+    File(&'a FileOutcome),
+    Duplicates(&'a DuplicateGroup),
+    Summary(&'a Statistics),
+}
+
+pub(crate) fn print_file_outcome(file_outcome: &FileOutcome) {
+    print_json_record(&JsonRecord::File(file_outcome));
+}
+
+pub(crate) fn print_duplicate_group(duplicate_group: &DuplicateGroup) {
+    print_json_record(&JsonRecord::Duplicates(duplicate_group));
+}
+
+pub(crate) fn print_summary(statistics: &Statistics) {
+    print_json_record(&JsonRecord::Summary(statistics));
+}
+
+fn print_json_record(record: &JsonRecord) {
+    let mut stdout = std::io::stdout().lock();
+
+    if let Err(error) = serde_json::to_writer(&mut stdout, record)
+        .map_err(std::io::Error::from)
+        .and_then(|_| writeln!(stdout))
+    {
+        log::error!("Failed to write JSON output: {}", error);
+    }
+}
+
+/// Paths are not necessarily valid UTF-8, which JSON strings must be.
+fn serialize_path_lossy<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
+pub(crate) fn serialize_paths_lossy<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(paths.iter().map(|path| path.to_string_lossy()))
+}
+
+fn serialize_optional_path_lossy<S: Serializer>(path: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error> {
+    match path {
+        Some(path) => serialize_path_lossy(path, serializer),
+        None => serializer.serialize_none(),
+    }
+}
@@ -0,0 +1,49 @@
+use crate::Extraction;
+
+use std::ffi::OsStr;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+
+/// Files with the same stem in the same directory belong together, like `IMG_1234.CR2` and `IMG_1234.JPG`.
+fn pair_key(path: &Path) -> (Option<&Path>, Option<&OsStr>) {
+    (path.parent(), path.file_stem())
+}
+
+/// Moves the files which belong together next to each other, so they are extracted one after another.
+/// The order of the files within a pair is kept.
+pub(crate) fn sort_paths_into_pairs(paths: &mut [PathBuf]) {
+    paths.sort_by(|a, b| pair_key(a).cmp(&pair_key(b)));
+}
+
+/// Groups the extractions of files which belong together. Without pairing, each extraction is a group of its own.
+/// The paths must have been sorted with [sort_paths_into_pairs] before the extraction.
+pub(crate) struct PairedExtractions<I: Iterator<Item = Extraction>> {
+    extractions: Peekable<I>,
+    keep_pairs: bool,
+}
+
+impl<I: Iterator<Item = Extraction>> PairedExtractions<I> {
+    pub(crate) fn new(extractions: I, keep_pairs: bool) -> Self {
+        Self {
+            extractions: extractions.peekable(),
+            keep_pairs,
+        }
+    }
+}
+
+impl<I: Iterator<Item = Extraction>> Iterator for PairedExtractions<I> {
+    type Item = Vec<Extraction>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut group = vec![self.extractions.next()?];
+
+        while self.keep_pairs
+            && let Some(next_extraction) = self.extractions.peek()
+            && pair_key(next_extraction.path()) == pair_key(group[0].path())
+        {
+            group.extend(self.extractions.next());
+        }
+
+        Some(group)
+    }
+}
@@ -0,0 +1,82 @@
+use crate::extractor::ExtractorChain;
+use crate::{Extraction, extract_creation_datetime_from_path};
+
+use nom_exif::MediaParser;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, channel};
+
+/// Each worker thread has its own [MediaParser] and takes the next unprocessed path, until all paths are processed.
+/// The results are sent back with the index of their path, so that the returned iterator can yield them in the original order.
+pub(crate) fn extract_creation_datetimes_in_parallel(paths: Vec<PathBuf>, jobs: usize, extractor_chain: ExtractorChain) -> OrderedExtractions {
+    let paths = Arc::new(paths);
+    let next_index = Arc::new(AtomicUsize::new(0));
+    let (sender, receiver) = channel::<(usize, Option<Extraction>)>();
+
+    for _ in 0..jobs.min(paths.len()) {
+        let paths = Arc::clone(&paths);
+        let next_index = Arc::clone(&next_index);
+        let extractor_chain = extractor_chain.clone();
+        let sender = sender.clone();
+
+        std::thread::spawn(move || {
+            let mut media_parser = MediaParser::new();
+
+            loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                let Some(path_buf) = paths.get(index) else {
+                    break;
+                };
+
+                let extraction = extract_creation_datetime_from_path(path_buf.clone(), &mut media_parser, &extractor_chain);
+
+                if sender.send((index, extraction)).is_err() {
+                    // The receiving side stopped iterating, so there is no point in continuing.
+                    break;
+                }
+            }
+        });
+    }
+
+    OrderedExtractions {
+        receiver,
+        pending: BTreeMap::new(),
+        next_index: 0,
+    }
+}
+
+pub(crate) struct OrderedExtractions {
+    receiver: Receiver<(usize, Option<Extraction>)>,
+    /// Results that arrived before the results of all preceding paths.
+    pending: BTreeMap<usize, Option<Extraction>>,
+    next_index: usize,
+}
+
+impl Iterator for OrderedExtractions {
+    type Item = Extraction;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(extraction) = self.pending.remove(&self.next_index) {
+                self.next_index += 1;
+                match extraction {
+                    Some(extraction) => return Some(extraction),
+                    None => continue,
+                }
+            }
+
+            match self.receiver.recv() {
+                Ok((index, extraction)) => {
+                    self.pending.insert(index, extraction);
+                }
+                Err(_) => {
+                    // All workers are done. If a worker panicked, its result is missing, so the remaining results are returned without waiting for it.
+                    let (&index, _) = self.pending.first_key_value()?;
+                    self.next_index = index;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::extract::GpsCoordinates;
+
+use reverse_geocoder::ReverseGeocoder;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+/// Loading the embedded places takes a moment, so it is only done once they are needed.
+static REVERSE_GEOCODER: LazyLock<ReverseGeocoder> = LazyLock::new(ReverseGeocoder::new);
+
+/// The names of the places which were already looked up, by the coordinates rounded to about 10 meters.
+/// Photos of a run are often taken at the same few places, so most lookups are answered from the cache.
+#[derive(Debug, Default)]
+pub(crate) struct PlaceCache {
+    places: Mutex<HashMap<(i64, i64), String>>,
+}
+
+impl PlaceCache {
+    /// The name of the nearest city or town of the embedded GeoNames dataset, like `Lisbon`.
+    pub(crate) fn place_at(&self, gps: GpsCoordinates) -> String {
+        let key = ((gps.latitude * 10_000.0).round() as i64, (gps.longitude * 10_000.0).round() as i64);
+        let mut places = self.places.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        places
+            .entry(key)
+            .or_insert_with(|| REVERSE_GEOCODER.search((gps.latitude, gps.longitude)).record.name.clone())
+            .clone()
+    }
+}
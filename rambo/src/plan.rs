@@ -0,0 +1,201 @@
+use crate::error::RamboError;
+use crate::naming::UnicodeNormalization;
+use crate::order::{RenameOrder, order_renames};
+use crate::rename::{
+    ClaimedTargets, ConflictStrategy, RenameAction, RenameOutcome, RenameSettings, move_to_temporary_names, rename_file_to, restore_from_temporary_name,
+};
+use crate::rename_map::write_backup_rename_map;
+use crate::statistics::Statistics;
+use crate::{current_working_directory, log_error, print_human_summary, strip_path_prefix};
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// The renames of a run, which are determined without touching any file, so they can be reviewed or edited before they are applied.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RenamePlan {
+    /// Copy the files to their new names instead of renaming them.
+    #[serde(default)]
+    pub copy: bool,
+    pub renames: Vec<PlannedRename>,
+}
+
+/// Relative paths are relative to the current working directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedRename {
+    pub source: PathBuf,
+    pub target: PathBuf,
+}
+
+impl RenamePlan {
+    /// Only the files that would be renamed or copied are part of the plan.
+    pub(crate) fn from_rename_outcomes(rename_outcomes: &[RenameOutcome], copy: bool, current_working_directory: &Path) -> Self {
+        let renames = rename_outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome.action, RenameAction::Renamed | RenameAction::Copied))
+            .map(|outcome| PlannedRename {
+                source: strip_path_prefix(&outcome.source, current_working_directory).to_path_buf(),
+                target: strip_path_prefix(&outcome.target, current_working_directory).to_path_buf(),
+            })
+            .collect();
+
+        Self { copy, renames }
+    }
+
+    pub fn read_from_file(plan_path: &Path) -> Result<Self, RamboError> {
+        let plan_file = File::open(plan_path).map_err(RamboError::io(format!("Failed to open plan '{}'", plan_path.display())))?;
+
+        serde_json::from_reader(BufReader::new(plan_file)).map_err(RamboError::invalid_file(format!("Failed to read plan '{}'", plan_path.display())))
+    }
+
+    /// Paths which are not valid UTF-8 cannot be written to a plan.
+    pub fn write_to_file(&self, plan_path: &Path) -> Result<(), RamboError> {
+        let plan_file = File::create(plan_path).map_err(RamboError::io(format!("Failed to create plan '{}'", plan_path.display())))?;
+        let mut writer = BufWriter::new(plan_file);
+
+        serde_json::to_writer_pretty(&mut writer, self)
+            .and_then(|_| writeln!(writer).and_then(|_| writer.flush()).map_err(serde_json::Error::io))
+            .map_err(RamboError::invalid_file(format!("Failed to write plan '{}'", plan_path.display())))
+    }
+}
+
+pub struct ApplyOptions {
+    pub plan_file: PathBuf,
+    pub no_dry_run: bool,
+    /// Applied when a target of the plan was taken after the plan was created.
+    pub on_conflict: ConflictStrategy,
+    /// Move the files which are overwritten because of [ConflictStrategy::Overwrite] to the trash instead.
+    pub use_trash: bool,
+    pub store_original_xattr: bool,
+    pub fail_on_vanished: bool,
+    /// Write the old and the new path of each renamed file to a new rename map in the current working directory, unless it is a dry run.
+    pub backup_map: bool,
+}
+
+pub fn apply(options: ApplyOptions) -> ExitCode {
+    let mut statistics = Statistics::new();
+
+    let current_working_directory = match current_working_directory() {
+        Ok(current_working_directory) => current_working_directory,
+        Err(error) => return log_error(error),
+    };
+
+    let plan = match RenamePlan::read_from_file(&options.plan_file) {
+        Ok(plan) => plan,
+        Err(error) => {
+            log::error!("{:#}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let store_original_xattr = if options.store_original_xattr && xattr::SUPPORTED_PLATFORM.not() {
+        log::warn!("Extended attributes are not supported on this platform. The original file names will not be stored.");
+        false
+    } else {
+        options.store_original_xattr
+    };
+
+    let rename_settings = RenameSettings {
+        is_dry_run: options.no_dry_run.not(),
+        show_unchanged_in_dry_run: true,
+        store_original_xattr,
+        on_conflict: options.on_conflict,
+        copy: plan.copy,
+        // The extensions of the plan were already fixed when it was created.
+        fix_extensions: false,
+        max_path_length: None,
+        normalization: UnicodeNormalization::None,
+        use_trash: options.use_trash,
+    };
+
+    let mut renames = Vec::<(PathBuf, PathBuf)>::with_capacity(plan.renames.len());
+    for planned_rename in &plan.renames {
+        let source = current_working_directory.join(&planned_rename.source);
+        if source.exists().not() {
+            log::warn!("The file vanished after the plan was created: {}", planned_rename.source.display());
+            statistics.vanished_files += 1;
+            continue;
+        }
+
+        renames.push((source, current_working_directory.join(&planned_rename.target)));
+    }
+
+    let rename_outcomes = rename_files_in_order(
+        &renames,
+        &rename_settings,
+        &mut ClaimedTargets::default(),
+        &current_working_directory,
+        &mut statistics,
+    );
+
+    let mut has_failed_outputs = false;
+    if options.backup_map
+        && options.no_dry_run
+        && rename_outcomes
+            .iter()
+            .any(|outcome| matches!(outcome.action, RenameAction::Renamed | RenameAction::Copied))
+    {
+        match write_backup_rename_map(&current_working_directory, &rename_outcomes, &current_working_directory) {
+            Ok(map_path) => log::info!("Wrote rename map to {}", strip_path_prefix(&map_path, &current_working_directory).display()),
+            Err(error) => {
+                log::error!("{}", error);
+                has_failed_outputs = true;
+            }
+        }
+    }
+
+    print_human_summary(&statistics, plan.copy);
+
+    if options.no_dry_run.not() {
+        log::warn!("This was just a dry run. To actually apply the plan, use the '--no-dry-run' flag.")
+    }
+
+    if statistics.failed_files > 0 || has_failed_outputs || (options.fail_on_vanished && statistics.vanished_files > 0) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Renames each file to its new path like a run does: no file is renamed onto the current name of a file which is renamed later,
+/// and files which swap their names are given temporary names first. Files which are copied keep their order, as their names never become free.
+/// Returns the outcomes in the order in which the files were renamed.
+pub(crate) fn rename_files_in_order(
+    renames: &[(PathBuf, PathBuf)],
+    settings: &RenameSettings,
+    claimed_targets: &mut ClaimedTargets,
+    current_working_directory: &Path,
+    statistics: &mut Statistics,
+) -> Vec<RenameOutcome> {
+    let rename_order = if settings.copy {
+        RenameOrder::unchanged(renames.len())
+    } else {
+        order_renames(&renames.iter().map(|rename| vec![rename.clone()]).collect::<Vec<_>>())
+    };
+
+    let temporary_paths = move_to_temporary_names(
+        rename_order.via_temporary_names.iter().map(|&index| renames[index].0.as_path()),
+        settings,
+        claimed_targets,
+        current_working_directory,
+    );
+
+    rename_order
+        .indices
+        .into_iter()
+        .map(|index| {
+            let (source, target) = &renames[index];
+            let current_source = temporary_paths.get(source).unwrap_or(source);
+
+            let mut rename_outcome = rename_file_to(current_source, target.clone(), settings, claimed_targets, current_working_directory, statistics);
+            if rename_outcome.source != *source {
+                restore_from_temporary_name(&mut rename_outcome, source, current_working_directory);
+            }
+            rename_outcome
+        })
+        .collect()
+}
@@ -0,0 +1,25 @@
+use crate::RamboOptions;
+use crate::output::FileOutcome;
+
+use std::path::PathBuf;
+
+/// The progress of a run, which is sent before each file is renamed and once more after the last one, e.g. to render a progress view in a GUI.
+#[derive(Debug, Clone)]
+pub struct ProgressUpdate {
+    /// The number of files which are renamed in this run, after the filters and the limit are applied.
+    pub total: usize,
+    pub done: usize,
+    /// The file which is processed next, `None` in the update after the last file.
+    pub current_path: Option<PathBuf>,
+    /// The outcome of the file which was processed before, if any.
+    pub last_outcome: Option<FileOutcome>,
+}
+
+impl RamboOptions {
+    /// The run goes on if nobody receives the updates anymore.
+    pub(crate) fn send_progress(&self, progress_update: impl FnOnce() -> ProgressUpdate) {
+        if let Some(progress_sender) = &self.progress_sender {
+            let _ = progress_sender.send(progress_update());
+        }
+    }
+}
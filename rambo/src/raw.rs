@@ -0,0 +1,553 @@
+use crate::error::ExtractError;
+use crate::extract::{DatetimeSource, ExtractedDatetime, GpsCoordinates, MediaMetadata, in_local_time_zone, subsec_to_nanoseconds};
+
+use chrono::{DateTime, NaiveDateTime, Timelike};
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::ops::Not;
+
+/// The containers of RAW formats which are read natively, without nom-exif.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RawContainer {
+    /// The TIFF based formats, like CR2, NEF, ARW, DNG, ORF and RW2, and plain TIFF files.
+    Tiff,
+    /// Fujifilm's RAF, which embeds a JPEG with the EXIF data.
+    Raf,
+    /// Canon's CR3, which is based on the ISO base media file format and keeps the EXIF data in `CMT*` boxes.
+    Cr3,
+}
+
+/// The UUID of the box in the `moov` box of a CR3 file, which contains the `CMT*` boxes.
+const CANON_UUID: [u8; 16] = [0x85, 0xC0, 0xB6, 0x87, 0x82, 0x0F, 0x11, 0xE0, 0x81, 0x11, 0xF4, 0xCE, 0x46, 0x2B, 0x6A, 0x48];
+
+/// Values which are larger, like maker notes or thumbnails, are not read, as none of the read tags is that large.
+const MAX_VALUE_LENGTH: u32 = 4096;
+
+/// Boxes which are larger, like the media data, are not read, as no metadata is that large.
+const MAX_BOX_CONTENT_LENGTH: u32 = 1 << 20;
+
+/// Damaged files may claim any number of entries.
+const MAX_IFD_ENTRIES: u16 = 1024;
+
+const MAKE_TAG: u16 = 0x010F;
+const MODEL_TAG: u16 = 0x0110;
+const DATETIME_TAG: u16 = 0x0132;
+const EXIF_IFD_TAG: u16 = 0x8769;
+const GPS_IFD_TAG: u16 = 0x8825;
+const EXPOSURE_TIME_TAG: u16 = 0x829A;
+const F_NUMBER_TAG: u16 = 0x829D;
+const ISO_TAG: u16 = 0x8827;
+const DATETIME_ORIGINAL_TAG: u16 = 0x9003;
+const CREATE_DATE_TAG: u16 = 0x9004;
+const OFFSET_TIME_TAG: u16 = 0x9010;
+const OFFSET_TIME_ORIGINAL_TAG: u16 = 0x9011;
+const OFFSET_TIME_DIGITIZED_TAG: u16 = 0x9012;
+const SUBSEC_TIME_TAG: u16 = 0x9290;
+const SUBSEC_TIME_ORIGINAL_TAG: u16 = 0x9291;
+const SUBSEC_TIME_DIGITIZED_TAG: u16 = 0x9292;
+const BODY_SERIAL_NUMBER_TAG: u16 = 0xA431;
+const LENS_MODEL_TAG: u16 = 0xA434;
+const GPS_LATITUDE_REF_TAG: u16 = 0x0001;
+const GPS_LATITUDE_TAG: u16 = 0x0002;
+const GPS_LONGITUDE_REF_TAG: u16 = 0x0003;
+const GPS_LONGITUDE_TAG: u16 = 0x0004;
+
+/// Each datetime tag with the tags of its fraction of a second and its offset and the name of the datetime tag, in the order of their precedence.
+/// Like for the EXIF data read with nom-exif, `ModifyDate` is not part of it, as it changes when the file is edited.
+const TAGS_FOR_CREATION_DATETIME: [(u16, u16, u16, &str); 2] = [
+    (DATETIME_ORIGINAL_TAG, SUBSEC_TIME_ORIGINAL_TAG, OFFSET_TIME_ORIGINAL_TAG, "DateTimeOriginal"),
+    (CREATE_DATE_TAG, SUBSEC_TIME_DIGITIZED_TAG, OFFSET_TIME_DIGITIZED_TAG, "CreateDate"),
+];
+
+/// The datetime tags which can be chosen as a [crate::DateSource], with the tags of their fraction of a second and their offset and the name of the datetime tag.
+const DATETIME_TAGS: [(u16, u16, u16, &str); 3] = [
+    (DATETIME_ORIGINAL_TAG, SUBSEC_TIME_ORIGINAL_TAG, OFFSET_TIME_ORIGINAL_TAG, "DateTimeOriginal"),
+    (CREATE_DATE_TAG, SUBSEC_TIME_DIGITIZED_TAG, OFFSET_TIME_DIGITIZED_TAG, "CreateDate"),
+    (DATETIME_TAG, SUBSEC_TIME_TAG, OFFSET_TIME_TAG, "ModifyDate"),
+];
+
+/// Whether the file is a RAW container which is read natively, by its magic bytes. The reader is rewound afterwards.
+pub(crate) fn is_raw_container<R: Read + Seek>(reader: &mut R) -> bool {
+    detect_raw_container(reader).is_ok_and(|raw_container| raw_container.is_some())
+}
+
+/// Returns [None] if the file is no RAW container which is read natively, so that it is parsed with nom-exif instead.
+pub(crate) fn extract_creation_datetime_from_raw<R: Read + Seek>(reader: &mut R) -> Option<Result<ExtractedDatetime, ExtractError>> {
+    read_raw_ifds(reader).map(|raw_ifds| raw_ifds.and_then(|raw_ifds| raw_ifds.creation_datetime()))
+}
+
+/// Returns [None] if the file is no RAW container which is read natively, so that it is parsed with nom-exif instead.
+pub(crate) fn extract_exif_tag_datetime_from_raw<R: Read + Seek>(reader: &mut R, tag_name: &'static str) -> Option<Result<ExtractedDatetime, ExtractError>> {
+    read_raw_ifds(reader).map(|raw_ifds| raw_ifds.and_then(|raw_ifds| raw_ifds.exif_tag_datetime(tag_name)))
+}
+
+/// Returns [None] if the file is no RAW container which is read natively, so that it is parsed with nom-exif instead.
+pub(crate) fn extract_metadata_from_raw<R: Read + Seek>(reader: &mut R) -> Option<Result<MediaMetadata, ExtractError>> {
+    read_raw_ifds(reader).map(|raw_ifds| raw_ifds.and_then(|raw_ifds| raw_ifds.metadata()))
+}
+
+fn detect_raw_container<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<RawContainer>> {
+    let mut header = [0u8; 16];
+    let header_length = read_up_to(reader, &mut header)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    Ok(match &header[..header_length] {
+        // ORF uses `RO` or `RS` and RW2 uses `U` instead of the TIFF magic number 42.
+        [b'I', b'I', 0x2A, 0x00, ..] | [b'M', b'M', 0x00, 0x2A, ..] | [b'I', b'I', b'R', b'O' | b'S', ..] | [b'I', b'I', 0x55, 0x00, ..] => {
+            Some(RawContainer::Tiff)
+        }
+        [b'F', b'U', b'J', b'I', b'F', b'I', b'L', b'M', b'C', b'C', b'D', b'-', b'R', b'A', b'W', ..] => Some(RawContainer::Raf),
+        [_, _, _, _, b'f', b't', b'y', b'p', b'c', b'r', b'x', b' ', ..] => Some(RawContainer::Cr3),
+        _ => None,
+    })
+}
+
+/// Like [Read::read_exact], but a shorter file is no error.
+pub(crate) fn read_up_to<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut length = 0;
+    while length < buffer.len() {
+        match reader.read(&mut buffer[length..]) {
+            Ok(0) => break,
+            Ok(read_length) => length += read_length,
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(length)
+}
+
+fn read_raw_ifds<R: Read + Seek>(reader: &mut R) -> Option<Result<RawIfds, ExtractError>> {
+    let raw_container = match detect_raw_container(reader) {
+        Ok(raw_container) => raw_container?,
+        Err(error) => return Some(Err(ExtractError::io("Cannot read the file")(error))),
+    };
+
+    let raw_ifds = match raw_container {
+        RawContainer::Tiff => read_tiff_ifds(reader, 0),
+        RawContainer::Raf => read_raf_ifds(reader),
+        RawContainer::Cr3 => read_cr3_ifds(reader),
+    };
+
+    Some(raw_ifds.map_err(ExtractError::io(format!("Failed to parse the {:?} container", raw_container))))
+}
+
+/// IFD0 points to the EXIF IFD and the GPS IFD.
+pub(crate) fn read_tiff_ifds<R: Read + Seek>(reader: &mut R, base: u64) -> std::io::Result<RawIfds> {
+    let (mut tiff, ifd0_offset) = Tiff::open(reader, base)?;
+    let ifd0 = tiff.read_ifd(ifd0_offset)?;
+
+    let exif = match ifd0.unsigned(EXIF_IFD_TAG) {
+        Some(offset) => Some(tiff.read_ifd(offset)?),
+        None => None,
+    };
+    // A broken GPS IFD only loses the coordinates.
+    let gps = ifd0.unsigned(GPS_IFD_TAG).and_then(|offset| tiff.read_ifd(offset).ok());
+
+    Ok(RawIfds { ifd0: Some(ifd0), exif, gps })
+}
+
+/// The header of a RAF file points to the embedded JPEG, whose `APP1` segment contains the EXIF data as a TIFF structure.
+fn read_raf_ifds<R: Read + Seek>(reader: &mut R) -> std::io::Result<RawIfds> {
+    reader.seek(SeekFrom::Start(84))?;
+    let jpeg_offset = u64::from(read_u32_be(reader)?);
+
+    reader.seek(SeekFrom::Start(jpeg_offset))?;
+    let mut marker = [0u8; 2];
+    reader.read_exact(&mut marker)?;
+    if marker != [0xFF, 0xD8] {
+        return Err(invalid_data("The embedded JPEG is missing"));
+    }
+
+    loop {
+        let mut segment_header = [0u8; 4];
+        reader.read_exact(&mut segment_header)?;
+        let segment_length = u16::from_be_bytes([segment_header[2], segment_header[3]]);
+        if segment_header[0] != 0xFF || segment_header[1] == 0xDA || segment_length < 2 {
+            return Err(invalid_data("The embedded JPEG has no EXIF data"));
+        }
+
+        let segment_start = reader.stream_position()?;
+        if segment_header[1] == 0xE1 {
+            let mut identifier = [0u8; 6];
+            reader.read_exact(&mut identifier)?;
+            if &identifier == b"Exif\0\0" {
+                return read_tiff_ifds(reader, segment_start + 6);
+            }
+        }
+        reader.seek(SeekFrom::Start(segment_start + u64::from(segment_length) - 2))?;
+    }
+}
+
+/// In the Canon box of the `moov` box, `CMT1` holds IFD0, `CMT2` the EXIF IFD and `CMT4` the GPS IFD, each as a TIFF structure of its own.
+fn read_cr3_ifds<R: Read + Seek>(reader: &mut R) -> std::io::Result<RawIfds> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    let (moov_start, moov_end) = find_box(reader, 0, end, |box_type, _| box_type == b"moov")?.ok_or_else(|| invalid_data("There is no 'moov' box"))?;
+    let (canon_start, canon_end) = find_box(reader, moov_start, moov_end, |box_type, uuid| box_type == b"uuid" && uuid == Some(CANON_UUID))?
+        .ok_or_else(|| invalid_data("There is no Canon box"))?;
+    // The content of the Canon box starts after its UUID.
+    let canon_start = canon_start + 16;
+
+    let mut read_cmt_ifd = |box_type: &[u8; 4]| -> std::io::Result<Option<Ifd>> {
+        match find_box(&mut *reader, canon_start, canon_end, |other_box_type, _| other_box_type == box_type)? {
+            Some((cmt_start, _)) => {
+                let (mut tiff, ifd_offset) = Tiff::open(&mut *reader, cmt_start)?;
+                tiff.read_ifd(ifd_offset).map(Some)
+            }
+            None => Ok(None),
+        }
+    };
+
+    let ifd0 = read_cmt_ifd(b"CMT1")?;
+    let exif = read_cmt_ifd(b"CMT2")?;
+    let gps = read_cmt_ifd(b"CMT4").ok().flatten();
+
+    Ok(RawIfds { ifd0, exif, gps })
+}
+
+/// Returns the start and the end of the content of the first box between `start` and `end` for which the predicate is true.
+/// The predicate gets the type of each box and, for `uuid` boxes, their UUID.
+pub(crate) fn find_box<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    predicate: impl Fn(&[u8; 4], Option<[u8; 16]>) -> bool,
+) -> std::io::Result<Option<(u64, u64)>> {
+    let mut position = start;
+
+    while position + 8 <= end {
+        reader.seek(SeekFrom::Start(position))?;
+        let size = u64::from(read_u32_be(reader)?);
+        let mut box_type = [0u8; 4];
+        reader.read_exact(&mut box_type)?;
+
+        let (content_start, box_end) = match size {
+            // The size of the box is in the following 64 bits.
+            1 => {
+                let mut large_size = [0u8; 8];
+                reader.read_exact(&mut large_size)?;
+                (position + 16, position + u64::from_be_bytes(large_size))
+            }
+            // The box extends to the end.
+            0 => (position + 8, end),
+            size => (position + 8, position + size),
+        };
+        if box_end <= position || box_end > end {
+            return Err(invalid_data("A box has an invalid size"));
+        }
+
+        let uuid = if &box_type == b"uuid" {
+            let mut uuid = [0u8; 16];
+            reader.read_exact(&mut uuid)?;
+            Some(uuid)
+        } else {
+            None
+        };
+
+        if predicate(&box_type, uuid) {
+            return Ok(Some((content_start, box_end)));
+        }
+        position = box_end;
+    }
+
+    Ok(None)
+}
+
+/// Reads the content of the first box of the type between `start` and `end`.
+pub(crate) fn read_box_content<R: Read + Seek>(reader: &mut R, start: u64, end: u64, box_type: &[u8; 4]) -> std::io::Result<Option<Vec<u8>>> {
+    let Some((content_start, content_end)) = find_box(reader, start, end, |other_box_type, _| other_box_type == box_type)? else {
+        return Ok(None);
+    };
+    let content_length = content_end - content_start;
+    if content_length > u64::from(MAX_BOX_CONTENT_LENGTH) {
+        return Err(invalid_data("A box is too large"));
+    }
+
+    let mut content = vec![0u8; content_length as usize];
+    reader.seek(SeekFrom::Start(content_start))?;
+    reader.read_exact(&mut content)?;
+    Ok(Some(content))
+}
+
+pub(crate) fn read_u32_be<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+pub(crate) fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+/// A TIFF structure, whose offsets are relative to its start.
+struct Tiff<'a, R> {
+    reader: &'a mut R,
+    base: u64,
+    is_little_endian: bool,
+}
+
+impl<'a, R: Read + Seek> Tiff<'a, R> {
+    /// Returns the TIFF structure and the offset of its first IFD.
+    fn open(reader: &'a mut R, base: u64) -> std::io::Result<(Self, u32)> {
+        reader.seek(SeekFrom::Start(base))?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+
+        let is_little_endian = match &header[..2] {
+            b"II" => true,
+            b"MM" => false,
+            _ => return Err(invalid_data("The TIFF header is invalid")),
+        };
+        let tiff = Self {
+            reader,
+            base,
+            is_little_endian,
+        };
+        let first_ifd_offset = tiff.u32(&header[4..8]);
+
+        Ok((tiff, first_ifd_offset))
+    }
+
+    fn read_ifd(&mut self, offset: u32) -> std::io::Result<Ifd> {
+        self.reader.seek(SeekFrom::Start(self.base + u64::from(offset)))?;
+        let mut count = [0u8; 2];
+        self.reader.read_exact(&mut count)?;
+        let count = self.u16(&count);
+        if count > MAX_IFD_ENTRIES {
+            return Err(invalid_data("An IFD has too many entries"));
+        }
+
+        let mut raw_entries = vec![0u8; usize::from(count) * 12];
+        self.reader.read_exact(&mut raw_entries)?;
+
+        let mut entries = HashMap::new();
+        for raw_entry in raw_entries.chunks_exact(12) {
+            let tag = self.u16(&raw_entry[0..2]);
+            let field_type = self.u16(&raw_entry[2..4]);
+            let value_count = self.u32(&raw_entry[4..8]);
+            let Some(value_length) = field_type_size(field_type).and_then(|size| size.checked_mul(value_count)) else {
+                continue;
+            };
+
+            let data = if value_length <= 4 {
+                raw_entry[8..8 + value_length as usize].to_vec()
+            } else if value_length <= MAX_VALUE_LENGTH {
+                let value_offset = self.u32(&raw_entry[8..12]);
+                let mut data = vec![0u8; value_length as usize];
+                self.reader.seek(SeekFrom::Start(self.base + u64::from(value_offset)))?;
+                match self.reader.read_exact(&mut data) {
+                    Ok(_) => data,
+                    // A value beyond the end of the file, e.g. of a truncated object head, is skipped.
+                    Err(error) if error.kind() == ErrorKind::UnexpectedEof => continue,
+                    Err(error) => return Err(error),
+                }
+            } else {
+                continue;
+            };
+
+            entries.insert(tag, IfdEntry { field_type, data });
+        }
+
+        Ok(Ifd {
+            is_little_endian: self.is_little_endian,
+            entries,
+        })
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        read_u16(bytes, self.is_little_endian)
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        read_u32(bytes, self.is_little_endian)
+    }
+}
+
+/// In bytes, for the field types of TIFF 6.0. Unknown field types are skipped.
+fn field_type_size(field_type: u16) -> Option<u32> {
+    match field_type {
+        // BYTE, ASCII, SBYTE, UNDEFINED
+        1 | 2 | 6 | 7 => Some(1),
+        // SHORT, SSHORT
+        3 | 8 => Some(2),
+        // LONG, SLONG, FLOAT, IFD
+        4 | 9 | 11 | 13 => Some(4),
+        // RATIONAL, SRATIONAL, DOUBLE
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+fn read_u16(bytes: &[u8], is_little_endian: bool) -> u16 {
+    let bytes = [bytes[0], bytes[1]];
+    if is_little_endian {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    }
+}
+
+fn read_u32(bytes: &[u8], is_little_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if is_little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    }
+}
+
+struct IfdEntry {
+    field_type: u16,
+    data: Vec<u8>,
+}
+
+/// The entries of an IFD by their tag, with their values.
+struct Ifd {
+    is_little_endian: bool,
+    entries: HashMap<u16, IfdEntry>,
+}
+
+impl Ifd {
+    /// Text values are often padded with spaces or NUL characters.
+    fn text(&self, tag: u16) -> Option<String> {
+        let entry = self.entries.get(&tag).filter(|entry| entry.field_type == 2 || entry.field_type == 7)?;
+
+        Some(
+            String::from_utf8_lossy(&entry.data)
+                .trim_matches(|char: char| char.is_whitespace() || char == '\0')
+                .to_string(),
+        )
+        .filter(|text| text.is_empty().not())
+    }
+
+    /// The first value of a SHORT or LONG entry.
+    fn unsigned(&self, tag: u16) -> Option<u32> {
+        let entry = self.entries.get(&tag)?;
+
+        match entry.field_type {
+            3 if entry.data.len() >= 2 => Some(u32::from(read_u16(&entry.data, self.is_little_endian))),
+            4 | 13 if entry.data.len() >= 4 => Some(read_u32(&entry.data, self.is_little_endian)),
+            _ => None,
+        }
+    }
+
+    /// The value at the index of a RATIONAL or SRATIONAL entry. A zero denominator marks an unknown value.
+    fn rational(&self, tag: u16, index: usize) -> Option<f64> {
+        let entry = self.entries.get(&tag).filter(|entry| entry.field_type == 5 || entry.field_type == 10)?;
+        let bytes = entry.data.get(index * 8..index * 8 + 8)?;
+        let numerator = read_u32(&bytes[..4], self.is_little_endian);
+        let denominator = read_u32(&bytes[4..], self.is_little_endian);
+
+        match entry.field_type {
+            _ if denominator == 0 => None,
+            10 => Some(f64::from(numerator as i32) / f64::from(denominator as i32)),
+            _ => Some(f64::from(numerator) / f64::from(denominator)),
+        }
+    }
+}
+
+/// The IFDs of a RAW file, or of the EXIF data of an image, which contain the metadata. Any of them may be missing.
+pub(crate) struct RawIfds {
+    ifd0: Option<Ifd>,
+    exif: Option<Ifd>,
+    gps: Option<Ifd>,
+}
+
+impl RawIfds {
+    /// The datetime tags are in the EXIF IFD.
+    pub(crate) fn creation_datetime(&self) -> Result<ExtractedDatetime, ExtractError> {
+        for datetime_tags in TAGS_FOR_CREATION_DATETIME {
+            if let Some(extracted_datetime) = self.tag_datetime(datetime_tags)? {
+                return Ok(extracted_datetime);
+            }
+        }
+
+        Err(ExtractError::MissingDatetime("RAW EXIF"))
+    }
+
+    /// Only reads the datetime tag with the name, like `DateTimeOriginal`, instead of the first one which is set.
+    /// `ModifyDate` is in IFD0 instead of the EXIF IFD.
+    pub(crate) fn exif_tag_datetime(&self, tag_name: &'static str) -> Result<ExtractedDatetime, ExtractError> {
+        let datetime_tags = DATETIME_TAGS.into_iter().find(|(.., name)| *name == tag_name);
+
+        datetime_tags
+            .map(|datetime_tags| self.tag_datetime(datetime_tags))
+            .transpose()?
+            .flatten()
+            .ok_or(ExtractError::MissingDatetime(tag_name))
+    }
+
+    /// Returns [None] if the datetime tag is not set.
+    fn tag_datetime(&self, (datetime_tag, subsec_tag, offset_tag, tag_name): (u16, u16, u16, &'static str)) -> Result<Option<ExtractedDatetime>, ExtractError> {
+        let Some(value) = self.exif_text(datetime_tag) else {
+            return Ok(None);
+        };
+        // Unset datetimes are often all zeros or spaces.
+        let Ok(naive_datetime) = NaiveDateTime::parse_from_str(&value, "%Y:%m:%d %H:%M:%S") else {
+            return Ok(None);
+        };
+
+        let naive_datetime = self
+            .exif_text(subsec_tag)
+            .and_then(|subsec| subsec_to_nanoseconds(&subsec))
+            .and_then(|nanoseconds| naive_datetime.with_nanosecond(nanoseconds))
+            .unwrap_or(naive_datetime);
+        let offset = self
+            .exif_text(offset_tag)
+            .and_then(|offset| DateTime::parse_from_rfc3339(&format!("2000-01-01T00:00:00{}", offset)).ok())
+            .map(|datetime| *datetime.offset());
+
+        let datetime = match offset {
+            Some(offset) => naive_datetime
+                .and_local_timezone(offset)
+                .single()
+                .ok_or_else(|| ExtractError::InvalidDatetime(format!("The datetime {} of the RAW file is invalid!", naive_datetime)))?,
+            None => in_local_time_zone(naive_datetime)?,
+        };
+
+        Ok(Some(ExtractedDatetime {
+            datetime,
+            source: DatetimeSource::ExifTag(tag_name),
+        }))
+    }
+
+    pub(crate) fn metadata(&self) -> Result<MediaMetadata, ExtractError> {
+        let datetime = self.creation_datetime()?.datetime;
+        let exif = self.exif.as_ref();
+
+        Ok(MediaMetadata {
+            datetime,
+            camera_make: self.ifd0.as_ref().and_then(|ifd0| ifd0.text(MAKE_TAG)),
+            camera_model: self.ifd0.as_ref().and_then(|ifd0| ifd0.text(MODEL_TAG)),
+            camera_serial: exif.and_then(|exif| exif.text(BODY_SERIAL_NUMBER_TAG)),
+            lens_model: exif.and_then(|exif| exif.text(LENS_MODEL_TAG)),
+            iso: exif.and_then(|exif| exif.unsigned(ISO_TAG)).filter(|iso| *iso > 0),
+            f_number: exif.and_then(|exif| exif.rational(F_NUMBER_TAG, 0)),
+            exposure_time: exif.and_then(|exif| exif.rational(EXPOSURE_TIME_TAG, 0)),
+            gps: self.gps.as_ref().and_then(gps_coordinates),
+            has_subseconds: datetime.nanosecond() != 0,
+            extractor: None,
+        })
+    }
+
+    /// Some cameras write the datetime tags to IFD0 instead of the EXIF IFD.
+    fn exif_text(&self, tag: u16) -> Option<String> {
+        [&self.exif, &self.ifd0].into_iter().flatten().find_map(|ifd| ifd.text(tag))
+    }
+}
+
+/// The coordinates are degrees, minutes and seconds.
+fn gps_coordinates(gps: &Ifd) -> Option<GpsCoordinates> {
+    let degrees = |tag: u16| Some(gps.rational(tag, 0)? + gps.rational(tag, 1)? / 60.0 + gps.rational(tag, 2)? / 3600.0);
+    let sign = |tag: u16, negative_reference: &str| {
+        if gps.text(tag).as_deref() == Some(negative_reference) {
+            -1.0
+        } else {
+            1.0
+        }
+    };
+
+    Some(GpsCoordinates {
+        latitude: sign(GPS_LATITUDE_REF_TAG, "S") * degrees(GPS_LATITUDE_TAG)?,
+        longitude: sign(GPS_LONGITUDE_REF_TAG, "W") * degrees(GPS_LONGITUDE_TAG)?,
+    })
+}
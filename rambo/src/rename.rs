@@ -1,40 +1,878 @@
+use crate::duplicates::compute_xxh3;
 use crate::format_path_buf_without_prefix;
+use crate::metadata::preserve_metadata;
+use crate::naming::{UnicodeNormalization, file_system_limit_violation};
+use crate::sniff::corrected_extension;
 use crate::statistics::Statistics;
 
-use std::path::PathBuf;
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use xxhash_rust::xxh3::Xxh3;
 
+/// What to do when the new name of a file is already taken by another file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// Keep the original name of the file.
+    Skip,
+    /// Append `-1`, `-2`, ... to the new name until it is free.
+    Suffix,
+    /// Replace the other file.
+    Overwrite,
+    /// Count the file as failed and keep its original name.
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameAction {
+    AlreadyCorrect,
+    Renamed,
+    /// The file was copied to its new name, leaving the original untouched.
+    Copied,
+    /// The file was not renamed, because its new name was already taken.
+    Skipped,
+    Vanished,
+    Failed,
+}
+
+#[derive(Debug)]
+pub struct RenameOutcome {
+    pub source: PathBuf,
+    /// The path the file was (or would be) renamed to, after resolving a conflict.
+    pub target: PathBuf,
+    /// The path derived from the format, before resolving a conflict.
+    pub requested_target: PathBuf,
+    /// The strategy that was applied, if the requested target was already taken.
+    pub conflict: Option<ConflictStrategy>,
+    pub action: RenameAction,
+    pub error: Option<String>,
+}
+
+pub struct RenameSettings {
+    pub is_dry_run: bool,
+    pub show_unchanged_in_dry_run: bool,
+    pub store_original_xattr: bool,
+    pub on_conflict: ConflictStrategy,
+    /// Copy the files to their new names instead of renaming them.
+    pub copy: bool,
+    /// Replace extensions that do not fit the format of the file, like `.jpg` for a HEIC file.
+    pub fix_extensions: bool,
+    /// When set, new paths with more characters fail, like the ones which Windows does not allow without long paths.
+    pub max_path_length: Option<usize>,
+    /// Names which differ only in this normalization count as the same.
+    pub normalization: UnicodeNormalization,
+    /// Move the files which would be overwritten to the trash first, see [ConflictStrategy::Overwrite].
+    pub use_trash: bool,
+}
+
+/// Keeps track of the names that are taken and freed by the renames of this run,
+/// so that a dry run detects the same conflicts as a real run, although nothing is renamed on disk.
+#[derive(Debug, Default)]
+pub struct ClaimedTargets {
+    claimed: HashSet<PathBuf>,
+    vacated: HashSet<PathBuf>,
+}
+
+impl ClaimedTargets {
+    pub(crate) fn is_taken(&self, path: &Path) -> bool {
+        self.claimed.contains(path) || (self.vacated.contains(path).not() && path.exists())
+    }
+
+    fn claim_copy(&mut self, target: &Path) {
+        self.vacated.remove(target);
+        self.claimed.insert(target.to_path_buf());
+    }
+
+    /// Frees the current name of a file, which is given a temporary name before it is renamed.
+    fn vacate(&mut self, path: &Path) {
+        self.claimed.remove(path);
+        self.vacated.insert(path.to_path_buf());
+    }
+
+    pub(crate) fn claim(&mut self, source: &Path, target: &Path) {
+        self.vacated.remove(target);
+        self.claimed.insert(target.to_path_buf());
+        if source != target {
+            self.claimed.remove(source);
+            self.vacated.insert(source.to_path_buf());
+        }
+    }
+
+    /// Forgets that a name was taken or freed by this run, so that the file system decides again, like after a rename was undone.
+    fn release(&mut self, path: &Path) {
+        self.claimed.remove(path);
+        self.vacated.remove(path);
+    }
+}
+
+/// In a dry run, the returned [RenameOutcome] describes what would have happened.
+/// The `target_directory` is the directory the renamed file is moved to, which is usually the directory it is already in.
 pub fn rename_file(
-    file_path_buf: &PathBuf,
+    file_path_buf: &Path,
+    target_directory: &Path,
     new_file_name_without_extension: &str,
-    is_dry_run: bool,
-    current_working_directory: &str,
+    settings: &RenameSettings,
+    claimed_targets: &mut ClaimedTargets,
+    current_working_directory: &Path,
+    statistics: &mut Statistics,
+) -> RenameOutcome {
+    rename_file_to(
+        file_path_buf,
+        requested_file_path(file_path_buf, target_directory, new_file_name_without_extension, settings.fix_extensions),
+        settings,
+        claimed_targets,
+        current_working_directory,
+        statistics,
+    )
+}
+
+/// The extension is appended rather than set, because the new name may contain dots itself, like the decimals of GPS coordinates.
+pub(crate) fn requested_file_path(file_path_buf: &Path, target_directory: &Path, new_file_name_without_extension: &str, fix_extension: bool) -> PathBuf {
+    let extension = fix_extension
+        .then(|| corrected_extension(file_path_buf))
+        .flatten()
+        .map(OsString::from)
+        .or_else(|| file_path_buf.extension().map(OsStr::to_ascii_lowercase));
+
+    let mut new_file_name = OsString::from(new_file_name_without_extension);
+    if let Some(extension) = extension {
+        new_file_name.push(".");
+        new_file_name.push(extension);
+    }
+    target_directory.join(new_file_name)
+}
+
+/// Renames files which belong together, like the RAW and the JPEG of the same photo, to the same new name, each keeping its own extension.
+/// Either all of them are renamed or none: a conflict of one file applies to all of them,
+/// and if renaming one of them fails, the ones renamed so far are renamed back.
+pub fn rename_files_as_unit(
+    file_path_bufs: &[PathBuf],
+    target_directory: &Path,
+    new_file_name_without_extension: &str,
+    settings: &RenameSettings,
+    claimed_targets: &mut ClaimedTargets,
+    current_working_directory: &Path,
+    statistics: &mut Statistics,
+) -> Vec<RenameOutcome> {
+    let requested_file_paths = |new_file_name_without_extension: &str| {
+        file_path_bufs
+            .iter()
+            .map(|file_path_buf| requested_file_path(file_path_buf, target_directory, new_file_name_without_extension, settings.fix_extensions))
+            .collect::<Vec<_>>()
+    };
+    let is_free = |claimed_targets: &ClaimedTargets, targets: &[PathBuf]| {
+        file_path_bufs.iter().zip(targets).all(|(file_path_buf, target)| {
+            settings.normalization.is_same_path(file_path_buf, target) || claimed_targets.is_taken(target).not() || is_same_file(file_path_buf, target)
+        })
+    };
+    let clean_file_names = || {
+        file_path_bufs
+            .iter()
+            .map(|file_path_buf| format_path_buf_without_prefix(file_path_buf, current_working_directory))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let requested_targets = requested_file_paths(new_file_name_without_extension);
+
+    let (targets, conflict) = if is_free(claimed_targets, &requested_targets) {
+        (requested_targets.clone(), None)
+    } else {
+        match settings.on_conflict {
+            ConflictStrategy::Skip | ConflictStrategy::Fail => {
+                statistics.conflicting_files += file_path_bufs.len() as u64;
+                let (action, error) = if settings.on_conflict == ConflictStrategy::Skip {
+                    log::warn!("Skipping {}, because their new name is already taken", clean_file_names());
+                    statistics.skipped_files += file_path_bufs.len() as u64;
+                    (RenameAction::Skipped, None)
+                } else {
+                    log::warn!("Cannot rename {}, because their new name is already taken", clean_file_names());
+                    statistics.failed_files += file_path_bufs.len() as u64;
+                    (RenameAction::Failed, Some("The new name is already taken".to_string()))
+                };
+
+                return file_path_bufs
+                    .iter()
+                    .zip(requested_targets)
+                    .map(|(file_path_buf, requested_target)| RenameOutcome {
+                        source: file_path_buf.clone(),
+                        target: file_path_buf.clone(),
+                        requested_target,
+                        conflict: Some(settings.on_conflict),
+                        action,
+                        error: error.clone(),
+                    })
+                    .collect();
+            }
+            // The files whose new name is taken are counted as conflicting when they are renamed.
+            ConflictStrategy::Overwrite => (requested_targets.clone(), Some(ConflictStrategy::Overwrite)),
+            ConflictStrategy::Suffix => {
+                statistics.conflicting_files += file_path_bufs.len() as u64;
+                let suffixed_targets = (1..)
+                    .map(|counter| requested_file_paths(&format!("{}-{}", new_file_name_without_extension, counter)))
+                    .find(|suffixed_targets| is_free(claimed_targets, suffixed_targets))
+                    .expect("there is always a free suffix");
+                (suffixed_targets, Some(ConflictStrategy::Suffix))
+            }
+        }
+    };
+
+    let mut outcomes = Vec::<RenameOutcome>::with_capacity(file_path_bufs.len());
+
+    for ((file_path_buf, target), requested_target) in file_path_bufs.iter().zip(targets).zip(requested_targets) {
+        let outcome = rename_file_to(file_path_buf, target, settings, claimed_targets, current_working_directory, statistics);
+        let outcome = RenameOutcome {
+            requested_target,
+            conflict: outcome.conflict.or(conflict.filter(|_| outcome.action != RenameAction::AlreadyCorrect)),
+            ..outcome
+        };
+
+        let has_failed = matches!(outcome.action, RenameAction::Failed | RenameAction::Vanished);
+        outcomes.push(outcome);
+
+        if has_failed {
+            undo_renames_of_unit(&mut outcomes, settings, claimed_targets, current_working_directory, statistics);
+            break;
+        }
+    }
+
+    // The files after the one which failed are not renamed at all.
+    for file_path_buf in &file_path_bufs[outcomes.len()..] {
+        log::warn!(
+            "Not renaming {}, because a file with the same name could not be renamed",
+            format_path_buf_without_prefix(file_path_buf, current_working_directory)
+        );
+        statistics.failed_files += 1;
+        outcomes.push(RenameOutcome {
+            source: file_path_buf.clone(),
+            target: file_path_buf.clone(),
+            requested_target: requested_file_path(file_path_buf, target_directory, new_file_name_without_extension, settings.fix_extensions),
+            conflict: None,
+            action: RenameAction::Failed,
+            error: Some("A file with the same name could not be renamed".to_string()),
+        });
+    }
+
+    outcomes
+}
+
+/// Renames the files of a unit back, which were renamed before another file of the unit failed.
+/// In a dry run, nothing is renamed back, but the files are counted as skipped and their names are released as in a real run.
+fn undo_renames_of_unit(
+    outcomes: &mut [RenameOutcome],
+    settings: &RenameSettings,
+    claimed_targets: &mut ClaimedTargets,
+    current_working_directory: &Path,
     statistics: &mut Statistics,
 ) {
-    let mut new_file_path_buf = file_path_buf.clone();
-    new_file_path_buf.set_file_name(new_file_name_without_extension);
-    if let Some(extension) = file_path_buf.extension() {
-        new_file_path_buf.set_extension(extension.to_ascii_lowercase());
+    for outcome in outcomes.iter_mut() {
+        if matches!(outcome.action, RenameAction::Renamed | RenameAction::Copied).not() {
+            continue;
+        }
+
+        if settings.copy {
+            statistics.copied_files -= 1;
+        } else {
+            statistics.renamed_files -= 1;
+        }
+        claimed_targets.release(&outcome.source);
+        claimed_targets.release(&outcome.target);
+
+        if settings.is_dry_run {
+            log::warn!(
+                "Would not rename {}, because a file with the same name could not be renamed",
+                format_path_buf_without_prefix(&outcome.source, current_working_directory)
+            );
+            statistics.skipped_files += 1;
+            outcome.action = RenameAction::Skipped;
+            outcome.target = outcome.source.clone();
+            continue;
+        }
+
+        let undo_result = if settings.copy {
+            std::fs::remove_file(&outcome.target)
+        } else {
+            move_file(&outcome.target, &outcome.source)
+        };
+
+        match undo_result {
+            Ok(_) => log::warn!(
+                "Undid the renaming of {}, because a file with the same name could not be renamed",
+                format_path_buf_without_prefix(&outcome.source, current_working_directory)
+            ),
+            Err(error) => log::error!(
+                "Failed to undo the renaming of {}: {}",
+                format_path_buf_without_prefix(&outcome.source, current_working_directory),
+                error
+            ),
+        }
+
+        statistics.failed_files += 1;
+        outcome.action = RenameAction::Failed;
+        outcome.error = Some("A file with the same name could not be renamed".to_string());
     }
+}
+
+/// Like [rename_file], but with the complete new path of the file, e.g. from a [crate::RenamePlan].
+pub fn rename_file_to(
+    file_path_buf: &Path,
+    requested_file_path_buf: PathBuf,
+    settings: &RenameSettings,
+    claimed_targets: &mut ClaimedTargets,
+    current_working_directory: &Path,
+    statistics: &mut Statistics,
+) -> RenameOutcome {
+    let target_directory = requested_file_path_buf.parent().map(Path::to_path_buf).unwrap_or_default();
 
-    let clean_file_name_old = format_path_buf_without_prefix(file_path_buf, &current_working_directory);
-    let clean_file_name_new = format_path_buf_without_prefix(&new_file_path_buf, &current_working_directory);
+    let clean_file_name_old = format_path_buf_without_prefix(file_path_buf, current_working_directory);
+    let clean_file_name_requested = format_path_buf_without_prefix(&requested_file_path_buf, current_working_directory);
 
-    if *file_path_buf == new_file_path_buf {
-        log::info!("This file has already the correct name: {}", clean_file_name_new);
+    let outcome = |target: PathBuf, conflict: Option<ConflictStrategy>, action: RenameAction| RenameOutcome {
+        source: file_path_buf.to_path_buf(),
+        target,
+        requested_target: requested_file_path_buf.clone(),
+        conflict,
+        action,
+        error: None,
+    };
+
+    let already_correct = |claimed_targets: &mut ClaimedTargets, statistics: &mut Statistics| {
+        if settings.is_dry_run.not() || settings.show_unchanged_in_dry_run {
+            log::info!(
+                "This file has already the correct name: {}",
+                format_path_buf_without_prefix(file_path_buf, current_working_directory)
+            );
+        }
         statistics.skipped_files += 1;
-    } else if is_dry_run {
-        log::info!("[DRY RUN] Renaming: {} ==> {}", clean_file_name_old, clean_file_name_new);
-        statistics.renamed_files += 1;
-    } else {
-        match std::fs::rename(&file_path_buf, &new_file_path_buf) {
-            Ok(_) => {
-                log::info!("Renaming: {} ==> {}", clean_file_name_old, clean_file_name_new);
-                statistics.renamed_files += 1;
+        claimed_targets.claim(file_path_buf, file_path_buf);
+    };
+
+    if settings.normalization.is_same_path(file_path_buf, &requested_file_path_buf) {
+        already_correct(claimed_targets, statistics);
+        return outcome(file_path_buf.to_path_buf(), None, RenameAction::AlreadyCorrect);
+    }
+
+    // On a case-insensitive file system, the new name may lead to the file itself, e.g. if only the case of its extension changes.
+    let is_renamed_to_itself = claimed_targets.claimed.contains(&requested_file_path_buf).not() && is_same_file(file_path_buf, &requested_file_path_buf);
+    if is_renamed_to_itself && settings.copy {
+        already_correct(claimed_targets, statistics);
+        return outcome(file_path_buf.to_path_buf(), None, RenameAction::AlreadyCorrect);
+    }
+
+    let (new_file_path_buf, conflict) = if is_renamed_to_itself.not() && claimed_targets.is_taken(&requested_file_path_buf) {
+        match settings.on_conflict {
+            ConflictStrategy::Skip => {
+                statistics.conflicting_files += 1;
+                log::warn!(
+                    "Skipping {}, because the file {} already exists",
+                    clean_file_name_old,
+                    clean_file_name_requested
+                );
+                statistics.skipped_files += 1;
+                return outcome(file_path_buf.to_path_buf(), Some(ConflictStrategy::Skip), RenameAction::Skipped);
             }
-            Err(error) => {
-                log::warn!("Failed to rename {} to {}: {}", clean_file_name_old, clean_file_name_new, error);
+            ConflictStrategy::Fail => {
+                statistics.conflicting_files += 1;
+                log::warn!(
+                    "Cannot rename {}, because the file {} already exists",
+                    clean_file_name_old,
+                    clean_file_name_requested
+                );
                 statistics.failed_files += 1;
+                return RenameOutcome {
+                    error: Some(format!("The file {} already exists", clean_file_name_requested)),
+                    ..outcome(file_path_buf.to_path_buf(), Some(ConflictStrategy::Fail), RenameAction::Failed)
+                };
+            }
+            ConflictStrategy::Overwrite => {
+                statistics.conflicting_files += 1;
+                log::warn!(
+                    "The file {} already exists and will be overwritten by {}",
+                    clean_file_name_requested,
+                    clean_file_name_old
+                );
+                (requested_file_path_buf.clone(), Some(ConflictStrategy::Overwrite))
+            }
+            ConflictStrategy::Suffix => {
+                let suffixed_file_path_buf = find_free_suffixed_path(&requested_file_path_buf, file_path_buf, claimed_targets, settings.normalization);
+                if settings.normalization.is_same_path(&suffixed_file_path_buf, file_path_buf) {
+                    // The file already got a suffix in a previous run.
+                    already_correct(claimed_targets, statistics);
+                    return outcome(suffixed_file_path_buf, None, RenameAction::AlreadyCorrect);
+                }
+                statistics.conflicting_files += 1;
+                (suffixed_file_path_buf, Some(ConflictStrategy::Suffix))
             }
+        }
+    } else {
+        (requested_file_path_buf.clone(), None)
+    };
+
+    let clean_file_name_new = format_path_buf_without_prefix(&new_file_path_buf, current_working_directory);
+
+    if let Some(violation) = file_system_limit_violation(&new_file_path_buf, settings.max_path_length) {
+        log::warn!("Cannot rename {} to {}, because {}", clean_file_name_old, clean_file_name_new, violation);
+        statistics.failed_files += 1;
+        statistics.invalid_names += 1;
+        return RenameOutcome {
+            error: Some(format!("The new name is invalid, because {}", violation)),
+            ..outcome(file_path_buf.to_path_buf(), conflict, RenameAction::Failed)
+        };
+    }
+
+    let (verb, verb_infinitive, action) = if settings.copy {
+        ("Copying", "copy", RenameAction::Copied)
+    } else {
+        ("Renaming", "rename", RenameAction::Renamed)
+    };
+
+    let has_fixed_extension = new_file_path_buf.extension() != file_path_buf.extension().map(OsStr::to_ascii_lowercase).as_deref();
+
+    let count_transferred_file = |statistics: &mut Statistics, claimed_targets: &mut ClaimedTargets| {
+        if has_fixed_extension {
+            statistics.fixed_extensions += 1;
+        }
+        if settings.copy {
+            statistics.copied_files += 1;
+            claimed_targets.claim_copy(&new_file_path_buf);
+        } else {
+            statistics.renamed_files += 1;
+            claimed_targets.claim(file_path_buf, &new_file_path_buf);
+        }
+    };
+
+    let trashes_overwritten_file = settings.use_trash && conflict == Some(ConflictStrategy::Overwrite);
+
+    if settings.is_dry_run {
+        if trashes_overwritten_file {
+            log::info!("[DRY RUN] Moving to trash: {}", clean_file_name_new);
+            statistics.trashed_files += 1;
+        }
+        log::info!("[DRY RUN] {}: {} ==> {}", verb, clean_file_name_old, clean_file_name_new);
+        count_transferred_file(statistics, claimed_targets);
+        return outcome(new_file_path_buf, conflict, action);
+    }
+
+    if target_directory.as_os_str().is_empty().not()
+        && target_directory.exists().not()
+        && let Err(error) = std::fs::create_dir_all(&target_directory)
+    {
+        log::warn!(
+            "Failed to create the directory {} for {}: {}",
+            format_path_buf_without_prefix(&target_directory, current_working_directory),
+            clean_file_name_old,
+            error
+        );
+        statistics.failed_files += 1;
+        return RenameOutcome {
+            error: Some(format!("Failed to create the directory: {}", error)),
+            ..outcome(file_path_buf.to_path_buf(), conflict, RenameAction::Failed)
         };
     }
+
+    if trashes_overwritten_file && new_file_path_buf.exists() {
+        if let Err(error) = move_to_trash(&new_file_path_buf) {
+            log::warn!("Failed to move {} to the trash before overwriting it: {}", clean_file_name_new, error);
+            statistics.failed_files += 1;
+            return RenameOutcome {
+                error: Some(format!("Failed to move the overwritten file to the trash: {}", error)),
+                ..outcome(file_path_buf.to_path_buf(), conflict, RenameAction::Failed)
+            };
+        }
+        log::info!("Moving to trash: {}", clean_file_name_new);
+        statistics.trashed_files += 1;
+    }
+
+    let transfer_result = if settings.copy {
+        copy_file(file_path_buf, &new_file_path_buf)
+    } else if is_renamed_to_itself {
+        move_file_via_temporary_name(file_path_buf, &new_file_path_buf)
+    } else {
+        move_file(file_path_buf, &new_file_path_buf)
+    };
+
+    match transfer_result {
+        Ok(_) => {
+            log::info!("{}: {} ==> {}", verb, clean_file_name_old, clean_file_name_new);
+            count_transferred_file(statistics, claimed_targets);
+            if settings.store_original_xattr {
+                store_original_file_name_in_xattr(file_path_buf, &new_file_path_buf, &clean_file_name_new);
+            }
+            outcome(new_file_path_buf, conflict, action)
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound && file_path_buf.exists().not() => {
+            log::warn!("The file vanished before it could be renamed: {}", clean_file_name_old);
+            statistics.vanished_files += 1;
+            outcome(new_file_path_buf, conflict, RenameAction::Vanished)
+        }
+        Err(error) => {
+            log::warn!("Failed to {} {} to {}: {}", verb_infinitive, clean_file_name_old, clean_file_name_new, error);
+            statistics.failed_files += 1;
+            RenameOutcome {
+                error: Some(format!("Failed to {}: {}", verb_infinitive, error)),
+                ..outcome(new_file_path_buf, conflict, RenameAction::Failed)
+            }
+        }
+    }
+}
+
+/// Sets the modification time of the file, where it ended up, to its creation datetime, because many gallery apps sort files by it.
+/// Files whose modification time is already correct are left untouched.
+pub(crate) fn sync_modification_time(
+    outcome: &RenameOutcome,
+    datetime: &DateTime<FixedOffset>,
+    is_dry_run: bool,
+    current_working_directory: &Path,
+    statistics: &mut Statistics,
+) {
+    if matches!(
+        outcome.action,
+        RenameAction::AlreadyCorrect | RenameAction::Renamed | RenameAction::Copied | RenameAction::Skipped
+    )
+    .not()
+    {
+        return;
+    }
+
+    // In a dry run, the file has not been renamed yet.
+    let current_file_path = if is_dry_run {
+        &outcome.source
+    } else {
+        &outcome.target
+    };
+    let clean_file_name = format_path_buf_without_prefix(&outcome.target, current_working_directory);
+    let modification_time = SystemTime::from(*datetime);
+
+    if let Ok(current_modification_time) = current_file_path.metadata().and_then(|metadata| metadata.modified())
+        && current_modification_time == modification_time
+    {
+        return;
+    }
+
+    if is_dry_run {
+        log::info!(
+            "[DRY RUN] Setting modification time: {} ==> {}",
+            clean_file_name,
+            datetime.format("%Y-%m-%d %H:%M:%S %:z")
+        );
+        statistics.synced_modification_times += 1;
+        return;
+    }
+
+    match File::options()
+        .write(true)
+        .open(current_file_path)
+        .and_then(|file| file.set_modified(modification_time))
+    {
+        Ok(_) => {
+            log::info!(
+                "Setting modification time: {} ==> {}",
+                clean_file_name,
+                datetime.format("%Y-%m-%d %H:%M:%S %:z")
+            );
+            statistics.synced_modification_times += 1;
+        }
+        Err(error) => log::warn!("Failed to set the modification time of {}: {}", clean_file_name, error),
+    }
+}
+
+/// Renaming fails if the target is on another file system, e.g. with `--move-to`. In that case, the file is copied and the original is removed.
+pub(crate) fn move_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    match std::fs::rename(source, target) {
+        Err(error) if error.kind() == ErrorKind::CrossesDevices => {
+            log::debug!("Falling back to copy and delete, because {} is on another file system", target.display());
+            move_file_across_file_systems(source, target)
+        }
+        result => result,
+    }
+}
+
+/// From this size on, the progress of copying a file to another file system is logged, e.g. for large videos.
+const LOGGED_COPY_PROGRESS_SIZE: u64 = 256 * 1024 * 1024;
+
+/// The copy is read back and compared to the hash of the original before the original is deleted. It keeps the metadata of the original, see [preserve_metadata].
+/// An incomplete or differing copy is removed and the original is kept.
+fn move_file_across_file_systems(source: &Path, target: &Path) -> std::io::Result<()> {
+    let size = std::fs::metadata(source)?.len();
+    let target_existed = target.exists();
+
+    let copy_result = copy_file_with_progress(source, target, size).and_then(|source_hash| {
+        if compute_xxh3(target)? != source_hash {
+            return Err(std::io::Error::other("The copy differs from the original"));
+        }
+
+        preserve_metadata(source, target)
+    });
+
+    if let Err(error) = copy_result {
+        if target_existed.not() {
+            let _ = std::fs::remove_file(target);
+        }
+        return Err(error);
+    }
+
+    std::fs::remove_file(source)
+}
+
+/// Returns the hash of the copied content.
+fn copy_file_with_progress(source: &Path, target: &Path, size: u64) -> std::io::Result<u128> {
+    let mut reader = File::open(source)?;
+    let mut writer = File::create(target)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    let mut copied_bytes = 0;
+    let mut logged_percent = 0;
+
+    loop {
+        let read_bytes = reader.read(&mut buffer)?;
+        if read_bytes == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read_bytes])?;
+        hasher.update(&buffer[..read_bytes]);
+        copied_bytes += read_bytes as u64;
+
+        let percent = copied_bytes * 100 / size.max(1);
+        if size >= LOGGED_COPY_PROGRESS_SIZE && percent >= logged_percent + 10 {
+            logged_percent = percent - percent % 10;
+            log::info!("Copying {} to another file system: {}%", source.display(), logged_percent);
+        }
+    }
+    writer.sync_all()?;
+
+    Ok(hasher.digest128())
+}
+
+/// Renames the file to a temporary name first, as some case-insensitive file systems ignore a rename to a name which differs only in case.
+/// If the target is a hard link of the file instead, renaming onto it does nothing, so the temporary name is removed.
+/// The temporary name is the first one which is not taken by another file, like `IMG_0001.jpg.rambo-rename` or `IMG_0001.jpg.rambo-rename-2`.
+fn move_file_via_temporary_name(source: &Path, target: &Path) -> std::io::Result<()> {
+    let temporary_path = (1..)
+        .map(|counter| {
+            let mut temporary_file_name = source.file_name().unwrap_or_default().to_os_string();
+            temporary_file_name.push(".rambo-rename");
+            if counter > 1 {
+                temporary_file_name.push(format!("-{}", counter));
+            }
+            source.with_file_name(temporary_file_name)
+        })
+        .find(|temporary_path| temporary_path.symlink_metadata().is_err())
+        .expect("there is always a free temporary name");
+
+    std::fs::rename(source, &temporary_path)?;
+    std::fs::rename(&temporary_path, target).inspect_err(|_| {
+        let _ = std::fs::rename(&temporary_path, source);
+    })?;
+
+    if is_same_file(&temporary_path, target) {
+        std::fs::remove_file(&temporary_path)?;
+    }
+    Ok(())
+}
+
+/// Gives the file a temporary name next to it, which keeps its extension, so that its current name is free for another file of the run, like when two files swap their names.
+/// The original file name is stored before, if it is to be stored at all, so that the temporary name never becomes the original one.
+pub(crate) fn move_to_temporary_name(file_path: &Path, store_original_xattr: bool) -> std::io::Result<PathBuf> {
+    let mut temporary_file_name = file_path.file_stem().unwrap_or_default().to_os_string();
+    temporary_file_name.push(".rambo-swap");
+    if let Some(extension) = file_path.extension() {
+        temporary_file_name.push(".");
+        temporary_file_name.push(extension);
+    }
+    let temporary_path = file_path.with_file_name(temporary_file_name);
+
+    if temporary_path.exists() {
+        return Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("The temporary name {} is already taken", temporary_path.display()),
+        ));
+    }
+
+    if store_original_xattr {
+        store_original_file_name_in_xattr(file_path, file_path, &file_path.display().to_string());
+    }
+    std::fs::rename(file_path, &temporary_path)?;
+
+    Ok(temporary_path)
+}
+
+/// Gives each file a temporary name, so that the current names are free for the other files of the run, see [move_to_temporary_name].
+/// Returns the temporary path of each file by its current path. In a dry run, the current names are only freed in the claimed targets.
+pub(crate) fn move_to_temporary_names<'a>(
+    file_paths: impl Iterator<Item = &'a Path>,
+    settings: &RenameSettings,
+    claimed_targets: &mut ClaimedTargets,
+    current_working_directory: &Path,
+) -> HashMap<PathBuf, PathBuf> {
+    let mut temporary_paths = HashMap::<PathBuf, PathBuf>::new();
+
+    for file_path in file_paths {
+        let clean_file_name = format_path_buf_without_prefix(file_path, current_working_directory);
+
+        if settings.is_dry_run {
+            log::info!(
+                "[DRY RUN] Renaming via a temporary name, because its name is taken by another file of the run: {}",
+                clean_file_name
+            );
+            claimed_targets.vacate(file_path);
+            continue;
+        }
+
+        match move_to_temporary_name(file_path, settings.store_original_xattr) {
+            Ok(temporary_path) => {
+                log::info!(
+                    "Renaming via a temporary name, because its name is taken by another file of the run: {}",
+                    clean_file_name
+                );
+                temporary_paths.insert(file_path.to_path_buf(), temporary_path);
+            }
+            // The file is renamed from its current name, so its new name is a conflict.
+            Err(error) => log::warn!("Failed to give {} a temporary name: {}", clean_file_name, error),
+        }
+    }
+
+    temporary_paths
+}
+
+/// The outcome of a file which was renamed from a temporary name refers to its original name again.
+/// If the file was not renamed after all, e.g. because of a conflict, it gets its original name back.
+pub(crate) fn restore_from_temporary_name(rename_outcome: &mut RenameOutcome, original_path: &Path, current_working_directory: &Path) {
+    let temporary_path = std::mem::replace(&mut rename_outcome.source, original_path.to_path_buf());
+
+    if rename_outcome.target == temporary_path {
+        rename_outcome.target = original_path.to_path_buf();
+    }
+    if matches!(rename_outcome.action, RenameAction::Renamed | RenameAction::Vanished) {
+        return;
+    }
+
+    if let Err(error) = move_file(&temporary_path, original_path) {
+        log::error!(
+            "Failed to give {} its original name {} back: {}",
+            format_path_buf_without_prefix(&temporary_path, current_working_directory),
+            format_path_buf_without_prefix(original_path, current_working_directory),
+            error
+        );
+    }
+}
+
+/// Whether both paths lead to the same existing file, like two names which differ only in case on a case-insensitive file system, or two hard links.
+#[cfg(unix)]
+pub(crate) fn is_same_file(path: &Path, other_path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (path.metadata(), other_path.metadata()) {
+        (Ok(metadata), Ok(other_metadata)) => metadata.dev() == other_metadata.dev() && metadata.ino() == other_metadata.ino(),
+        _ => false,
+    }
+}
+
+/// Whether both paths lead to the same existing file, like two names which differ only in case on a case-insensitive file system.
+#[cfg(not(unix))]
+pub(crate) fn is_same_file(path: &Path, other_path: &Path) -> bool {
+    match (path.canonicalize(), other_path.canonicalize()) {
+        (Ok(canonical_path), Ok(other_canonical_path)) => canonical_path == other_canonical_path,
+        _ => false,
+    }
+}
+
+/// Removes the incomplete copy, if copying fails midway. The copy keeps the metadata of the original, see [preserve_metadata].
+pub(crate) fn copy_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    let target_existed = target.exists();
+
+    std::fs::copy(source, target).and_then(|_| preserve_metadata(source, target)).inspect_err(|_| {
+        if target_existed.not() {
+            let _ = std::fs::remove_file(target);
+        }
+    })
+}
+
+/// Moves the file to the trash of the system, from where it can be restored, instead of deleting it.
+pub(crate) fn move_to_trash(path: &Path) -> std::io::Result<()> {
+    trash::delete(path).map_err(std::io::Error::other)
+}
+
+/// Appends `-1`, `-2`, ... to the file stem until the path is not taken.
+/// The source file itself is never considered as taken, as it will be renamed anyway.
+fn find_free_suffixed_path(requested_path: &Path, source_path: &Path, claimed_targets: &ClaimedTargets, normalization: UnicodeNormalization) -> PathBuf {
+    let stem = requested_path.file_stem().unwrap_or_default();
+    let extension = requested_path.extension();
+
+    (1..)
+        .map(|counter| {
+            let mut file_name = stem.to_os_string();
+            file_name.push(format!("-{}", counter));
+            if let Some(extension) = extension {
+                file_name.push(".");
+                file_name.push(extension);
+            }
+            requested_path.with_file_name(file_name)
+        })
+        .find(|candidate| normalization.is_same_path(candidate, source_path) || claimed_targets.is_taken(candidate).not())
+        .expect("there is always a free suffix")
+}
+
+const ORIGINAL_FILE_NAME_XATTR: &str = "user.rambo.original";
+
+/// A file which was renamed before keeps the name it had before its first renaming, so that it can always be restored.
+fn store_original_file_name_in_xattr(original_file_path: &Path, new_file_path: &Path, clean_file_name_new: &str) {
+    let Some(original_file_name) = stored_original_file_name(new_file_path)
+        .or_else(|| stored_original_file_name(original_file_path))
+        .or_else(|| original_file_path.file_name().map(OsStr::to_os_string))
+    else {
+        return;
+    };
+
+    if let Err(error) = xattr::set(new_file_path, ORIGINAL_FILE_NAME_XATTR, original_file_name.as_encoded_bytes()) {
+        log::warn!(
+            "Failed to store the original file name in the extended attribute '{}' of {}: {}",
+            ORIGINAL_FILE_NAME_XATTR,
+            clean_file_name_new,
+            error
+        );
+    }
+}
+
+fn stored_original_file_name(file_path: &Path) -> Option<OsString> {
+    if xattr::SUPPORTED_PLATFORM.not() {
+        return None;
+    }
+
+    let value = xattr::get(file_path, ORIGINAL_FILE_NAME_XATTR).ok()??;
+    Some(value).filter(|value| value.is_empty().not()).and_then(os_string_from_bytes)
+}
+
+/// The name is stored as the raw bytes of the file name, which need not be UTF-8 on Unix.
+#[cfg(unix)]
+fn os_string_from_bytes(bytes: Vec<u8>) -> Option<OsString> {
+    use std::os::unix::ffi::OsStringExt;
+
+    Some(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn os_string_from_bytes(bytes: Vec<u8>) -> Option<OsString> {
+    String::from_utf8(bytes).ok().map(OsString::from)
+}
+
+/// The stem of the original file name for the `{original}` token. It is the one stored by [RenameSettings::store_original_xattr], if the file has been renamed before,
+/// so that renaming a file again does not nest its former names.
+pub(crate) fn original_file_stem(file_path: &Path) -> OsString {
+    let original_file_name = stored_original_file_name(file_path);
+
+    original_file_name
+        .as_deref()
+        .map(Path::new)
+        .unwrap_or(file_path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_os_string()
 }
@@ -0,0 +1,199 @@
+use crate::error::RamboError;
+use crate::input::path_from_bytes;
+use crate::rename::{RenameAction, RenameOutcome};
+use crate::strip_path_prefix;
+
+use chrono::Local;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// The start of the names of the rename maps, which are followed by the time of the run, like `rambo-renames-20240601-183000.csv`.
+pub const RENAME_MAP_PREFIX: &str = "rambo-renames-";
+
+/// A file which was renamed or copied from the old to the new path, as read from a rename map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MappedRename {
+    pub(crate) old_path: PathBuf,
+    pub(crate) new_path: PathBuf,
+    /// The new path is a copy, so the file at the old path was kept.
+    pub(crate) is_copy: bool,
+}
+
+/// Whether the file is a rename map, which is never processed itself.
+pub(crate) fn is_rename_map(path: &Path) -> bool {
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .is_some_and(|file_name| file_name.starts_with(RENAME_MAP_PREFIX) && file_name.ends_with(".csv"))
+}
+
+/// Writes the old and the new path of each renamed or copied file as CSV, so that the renaming can be undone.
+/// Returns the number of files that were written to the rename map.
+pub(crate) fn write_rename_map(map_path: &Path, outcomes: &[RenameOutcome], current_working_directory: &Path) -> Result<usize, RamboError> {
+    let map_file = File::create(map_path).map_err(RamboError::io(format!("Failed to create rename map '{}'", map_path.display())))?;
+
+    write_rename_map_file(map_file, map_path, outcomes, current_working_directory)
+}
+
+/// Writes the rename map into a new `rambo-renames-<TIMESTAMP>.csv` in the directory, so that external scripts can revert the renaming as well.
+/// Returns the path of the rename map.
+pub(crate) fn write_backup_rename_map(directory: &Path, outcomes: &[RenameOutcome], current_working_directory: &Path) -> Result<PathBuf, RamboError> {
+    let (map_path, map_file) =
+        create_rename_map_file(directory).map_err(RamboError::io(format!("Failed to create rename map in '{}'", directory.display())))?;
+
+    write_rename_map_file(map_file, &map_path, outcomes, current_working_directory)?;
+
+    Ok(map_path)
+}
+
+/// The paths are written relative to the directory of the rename map, if possible, and as raw bytes, so paths which are not valid UTF-8 are kept as well.
+fn write_rename_map_file(map_file: File, map_path: &Path, outcomes: &[RenameOutcome], current_working_directory: &Path) -> Result<usize, RamboError> {
+    let map_directory = map_directory(map_path, current_working_directory);
+    let mut writer = BufWriter::new(map_file);
+
+    let write_error_description = || format!("Failed to write rename map '{}'", map_path.display());
+
+    writeln!(writer, "old_name,new_name,action").map_err(RamboError::io(write_error_description()))?;
+
+    let mapped_outcomes = outcomes
+        .iter()
+        .filter(|outcome| matches!(outcome.action, RenameAction::Renamed | RenameAction::Copied))
+        .collect::<Vec<_>>();
+
+    for outcome in mapped_outcomes.iter() {
+        write_csv_field(&mut writer, strip_path_prefix(&outcome.source, &map_directory))
+            .and_then(|_| write!(writer, ","))
+            .and_then(|_| write_csv_field(&mut writer, strip_path_prefix(&outcome.target, &map_directory)))
+            .and_then(|_| match outcome.action {
+                RenameAction::Copied => writeln!(writer, ",copied"),
+                _ => writeln!(writer, ",renamed"),
+            })
+            .map_err(RamboError::io(write_error_description()))?;
+    }
+
+    writer.flush().map_err(RamboError::io(write_error_description()))?;
+
+    Ok(mapped_outcomes.len())
+}
+
+/// Several runs within the same second, like the batches of the watch mode, get a counter appended instead of replacing the rename map of the previous run.
+fn create_rename_map_file(directory: &Path) -> std::io::Result<(PathBuf, File)> {
+    let timestamp = Local::now().format("%Y%m%d-%H%M%S");
+
+    for counter in 1.. {
+        let map_file_name = if counter == 1 {
+            format!("{}{}.csv", RENAME_MAP_PREFIX, timestamp)
+        } else {
+            format!("{}{}-{}.csv", RENAME_MAP_PREFIX, timestamp, counter)
+        };
+        let map_path = directory.join(map_file_name);
+
+        match File::create_new(&map_path) {
+            Ok(map_file) => return Ok((map_path, map_file)),
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    unreachable!("there is always a free counter")
+}
+
+/// Reads the renamed and copied files of a rename map in the order of the run.
+/// Relative paths are resolved against the directory of the rename map.
+pub(crate) fn read_rename_map(map_path: &Path, current_working_directory: &Path) -> Result<Vec<MappedRename>, RamboError> {
+    let content = std::fs::read(map_path).map_err(RamboError::io(format!("Failed to read rename map '{}'", map_path.display())))?;
+    let map_directory = map_directory(map_path, current_working_directory);
+    let invalid_map = |message: String| {
+        RamboError::invalid_file(format!("Failed to read rename map '{}'", map_path.display()))(std::io::Error::new(ErrorKind::InvalidData, message))
+    };
+
+    let mut records = parse_csv_records(&content).into_iter();
+    match records.next() {
+        Some(header) if header == [b"old_name".to_vec(), b"new_name".to_vec(), b"action".to_vec()] => {}
+        _ => return Err(invalid_map("the header 'old_name,new_name,action' is missing".to_string())),
+    }
+
+    records
+        .enumerate()
+        .map(|(index, record)| match <[Vec<u8>; 3]>::try_from(record) {
+            Ok([old_name, new_name, action])
+                if old_name.is_empty().not() && new_name.is_empty().not() && matches!(action.as_slice(), b"renamed" | b"copied") =>
+            {
+                Ok(MappedRename {
+                    old_path: map_directory.join(path_from_bytes(&old_name)),
+                    new_path: map_directory.join(path_from_bytes(&new_name)),
+                    is_copy: action == b"copied",
+                })
+            }
+            // The header is the first line.
+            _ => Err(invalid_map(format!(
+                "line {} does not consist of an old name, a new name and 'renamed' or 'copied'",
+                index + 2
+            ))),
+        })
+        .collect()
+}
+
+/// The paths of the renamed files are canonical, so the directory is canonicalized as well for them to be written relative to it.
+fn map_directory(map_path: &Path, current_working_directory: &Path) -> PathBuf {
+    let map_directory = current_working_directory
+        .join(map_path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| current_working_directory.to_path_buf());
+
+    map_directory.canonicalize().unwrap_or(map_directory)
+}
+
+/// Paths with a comma, a quote or a line break are quoted, with their quotes doubled.
+fn write_csv_field(writer: &mut impl Write, path: &Path) -> std::io::Result<()> {
+    let bytes = path.as_os_str().as_encoded_bytes();
+
+    if bytes.iter().any(|byte| matches!(byte, b',' | b'"' | b'\n' | b'\r')) {
+        writer.write_all(b"\"")?;
+        for chunk in bytes.split_inclusive(|&byte| byte == b'"') {
+            writer.write_all(chunk)?;
+            if chunk.ends_with(b"\"") {
+                writer.write_all(b"\"")?;
+            }
+        }
+        writer.write_all(b"\"")
+    } else {
+        writer.write_all(bytes)
+    }
+}
+
+/// Splits the content into records of fields, where quoted fields may contain commas, line breaks and doubled quotes.
+fn parse_csv_records(content: &[u8]) -> Vec<Vec<Vec<u8>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = Vec::new();
+    let mut is_quoted = false;
+
+    let mut bytes = content.iter().copied().peekable();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'"' if is_quoted && bytes.peek() == Some(&b'"') => {
+                field.push(b'"');
+                bytes.next();
+            }
+            b'"' => is_quoted = is_quoted.not(),
+            b',' if is_quoted.not() => record.push(std::mem::take(&mut field)),
+            b'\r' if is_quoted.not() && bytes.peek() == Some(&b'\n') => {}
+            b'\n' if is_quoted.not() => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(byte),
+        }
+    }
+
+    if field.is_empty().not() || record.is_empty().not() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
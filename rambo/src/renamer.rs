@@ -0,0 +1,16 @@
+use crate::template::{Template, TemplateContext};
+
+use std::path::Path;
+
+/// Decides the new name of a file, without the extension, which is kept or fixed separately.
+/// The names of the files of a pair are decided once, for the first file of the pair. A name which is already taken is resolved by the [crate::ConflictStrategy].
+/// By default, the [Template] of the format or the template file is rendered.
+pub trait Renamer: Send + Sync {
+    fn new_file_name(&self, source: &Path, context: &TemplateContext) -> String;
+}
+
+impl Renamer for Template {
+    fn new_file_name(&self, _source: &Path, context: &TemplateContext) -> String {
+        self.render(context)
+    }
+}
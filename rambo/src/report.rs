@@ -0,0 +1,22 @@
+use crate::output::FileOutcome;
+use crate::statistics::Statistics;
+
+/// The result of a run which was carried out, even if single files failed.
+#[derive(Debug, Default)]
+pub struct RunReport {
+    pub statistics: Statistics,
+    /// The outcome of each file, in the order the files were processed.
+    pub file_outcomes: Vec<FileOutcome>,
+    /// Whether writing one of the requested files failed, like the collisions report, the checksum manifest or the plan.
+    pub has_failed_outputs: bool,
+    /// Whether the run was stopped by its [crate::CancellationToken]. The files which were not processed are counted as untouched.
+    pub is_cancelled: bool,
+}
+
+impl RunReport {
+    /// Whether the run should count as failed, because a file or an output failed.
+    /// With `fail_on_vanished`, files which vanished between matching and renaming count as failed as well.
+    pub fn has_failures(&self, fail_on_vanished: bool) -> bool {
+        self.statistics.failed_files > 0 || self.has_failed_outputs || (fail_on_vanished && self.statistics.vanished_files > 0)
+    }
+}
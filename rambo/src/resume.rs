@@ -0,0 +1,198 @@
+use crate::error::RamboError;
+use crate::extract::MediaMetadata;
+use crate::format_path_buf_without_prefix;
+use crate::output::FileOutcome;
+use crate::rename::RenameAction;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// The name of the state file in the current working directory, in which each run that is no dry run records its progress, so that it can be resumed when it is interrupted.
+/// It is removed once the run is completed.
+pub const RESUME_STATE_FILE_NAME: &str = ".rambo-resume.jsonl";
+
+/// A line of the state file.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ResumeRecord {
+    /// The metadata of the file was extracted, but the file is not handled yet.
+    Extracted { path: PathBuf, metadata: MediaMetadata },
+    /// The file was handled, e.g. renamed or skipped, and is not processed again.
+    Handled { path: PathBuf, new_path: Option<PathBuf> },
+}
+
+/// The progress of an interrupted run, as it was recorded in its state file.
+#[derive(Default)]
+pub(crate) struct ResumeState {
+    /// The original and the new paths of the handled files.
+    handled_paths: HashSet<PathBuf>,
+    /// The metadata of the files which were extracted, but not handled yet.
+    pub(crate) extractions: HashMap<PathBuf, MediaMetadata>,
+}
+
+impl ResumeState {
+    /// Returns `None` if there is no state file, i.e. no interrupted run.
+    /// Lines which cannot be read, like the last one of a run which was killed while writing it, are ignored.
+    pub(crate) fn read(directory: &Path) -> Result<Option<Self>, RamboError> {
+        let state_path = directory.join(RESUME_STATE_FILE_NAME);
+        let read_error_description = || format!("Failed to read resume state '{}'", state_path.display());
+
+        let state_file = match File::open(&state_path) {
+            Ok(state_file) => state_file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(RamboError::io(read_error_description())(error)),
+        };
+
+        let mut state = Self::default();
+        for line in BufReader::new(state_file).lines() {
+            let line = line.map_err(RamboError::io(read_error_description()))?;
+
+            match serde_json::from_str::<ResumeRecord>(&line) {
+                Ok(ResumeRecord::Extracted { path, metadata }) => {
+                    state.extractions.insert(path, metadata);
+                }
+                Ok(ResumeRecord::Handled { path, new_path }) => {
+                    state.extractions.remove(&path);
+                    state.handled_paths.extend(std::iter::once(path).chain(new_path));
+                }
+                Err(_) => {}
+            }
+        }
+
+        Ok(Some(state))
+    }
+
+    /// Removes the files which were handled by the interrupted run, by their original or their new path. Returns how many were removed.
+    pub(crate) fn remove_handled_paths(&self, paths: &mut Vec<PathBuf>) -> usize {
+        let total_paths = paths.len();
+        paths.retain(|path_buf| self.handled_paths.contains(path_buf).not());
+
+        total_paths - paths.len()
+    }
+
+    /// The metadata which the interrupted run extracted from the files, so that it is not extracted again.
+    pub(crate) fn known_extractions(&self, paths: &[PathBuf]) -> HashMap<PathBuf, MediaMetadata> {
+        paths
+            .iter()
+            .filter_map(|path_buf| self.extractions.get(path_buf).map(|metadata| (path_buf.clone(), metadata.clone())))
+            .collect()
+    }
+}
+
+/// Records the progress of a run in the state file, one JSON object per line.
+/// Each line is written right away, so that the progress is kept even if the run is killed.
+pub(crate) struct ResumeJournal {
+    state_path: PathBuf,
+    /// `None` in a dry run or once the state file cannot be written.
+    state_file: Option<File>,
+    /// The number of file outcomes of the run which are recorded already.
+    recorded_outcomes: usize,
+}
+
+impl ResumeJournal {
+    /// When resuming, the state file of the interrupted run is continued. Otherwise, a state file which was left over is replaced.
+    /// If the state file cannot be opened, the run is carried out nonetheless.
+    pub(crate) fn open(directory: &Path, is_resumed: bool) -> Self {
+        let state_path = directory.join(RESUME_STATE_FILE_NAME);
+
+        let state_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(is_resumed)
+            .truncate(is_resumed.not())
+            .open(&state_path);
+        let state_file = match state_file {
+            Ok(state_file) => Some(state_file),
+            Err(error) => {
+                log::warn!(
+                    "Failed to create resume state '{}', the run cannot be resumed if it is interrupted: {}",
+                    state_path.display(),
+                    error
+                );
+                None
+            }
+        };
+
+        Self {
+            state_path,
+            state_file,
+            recorded_outcomes: 0,
+        }
+    }
+
+    /// Records nothing, like in a dry run, where nothing needs to be resumed.
+    pub(crate) fn disabled() -> Self {
+        Self {
+            state_path: PathBuf::new(),
+            state_file: None,
+            recorded_outcomes: 0,
+        }
+    }
+
+    pub(crate) fn record_extraction(&mut self, path: &Path, metadata: &MediaMetadata) {
+        self.write_record(&ResumeRecord::Extracted {
+            path: path.to_path_buf(),
+            metadata: metadata.clone(),
+        });
+    }
+
+    /// Records the outcomes which were added since the last call.
+    /// Files which failed or vanished are not handled, so they are processed again when the run is resumed.
+    pub(crate) fn record_new_outcomes(&mut self, file_outcomes: &[FileOutcome]) {
+        let new_outcomes = file_outcomes.get(self.recorded_outcomes..).unwrap_or_default();
+        self.recorded_outcomes = file_outcomes.len();
+
+        for file_outcome in new_outcomes {
+            if matches!(file_outcome.action, RenameAction::Failed | RenameAction::Vanished) {
+                continue;
+            }
+
+            self.write_record(&ResumeRecord::Handled {
+                path: file_outcome.path.clone(),
+                new_path: file_outcome.new_path.clone(),
+            });
+        }
+    }
+
+    /// Removes the state file once the run is completed, so that there is nothing left to resume.
+    pub(crate) fn finish(self, current_working_directory: &Path) {
+        if self.state_path.as_os_str().is_empty() {
+            return;
+        }
+        drop(self.state_file);
+
+        if let Err(error) = std::fs::remove_file(&self.state_path)
+            && error.kind() != ErrorKind::NotFound
+        {
+            log::warn!(
+                "Failed to remove resume state {}: {}",
+                format_path_buf_without_prefix(&self.state_path, current_working_directory),
+                error
+            );
+        }
+    }
+
+    /// Paths which are not valid UTF-8 cannot be recorded, so such files are processed again when the run is resumed.
+    fn write_record(&mut self, record: &ResumeRecord) {
+        let Some(state_file) = &mut self.state_file else {
+            return;
+        };
+        let Ok(mut line) = serde_json::to_vec(record) else {
+            return;
+        };
+        line.push(b'\n');
+
+        if let Err(error) = state_file.write_all(&line) {
+            log::warn!(
+                "Failed to write resume state '{}', the run cannot be resumed if it is interrupted: {}",
+                self.state_path.display(),
+                error
+            );
+            self.state_file = None;
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::error::RamboError;
+use crate::storage::{ObjectStorage, StorageError};
+
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::{Bucket, Region};
+use std::ops::Not;
+
+/// A bucket of an S3-compatible object storage, like AWS S3 or MinIO.
+pub struct S3Storage {
+    bucket: Box<Bucket>,
+}
+
+impl S3Storage {
+    /// With an endpoint, like `http://localhost:9000` for MinIO, the bucket is addressed with path-style requests. Without one, the bucket of AWS S3 in the region is used.
+    /// The credentials are read from `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY` or from the profile in `~/.aws/credentials`.
+    pub fn new(bucket_name: &str, region: &str, endpoint: Option<&str>) -> Result<Self, RamboError> {
+        let region = match endpoint {
+            Some(endpoint) => Region::Custom {
+                region: region.to_string(),
+                endpoint: endpoint.to_string(),
+            },
+            None => region
+                .parse()
+                .map_err(|error| RamboError::InvalidSetting(format!("The region '{}' is invalid: {}", region, error)))?,
+        };
+        let credentials = Credentials::default().map_err(|error| RamboError::Storage {
+            description: "Failed to read the credentials".to_string(),
+            source: Box::new(error),
+        })?;
+
+        let bucket = Bucket::new(bucket_name, region, credentials).map_err(|error| RamboError::Storage {
+            description: format!("Cannot access the bucket '{}'", bucket_name),
+            source: Box::new(error),
+        })?;
+        let bucket = match endpoint {
+            Some(_) => bucket.with_path_style(),
+            None => bucket,
+        };
+
+        Ok(Self { bucket })
+    }
+}
+
+impl ObjectStorage for S3Storage {
+    fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let list_results = self.bucket.list(prefix.to_string(), None)?;
+
+        Ok(list_results
+            .into_iter()
+            .flat_map(|list_result| list_result.contents)
+            .map(|object| object.key)
+            .filter(|key| key.ends_with('/').not())
+            .collect())
+    }
+
+    fn read_head(&self, key: &str, length: u64) -> Result<Vec<u8>, StorageError> {
+        let response = self.bucket.get_object_range(key, 0, Some(length.saturating_sub(1)))?;
+
+        Ok(response.to_vec())
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        match self.bucket.head_object(key) {
+            Ok(_) => Ok(true),
+            Err(S3Error::HttpFailWithBody(404, _)) => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn rename(&self, key: &str, new_key: &str) -> Result<(), StorageError> {
+        self.bucket.copy_object_internal(key, new_key)?;
+        self.bucket.delete_object(key)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use crate::error::RamboError;
+use crate::glob::glob_root;
+use crate::storage::{ObjectStorage, StorageError};
+
+use glob::{MatchOptions, Pattern};
+use ssh2::{CheckResult, ErrorCode, KnownHostFileKind, Session, Sftp};
+use std::io::Read;
+use std::net::TcpStream;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The SFTP status code of a file which does not exist.
+const SFTP_NO_SUCH_FILE: i32 = 2;
+
+/// The files on a remote server matching a glob pattern, like `sftp://user@nas/photos/**/*.jpg`.
+/// Without a user, the local user name is used. The port defaults to 22.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SftpSource {
+    pub user: String,
+    pub host: String,
+    pub port: u16,
+    /// An absolute glob pattern, like `/photos/**/*.jpg`.
+    pub pattern: String,
+}
+
+impl SftpSource {
+    /// The directory of the leading components of the pattern which contain no wildcards, which is the prefix of all matched files.
+    pub fn root(&self) -> String {
+        let root = glob_root(&self.pattern).to_string_lossy().into_owned();
+
+        match root.ends_with('/') {
+            true => root,
+            false => format!("{}/", root),
+        }
+    }
+}
+
+impl FromStr for SftpSource {
+    type Err = RamboError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || RamboError::InvalidInput(format!("'{}' is no SFTP source like 'sftp://user@host/path/**/*'", value));
+
+        let location = value.strip_prefix("sftp://").ok_or_else(invalid)?;
+        let (authority, path) = location.split_once('/').ok_or_else(invalid)?;
+        let (user, host_and_port) = match authority.split_once('@') {
+            Some((user, host_and_port)) => (user.to_string(), host_and_port),
+            None => (std::env::var("USER").map_err(|_| invalid())?, authority),
+        };
+        let (host, port) = match host_and_port.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (host_and_port, 22),
+        };
+        if host.is_empty() || path.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            user,
+            host: host.to_string(),
+            port,
+            pattern: format!("/{}", path),
+        })
+    }
+}
+
+/// The files on a remote server, which are accessed with SFTP.
+pub struct SftpStorage {
+    sftp: Sftp,
+    pattern: Pattern,
+}
+
+impl SftpStorage {
+    /// The host key must be listed in `~/.ssh/known_hosts`. The user is authenticated with the SSH agent, or else with `~/.ssh/id_ed25519` or `~/.ssh/id_rsa`.
+    /// [ObjectStorage::list] only returns the files matching the pattern of the source, so pass [SftpSource::root] as the prefix.
+    pub fn connect(source: &SftpSource) -> Result<Self, RamboError> {
+        let connection_error = |description: String| {
+            move |error: ssh2::Error| RamboError::Storage {
+                description,
+                source: Box::new(error),
+            }
+        };
+
+        let pattern = Pattern::new(&source.pattern)
+            .map_err(|error| RamboError::InvalidInput(format!("Failed to interpret glob pattern '{}': {}", source.pattern, error)))?;
+
+        let tcp_stream =
+            TcpStream::connect((source.host.as_str(), source.port)).map_err(RamboError::io(format!("Failed to connect to {}:{}", source.host, source.port)))?;
+        let mut session = Session::new().map_err(connection_error("Failed to create the SSH session".to_string()))?;
+        session.set_tcp_stream(tcp_stream);
+        session
+            .handshake()
+            .map_err(connection_error(format!("Failed the SSH handshake with {}", source.host)))?;
+
+        verify_host_key(&session, source)?;
+
+        let home = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+        let is_authenticated = session.userauth_agent(&source.user).is_ok()
+            || ["id_ed25519", "id_rsa"].into_iter().any(|key_file_name| {
+                let private_key = home.join(".ssh").join(key_file_name);
+                private_key.is_file() && session.userauth_pubkey_file(&source.user, None, &private_key, None).is_ok()
+            });
+        if is_authenticated.not() || session.authenticated().not() {
+            return Err(RamboError::InvalidInput(format!(
+                "Cannot authenticate as '{}' on {}, neither with the SSH agent nor with a key in ~/.ssh",
+                source.user, source.host
+            )));
+        }
+
+        let sftp = session.sftp().map_err(connection_error(format!("Failed to start SFTP on {}", source.host)))?;
+
+        Ok(Self { sftp, pattern })
+    }
+
+    fn collect_files(&self, directory: &Path, prefix: &str, keys: &mut Vec<String>) -> Result<(), StorageError> {
+        let match_options = MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+
+        for (path, file_stat) in self.sftp.readdir(directory)? {
+            if file_stat.is_dir() {
+                self.collect_files(&path, prefix, keys)?;
+            } else if file_stat.is_file()
+                && let Some(key) = path.to_str()
+                && key.starts_with(prefix)
+                && self.pattern.matches_with(key, match_options)
+            {
+                keys.push(key.to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Connecting to an unknown host, or to one whose key changed, would expose the credentials and the files to whoever answers.
+fn verify_host_key(session: &Session, source: &SftpSource) -> Result<(), RamboError> {
+    let unverified = |reason: &str| RamboError::InvalidInput(format!("Cannot verify the host key of {}: {}", source.host, reason));
+
+    let (host_key, _) = session.host_key().ok_or_else(|| unverified("the host sent no key"))?;
+    let mut known_hosts = session.known_hosts().map_err(|_| unverified("the known hosts cannot be read"))?;
+    let known_hosts_path = std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default().join(".ssh").join("known_hosts");
+    known_hosts
+        .read_file(&known_hosts_path, KnownHostFileKind::OpenSSH)
+        .map_err(|_| unverified("~/.ssh/known_hosts cannot be read"))?;
+
+    match known_hosts.check_port(&source.host, source.port, host_key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => Err(unverified("the key differs from the one in ~/.ssh/known_hosts")),
+        CheckResult::NotFound => Err(unverified("the host is not listed in ~/.ssh/known_hosts, connect with ssh once to add it")),
+        CheckResult::Failure => Err(unverified("the check failed")),
+    }
+}
+
+impl ObjectStorage for SftpStorage {
+    /// Walks the directory of the prefix and all its subdirectories. Symlinks are not followed.
+    fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let directory = prefix.rfind('/').map(|index| &prefix[..=index]).unwrap_or("/");
+
+        let mut keys = Vec::new();
+        self.collect_files(Path::new(directory), prefix, &mut keys)?;
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    fn read_head(&self, key: &str, length: u64) -> Result<Vec<u8>, StorageError> {
+        let mut head = Vec::new();
+        self.sftp.open(Path::new(key))?.take(length).read_to_end(&mut head)?;
+
+        Ok(head)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        match self.sftp.lstat(Path::new(key)) {
+            Ok(_) => Ok(true),
+            Err(error) if error.code() == ErrorCode::SFTP(SFTP_NO_SUCH_FILE) => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Many servers do not overwrite with a rename, so a taken target is removed first. This only happens with [crate::ConflictStrategy::Overwrite].
+    fn rename(&self, key: &str, new_key: &str) -> Result<(), StorageError> {
+        if self.exists(new_key)? {
+            self.sftp.unlink(Path::new(new_key))?;
+        }
+        self.sftp.rename(Path::new(key), Path::new(new_key), None)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,357 @@
+use crate::error::ExtractError;
+use crate::extract::extract_creation_datetime_from_xmp;
+use crate::format_path_buf_without_prefix;
+use crate::rename::{RenameAction, RenameOutcome, copy_file, move_file};
+use crate::statistics::Statistics;
+
+use chrono::{DateTime, FixedOffset, Local, TimeZone};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+const TAKEOUT_SIDECAR_SUFFIX: &str = ".supplemental-metadata";
+
+/// The part of a Google Takeout sidecar which contains the creation datetime.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TakeoutMetadata {
+    photo_taken_time: TakeoutTimestamp,
+}
+
+#[derive(Debug, Deserialize)]
+struct TakeoutTimestamp {
+    /// Seconds since the Unix epoch, as a string.
+    timestamp: String,
+}
+
+/// Google Takeout names the sidecar of `IMG_1234.jpg` `IMG_1234.jpg.supplemental-metadata.json`, or `IMG_1234.jpg.json` in older exports.
+/// Long file names are truncated, e.g. to `IMG_1234.jpg.supplemental-me.json`.
+pub(crate) fn find_takeout_sidecar(media_file_path: &Path) -> Option<PathBuf> {
+    let media_file_name = media_file_path.file_name()?;
+
+    let sidecar_path = |infix: &str| {
+        let mut sidecar_file_name = media_file_name.to_os_string();
+        sidecar_file_name.push(infix);
+        sidecar_file_name.push(".json");
+        media_file_path.with_file_name(sidecar_file_name)
+    };
+
+    let exact_sidecar_path = [sidecar_path(TAKEOUT_SIDECAR_SUFFIX), sidecar_path("")]
+        .into_iter()
+        .find(|sidecar_path| sidecar_path.is_file());
+    if exact_sidecar_path.is_some() {
+        return exact_sidecar_path;
+    }
+
+    let media_file_name = media_file_name.to_str()?;
+    let directory = media_file_path
+        .parent()
+        .filter(|directory| directory.as_os_str().is_empty().not())
+        .unwrap_or(Path::new("."));
+
+    std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|candidate_path| {
+            candidate_path
+                .file_name()
+                .and_then(|candidate_file_name| candidate_file_name.to_str())
+                .and_then(|candidate_file_name| candidate_file_name.strip_prefix(media_file_name))
+                .and_then(|rest| rest.strip_suffix(".json"))
+                .is_some_and(|infix| infix.len() > 1 && TAKEOUT_SIDECAR_SUFFIX.starts_with(infix))
+        })
+}
+
+/// The timestamp of the sidecar is in UTC, so the datetime is converted to the local time zone.
+pub fn extract_creation_datetime_from_takeout_sidecar(media_file_path: &Path) -> Result<DateTime<FixedOffset>, ExtractError> {
+    let sidecar_path = find_takeout_sidecar(media_file_path).ok_or_else(|| ExtractError::Unavailable("There is no Google Takeout sidecar!".to_string()))?;
+
+    let sidecar_file = File::open(&sidecar_path).map_err(ExtractError::io(format!(
+        "Failed to open the Google Takeout sidecar '{}'",
+        sidecar_path.display()
+    )))?;
+    let metadata: TakeoutMetadata = serde_json::from_reader(BufReader::new(sidecar_file)).map_err(ExtractError::parse(format!(
+        "Failed to read the Google Takeout sidecar '{}'",
+        sidecar_path.display()
+    )))?;
+
+    let timestamp = metadata.photo_taken_time.timestamp.parse::<i64>().map_err(ExtractError::parse(format!(
+        "The timestamp '{}' of the Google Takeout sidecar is not a number!",
+        metadata.photo_taken_time.timestamp
+    )))?;
+
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|datetime| datetime.fixed_offset())
+        .ok_or_else(|| ExtractError::InvalidDatetime(format!("The timestamp {} of the Google Takeout sidecar is out of range!", timestamp)))
+}
+
+/// Darktable names the XMP sidecar of `IMG_1234.CR2` `IMG_1234.CR2.xmp`, while Lightroom names it `IMG_1234.xmp`.
+pub(crate) fn find_xmp_sidecar(media_file_path: &Path) -> Option<PathBuf> {
+    let media_file_name = media_file_path.file_name()?;
+
+    [media_file_path.to_path_buf(), media_file_path.with_extension("")]
+        .into_iter()
+        .flat_map(|base_path| {
+            ["xmp", "XMP"].map(|extension| {
+                let mut sidecar_path = base_path.clone().into_os_string();
+                sidecar_path.push(".");
+                sidecar_path.push(extension);
+                PathBuf::from(sidecar_path)
+            })
+        })
+        .find(|sidecar_path| sidecar_path.file_name() != Some(media_file_name) && sidecar_path.is_file())
+}
+
+/// Returns [None] if the file has no XMP sidecar.
+pub fn extract_creation_datetime_from_xmp_sidecar(media_file_path: &Path) -> Option<Result<DateTime<FixedOffset>, ExtractError>> {
+    let sidecar_path = find_xmp_sidecar(media_file_path)?;
+
+    let result = std::fs::read_to_string(&sidecar_path)
+        .map_err(ExtractError::io(format!("Failed to read the XMP sidecar '{}'", sidecar_path.display())))
+        .and_then(|xmp| extract_creation_datetime_from_xmp(&xmp));
+
+    Some(result)
+}
+
+/// XMP sidecars are not media files themselves.
+pub(crate) fn remove_xmp_sidecars(paths: &mut Vec<PathBuf>) {
+    paths.retain(|path_buf| path_buf.extension().is_none_or(|extension| extension.eq_ignore_ascii_case("xmp").not()));
+}
+
+/// Finds the sidecars of the given media files and removes them from the paths, so they are not processed as media files themselves.
+/// Sidecars have one of the given extensions (case-insensitive) and are named like their media file, e.g. `IMG_1234.xmp` or `IMG_1234.CR2.xmp` for `IMG_1234.CR2`.
+/// Files with one of these extensions are never media files with sidecars themselves.
+/// Returns the sidecars of each media file that has some.
+pub(crate) fn separate_sidecars(paths: &mut Vec<PathBuf>, sidecar_extensions: &[String], include_takeout_sidecars: bool) -> HashMap<PathBuf, Vec<PathBuf>> {
+    let has_sidecar_extension = |path: &Path| {
+        path.extension().is_some_and(|extension| {
+            sidecar_extensions
+                .iter()
+                .any(|sidecar_extension| extension.eq_ignore_ascii_case(sidecar_extension.trim_start_matches('.')))
+        })
+    };
+
+    let mut directory_entries = HashMap::<PathBuf, Vec<PathBuf>>::new();
+    let mut sidecars = HashMap::<PathBuf, Vec<PathBuf>>::new();
+
+    for path_buf in paths.iter() {
+        let mut sidecar_paths = Vec::new();
+
+        if sidecar_extensions.is_empty().not() && has_sidecar_extension(path_buf).not() {
+            let directory = path_buf.parent().map(Path::to_path_buf).unwrap_or_default();
+            let entries = directory_entries
+                .entry(directory)
+                .or_insert_with_key(|directory| read_directory_entries(directory));
+
+            sidecar_paths.extend(
+                entries
+                    .iter()
+                    .filter(|entry| *entry != path_buf && has_sidecar_extension(entry))
+                    .filter(|entry| entry.file_stem() == path_buf.file_stem() || entry.file_stem() == path_buf.file_name())
+                    .cloned(),
+            );
+        }
+
+        if include_takeout_sidecars && let Some(takeout_sidecar_path) = find_takeout_sidecar(path_buf) {
+            sidecar_paths.push(takeout_sidecar_path);
+        }
+
+        if sidecar_paths.is_empty().not() {
+            sidecar_paths.sort();
+            sidecar_paths.dedup();
+            sidecars.insert(path_buf.clone(), sidecar_paths);
+        }
+    }
+
+    let all_sidecar_paths = sidecars.values().flatten().cloned().collect::<HashSet<_>>();
+    paths.retain(|path_buf| all_sidecar_paths.contains(path_buf).not());
+
+    sidecars
+}
+
+fn read_directory_entries(directory: &Path) -> Vec<PathBuf> {
+    let readable_directory = if directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        directory
+    };
+
+    match std::fs::read_dir(readable_directory) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| directory.join(entry.file_name()))
+            .filter(|path_buf| path_buf.is_file())
+            .collect(),
+        Err(error) => {
+            log::warn!("Cannot look for sidecars in {}: {}", readable_directory.display(), error);
+            Vec::new()
+        }
+    }
+}
+
+/// The new name of a sidecar follows the new name of its media file:
+/// `IMG_1234.CR2.xmp` and `IMG_1234.xmp` of `IMG_1234.CR2` become `<NEW>.cr2.xmp` and `<NEW>.xmp`.
+fn sidecar_target(sidecar_path: &Path, source: &Path, target: &Path) -> Option<PathBuf> {
+    let sidecar_file_name = sidecar_path.file_name()?;
+
+    if let Some(sidecar_suffix) = sidecar_file_name.as_encoded_bytes().strip_prefix(source.file_name()?.as_encoded_bytes()) {
+        let mut new_sidecar_file_name = target.file_name()?.to_os_string();
+        new_sidecar_file_name.push(String::from_utf8_lossy(sidecar_suffix).as_ref());
+        return Some(target.with_file_name(new_sidecar_file_name));
+    }
+
+    let mut new_sidecar_file_name = target.file_stem()?.to_os_string();
+    if let Some(extension) = sidecar_path.extension() {
+        new_sidecar_file_name.push(".");
+        new_sidecar_file_name.push(extension);
+    }
+    Some(target.with_file_name(new_sidecar_file_name))
+}
+
+/// Gives the sidecars the new name of their media file. They are copied instead, if the media file was copied.
+/// If a sidecar cannot be renamed, the media file and the sidecars renamed so far are renamed back, so that they stay together.
+/// In that case, the outcome is changed to [RenameAction::Failed].
+pub(crate) fn rename_sidecars(
+    sidecar_paths: &[PathBuf],
+    rename_outcome: &mut RenameOutcome,
+    is_dry_run: bool,
+    current_working_directory: &Path,
+    statistics: &mut Statistics,
+) {
+    let (verb, verb_infinitive, is_copy) = match rename_outcome.action {
+        RenameAction::Renamed => ("Renaming", "rename", false),
+        RenameAction::Copied => ("Copying", "copy", true),
+        _ => return,
+    };
+
+    let planned_sidecars = sidecar_paths
+        .iter()
+        .filter_map(|sidecar_path| sidecar_target(sidecar_path, &rename_outcome.source, &rename_outcome.target).map(|target| (sidecar_path.as_path(), target)))
+        .filter(|(sidecar_path, target)| sidecar_path != target)
+        .collect::<Vec<_>>();
+
+    let error = match planned_sidecars.iter().find(|(_, target)| target.exists()) {
+        Some((sidecar_path, target)) => Some(format!(
+            "Cannot {} the sidecar {}, because the file {} already exists",
+            verb_infinitive,
+            format_path_buf_without_prefix(sidecar_path, current_working_directory),
+            format_path_buf_without_prefix(target, current_working_directory)
+        )),
+        None if is_dry_run => {
+            for (sidecar_path, target) in &planned_sidecars {
+                log::info!(
+                    "[DRY RUN] {} sidecar: {} ==> {}",
+                    verb,
+                    format_path_buf_without_prefix(sidecar_path, current_working_directory),
+                    format_path_buf_without_prefix(target, current_working_directory)
+                );
+            }
+            statistics.sidecar_files += planned_sidecars.len() as u64;
+            return;
+        }
+        None => transfer_sidecars(&planned_sidecars, is_copy, verb, verb_infinitive, current_working_directory, statistics).err(),
+    };
+
+    let Some(error) = error else {
+        return;
+    };
+
+    log::warn!("{}", error);
+
+    if is_dry_run.not() {
+        let undo_result = if is_copy {
+            std::fs::remove_file(&rename_outcome.target)
+        } else {
+            move_file(&rename_outcome.target, &rename_outcome.source)
+        };
+        if let Err(undo_error) = undo_result {
+            log::error!(
+                "Failed to undo the {} of {}: {}",
+                if is_copy {
+                    "copy"
+                } else {
+                    "renaming"
+                },
+                format_path_buf_without_prefix(&rename_outcome.source, current_working_directory),
+                undo_error
+            );
+        }
+    }
+
+    if is_copy {
+        statistics.copied_files -= 1;
+    } else {
+        statistics.renamed_files -= 1;
+    }
+    statistics.failed_files += 1;
+    rename_outcome.action = RenameAction::Failed;
+    rename_outcome.error = Some(error);
+}
+
+/// The sidecars which were renamed or copied along with their media file, with the paths they were transferred to.
+pub(crate) fn transferred_sidecars(sidecar_paths: &[PathBuf], rename_outcome: &RenameOutcome) -> Vec<(PathBuf, PathBuf)> {
+    if matches!(rename_outcome.action, RenameAction::Renamed | RenameAction::Copied).not() {
+        return Vec::new();
+    }
+
+    sidecar_paths
+        .iter()
+        .filter_map(|sidecar_path| sidecar_target(sidecar_path, &rename_outcome.source, &rename_outcome.target).map(|target| (sidecar_path.clone(), target)))
+        .filter(|(sidecar_path, target)| sidecar_path != target && target.exists())
+        .collect()
+}
+
+/// Stops at the first sidecar which cannot be transferred and transfers the sidecars back which were transferred so far.
+fn transfer_sidecars(
+    planned_sidecars: &[(&Path, PathBuf)],
+    is_copy: bool,
+    verb: &str,
+    verb_infinitive: &str,
+    current_working_directory: &Path,
+    statistics: &mut Statistics,
+) -> Result<(), String> {
+    for (index, (sidecar_path, target)) in planned_sidecars.iter().enumerate() {
+        let clean_sidecar_name_old = format_path_buf_without_prefix(sidecar_path, current_working_directory);
+        let clean_sidecar_name_new = format_path_buf_without_prefix(target, current_working_directory);
+
+        let transfer_result = if is_copy {
+            copy_file(sidecar_path, target)
+        } else {
+            move_file(sidecar_path, target)
+        };
+
+        if let Err(error) = transfer_result {
+            for (transferred_sidecar_path, transferred_target) in &planned_sidecars[..index] {
+                let undo_result = if is_copy {
+                    std::fs::remove_file(transferred_target)
+                } else {
+                    move_file(transferred_target, transferred_sidecar_path)
+                };
+                if let Err(undo_error) = undo_result {
+                    log::error!(
+                        "Failed to undo the transfer of the sidecar {}: {}",
+                        format_path_buf_without_prefix(transferred_sidecar_path, current_working_directory),
+                        undo_error
+                    );
+                }
+            }
+            statistics.sidecar_files -= index as u64;
+
+            return Err(format!(
+                "Failed to {} the sidecar {} to {}: {}",
+                verb_infinitive, clean_sidecar_name_old, clean_sidecar_name_new, error
+            ));
+        }
+
+        log::info!("{} sidecar: {} ==> {}", verb, clean_sidecar_name_old, clean_sidecar_name_new);
+        statistics.sidecar_files += 1;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// The extensions that are correct for each recognized format, the preferred one first.
+const JPEG_EXTENSIONS: &[&str] = &["jpg", "jpeg"];
+const PNG_EXTENSIONS: &[&str] = &["png"];
+const GIF_EXTENSIONS: &[&str] = &["gif"];
+const WEBP_EXTENSIONS: &[&str] = &["webp"];
+const HEIF_EXTENSIONS: &[&str] = &["heic", "heif"];
+const AVIF_EXTENSIONS: &[&str] = &["avif"];
+const CR3_EXTENSIONS: &[&str] = &["cr3"];
+const MOV_EXTENSIONS: &[&str] = &["mov", "qt"];
+const MP4_EXTENSIONS: &[&str] = &["mp4", "m4v"];
+const THREE_GP_EXTENSIONS: &[&str] = &["3gp", "3g2"];
+
+/// Returns the preferred extension of the format of the file, if the format is recognized by its magic bytes and the current extension does not fit it.
+/// TIFF based formats are not recognized, because most RAW formats are TIFF files with their own extension.
+pub(crate) fn corrected_extension(file_path: &Path) -> Option<&'static str> {
+    let mut header = [0u8; 16];
+    let header_length = File::open(file_path).and_then(|mut file| file.read(&mut header)).ok()?;
+    let extensions = extensions_of_format(&header[..header_length])?;
+
+    let current_extension = file_path.extension().and_then(OsStr::to_str).unwrap_or_default();
+    if extensions.iter().any(|extension| extension.eq_ignore_ascii_case(current_extension)) {
+        return None;
+    }
+
+    extensions.first().copied()
+}
+
+fn extensions_of_format(header: &[u8]) -> Option<&'static [&'static str]> {
+    match header {
+        [0xFF, 0xD8, 0xFF, ..] => Some(JPEG_EXTENSIONS),
+        [0x89, b'P', b'N', b'G', ..] => Some(PNG_EXTENSIONS),
+        [b'G', b'I', b'F', b'8', ..] => Some(GIF_EXTENSIONS),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(WEBP_EXTENSIONS),
+        [_, _, _, _, b'f', b't', b'y', b'p', b0, b1, b2, b3, ..] => extensions_of_iso_media_brand(&[*b0, *b1, *b2, *b3]),
+        // QuickTime files from before the 'ftyp' box was introduced start with one of these atoms.
+        [_, _, _, _, b'm', b'o', b'o', b'v', ..] | [_, _, _, _, b'm', b'd', b'a', b't', ..] | [_, _, _, _, b'w', b'i', b'd', b'e', ..] => Some(MOV_EXTENSIONS),
+        _ => None,
+    }
+}
+
+/// The major brand in the 'ftyp' box distinguishes the formats based on the ISO base media file format.
+fn extensions_of_iso_media_brand(brand: &[u8; 4]) -> Option<&'static [&'static str]> {
+    match brand {
+        b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"mif1" | b"msf1" => Some(HEIF_EXTENSIONS),
+        b"avif" | b"avis" => Some(AVIF_EXTENSIONS),
+        b"crx " => Some(CR3_EXTENSIONS),
+        b"qt  " => Some(MOV_EXTENSIONS),
+        [b'3', b'g', ..] => Some(THREE_GP_EXTENSIONS),
+        b"isom" | b"iso2" | b"iso4" | b"iso5" | b"iso6" | b"mp41" | b"mp42" | b"avc1" | b"M4V " | b"dash" | b"MSNV" => Some(MP4_EXTENSIONS),
+        _ => None,
+    }
+}
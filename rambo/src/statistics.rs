@@ -1,8 +1,31 @@
-#[derive(Debug, Default)]
+use serde::Serialize;
+
+#[derive(Debug, Default, Serialize)]
 pub struct Statistics {
     pub skipped_files: u64,
+    /// Files which were skipped by the filters on their extension, kind or size, before their creation datetime was extracted.
+    pub filtered_files: u64,
     pub failed_files: u64,
     pub renamed_files: u64,
+    pub copied_files: u64,
+    /// Files which were not processed at all, because the limit of files was reached.
+    pub untouched_files: u64,
+    /// Files which were not processed again, because the interrupted run which was resumed already handled them.
+    pub resumed_files: u64,
+    pub vanished_files: u64,
+    pub conflicting_files: u64,
+    /// Files whose new name does not fit the limits of the file system, like its length. They are counted as failed as well.
+    pub invalid_names: u64,
+    /// Sidecars which were renamed or copied along with their media file.
+    pub sidecar_files: u64,
+    /// Files whose modification time was set to their creation datetime.
+    pub synced_modification_times: u64,
+    /// Files whose extension was replaced, because it did not fit their format.
+    pub fixed_extensions: u64,
+    /// Files which were moved to the trash instead of being overwritten.
+    pub trashed_files: u64,
+    /// Files with the same content as another file of the run, not counting the first file of each group.
+    pub duplicate_files: u64,
 }
 
 impl Statistics {
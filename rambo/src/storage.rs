@@ -0,0 +1,296 @@
+use crate::audio::extract_metadata_from_audio;
+use crate::avchd::extract_metadata_from_transport_stream;
+use crate::counter::Counter;
+use crate::error::{ExtractError, RamboError};
+use crate::event::RunEvent;
+use crate::extract::{MediaMetadata, extract_metadata_from_media_source};
+use crate::image::extract_metadata_from_image;
+use crate::naming::sanitize_file_name;
+use crate::output::FileOutcome;
+use crate::raw::extract_metadata_from_raw;
+use crate::rename::{ConflictStrategy, RenameAction};
+use crate::report::RunReport;
+use crate::statistics::Statistics;
+use crate::template::TemplateContext;
+use crate::{CancellationToken, RamboOptions, RunContext, datetime_with_sequence, print_human_summary, record_file_outcome};
+
+use chrono::{DateTime, FixedOffset};
+use nom_exif::{MediaParser, MediaSource};
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// The error of an [ObjectStorage], like a failed request.
+pub type StorageError = Box<dyn std::error::Error + Send + Sync>;
+
+/// How many bytes from the start of an object are read to extract its creation datetime.
+/// The EXIF data of photos is at their start, but videos whose track info is at their end cannot be processed.
+pub const OBJECT_HEAD_LENGTH: u64 = 1 << 20;
+
+/// An object storage, like a bucket of an S3-compatible service, whose objects are renamed like files.
+/// The keys are separated by `/`, so the new key of an object keeps the part up to its last `/`.
+pub trait ObjectStorage: Send + Sync {
+    /// Returns the keys of all objects which start with the prefix.
+    fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError>;
+
+    /// Returns the first `length` bytes of the object, or all of them if it is smaller.
+    fn read_head(&self, key: &str, length: u64) -> Result<Vec<u8>, StorageError>;
+
+    fn exists(&self, key: &str) -> Result<bool, StorageError>;
+
+    /// Object storages cannot rename objects, so it is usually a copy to the new key followed by a delete of the old one.
+    fn rename(&self, key: &str, new_key: &str) -> Result<(), StorageError>;
+}
+
+/// Like [crate::run], but renames the objects of the storage whose key starts with the prefix, instead of files.
+/// Only the naming, the time zone, the shifts, the date range, the extension filters, [RamboOptions::on_conflict] and the dry run apply.
+/// In a dry run, the objects are only read.
+pub fn run_on_storage(storage: &dyn ObjectStorage, prefix: &str, options: RamboOptions) -> Result<RunReport, RamboError> {
+    let run_context = RunContext::new(&options)?;
+    let mut statistics = Statistics::new();
+    let is_dry_run = options.no_dry_run.not();
+
+    let keys = storage.list(prefix).map_err(|source| RamboError::Storage {
+        description: format!("Failed to list the objects with prefix '{}'", prefix),
+        source,
+    })?;
+    log::info!("Found {} objects with prefix '{}'", keys.len(), prefix);
+
+    let mut taken_keys = keys.iter().cloned().collect::<HashSet<_>>();
+    let mut file_outcomes = Vec::<FileOutcome>::new();
+    let mut media_parser = MediaParser::new();
+    let mut counter = Counter::new(options.counter_reset);
+    let mut files_per_second = HashMap::<DateTime<FixedOffset>, u32>::new();
+    let mut is_cancelled = false;
+
+    for (index, key) in keys.iter().enumerate() {
+        if options.cancellation_token.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            statistics.untouched_files = (keys.len() - index) as u64;
+            is_cancelled = true;
+            break;
+        }
+
+        let path_buf = PathBuf::from(key);
+        options.emit_event(RunEvent::FileMatched { path: &path_buf });
+
+        let skipped_outcome = |path: PathBuf, datetime: Option<DateTime<FixedOffset>>, action: RenameAction, error: Option<String>| FileOutcome {
+            path,
+            new_path: None,
+            datetime,
+            action,
+            conflict: None,
+            error,
+        };
+
+        if run_context.file_filter.passes_extension(&path_buf).not() {
+            statistics.filtered_files += 1;
+            record_file_outcome(skipped_outcome(path_buf, None, RenameAction::Skipped, None), &options, &mut file_outcomes);
+            continue;
+        }
+
+        let metadata = match extract_metadata_from_object(storage, key, &mut media_parser) {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                log::warn!("Cannot extract creation datetime from {}: {}", key, error);
+                statistics.failed_files += 1;
+                record_file_outcome(
+                    skipped_outcome(path_buf, None, RenameAction::Failed, Some(error.to_string())),
+                    &options,
+                    &mut file_outcomes,
+                );
+                continue;
+            }
+        };
+        options.emit_event(RunEvent::DatetimeExtracted {
+            path: &path_buf,
+            datetime: metadata.datetime,
+        });
+
+        let datetime = run_context.datetime_transform.apply(&metadata);
+        if run_context.date_range.contains(&datetime).not() {
+            log::info!("Skipping {}, because its creation datetime {} is outside of the date range", key, datetime);
+            statistics.skipped_files += 1;
+            record_file_outcome(
+                skipped_outcome(path_buf, Some(datetime), RenameAction::Skipped, None),
+                &options,
+                &mut file_outcomes,
+            );
+            continue;
+        }
+
+        let new_file_name = run_context.renamer.new_file_name(
+            &path_buf,
+            &TemplateContext {
+                datetime: &datetime_with_sequence(&datetime, metadata.has_subseconds, &mut files_per_second),
+                metadata: &metadata,
+                original_file_name: path_buf.file_stem().unwrap_or_default(),
+                directory_name: path_buf.parent().and_then(Path::file_name).unwrap_or_default(),
+                file_path: None,
+                counter: counter.next(&path_buf, &datetime),
+            },
+        );
+        let new_file_name = options
+            .normalize
+            .normalize(&sanitize_file_name(&new_file_name, options.replacement_char, options.fs_compat));
+
+        let file_outcome = rename_object(storage, key, &new_file_name, &options, is_dry_run, &mut taken_keys, &mut statistics);
+        record_file_outcome(
+            FileOutcome {
+                datetime: Some(datetime),
+                ..file_outcome
+            },
+            &options,
+            &mut file_outcomes,
+        );
+    }
+
+    if is_cancelled {
+        log::warn!("The run was cancelled, {} objects were left untouched", statistics.untouched_files);
+    }
+
+    print_human_summary(&statistics, false);
+    if is_dry_run {
+        log::warn!("This was just a dry run. To actually apply the renaming, use the '--no-dry-run' flag.")
+    }
+
+    Ok(RunReport {
+        statistics,
+        file_outcomes,
+        has_failed_outputs: false,
+        is_cancelled,
+    })
+}
+
+fn extract_metadata_from_object(storage: &dyn ObjectStorage, key: &str, media_parser: &mut MediaParser) -> Result<MediaMetadata, ExtractError> {
+    let head = storage.read_head(key, OBJECT_HEAD_LENGTH).map_err(ExtractError::Other)?;
+    let mut head_reader = Cursor::new(&head);
+    if let Some(metadata) = extract_metadata_from_raw(&mut head_reader)
+        .or_else(|| extract_metadata_from_image(&mut head_reader))
+        .or_else(|| extract_metadata_from_transport_stream(&mut head_reader))
+        .or_else(|| extract_metadata_from_audio(&mut head_reader))
+    {
+        return metadata;
+    }
+    let media_source = MediaSource::seekable(Cursor::new(head)).map_err(ExtractError::parse("Cannot process the object"))?;
+
+    extract_metadata_from_media_source(media_source, media_parser)
+}
+
+/// The extension is kept in lowercase, like it is for files. A taken key is resolved with [RamboOptions::on_conflict].
+fn rename_object(
+    storage: &dyn ObjectStorage,
+    key: &str,
+    new_file_name: &str,
+    options: &RamboOptions,
+    is_dry_run: bool,
+    taken_keys: &mut HashSet<String>,
+    statistics: &mut Statistics,
+) -> FileOutcome {
+    let outcome = |new_key: Option<&str>, action: RenameAction, conflict: Option<ConflictStrategy>, error: Option<String>| FileOutcome {
+        path: PathBuf::from(key),
+        new_path: new_key.map(PathBuf::from),
+        datetime: None,
+        action,
+        conflict,
+        error,
+    };
+
+    let already_correct = |statistics: &mut Statistics| {
+        if is_dry_run.not() || options.dry_run_hide_unchanged.not() {
+            log::info!("This object has already the correct key: {}", key);
+        }
+        statistics.skipped_files += 1;
+        outcome(Some(key), RenameAction::AlreadyCorrect, None, None)
+    };
+    let is_taken = |candidate: &str| -> Result<bool, StorageError> { Ok(taken_keys.contains(candidate) || storage.exists(candidate)?) };
+
+    let requested_key = object_key(key, new_file_name, None);
+    if requested_key == key {
+        return already_correct(statistics);
+    }
+
+    let (new_key, conflict) = match is_taken(&requested_key) {
+        Ok(false) => (requested_key, None),
+        Ok(true) => match options.on_conflict {
+            ConflictStrategy::Skip => {
+                log::warn!("Skipping {}, because the object {} already exists", key, requested_key);
+                statistics.conflicting_files += 1;
+                statistics.skipped_files += 1;
+                return outcome(None, RenameAction::Skipped, Some(ConflictStrategy::Skip), None);
+            }
+            ConflictStrategy::Fail => {
+                log::warn!("Cannot rename {}, because the object {} already exists", key, requested_key);
+                statistics.conflicting_files += 1;
+                statistics.failed_files += 1;
+                let error = format!("The object {} already exists", requested_key);
+                return outcome(None, RenameAction::Failed, Some(ConflictStrategy::Fail), Some(error));
+            }
+            ConflictStrategy::Overwrite => {
+                log::warn!("The object {} already exists and will be overwritten by {}", requested_key, key);
+                statistics.conflicting_files += 1;
+                (requested_key, Some(ConflictStrategy::Overwrite))
+            }
+            ConflictStrategy::Suffix => match find_free_suffixed_key(key, new_file_name, is_taken) {
+                // The object already got a suffix in a previous run.
+                Ok(suffixed_key) if suffixed_key == key => return already_correct(statistics),
+                Ok(suffixed_key) => {
+                    statistics.conflicting_files += 1;
+                    (suffixed_key, Some(ConflictStrategy::Suffix))
+                }
+                Err(error) => return failed_lookup(key, error, statistics, outcome),
+            },
+        },
+        Err(error) => return failed_lookup(key, error, statistics, outcome),
+    };
+
+    if is_dry_run {
+        log::info!("[DRY RUN] Renaming: {} ==> {}", key, new_key);
+    } else if let Err(error) = storage.rename(key, &new_key) {
+        log::warn!("Failed to rename {} to {}: {}", key, new_key, error);
+        statistics.failed_files += 1;
+        return outcome(Some(&new_key), RenameAction::Failed, conflict, Some(format!("Failed to rename: {}", error)));
+    } else {
+        log::info!("Renaming: {} ==> {}", key, new_key);
+    }
+
+    statistics.renamed_files += 1;
+    taken_keys.remove(key);
+    taken_keys.insert(new_key.clone());
+    outcome(Some(&new_key), RenameAction::Renamed, conflict, None)
+}
+
+fn failed_lookup(
+    key: &str,
+    error: StorageError,
+    statistics: &mut Statistics,
+    outcome: impl Fn(Option<&str>, RenameAction, Option<ConflictStrategy>, Option<String>) -> FileOutcome,
+) -> FileOutcome {
+    log::warn!("Cannot check whether the new key of {} is taken: {}", key, error);
+    statistics.failed_files += 1;
+    outcome(None, RenameAction::Failed, None, Some(format!("Failed to check the new key: {}", error)))
+}
+
+/// The object itself is never considered as taken, as it will be renamed anyway.
+fn find_free_suffixed_key(key: &str, new_file_name: &str, is_taken: impl Fn(&str) -> Result<bool, StorageError>) -> Result<String, StorageError> {
+    let mut suffix = 1;
+    loop {
+        let candidate = object_key(key, new_file_name, Some(suffix));
+        if candidate == key || is_taken(&candidate)?.not() {
+            return Ok(candidate);
+        }
+        suffix += 1;
+    }
+}
+
+/// The key in the same "directory" as the original key, with `-1`, `-2`, ... appended to the new name for a suffix.
+fn object_key(key: &str, new_file_name: &str, suffix: Option<u32>) -> String {
+    let directory = key.rfind('/').map(|index| &key[..=index]).unwrap_or_default();
+    let suffix = suffix.map(|suffix| format!("-{}", suffix)).unwrap_or_default();
+    let extension = Path::new(key)
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy().to_ascii_lowercase()))
+        .unwrap_or_default();
+
+    format!("{}{}{}{}", directory, new_file_name, suffix, extension)
+}
@@ -0,0 +1,529 @@
+use crate::duplicates::compute_xxh3;
+use crate::error::RamboError;
+use crate::extract::MediaMetadata;
+use crate::extract::{read_xmp, xmp_keywords, xmp_rating};
+use crate::place::PlaceCache;
+
+use chrono::format::{Fixed, Item, Numeric, Pad, StrftimeItems};
+use chrono::{DateTime, FixedOffset};
+use regex::Regex;
+use std::ffi::OsStr;
+use std::ops::Not;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A template for the new file name (without the extension).
+///
+/// - `{date:FORMAT}` is replaced by the creation datetime, formatted with the chrono specifiers in `FORMAT`.
+/// - `{camera}` (or `{model}`) and `{make}` are replaced by the camera model and make from EXIF or QuickTime metadata, if the metadata contains them.
+///   Spaces and slashes are replaced by `-`, like `X-T5` or `Canon-EOS-R6`.
+/// - `{serial}` is replaced by the serial number of the camera body, if the metadata contains it. `{serial:NAME}` is replaced by `NAME` instead of an empty value.
+/// - `{lens}` is replaced by the lens model, like `XF16-55mm-F2.8-R-LM-WR`, if the metadata contains it. `{lens:NAME}` is replaced by `NAME` instead of an empty value.
+/// - `{iso}`, `{fnumber}` and `{shutter}` are replaced by the exposure settings, like `400`, `2.8` and `1-250` (or `2s` for long exposures), if the metadata contains them.
+/// - `{gps}` is replaced by the GPS coordinates in decimal degrees, like `+48.85770+2.29500`, if the metadata contains them.
+/// - `{lat}` and `{lon}` are replaced by the latitude and longitude in decimal degrees with 5 decimal places, if the metadata contains them. `{lat:2}` rounds to 2 decimal places.
+/// - `{place}` is replaced by the name of the nearest city or town to the GPS coordinates, like `Lisbon`, if the metadata contains them. The places are embedded, so no service is queried.
+/// - `{original}` is replaced by the original file name without the extension, like `IMG_1234` in `%Y-%m-%d__{original}`.
+///   If the original file name was stored in an extended attribute when the file was renamed before, that one is used instead of the current name.
+/// - `{dir}` is replaced by the name of the directory the file is in, like `ChristmasParty` for `ChristmasParty/IMG_1234.jpg`.
+/// - `{hash}` is replaced by the XXH3 hash of the content of the file in 32 hex digits. `{hash:8}` keeps only the first 8 of them.
+/// - `{rating}` is replaced by the `xmp:Rating` of the XMP sidecar or the embedded XMP data, like `5`, and `{keyword1}`, `{keyword2}`, ... by the keywords in it, if it contains them.
+/// - `{counter}` is replaced by the number of the file in this run, starting at 1. `{counter:03}` pads it with zeros to 3 digits.
+///   With a [crate::CounterReset], it starts again at 1 for each directory or each day.
+/// - `[...]` is an optional segment, which is left out entirely if any of the tokens inside renders to an empty value.
+/// - `\{`, `\}`, `\[`, `\]` and `\\` escape the special characters.
+///
+/// Template files may span multiple lines. Each line is trimmed and the lines are joined without a separator.
+/// Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone)]
+pub struct Template {
+    segments: Vec<Segment>,
+    /// Shared by the clones of the template, so that each place is looked up once per run.
+    places: Arc<PlaceCache>,
+}
+
+/// The number of hex digits of a 128 bit hash.
+const HASH_LENGTH: usize = 32;
+
+/// The decimal places of `{lat}` and `{lon}`, which is about 1 meter, like the ones of `{gps}`.
+const DEFAULT_GPS_PRECISION: usize = 5;
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Token(Token),
+    Optional(Vec<Segment>),
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Date(String),
+    Camera,
+    Make,
+    Serial {
+        placeholder: String,
+    },
+    Lens {
+        placeholder: String,
+    },
+    Iso,
+    FNumber,
+    Shutter,
+    Gps,
+    Latitude {
+        precision: usize,
+    },
+    Longitude {
+        precision: usize,
+    },
+    Place,
+    Original,
+    Directory,
+    Hash {
+        length: usize,
+    },
+    Rating,
+    /// The index of the keyword, starting at 0.
+    Keyword {
+        index: usize,
+    },
+    Counter {
+        width: usize,
+    },
+}
+
+/// The values a file is named with.
+pub struct TemplateContext<'a> {
+    /// The creation datetime, which may differ from the one of the metadata, e.g. because of another time zone.
+    pub datetime: &'a DateTime<FixedOffset>,
+    pub metadata: &'a MediaMetadata,
+    pub original_file_name: &'a OsStr,
+    /// The name of the directory the file is in before the renaming, like the folder of an album.
+    pub directory_name: &'a OsStr,
+    /// The local file the `{hash}`, `{rating}` and `{keyword1}` tokens are read from. [None] for objects of an [crate::ObjectStorage], whose content is not read.
+    pub file_path: Option<&'a Path>,
+    /// The number of the file in processing order, starting at 1, see [crate::CounterReset].
+    pub counter: u64,
+}
+
+impl Template {
+    pub fn from_file(path: &Path) -> Result<Self, RamboError> {
+        let content = std::fs::read_to_string(path).map_err(RamboError::io(format!("Failed to read template file '{}'", path.display())))?;
+
+        let source = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.is_empty().not() && line.starts_with('#').not())
+            .collect::<String>();
+
+        Self::parse(&source).map_err(|error| RamboError::InvalidSetting(format!("Invalid template in file '{}': {}", path.display(), error)))
+    }
+
+    pub fn parse(source: &str) -> Result<Self, RamboError> {
+        let mut chars = source.chars();
+        let segments = parse_segments(&mut chars, false)?;
+
+        Ok(Self {
+            segments,
+            places: Arc::default(),
+        })
+    }
+
+    /// Parses the format of the `--format` flag, whose text outside of tokens contains chrono specifiers, like `%Y-%m-%d__{camera}`.
+    /// A format without tokens is therefore formatted like a plain date format.
+    pub fn parse_format(format: &str) -> Result<Self, RamboError> {
+        let mut template = Self::parse(format).map_err(|error| RamboError::InvalidSetting(format!("Invalid format '{}': {}", format, error)))?;
+        template.segments = literals_to_dates(template.segments)?;
+
+        Ok(template)
+    }
+
+    pub fn render(&self, context: &TemplateContext) -> String {
+        render_segments(&self.segments, context, &self.places, false).unwrap_or_default()
+    }
+
+    /// A pattern which matches the names the template renders, whatever the values of its tokens are, like `2024-05-01_X-T5` for `%Y-%m-%d_{camera}`.
+    /// `map_literal` turns the literal text into the text of the new names, like replacing the characters which are not allowed in file names.
+    pub(crate) fn name_pattern(&self, map_literal: impl Fn(&str) -> String) -> Result<NamePattern, RamboError> {
+        let mut pattern = String::from("^");
+        push_segments_pattern(&self.segments, &map_literal, &mut pattern);
+        // A name which was taken got a suffix like `-1`.
+        pattern.push_str(r"(?:-\d+)?$");
+
+        Regex::new(&pattern)
+            .map(|regex| NamePattern { regex })
+            .map_err(|error| RamboError::InvalidSetting(format!("The names of the format cannot be recognized: {}", error)))
+    }
+}
+
+/// Recognizes the names which a [Template] renders, see [Template::name_pattern].
+#[derive(Debug, Clone)]
+pub(crate) struct NamePattern {
+    regex: Regex,
+}
+
+impl NamePattern {
+    /// The name without the extension must match.
+    pub(crate) fn matches(&self, file_stem: &str) -> bool {
+        self.regex.is_match(file_stem)
+    }
+}
+
+fn push_segments_pattern(segments: &[Segment], map_literal: &impl Fn(&str) -> String, pattern: &mut String) {
+    for segment in segments {
+        match segment {
+            Segment::Literal(literal) => pattern.push_str(&regex::escape(&map_literal(literal))),
+            Segment::Token(token) => pattern.push_str(&token_pattern(token, map_literal)),
+            Segment::Optional(optional_segments) => {
+                pattern.push_str("(?:");
+                push_segments_pattern(optional_segments, map_literal, pattern);
+                pattern.push_str(")?");
+            }
+        }
+    }
+}
+
+/// The numbers only match the values they can have, like `01` to `12` for `%m`, so that names like `IMG_1234` do not pass as `%Y%m%d`.
+fn date_pattern(format: &str, map_literal: &impl Fn(&str) -> String) -> String {
+    StrftimeItems::new(format)
+        .map(|item| match item {
+            Item::Literal(literal) | Item::Space(literal) => regex::escape(&map_literal(literal)),
+            Item::OwnedLiteral(literal) | Item::OwnedSpace(literal) => regex::escape(&map_literal(&literal)),
+            Item::Numeric(numeric, pad) => numeric_pattern(&numeric, pad),
+            Item::Fixed(fixed) => fixed_pattern(&fixed).to_string(),
+            Item::Error => String::new(),
+        })
+        .collect()
+}
+
+fn numeric_pattern(numeric: &Numeric, pad: Pad) -> String {
+    let padded = |zero_padded: &str, width: usize| match pad {
+        Pad::Zero => format!("(?:{})", zero_padded),
+        Pad::Space => format!(r"[ \d]{{{}}}", width),
+        Pad::None => format!(r"\d{{1,{}}}", width),
+    };
+
+    match numeric {
+        Numeric::Year | Numeric::IsoYear => r"[+-]?\d{4,}".to_string(),
+        Numeric::Month => padded("0[1-9]|1[0-2]", 2),
+        Numeric::Day => padded(r"0[1-9]|[12]\d|3[01]", 2),
+        Numeric::Hour => padded(r"[01]\d|2[0-3]", 2),
+        Numeric::Hour12 => padded("0[1-9]|1[0-2]", 2),
+        Numeric::Minute => padded(r"[0-5]\d", 2),
+        Numeric::Second => padded(r"[0-5]\d|60", 2),
+        Numeric::Ordinal => padded(r"\d{3}", 3),
+        Numeric::YearDiv100
+        | Numeric::YearMod100
+        | Numeric::IsoYearDiv100
+        | Numeric::IsoYearMod100
+        | Numeric::WeekFromSun
+        | Numeric::WeekFromMon
+        | Numeric::IsoWeek => padded(r"\d{2}", 2),
+        Numeric::NumDaysFromSun | Numeric::WeekdayFromMon => r"\d".to_string(),
+        Numeric::Nanosecond => padded(r"\d{9}", 9),
+        _ => r"[+-]?\d+".to_string(),
+    }
+}
+
+fn fixed_pattern(fixed: &Fixed) -> &'static str {
+    match fixed {
+        Fixed::ShortMonthName | Fixed::ShortWeekdayName => "[A-Za-z]{3}",
+        Fixed::LongMonthName | Fixed::LongWeekdayName => "[A-Za-z]+",
+        Fixed::LowerAmPm => "[ap]m",
+        Fixed::UpperAmPm => "[AP]M",
+        Fixed::Nanosecond => r"(?:\.\d+)?",
+        Fixed::Nanosecond3 => r"\.\d{3}",
+        Fixed::Nanosecond6 => r"\.\d{6}",
+        Fixed::Nanosecond9 => r"\.\d{9}",
+        Fixed::TimezoneOffset | Fixed::TimezoneOffsetColon | Fixed::TimezoneOffsetDoubleColon | Fixed::TimezoneOffsetTripleColon => {
+            r"[+-]\d{2}(?:\D?\d{2}){0,2}"
+        }
+        Fixed::TimezoneOffsetZ | Fixed::TimezoneOffsetColonZ => r"(?:Z|[+-]\d{2}(?:\D?\d{2}){0,2})",
+        // Like the digits of `%3f`, which are internal to chrono.
+        Fixed::Internal(_) => r"\d+",
+        _ => ".+?",
+    }
+}
+
+/// The values of the tokens are sanitized, so they never contain whitespace.
+fn token_pattern(token: &Token, map_literal: &impl Fn(&str) -> String) -> String {
+    match token {
+        Token::Date(format) => date_pattern(format, map_literal),
+        Token::Camera | Token::Make | Token::Serial { .. } | Token::Lens { .. } | Token::Place | Token::Original | Token::Directory | Token::Keyword { .. } => {
+            r"\S*".to_string()
+        }
+        Token::Iso | Token::Rating => r"\d*".to_string(),
+        Token::FNumber => r"(?:\d+(?:\.\d)?)?".to_string(),
+        Token::Shutter => r"(?:1-\d+|\d+(?:\.\d)?s)?".to_string(),
+        Token::Gps => r"(?:[+-]\d+\.\d{5}[+-]\d+\.\d{5})?".to_string(),
+        Token::Latitude { precision } | Token::Longitude { precision } if *precision == 0 => r"(?:-?\d+)?".to_string(),
+        Token::Latitude { precision } | Token::Longitude { precision } => format!(r"(?:-?\d+\.\d{{{}}})?", precision),
+        Token::Hash { length } => format!("(?:[0-9a-f]{{{}}})?", length),
+        Token::Counter { width } => format!(r"\d{{{},}}", (*width).max(1)),
+    }
+}
+
+fn parse_segments(chars: &mut std::str::Chars, is_optional: bool) -> Result<Vec<Segment>, RamboError> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+
+    loop {
+        let Some(char) = chars.next() else {
+            if is_optional {
+                return Err(RamboError::InvalidSetting("An optional segment is missing its closing ']'".to_string()));
+            }
+            break;
+        };
+
+        match char {
+            '\\' => match chars.next() {
+                Some(escaped) => literal.push(escaped),
+                None => return Err(RamboError::InvalidSetting("The template must not end with an unescaped '\\'".to_string())),
+            },
+            '{' => {
+                push_literal(&mut segments, &mut literal);
+                segments.push(Segment::Token(parse_token(chars)?));
+            }
+            '}' => return Err(RamboError::InvalidSetting("Unexpected '}' without an opening '{'".to_string())),
+            '[' => {
+                push_literal(&mut segments, &mut literal);
+                segments.push(Segment::Optional(parse_segments(chars, true)?));
+            }
+            ']' if is_optional => break,
+            ']' => return Err(RamboError::InvalidSetting("Unexpected ']' without an opening '['".to_string())),
+            _ => literal.push(char),
+        }
+    }
+
+    push_literal(&mut segments, &mut literal);
+
+    Ok(segments)
+}
+
+fn literals_to_dates(segments: Vec<Segment>) -> Result<Vec<Segment>, RamboError> {
+    segments
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Literal(literal) => {
+                validate_date_format(&literal)?;
+                Ok(Segment::Token(Token::Date(literal)))
+            }
+            Segment::Optional(optional_segments) => Ok(Segment::Optional(literals_to_dates(optional_segments)?)),
+            token => Ok(token),
+        })
+        .collect()
+}
+
+fn push_literal(segments: &mut Vec<Segment>, literal: &mut String) {
+    if literal.is_empty().not() {
+        segments.push(Segment::Literal(std::mem::take(literal)));
+    }
+}
+
+fn parse_token(chars: &mut std::str::Chars) -> Result<Token, RamboError> {
+    let mut token_source = String::new();
+    loop {
+        match chars.next() {
+            Some('}') => break,
+            Some(char) => token_source.push(char),
+            None => {
+                return Err(RamboError::InvalidSetting(format!(
+                    "The token '{{{}' is missing its closing '}}'",
+                    token_source
+                )));
+            }
+        }
+    }
+
+    let (name, argument) = match token_source.split_once(':') {
+        Some((name, argument)) => (name.trim(), Some(argument)),
+        None => (token_source.trim(), None),
+    };
+
+    match (name, argument) {
+        ("date", Some(format)) => {
+            validate_date_format(format)?;
+            Ok(Token::Date(format.to_string()))
+        }
+        ("date", None) => Err(RamboError::InvalidSetting(
+            "The token '{date}' needs a format, like '{date:%Y-%m-%d}'".to_string(),
+        )),
+        ("camera" | "model", None) => Ok(Token::Camera),
+        ("make", None) => Ok(Token::Make),
+        ("iso", None) => Ok(Token::Iso),
+        ("fnumber", None) => Ok(Token::FNumber),
+        ("shutter", None) => Ok(Token::Shutter),
+        ("serial", placeholder) => Ok(Token::Serial {
+            placeholder: sanitize(placeholder.unwrap_or_default()),
+        }),
+        ("lens", placeholder) => Ok(Token::Lens {
+            placeholder: sanitize(placeholder.unwrap_or_default()),
+        }),
+        ("gps", None) => Ok(Token::Gps),
+        ("lat", None) => Ok(Token::Latitude {
+            precision: DEFAULT_GPS_PRECISION,
+        }),
+        ("lat", Some(precision)) => Ok(Token::Latitude {
+            precision: parse_precision("lat", precision)?,
+        }),
+        ("lon", None) => Ok(Token::Longitude {
+            precision: DEFAULT_GPS_PRECISION,
+        }),
+        ("lon", Some(precision)) => Ok(Token::Longitude {
+            precision: parse_precision("lon", precision)?,
+        }),
+        ("place", None) => Ok(Token::Place),
+        ("original", None) => Ok(Token::Original),
+        ("dir", None) => Ok(Token::Directory),
+        ("hash", None) => Ok(Token::Hash { length: HASH_LENGTH }),
+        ("hash", Some(length)) => match length.trim().parse::<usize>() {
+            Ok(length) if (1..=HASH_LENGTH).contains(&length) => Ok(Token::Hash { length }),
+            _ => Err(RamboError::InvalidSetting(format!(
+                "The length of the token '{{hash:{}}}' must be a number from 1 to {}, like '{{hash:8}}'",
+                length, HASH_LENGTH
+            ))),
+        },
+        ("rating", None) => Ok(Token::Rating),
+        (name, None) if name.starts_with("keyword") => match name["keyword".len()..].parse::<usize>() {
+            Ok(number) if number > 0 => Ok(Token::Keyword { index: number - 1 }),
+            _ => Err(RamboError::InvalidSetting(format!(
+                "The number of the token '{{{}}}' must be at least 1, like '{{keyword1}}'",
+                name
+            ))),
+        },
+        ("counter", None) => Ok(Token::Counter { width: 0 }),
+        ("counter", Some(width)) => match width.trim().parse::<usize>() {
+            Ok(width) => Ok(Token::Counter { width }),
+            Err(_) => Err(RamboError::InvalidSetting(format!(
+                "The width of the token '{{counter:{}}}' must be a number, like '{{counter:03}}'",
+                width
+            ))),
+        },
+        _ => Err(RamboError::InvalidSetting(format!("Unknown token '{{{}}}'", token_source))),
+    }
+}
+
+fn parse_precision(name: &str, precision: &str) -> Result<usize, RamboError> {
+    precision.trim().parse::<usize>().map_err(|_| {
+        RamboError::InvalidSetting(format!(
+            "The precision of the token '{{{}:{}}}' must be a number of decimal places, like '{{{}:2}}'",
+            name, precision, name
+        ))
+    })
+}
+
+/// Returns [None] if a token in an optional segment rendered to an empty value, so that the whole segment is left out.
+fn render_segments(segments: &[Segment], context: &TemplateContext, places: &PlaceCache, is_optional: bool) -> Option<String> {
+    let mut rendered = String::new();
+
+    for segment in segments {
+        match segment {
+            Segment::Literal(literal) => rendered.push_str(literal),
+            Segment::Token(token) => {
+                let value = render_token(token, context, places);
+                if value.is_empty() && is_optional {
+                    return None;
+                }
+                rendered.push_str(&value);
+            }
+            Segment::Optional(optional_segments) => {
+                if let Some(value) = render_segments(optional_segments, context, places, true) {
+                    rendered.push_str(&value);
+                }
+            }
+        }
+    }
+
+    Some(rendered)
+}
+
+fn render_token(token: &Token, context: &TemplateContext, places: &PlaceCache) -> String {
+    match token {
+        Token::Date(format) => context.datetime.format(format).to_string(),
+        Token::Camera => context.metadata.camera_model.as_deref().map(sanitize).unwrap_or_default(),
+        Token::Make => context.metadata.camera_make.as_deref().map(sanitize).unwrap_or_default(),
+        Token::Serial { placeholder } => context.metadata.camera_serial.as_deref().map(sanitize).unwrap_or_else(|| placeholder.clone()),
+        Token::Lens { placeholder } => context.metadata.lens_model.as_deref().map(sanitize).unwrap_or_else(|| placeholder.clone()),
+        Token::Iso => context.metadata.iso.map(|iso| iso.to_string()).unwrap_or_default(),
+        Token::FNumber => context.metadata.f_number.map(format_decimal).unwrap_or_default(),
+        Token::Shutter => context.metadata.exposure_time.map(format_exposure_time).unwrap_or_default(),
+        Token::Gps => context
+            .metadata
+            .gps
+            .map(|gps| format!("{:+.5}{:+.5}", gps.latitude, gps.longitude))
+            .unwrap_or_default(),
+        Token::Latitude { precision } => context
+            .metadata
+            .gps
+            .map(|gps| format!("{:.precision$}", gps.latitude, precision = *precision))
+            .unwrap_or_default(),
+        Token::Longitude { precision } => context
+            .metadata
+            .gps
+            .map(|gps| format!("{:.precision$}", gps.longitude, precision = *precision))
+            .unwrap_or_default(),
+        Token::Place => context.metadata.gps.map(|gps| sanitize(&places.place_at(gps))).unwrap_or_default(),
+        Token::Original => sanitize(&context.original_file_name.to_string_lossy()),
+        Token::Directory => sanitize(&context.directory_name.to_string_lossy()),
+        Token::Hash { length } => context.file_path.map(|file_path| render_hash(file_path, *length)).unwrap_or_default(),
+        Token::Rating => context
+            .file_path
+            .and_then(read_xmp)
+            .and_then(|xmp| xmp_rating(&xmp))
+            .map(|rating| rating.to_string())
+            .unwrap_or_default(),
+        Token::Keyword { index } => context
+            .file_path
+            .and_then(read_xmp)
+            .and_then(|xmp| xmp_keywords(&xmp).into_iter().nth(*index))
+            .map(|keyword| sanitize(&keyword))
+            .unwrap_or_default(),
+        Token::Counter { width } => format!("{:0width$}", context.counter, width = *width),
+    }
+}
+
+/// An empty value if the file cannot be read, so that an optional segment is left out.
+fn render_hash(file_path: &Path, length: usize) -> String {
+    match compute_xxh3(file_path) {
+        Ok(hash) => format!("{:032x}", hash)[..length].to_string(),
+        Err(error) => {
+            log::warn!("Failed to compute the hash of '{}': {}", file_path.display(), error);
+            String::new()
+        }
+    }
+}
+
+/// With one decimal place, which is left out for whole numbers, like `2.8` or `8`.
+fn format_decimal(value: f64) -> String {
+    format!("{:.1}", value).trim_end_matches(".0").to_string()
+}
+
+/// Exposures shorter than a second as a fraction, like `1-250`, as a `/` cannot be part of a file name. Longer ones in seconds, like `2s` or `2.5s`.
+/// Exposures which are no positive number, like a broken `0` in the metadata, render to an empty value.
+fn format_exposure_time(exposure_time: f64) -> String {
+    if (exposure_time > 0.0).not() {
+        String::new()
+    } else if exposure_time < 1.0 {
+        format!("1-{}", (1.0 / exposure_time).round())
+    } else {
+        format!("{}s", format_decimal(exposure_time))
+    }
+}
+
+/// Values from the metadata must not introduce directories into the file name. Whitespace is replaced as well, for file names that are easy to handle.
+fn sanitize(value: &str) -> String {
+    value
+        .split(|char: char| char.is_whitespace() || matches!(char, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .filter(|part| part.is_empty().not())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Formatting a datetime with an invalid format would panic, so formats from the user must be validated first.
+pub(crate) fn validate_date_format(format: &str) -> Result<(), RamboError> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(RamboError::InvalidSetting(format!("The date format '{}' is invalid", format)));
+    }
+
+    Ok(())
+}
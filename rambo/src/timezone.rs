@@ -0,0 +1,59 @@
+use crate::error::RamboError;
+use crate::extract::GpsCoordinates;
+
+use chrono::{DateTime, FixedOffset};
+use chrono_tz::Tz;
+use std::str::FromStr;
+use std::sync::LazyLock;
+use tzf_rs::DefaultFinder;
+
+/// Loading the time zone boundaries takes a moment, so it is only done once they are needed.
+static TIME_ZONE_FINDER: LazyLock<DefaultFinder> = LazyLock::new(DefaultFinder::new);
+
+/// The time zone in which the creation datetimes are rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetTimeZone {
+    /// The same offset for every file, like `+02:00`.
+    Offset(FixedOffset),
+    /// An IANA time zone, like `Europe/Berlin`. The offset of each file is the one that was in effect at its creation, so daylight saving time is respected.
+    Named(Tz),
+    /// The time zone at the GPS coordinates of each file, so that photos taken while travelling show the local time. Files without GPS coordinates keep their offset.
+    FromGps,
+}
+
+impl TargetTimeZone {
+    pub fn convert(&self, datetime: &DateTime<FixedOffset>, gps: Option<GpsCoordinates>) -> DateTime<FixedOffset> {
+        match self {
+            TargetTimeZone::Offset(offset) => datetime.with_timezone(offset),
+            TargetTimeZone::Named(time_zone) => datetime.with_timezone(time_zone).fixed_offset(),
+            TargetTimeZone::FromGps => match gps.and_then(time_zone_at) {
+                Some(time_zone) => datetime.with_timezone(&time_zone).fixed_offset(),
+                None => *datetime,
+            },
+        }
+    }
+}
+
+impl FromStr for TargetTimeZone {
+    type Err = RamboError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value == "from-gps" {
+            return Ok(TargetTimeZone::FromGps);
+        }
+
+        if let Ok(offset) = FixedOffset::from_str(value) {
+            return Ok(TargetTimeZone::Offset(offset));
+        }
+
+        Tz::from_str(value).map(TargetTimeZone::Named).map_err(|_| {
+            RamboError::InvalidSetting("It is neither an offset like '+02:00' nor a time zone name like 'Europe/Berlin' or 'from-gps'".to_string())
+        })
+    }
+}
+
+fn time_zone_at(gps: GpsCoordinates) -> Option<Tz> {
+    let time_zone_name = TIME_ZONE_FINDER.get_tz_name(gps.longitude, gps.latitude);
+
+    Tz::from_str(time_zone_name).ok()
+}
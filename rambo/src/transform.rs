@@ -0,0 +1,89 @@
+use crate::error::RamboError;
+use crate::extract::MediaMetadata;
+use crate::timezone::TargetTimeZone;
+
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use std::collections::BTreeMap;
+use std::ops::Not;
+use std::str::FromStr;
+
+/// Adjustments of the extracted creation datetime, which are applied before it is rendered, no matter where it was extracted from.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DatetimeTransform {
+    pub(crate) shift: Option<TimeShift>,
+    /// Keyed by the serial number or the model of the camera. They are applied in addition to the shift of all files.
+    pub(crate) camera_shifts: BTreeMap<String, TimeShift>,
+    pub(crate) time_zone: Option<TargetTimeZone>,
+}
+
+impl DatetimeTransform {
+    /// The shift corrects the clock of the camera, so it is applied before the datetime is converted into the target time zone.
+    pub(crate) fn apply(&self, metadata: &MediaMetadata) -> DateTime<FixedOffset> {
+        let camera_shift = [&metadata.camera_serial, &metadata.camera_model]
+            .into_iter()
+            .flatten()
+            .find_map(|camera| self.camera_shifts.get(camera));
+
+        let datetime = [camera_shift, self.shift.as_ref()]
+            .into_iter()
+            .flatten()
+            .fold(metadata.datetime, |datetime, TimeShift(time_delta)| {
+                datetime.checked_add_signed(*time_delta).unwrap_or(datetime)
+            });
+
+        match self.time_zone {
+            Some(time_zone) => time_zone.convert(&datetime, metadata.gps),
+            None => datetime,
+        }
+    }
+}
+
+/// A signed duration like `-1h37m` or `+30s`, made of days (`d`), hours (`h`), minutes (`m`) and seconds (`s`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TimeShift(TimeDelta);
+
+impl FromStr for TimeShift {
+    type Err = RamboError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (sign, amounts) = match value.strip_prefix('-') {
+            Some(amounts) => (-1, amounts),
+            None => (1, value.strip_prefix('+').unwrap_or(value)),
+        };
+        if amounts.is_empty() {
+            return Err(RamboError::InvalidSetting(
+                "It is empty, expected a duration like '-1h37m' or '+30s'".to_string(),
+            ));
+        }
+
+        let mut time_delta = TimeDelta::zero();
+        let mut number = String::new();
+        for char in amounts.chars() {
+            if char.is_ascii_digit() {
+                number.push(char);
+                continue;
+            }
+
+            let amount = number
+                .parse::<i64>()
+                .map_err(|_| RamboError::InvalidSetting(format!("Expected a number before '{}'", char)))?;
+            number.clear();
+            let unit_delta = match char {
+                'd' => TimeDelta::try_days(amount),
+                'h' => TimeDelta::try_hours(amount),
+                'm' => TimeDelta::try_minutes(amount),
+                's' => TimeDelta::try_seconds(amount),
+                _ => return Err(RamboError::InvalidSetting(format!("Unknown unit '{}', expected 'd', 'h', 'm' or 's'", char))),
+            };
+            time_delta = unit_delta
+                .and_then(|unit_delta| time_delta.checked_add(&unit_delta))
+                .ok_or_else(|| RamboError::InvalidSetting("The duration is too long".to_string()))?;
+        }
+
+        if number.is_empty().not() {
+            return Err(RamboError::InvalidSetting(format!("The number '{}' has no unit, like 's' for seconds", number)));
+        }
+
+        Ok(TimeShift(time_delta * sign))
+    }
+}
@@ -0,0 +1,121 @@
+use crate::naming::UnicodeNormalization;
+use crate::plan::rename_files_in_order;
+use crate::rename::{ClaimedTargets, ConflictStrategy, RenameSettings};
+use crate::rename_map::{MappedRename, read_rename_map};
+use crate::statistics::Statistics;
+use crate::{current_working_directory, format_path_buf_without_prefix, log_error, print_human_summary};
+
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+pub struct UndoOptions {
+    /// A rename map, as written by a run with [RamboOptions::rename_map](crate::RamboOptions::rename_map).
+    pub rename_map: PathBuf,
+    pub no_dry_run: bool,
+    pub fail_on_vanished: bool,
+}
+
+/// Renames the files of a rename map back to their old names, in the same cycle-safe order as a run, so that chains and swaps of names are resolved.
+/// Copies are removed instead, as their originals were kept. A file is not renamed back when its old name has been taken by another file in the meantime.
+pub fn undo(options: UndoOptions) -> ExitCode {
+    let mut statistics = Statistics::new();
+
+    let current_working_directory = match current_working_directory() {
+        Ok(current_working_directory) => current_working_directory,
+        Err(error) => return log_error(error),
+    };
+
+    let mapped_renames = match read_rename_map(&options.rename_map, &current_working_directory) {
+        Ok(mapped_renames) => mapped_renames,
+        Err(error) => return log_error(error),
+    };
+
+    let is_dry_run = options.no_dry_run.not();
+    let mut removed_copies = 0;
+    let mut renames_back = Vec::<(PathBuf, PathBuf)>::with_capacity(mapped_renames.len());
+
+    for mapped_rename in mapped_renames.into_iter().rev() {
+        if mapped_rename.new_path.exists().not() {
+            log::warn!(
+                "The file vanished since it was renamed: {}",
+                format_path_buf_without_prefix(&mapped_rename.new_path, &current_working_directory)
+            );
+            statistics.vanished_files += 1;
+        } else if mapped_rename.is_copy {
+            if remove_copy(&mapped_rename, is_dry_run, &current_working_directory) {
+                removed_copies += 1;
+            } else {
+                statistics.failed_files += 1;
+            }
+        } else {
+            renames_back.push((mapped_rename.new_path, mapped_rename.old_path));
+        }
+    }
+
+    let rename_settings = RenameSettings {
+        is_dry_run,
+        show_unchanged_in_dry_run: true,
+        store_original_xattr: false,
+        // The old name of a file is never replaced, as it was taken by another file in the meantime.
+        on_conflict: ConflictStrategy::Fail,
+        copy: false,
+        fix_extensions: false,
+        max_path_length: None,
+        normalization: UnicodeNormalization::None,
+        use_trash: false,
+    };
+    rename_files_in_order(
+        &renames_back,
+        &rename_settings,
+        &mut ClaimedTargets::default(),
+        &current_working_directory,
+        &mut statistics,
+    );
+
+    print_human_summary(&statistics, false);
+    if removed_copies > 0 {
+        println!("Removed copies: {}", removed_copies);
+    }
+
+    if is_dry_run {
+        log::warn!("This was just a dry run. To actually undo the renaming, use the '--no-dry-run' flag.")
+    }
+
+    if statistics.failed_files > 0 || (options.fail_on_vanished && statistics.vanished_files > 0) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// A copy is only removed while its original still exists, so that undoing never loses a file. Returns whether the copy was (or would be) removed.
+fn remove_copy(mapped_rename: &MappedRename, is_dry_run: bool, current_working_directory: &Path) -> bool {
+    let clean_file_name_copy = format_path_buf_without_prefix(&mapped_rename.new_path, current_working_directory);
+    let clean_file_name_original = format_path_buf_without_prefix(&mapped_rename.old_path, current_working_directory);
+
+    if mapped_rename.old_path.exists().not() {
+        log::warn!(
+            "Cannot remove the copy {}, because its original {} does not exist anymore",
+            clean_file_name_copy,
+            clean_file_name_original
+        );
+        return false;
+    }
+
+    if is_dry_run {
+        log::info!("[DRY RUN] Removing copy: {} (of {})", clean_file_name_copy, clean_file_name_original);
+        return true;
+    }
+
+    match std::fs::remove_file(&mapped_rename.new_path) {
+        Ok(_) => {
+            log::info!("Removing copy: {} (of {})", clean_file_name_copy, clean_file_name_original);
+            true
+        }
+        Err(error) => {
+            log::warn!("Failed to remove the copy {}: {}", clean_file_name_copy, error);
+            false
+        }
+    }
+}
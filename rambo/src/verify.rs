@@ -0,0 +1,163 @@
+use crate::counter::{Counter, CounterReset};
+use crate::extract::{Backend, DateSource, ExtractionSettings, Fallback};
+use crate::extractor::ExtractorChain;
+use crate::input::InputSource;
+use crate::naming::{FsCompat, UnicodeNormalization, sanitize_file_name};
+use crate::rename::{original_file_stem, requested_file_path};
+use crate::sidecar::remove_xmp_sidecars;
+use crate::statistics::Statistics;
+use crate::template::{Template, TemplateContext};
+use crate::{
+    collect_paths_or_exit, current_working_directory, datetime_transform, datetime_with_sequence, extract_creation_datetimes, format_path_buf_without_prefix,
+    log_error, record_extraction,
+};
+
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+pub struct VerifyOptions {
+    pub input: InputSource,
+    pub case_insensitive: bool,
+    pub include_symlinks: bool,
+    /// The maximum depth of the walked directories, 1 meaning only the files directly inside. `None` for no limit.
+    pub max_depth: Option<usize>,
+    pub format: String,
+    pub template_file: Option<PathBuf>,
+    pub counter_reset: CounterReset,
+    pub replacement_char: char,
+    pub fs_compat: Option<FsCompat>,
+    pub normalize: UnicodeNormalization,
+    pub time_zone: Option<String>,
+    pub shift: Option<String>,
+    /// Where the creation datetime is taken from, tried in order.
+    pub date_sources: Vec<DateSource>,
+    pub backend: Backend,
+    pub extractor_cmd: Option<String>,
+    pub fallbacks: Vec<Fallback>,
+    /// Whether the creation datetimes of PDFs and OOXML documents are read, from their document properties.
+    pub documents: bool,
+    pub jobs: usize,
+}
+
+/// Prints each file whose name does not match the name it would be renamed to, without renaming anything.
+/// A name with a suffix like `-1`, as it is given on a conflict, counts as matching.
+pub fn verify(options: VerifyOptions) -> ExitCode {
+    let mut statistics = Statistics::new();
+
+    let current_working_directory = match current_working_directory() {
+        Ok(current_working_directory) => current_working_directory,
+        Err(error) => return log_error(error),
+    };
+
+    let datetime_transform = match datetime_transform(options.time_zone.as_deref(), options.shift.as_deref(), &BTreeMap::new()) {
+        Ok(datetime_transform) => datetime_transform,
+        Err(error) => return log_error(error),
+    };
+
+    let template = match &options.template_file {
+        None => Template::parse_format(&options.format),
+        Some(template_file) => Template::from_file(template_file),
+    };
+    let template = match template {
+        Ok(template) => template,
+        Err(error) => {
+            log::error!("{:#}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut paths = match collect_paths_or_exit(
+        &options.input,
+        options.case_insensitive,
+        options.include_symlinks,
+        options.max_depth,
+        &mut statistics,
+    ) {
+        Ok(paths) => paths,
+        Err(exit_code) => return exit_code,
+    };
+
+    if options.date_sources.contains(&DateSource::Xmp) {
+        remove_xmp_sidecars(&mut paths);
+    }
+
+    let extractor_chain = ExtractorChain::from_settings(&ExtractionSettings {
+        date_sources: options.date_sources,
+        backend: options.backend,
+        extractor_cmd: options.extractor_cmd,
+        fallbacks: options.fallbacks,
+        documents: options.documents,
+    });
+
+    let mut matching_files = 0;
+    let mut mismatching_files = 0;
+    let mut counter = Counter::new(options.counter_reset);
+    let mut files_per_second = HashMap::new();
+
+    for extraction in extract_creation_datetimes(paths, options.jobs, extractor_chain) {
+        let Ok((path_buf, metadata)) = record_extraction(extraction, &current_working_directory, &mut statistics) else {
+            continue;
+        };
+
+        let datetime = datetime_transform.apply(&metadata);
+
+        let original_file_stem = original_file_stem(&path_buf);
+        let expected_name = template.render(&TemplateContext {
+            datetime: &datetime_with_sequence(&datetime, metadata.has_subseconds, &mut files_per_second),
+            metadata: &metadata,
+            original_file_name: &original_file_stem,
+            directory_name: path_buf.parent().and_then(Path::file_name).unwrap_or_default(),
+            file_path: Some(&path_buf),
+            counter: counter.next(&path_buf, &datetime),
+        });
+        let expected_name = options
+            .normalize
+            .normalize(&sanitize_file_name(&expected_name, options.replacement_char, options.fs_compat));
+        let expected_path = requested_file_path(&path_buf, path_buf.parent().unwrap_or(Path::new("")), &expected_name, false);
+
+        if has_expected_name(&path_buf, &expected_path, options.normalize) {
+            matching_files += 1;
+        } else {
+            mismatching_files += 1;
+            println!(
+                "{}: expected {}",
+                format_path_buf_without_prefix(&path_buf, &current_working_directory),
+                expected_path.file_name().unwrap_or_default().to_string_lossy()
+            );
+        }
+    }
+
+    println!("==============================");
+    println!("Failed files:      {}", statistics.failed_files);
+    println!("Matching files:    {}", matching_files);
+    println!("Mismatching files: {}", mismatching_files);
+
+    if statistics.failed_files > 0 || mismatching_files > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn has_expected_name(path: &Path, expected_path: &Path, normalization: UnicodeNormalization) -> bool {
+    if normalization.is_same_path(path, expected_path) {
+        return true;
+    }
+
+    let (Some(stem), Some(expected_stem)) = (path.file_stem(), expected_path.file_stem()) else {
+        return false;
+    };
+
+    path.extension() == expected_path.extension() && is_suffixed_stem(stem, expected_stem)
+}
+
+/// Whether the stem is the expected one with a suffix like `-1`, as it is appended on a conflict.
+fn is_suffixed_stem(stem: &OsStr, expected_stem: &OsStr) -> bool {
+    stem.to_string_lossy()
+        .strip_prefix(expected_stem.to_string_lossy().as_ref())
+        .and_then(|suffix| suffix.strip_prefix('-'))
+        .is_some_and(|counter| counter.is_empty().not() && counter.chars().all(|char| char.is_ascii_digit()))
+}
@@ -0,0 +1,53 @@
+use crate::glob::{GlobEvaluationError, sort_paths_and_errors};
+
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// An alternative to [crate::glob::evaluate_files_from_glob_patterns] that collects the files in a directory, optionally including all subdirectories.
+/// `max_depth` limits the recursion, 1 meaning only the files directly in the directory.
+pub fn evaluate_files_from_directory(
+    directory: &Path,
+    recursive: bool,
+    max_depth: Option<usize>,
+    include_symlinks: bool,
+) -> (Vec<PathBuf>, Vec<GlobEvaluationError>) {
+    let max_depth = if recursive {
+        max_depth.unwrap_or(usize::MAX)
+    } else {
+        1
+    };
+
+    let walk_entries = WalkDir::new(directory).min_depth(1).max_depth(max_depth).follow_links(include_symlinks);
+
+    let (mut paths, mut errors) = walk_entries.into_iter().fold(
+        (Vec::<PathBuf>::new(), Vec::<GlobEvaluationError>::new()),
+        |(mut paths, mut errors), walk_result| {
+            match walk_result {
+                Ok(entry) if entry.file_type().is_file() => match entry.path().canonicalize() {
+                    Ok(path) => paths.push(path),
+                    Err(error) => {
+                        let error_description = format!("Failed to canonicalize path '{}': {}", entry.path().display(), error);
+                        errors.push(GlobEvaluationError::Other {
+                            path_buf: entry.into_path(),
+                            description: error_description,
+                        });
+                    }
+                },
+                Ok(_) => {}
+                Err(error) => {
+                    let path_buf = error.path().map(Path::to_path_buf).unwrap_or_else(|| directory.to_path_buf());
+                    errors.push(GlobEvaluationError::Other {
+                        path_buf,
+                        description: error.to_string(),
+                    });
+                }
+            };
+
+            (paths, errors)
+        },
+    );
+
+    sort_paths_and_errors(&mut paths, &mut errors);
+
+    (paths, errors)
+}
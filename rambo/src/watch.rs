@@ -0,0 +1,238 @@
+use crate::cancel::CancellationToken;
+use crate::error::RamboError;
+use crate::glob::{GlobMatcher, PathMatcher, RegexMatcher, glob_root};
+use crate::ignore_file::IgnoreFiles;
+use crate::input::InputSource;
+use crate::statistics::Statistics;
+use crate::{RamboOptions, RunContext, log_error, rename_paths, strip_path_prefix};
+
+use glob::Pattern;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc::{RecvTimeoutError, channel};
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A file that was created or changed, but may still be written to.
+struct PendingFile {
+    last_change: Instant,
+    size: u64,
+}
+
+/// Watches for new files that match one of the patterns or are in the directory of the options and renames them once they are completely written.
+/// A file counts as completely written when its size has not changed for `settle_time`. Runs until the process is terminated or the [crate::CancellationToken] of the options is cancelled.
+pub fn watch(options: RamboOptions, settle_time: Duration) -> ExitCode {
+    let run_context = match RunContext::new(&options) {
+        Ok(run_context) => run_context,
+        Err(error) => return log_error(error),
+    };
+
+    let filter = match WatchFilter::new(&options, &run_context.current_working_directory) {
+        Ok(filter) => filter,
+        Err(error) => return log_error(error),
+    };
+
+    let (sender, receiver) = channel::<notify::Result<Event>>();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            log::error!("Failed to watch for new files: {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    for root in &filter.roots {
+        if let Err(error) = watcher.watch(root, filter.recursive_mode) {
+            log::error!("Failed to watch {}: {}", root.display(), error);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let watched_roots = filter.roots.iter().map(|root| root.display().to_string()).collect::<Vec<_>>();
+    log::info!("Watching {} for new files. Press Ctrl+C to stop.", watched_roots.join(", "));
+
+    let mut pending_files = BTreeMap::<PathBuf, PendingFile>::new();
+    // The renamed files are reported as new files as well, but they must not be processed again.
+    let mut created_files = HashSet::<PathBuf>::new();
+
+    loop {
+        if options.cancellation_token.as_ref().is_some_and(CancellationToken::is_cancelled) {
+            log::info!("Stopped watching for new files");
+            return ExitCode::SUCCESS;
+        }
+
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                for path in event.paths {
+                    if created_files.contains(&path).not()
+                        && filter.matches(&path)
+                        && let Ok(metadata) = path.metadata()
+                        && metadata.is_file()
+                    {
+                        pending_files.insert(
+                            path,
+                            PendingFile {
+                                last_change: Instant::now(),
+                                size: metadata.len(),
+                            },
+                        );
+                    }
+                }
+            }
+            Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Ok(Err(error)) => log::warn!("Failed to receive changes of the watched files: {}", error),
+            Err(RecvTimeoutError::Disconnected) => {
+                log::error!("Stopped receiving changes of the watched files");
+                return ExitCode::FAILURE;
+            }
+        }
+
+        let settled_paths = take_settled_files(&mut pending_files, settle_time);
+        if settled_paths.is_empty() {
+            continue;
+        }
+
+        let report = rename_paths(&options, &run_context, settled_paths, Statistics::new());
+        created_files.extend(report.file_outcomes.into_iter().filter_map(|file_outcome| file_outcome.new_path));
+    }
+}
+
+/// Removes the files whose size has not changed for `settle_time` from the pending files and returns their canonical paths.
+/// Files that vanished in the meantime are dropped.
+fn take_settled_files(pending_files: &mut BTreeMap<PathBuf, PendingFile>, settle_time: Duration) -> Vec<PathBuf> {
+    let mut settled_paths = Vec::new();
+
+    pending_files.retain(|path, pending_file| {
+        let Ok(metadata) = path.metadata() else {
+            return false;
+        };
+
+        if metadata.len() != pending_file.size {
+            pending_file.size = metadata.len();
+            pending_file.last_change = Instant::now();
+            return true;
+        }
+
+        if pending_file.last_change.elapsed() < settle_time {
+            return true;
+        }
+
+        match path.canonicalize() {
+            Ok(canonical_path) => settled_paths.push(canonical_path),
+            Err(error) => log::warn!("Failed to canonicalize path '{}': {}", path.display(), error),
+        }
+        false
+    });
+
+    settled_paths
+}
+
+/// Decides which of the changed files are processed, in the same way as the files of a normal run are collected.
+struct WatchFilter {
+    /// The directories which contain the files that may match, without directories which are inside another one.
+    roots: Vec<PathBuf>,
+    recursive_mode: RecursiveMode,
+    /// `None` if all files in the root directory are processed.
+    matchers: Option<Vec<Box<dyn PathMatcher>>>,
+    include_symlinks: bool,
+    current_working_directory: PathBuf,
+}
+
+impl WatchFilter {
+    fn new(options: &RamboOptions, current_working_directory: &Path) -> Result<Self, RamboError> {
+        let current_working_directory = current_working_directory.canonicalize().map_err(RamboError::CurrentWorkingDirectory)?;
+
+        let (roots, recursive_mode, matchers) = match &options.input {
+            InputSource::Directory { path, recursive } => {
+                let recursive_mode = if *recursive {
+                    RecursiveMode::Recursive
+                } else {
+                    RecursiveMode::NonRecursive
+                };
+                (vec![path.clone()], recursive_mode, None)
+            }
+            InputSource::GlobPatterns(patterns) => {
+                // Like in a normal run, a directory stands for all files in it and its subdirectories.
+                let patterns = patterns
+                    .iter()
+                    .map(|pattern| match Path::new(pattern).is_dir() {
+                        true => directory_pattern(pattern),
+                        false => pattern.clone(),
+                    })
+                    .collect::<Vec<_>>();
+                let matchers = patterns
+                    .iter()
+                    .map(|pattern| GlobMatcher::new(pattern, options.case_insensitive).map(|matcher| Box::new(matcher) as Box<dyn PathMatcher>))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let roots = patterns.iter().map(|pattern| glob_root(pattern)).collect();
+                (roots, RecursiveMode::Recursive, Some(matchers))
+            }
+            InputSource::Regex(pattern) => {
+                let matcher = RegexMatcher::new(pattern, options.case_insensitive)?;
+                (
+                    vec![current_working_directory.clone()],
+                    RecursiveMode::Recursive,
+                    Some(vec![Box::new(matcher) as Box<dyn PathMatcher>]),
+                )
+            }
+            InputSource::FileList { .. } => {
+                return Err(RamboError::InvalidInput(
+                    "Cannot watch for new files of a file list, use a glob pattern, a regex or a directory instead".to_string(),
+                ));
+            }
+        };
+
+        let mut roots = roots
+            .into_iter()
+            .map(|root| {
+                let root = current_working_directory.join(root);
+                root.canonicalize()
+                    .map_err(RamboError::io(format!("Cannot watch directory '{}'", root.display())))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // Watching a directory inside another watched one would report each change twice.
+        roots.sort();
+        roots.dedup_by(|root, other_root| root.starts_with(other_root));
+
+        Ok(Self {
+            roots,
+            recursive_mode,
+            matchers,
+            include_symlinks: options.include_symlinks,
+            current_working_directory,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        if self.include_symlinks.not() && path.is_symlink() {
+            return false;
+        }
+
+        // The ignore files are read again for each file, so that changes to them apply while watching.
+        if IgnoreFiles::default().is_ignored(path) {
+            return false;
+        }
+
+        match &self.matchers {
+            Some(matchers) => {
+                let relative_path = strip_path_prefix(path, &self.current_working_directory);
+                matchers.iter().any(|matcher| matcher.matches_path(relative_path))
+            }
+            None => self.recursive_mode == RecursiveMode::Recursive || self.roots.iter().any(|root| path.parent() == Some(root.as_path())),
+        }
+    }
+}
+
+/// The glob pattern which matches all files in the directory and its subdirectories.
+fn directory_pattern(directory: &str) -> String {
+    let directory = directory.trim_end_matches('/');
+    let directory = directory.strip_prefix("./").unwrap_or(directory);
+
+    match directory {
+        "" | "." => "**/*".to_string(),
+        _ => format!("{}/**/*", Pattern::escape(directory)),
+    }
+}
@@ -0,0 +1,163 @@
+use crate::storage::{ObjectStorage, StorageError};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use percent_encoding::{AsciiSet, CONTROLS, percent_decode_str, utf8_percent_encode};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use std::io::Read;
+
+/// The characters which are percent-encoded in a segment of a path.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// The files of a WebDAV server, like the auto-upload folder of Nextcloud. The keys are the paths relative to the URL of the storage.
+pub struct WebDavStorage {
+    agent: ureq::Agent,
+    /// Without a trailing slash, like `https://cloud.example.com/remote.php/dav/files/alice`.
+    base_url: String,
+    /// The path of the base URL, which the hrefs of the server start with.
+    base_path: String,
+    authorization: Option<String>,
+}
+
+impl WebDavStorage {
+    /// The URL of Nextcloud is like `https://cloud.example.com/remote.php/dav/files/<USER>`. Use an app password instead of the password of the account.
+    pub fn new(url: &str, user: Option<&str>, password: Option<&str>) -> Self {
+        let base_url = url.trim_end_matches('/').to_string();
+        let base_path = base_url
+            .split_once("://")
+            .and_then(|(_, location)| location.find('/').map(|index| location[index..].to_string()))
+            .unwrap_or_default();
+        let authorization = user.map(|user| format!("Basic {}", BASE64.encode(format!("{}:{}", user, password.unwrap_or_default()))));
+
+        Self {
+            agent: ureq::Agent::new(),
+            base_url,
+            base_path,
+            authorization,
+        }
+    }
+
+    fn url(&self, key: &str) -> String {
+        let encoded_key = key
+            .split('/')
+            .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+            .collect::<Vec<_>>();
+
+        format!("{}/{}", self.base_url, encoded_key.join("/"))
+    }
+
+    fn request(&self, method: &str, key: &str) -> ureq::Request {
+        let request = self.agent.request(method, &self.url(key));
+
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    /// Returns the keys of the files and of the subdirectories in the directory, which is a key ending with `/` or empty for the root.
+    fn read_directory(&self, directory: &str) -> Result<(Vec<String>, Vec<String>), StorageError> {
+        let response = self
+            .request("PROPFIND", directory)
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml")
+            .send_string(r#"<?xml version="1.0"?><d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/></d:prop></d:propfind>"#)?;
+
+        let mut files = Vec::new();
+        let mut directories = Vec::new();
+        for (href, is_collection) in parse_multistatus(&response.into_string()?)? {
+            let path = percent_decode_str(&href).decode_utf8_lossy().into_owned();
+            let Some(key) = path.strip_prefix(&self.base_path).map(|key| key.trim_start_matches('/')) else {
+                continue;
+            };
+
+            match is_collection {
+                true if key.trim_end_matches('/') != directory.trim_end_matches('/') => directories.push(format!("{}/", key.trim_end_matches('/'))),
+                true => {}
+                false => files.push(key.to_string()),
+            }
+        }
+
+        Ok((files, directories))
+    }
+}
+
+/// The href of each response of a `PROPFIND`, and whether it is a collection, i.e. a directory.
+fn parse_multistatus(xml: &str) -> Result<Vec<(String, bool)>, StorageError> {
+    let mut reader = Reader::from_str(xml);
+    let mut responses = Vec::new();
+    let mut href = String::new();
+    let mut is_collection = false;
+    let mut is_in_href = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) if start.local_name().as_ref() == b"response" => {
+                href.clear();
+                is_collection = false;
+            }
+            Event::Start(start) if start.local_name().as_ref() == b"href" => is_in_href = true,
+            Event::End(end) if end.local_name().as_ref() == b"href" => is_in_href = false,
+            Event::Text(text) if is_in_href => href.push_str(text.xml_content()?.trim()),
+            Event::Start(start) | Event::Empty(start) if start.local_name().as_ref() == b"collection" => is_collection = true,
+            Event::End(end) if end.local_name().as_ref() == b"response" => responses.push((href.clone(), is_collection)),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(responses)
+}
+
+impl ObjectStorage for WebDavStorage {
+    /// Walks the directory of the prefix and all its subdirectories, one `PROPFIND` per directory, as many servers refuse `Depth: infinity`.
+    fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let mut directories = vec![prefix.rfind('/').map(|index| prefix[..=index].to_string()).unwrap_or_default()];
+        let mut keys = Vec::new();
+
+        while let Some(directory) = directories.pop() {
+            let (files, subdirectories) = self.read_directory(&directory)?;
+            keys.extend(files.into_iter().filter(|key| key.starts_with(prefix)));
+            directories.extend(subdirectories);
+        }
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    fn read_head(&self, key: &str, length: u64) -> Result<Vec<u8>, StorageError> {
+        let response = self.request("GET", key).set("Range", &format!("bytes=0-{}", length.saturating_sub(1))).call()?;
+
+        // A server which ignores the range sends the whole file.
+        let mut head = Vec::new();
+        response.into_reader().take(length).read_to_end(&mut head)?;
+
+        Ok(head)
+    }
+
+    fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        match self.request("HEAD", key).call() {
+            Ok(_) => Ok(true),
+            Err(ureq::Error::Status(404, _)) => Ok(false),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// A `MOVE` request, which overwrites a taken target. This only happens with [crate::ConflictStrategy::Overwrite].
+    fn rename(&self, key: &str, new_key: &str) -> Result<(), StorageError> {
+        self.request("MOVE", key).set("Destination", &self.url(new_key)).set("Overwrite", "T").call()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use crate::extract::{XMP_PROPERTIES_FOR_CREATION_DATETIME, extract_creation_datetime, in_local_time_zone};
+use crate::input::InputSource;
+use crate::sidecar::{extract_creation_datetime_from_xmp_sidecar, find_xmp_sidecar, remove_xmp_sidecars};
+use crate::statistics::Statistics;
+use crate::{collect_paths_or_exit, current_working_directory, format_path_buf_without_prefix, log_error};
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use little_exif::exif_tag::ExifTag;
+use little_exif::metadata::Metadata;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// The error of little_exif for a file which has no EXIF data yet, which is the only case a file gets new EXIF data.
+/// For any other error the file is left untouched, as its existing EXIF data would be lost.
+const NO_EXIF_DATA_FOUND: &str = "No EXIF data found!";
+
+pub struct WriteExifOptions {
+    pub input: InputSource,
+    pub case_insensitive: bool,
+    pub include_symlinks: bool,
+    /// The maximum depth of the walked directories, 1 meaning only the files directly inside. `None` for no limit.
+    pub max_depth: Option<usize>,
+    /// The format which the names of the files are parsed with, like `%Y-%m-%d_%H-%M-%S`. Anything after the datetime, like a suffix, is ignored.
+    pub from_name: String,
+    /// Write an XMP sidecar instead of the EXIF data of the file, e.g. for videos or to leave the files untouched.
+    pub xmp_sidecar: bool,
+    pub no_dry_run: bool,
+}
+
+/// Parses the creation datetime from the name of each file and writes it to its `DateTimeOriginal` EXIF tag, or to its XMP sidecar.
+/// This is the inverse of a run, for files whose names encode their datetime but whose metadata is missing or wrong. Files whose metadata already has the datetime are left untouched.
+pub fn write_exif(options: WriteExifOptions) -> ExitCode {
+    let mut statistics = Statistics::new();
+
+    let current_working_directory = match current_working_directory() {
+        Ok(current_working_directory) => current_working_directory,
+        Err(error) => return log_error(error),
+    };
+
+    let mut paths = match collect_paths_or_exit(
+        &options.input,
+        options.case_insensitive,
+        options.include_symlinks,
+        options.max_depth,
+        &mut statistics,
+    ) {
+        Ok(paths) => paths,
+        Err(exit_code) => return exit_code,
+    };
+    remove_xmp_sidecars(&mut paths);
+
+    let is_dry_run = options.no_dry_run.not();
+    let mut unchanged_files = 0;
+    let mut written_files = 0;
+
+    for path_buf in paths {
+        let clean_file_name = format_path_buf_without_prefix(&path_buf, &current_working_directory);
+
+        let Some(datetime) = parse_datetime_from_file_name(&path_buf, &options.from_name) else {
+            log::warn!(
+                "Skipping {}, because its name does not match the format '{}'",
+                clean_file_name,
+                options.from_name
+            );
+            statistics.skipped_files += 1;
+            continue;
+        };
+
+        let existing_datetime = match options.xmp_sidecar {
+            true => extract_creation_datetime_from_xmp_sidecar(&path_buf).and_then(Result::ok),
+            false => extract_creation_datetime(&path_buf).ok().map(|extracted_datetime| extracted_datetime.datetime),
+        };
+        if existing_datetime.is_some_and(|existing_datetime| existing_datetime.naive_local() == datetime) {
+            log::debug!("{} has already the creation datetime {}", clean_file_name, datetime);
+            unchanged_files += 1;
+            continue;
+        }
+
+        let target = if options.xmp_sidecar {
+            "its XMP sidecar"
+        } else {
+            "its EXIF data"
+        };
+        if is_dry_run {
+            log::info!("[DRY RUN] Writing {} to {} of {}", datetime, target, clean_file_name);
+            written_files += 1;
+            continue;
+        }
+
+        let result = match options.xmp_sidecar {
+            true => write_datetime_to_xmp_sidecar(&path_buf, datetime),
+            false => write_datetime_to_exif(&path_buf, datetime),
+        };
+        match result {
+            Ok(_) => {
+                log::info!("Writing {} to {} of {}", datetime, target, clean_file_name);
+                written_files += 1;
+            }
+            Err(error) => {
+                log::warn!("Failed to write the creation datetime of {}: {}", clean_file_name, error);
+                statistics.failed_files += 1;
+            }
+        }
+    }
+
+    println!("==============================");
+    println!("Failed files:    {}", statistics.failed_files);
+    println!("Skipped files:   {}", statistics.skipped_files);
+    println!("Unchanged files: {}", unchanged_files);
+    println!("Written files:   {}", written_files);
+
+    if is_dry_run {
+        log::warn!("This was just a dry run. To actually write the creation datetimes, use the '--no-dry-run' flag.")
+    }
+
+    if statistics.failed_files > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// A format without a time, like `%Y-%m-%d`, yields the start of the day.
+fn parse_datetime_from_file_name(file_path: &Path, format: &str) -> Option<NaiveDateTime> {
+    let file_stem = file_path.file_stem()?.to_str()?;
+
+    NaiveDateTime::parse_and_remainder(file_stem, format)
+        .map(|(datetime, _)| datetime)
+        .or_else(|_| NaiveDate::parse_and_remainder(file_stem, format).map(|(date, _)| date.and_time(NaiveTime::MIN)))
+        .ok()
+}
+
+/// The offset of the local time zone is written to `OffsetTimeOriginal`, as the datetime is interpreted in it when it is read again.
+fn write_datetime_to_exif(file_path: &Path, datetime: NaiveDateTime) -> std::io::Result<()> {
+    let offset = in_local_time_zone(datetime).map_err(std::io::Error::other)?.offset().to_string();
+    let mut metadata = match Metadata::new_from_path(file_path) {
+        Ok(metadata) => metadata,
+        Err(error) if error.to_string() == NO_EXIF_DATA_FOUND => Metadata::new(),
+        Err(error) => return Err(error),
+    };
+
+    metadata.set_tag(ExifTag::DateTimeOriginal(datetime.format("%Y:%m:%d %H:%M:%S").to_string()));
+    metadata.set_tag(ExifTag::OffsetTimeOriginal(offset));
+    metadata.write_to_file(file_path)
+}
+
+/// An existing sidecar keeps its content, only the properties of the creation datetime are set. Otherwise, `<FILE NAME>.xmp` is created.
+fn write_datetime_to_xmp_sidecar(file_path: &Path, datetime: NaiveDateTime) -> std::io::Result<()> {
+    let value = datetime.format("%Y-%m-%dT%H:%M:%S").to_string();
+
+    let Some(sidecar_path) = find_xmp_sidecar(file_path) else {
+        let mut sidecar_path = file_path.as_os_str().to_owned();
+        sidecar_path.push(".xmp");
+        return std::fs::write(PathBuf::from(sidecar_path), new_xmp_packet(&value));
+    };
+
+    let xmp = std::fs::read_to_string(&sidecar_path)?;
+    let updated_xmp = set_xmp_creation_datetime(&xmp, &value)
+        .ok_or_else(|| std::io::Error::other(format!("The XMP sidecar '{}' has no 'rdf:Description'", sidecar_path.display())))?;
+    std::fs::write(sidecar_path, updated_xmp)
+}
+
+fn new_xmp_packet(value: &str) -> String {
+    format!(
+        r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about="" xmlns:exif="http://ns.adobe.com/exif/1.0/" exif:DateTimeOriginal="{}"/>
+ </rdf:RDF>
+</x:xmpmeta>
+"#,
+        value
+    )
+}
+
+/// Replaces the values of the properties of the creation datetime which are present, as attributes or as elements.
+/// If none is present, `exif:DateTimeOriginal` is added to the first `rdf:Description`.
+fn set_xmp_creation_datetime(xmp: &str, value: &str) -> Option<String> {
+    let mut updated_xmp = xmp.to_string();
+    let mut is_present = false;
+
+    for property in XMP_PROPERTIES_FOR_CREATION_DATETIME {
+        for (start, end) in [format!("{}=\"", property), format!("{}='", property), format!("<{}>", property)]
+            .into_iter()
+            .zip(["\"", "'", "<"])
+        {
+            if let Some(value_start) = updated_xmp.find(&start).map(|index| index + start.len())
+                && let Some(value_length) = updated_xmp[value_start..].find(end)
+            {
+                updated_xmp.replace_range(value_start..value_start + value_length, value);
+                is_present = true;
+            }
+        }
+    }
+    if is_present {
+        return Some(updated_xmp);
+    }
+
+    let description_start = updated_xmp.find("<rdf:Description")?;
+    let attribute_index = description_start + "<rdf:Description".len();
+    let namespace = match updated_xmp.contains("xmlns:exif=") {
+        true => "",
+        false => r#" xmlns:exif="http://ns.adobe.com/exif/1.0/""#,
+    };
+    updated_xmp.insert_str(attribute_index, &format!(r#"{} exif:DateTimeOriginal="{}""#, namespace, value));
+    Some(updated_xmp)
+}
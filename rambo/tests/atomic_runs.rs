@@ -0,0 +1,52 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{EXTRACTOR_READING_THE_CONTENT, options_for, temporary_directory};
+use rambo::{ConflictStrategy, RenameAction, run};
+use std::ops::Not;
+
+#[test]
+fn renames_nothing_when_one_file_cannot_be_renamed() {
+    let directory = temporary_directory("atomic-invalid");
+    std::fs::write(directory.join("2024-05-01__10-00-00.jpg"), "2024-05-01T10:00:00+02:00\nexisting").unwrap();
+    std::fs::write(directory.join("IMG_0001.jpg"), "2024-05-01T10:00:00+02:00\nconflicting").unwrap();
+    std::fs::write(directory.join("IMG_0002.jpg"), "2024-05-01T11:00:00+02:00\nvalid").unwrap();
+
+    let report = run(options_for(&directory)
+        .extractor_cmd(EXTRACTOR_READING_THE_CONTENT.to_string())
+        .on_conflict(ConflictStrategy::Fail)
+        .atomic(true)
+        .build())
+    .expect("the run can be carried out");
+
+    assert!(report.has_failures(false));
+    let failed_outcome = report
+        .file_outcomes
+        .iter()
+        .find(|file_outcome| file_outcome.path == directory.join("IMG_0001.jpg"))
+        .expect("the conflicting file has an outcome");
+    assert_eq!(failed_outcome.action, RenameAction::Failed);
+    assert!(directory.join("IMG_0001.jpg").exists());
+    assert!(directory.join("IMG_0002.jpg").exists());
+    assert!(directory.join("2024-05-01__11-00-00.jpg").exists().not());
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 3);
+}
+
+#[test]
+fn renames_all_files_when_every_file_can_be_renamed() {
+    let directory = temporary_directory("atomic-valid");
+    std::fs::write(directory.join("IMG_0001.jpg"), "2024-05-01T10:00:00+02:00\nfirst").unwrap();
+    std::fs::write(directory.join("IMG_0002.jpg"), "2024-05-01T11:00:00+02:00\nsecond").unwrap();
+
+    let report = run(options_for(&directory)
+        .extractor_cmd(EXTRACTOR_READING_THE_CONTENT.to_string())
+        .atomic(true)
+        .build())
+    .expect("the run can be carried out");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2024-05-01__10-00-00.jpg").exists());
+    assert!(directory.join("2024-05-01__11-00-00.jpg").exists());
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 2);
+}
@@ -0,0 +1,35 @@
+//! Helpers which are shared by the integration tests.
+#![allow(dead_code)]
+
+use rambo::{InputSource, RamboOptions, RamboOptionsBuilder};
+use std::path::{Path, PathBuf};
+
+/// Prints the datetime for every file, as an external extractor, so that the files of the tests need no metadata.
+pub fn extractor_printing(datetime: &str) -> String {
+    // `printf` repeats its format for the path which is appended, so only the first line matters.
+    format!("printf {}\\n", datetime)
+}
+
+/// Reads the datetime from the first line of each file, as an external extractor, so that every file of a test can have its own datetime.
+pub const EXTRACTOR_READING_THE_CONTENT: &str = "head -n 1";
+
+/// A fresh directory per test, so that the tests can run in parallel.
+pub fn temporary_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("rambo-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).expect("the temporary directory can be created");
+    directory
+}
+
+/// A real run over the files directly inside the directory, without a rename map in the working directory and with the defaults of the command line otherwise.
+pub fn options_for(directory: &Path) -> RamboOptionsBuilder {
+    let mut builder = RamboOptions::builder();
+    builder
+        .input(InputSource::Directory {
+            path: directory.to_path_buf(),
+            recursive: false,
+        })
+        .no_dry_run(true)
+        .backup_map(false);
+    builder
+}
@@ -0,0 +1,70 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{extractor_printing, options_for, temporary_directory};
+use rambo::{ConflictStrategy, RunReport, run};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+const TARGET_FILE_NAME: &str = "2024-05-01__10-00-00.jpg";
+
+/// Runs over a directory in which the target name of `IMG_0001.jpg` is already taken by another file.
+fn run_with_conflict(name: &str, on_conflict: ConflictStrategy) -> (RunReport, PathBuf) {
+    let directory = temporary_directory(name);
+    std::fs::write(directory.join(TARGET_FILE_NAME), "existing").unwrap();
+    std::fs::write(directory.join("IMG_0001.jpg"), "new").unwrap();
+
+    let report = run(options_for(&directory)
+        .extractor_cmd(extractor_printing("2024-05-01T10:00:00+02:00"))
+        .on_conflict(on_conflict)
+        .build())
+    .expect("the run can be carried out");
+
+    (report, directory)
+}
+
+fn read(directory: &Path, file_name: &str) -> String {
+    std::fs::read_to_string(directory.join(file_name)).unwrap()
+}
+
+#[test]
+fn skip_leaves_both_files_untouched() {
+    let (report, directory) = run_with_conflict("conflict-skip", ConflictStrategy::Skip);
+
+    assert!(report.has_failures(false).not());
+    assert_eq!(report.statistics.conflicting_files, 1);
+    assert_eq!(read(&directory, TARGET_FILE_NAME), "existing");
+    assert_eq!(read(&directory, "IMG_0001.jpg"), "new");
+}
+
+#[test]
+fn fail_leaves_both_files_untouched_and_reports_a_failure() {
+    let (report, directory) = run_with_conflict("conflict-fail", ConflictStrategy::Fail);
+
+    assert!(report.has_failures(false));
+    assert_eq!(report.statistics.conflicting_files, 1);
+    assert_eq!(read(&directory, TARGET_FILE_NAME), "existing");
+    assert_eq!(read(&directory, "IMG_0001.jpg"), "new");
+}
+
+#[test]
+fn overwrite_replaces_the_existing_file() {
+    let (report, directory) = run_with_conflict("conflict-overwrite", ConflictStrategy::Overwrite);
+
+    assert!(report.has_failures(false).not());
+    assert_eq!(report.statistics.conflicting_files, 1);
+    assert_eq!(read(&directory, TARGET_FILE_NAME), "new");
+    assert!(directory.join("IMG_0001.jpg").exists().not());
+}
+
+#[test]
+fn suffix_renames_to_the_first_free_name() {
+    let (report, directory) = run_with_conflict("conflict-suffix", ConflictStrategy::Suffix);
+
+    assert!(report.has_failures(false).not());
+    assert_eq!(report.statistics.conflicting_files, 1);
+    assert_eq!(read(&directory, TARGET_FILE_NAME), "existing");
+    assert_eq!(read(&directory, "2024-05-01__10-00-00-1.jpg"), "new");
+    assert!(directory.join("IMG_0001.jpg").exists().not());
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use common::{options_for, temporary_directory};
+use rambo::{Fallback, RunReport, run};
+use std::ops::Not;
+use std::path::PathBuf;
+
+/// Renames a file without metadata, so its creation datetime can only be parsed from its name.
+fn rename_with_name_fallback(test_name: &str, file_name: &str) -> (RunReport, PathBuf) {
+    let directory = temporary_directory(test_name);
+    std::fs::write(directory.join(file_name), "no metadata").unwrap();
+
+    let report = run(options_for(&directory).fallbacks(vec![Fallback::Name]).build()).expect("the run can be carried out");
+
+    (report, directory)
+}
+
+#[test]
+fn parses_the_name_of_a_camera_file() {
+    let (report, directory) = rename_with_name_fallback("fallback-camera", "IMG_20190704_183012.jpg");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2019-07-04__18-30-12.jpg").exists());
+}
+
+#[test]
+fn parses_the_name_of_a_screenshot() {
+    let (report, directory) = rename_with_name_fallback("fallback-screenshot", "Screenshot_20230101-120000.png");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2023-01-01__12-00-00.png").exists());
+}
+
+#[test]
+fn parses_the_date_of_a_whatsapp_file_as_midnight() {
+    let (report, directory) = rename_with_name_fallback("fallback-whatsapp", "IMG-20230101-WA0001.jpg");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2023-01-01__00-00-00.jpg").exists());
+}
+
+#[test]
+fn parses_the_name_of_a_macos_screenshot() {
+    let (report, directory) = rename_with_name_fallback("fallback-macos-screenshot", "Screenshot 2024-03-01 at 10.15.32.png");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2024-03-01__10-15-32.png").exists());
+}
+
+#[test]
+fn parses_the_name_of_a_localized_macos_screenshot() {
+    let (report, directory) = rename_with_name_fallback("fallback-localized-screenshot", "Bildschirmfoto 2024-03-01 um 10.15.32.png");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2024-03-01__10-15-32.png").exists());
+}
+
+#[test]
+fn parses_the_12_hour_clock_of_an_older_macos_screenshot() {
+    let (report, directory) = rename_with_name_fallback("fallback-12-hour-screenshot", "Screen Shot 2019-07-04 at 6.30.12\u{202F}PM.png");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2019-07-04__18-30-12.png").exists());
+}
+
+#[test]
+fn parses_the_name_of_a_windows_screenshot() {
+    let (report, directory) = rename_with_name_fallback("fallback-windows-screenshot", "Screenshot 2024-03-01 101532.png");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2024-03-01__10-15-32.png").exists());
+}
+
+#[test]
+fn parses_the_name_of_a_gnome_screenshot() {
+    let (report, directory) = rename_with_name_fallback("fallback-gnome-screenshot", "Screenshot from 2024-03-01 10-15-32.png");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2024-03-01__10-15-32.png").exists());
+}
+
+#[test]
+fn parses_the_name_of_a_whatsapp_web_export() {
+    let (report, directory) = rename_with_name_fallback("fallback-whatsapp-web", "WhatsApp Image 2023-01-05 at 10.15.30.jpeg");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2023-01-05__10-15-30.jpeg").exists());
+}
+
+#[test]
+fn parses_the_name_of_a_signal_export() {
+    let (report, directory) = rename_with_name_fallback("fallback-signal", "signal-2023-01-05-101530.jpg");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2023-01-05__10-15-30.jpg").exists());
+}
+
+#[test]
+fn parses_the_name_of_an_older_signal_export() {
+    let (report, directory) = rename_with_name_fallback("fallback-older-signal", "signal-2023-01-05-10-15-30-123.jpg");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2023-01-05__10-15-30.jpg").exists());
+}
+
+#[test]
+fn parses_the_name_of_a_telegram_export() {
+    let (report, directory) = rename_with_name_fallback("fallback-telegram", "photo_2023-01-05_10-15-30.jpg");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2023-01-05__10-15-30.jpg").exists());
+}
+
+#[test]
+fn parses_the_default_format_of_rambo_itself() {
+    let (report, directory) = rename_with_name_fallback("fallback-rambo", "2019-07-04__18-30-12.JPG");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("2019-07-04__18-30-12.jpg").exists());
+}
+
+#[test]
+fn fails_for_a_name_without_a_datetime() {
+    let (report, directory) = rename_with_name_fallback("fallback-no-datetime", "holiday_0042.jpg");
+
+    assert!(report.has_failures(false));
+    assert!(directory.join("holiday_0042.jpg").exists());
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+}
+
+#[test]
+fn ignores_counters_which_are_no_plausible_dates() {
+    let (report, directory) = rename_with_name_fallback("fallback-counter", "DSC_00010101_000000.jpg");
+
+    assert!(report.has_failures(false));
+    assert!(directory.join("DSC_00010101_000000.jpg").exists());
+    assert!(directory.join("0001-01-01__00-00-00.jpg").exists().not());
+}
@@ -0,0 +1,43 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{extractor_printing, options_for, temporary_directory};
+use rambo::run;
+use std::ffi::OsStr;
+use std::ops::Not;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+fn create_non_utf8_file(directory: &Path) -> PathBuf {
+    let file_path = directory.join(OsStr::from_bytes(b"\xff.jpg"));
+    std::fs::write(&file_path, b"no metadata").expect("the file can be created");
+    file_path
+}
+
+#[test]
+fn renames_a_file_with_a_non_utf8_name() {
+    let directory = temporary_directory("non-utf8-renamed");
+    let file_path = create_non_utf8_file(&directory);
+
+    let report = run(options_for(&directory).extractor_cmd(extractor_printing("2024-05-01T10:00:00+02:00")).build()).expect("the run can be carried out");
+
+    assert!(report.has_failures(false).not());
+    assert!(file_path.exists().not());
+    assert!(directory.join("2024-05-01__10-00-00.jpg").is_file());
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn reports_a_file_with_a_non_utf8_name_whose_datetime_cannot_be_extracted() {
+    let directory = temporary_directory("non-utf8-failed");
+    let file_path = create_non_utf8_file(&directory);
+
+    let report = run(options_for(&directory).build()).expect("the run can be carried out");
+
+    assert!(report.has_failures(false));
+    assert!(file_path.is_file());
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
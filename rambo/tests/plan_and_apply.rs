@@ -0,0 +1,96 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{extractor_printing, options_for, temporary_directory};
+use rambo::{ApplyOptions, ConflictStrategy, PlannedRename, RenamePlan, apply, run};
+use std::ops::Not;
+use std::path::Path;
+use std::process::ExitCode;
+
+fn apply_options(plan_file: &Path) -> ApplyOptions {
+    ApplyOptions {
+        plan_file: plan_file.to_path_buf(),
+        no_dry_run: true,
+        on_conflict: ConflictStrategy::Suffix,
+        use_trash: false,
+        backup_map: false,
+        store_original_xattr: false,
+        fail_on_vanished: false,
+    }
+}
+
+#[test]
+fn a_plan_renames_nothing_until_it_is_applied() {
+    let directory = temporary_directory("plan-then-apply");
+    std::fs::write(directory.join("IMG_0001.jpg"), "content").unwrap();
+    let plan_file = directory.join("plan.json");
+
+    let report = run(options_for(&directory)
+        .extractor_cmd(extractor_printing("2024-05-01T10:00:00+02:00"))
+        .plan_file(plan_file.clone())
+        .build())
+    .expect("the run can be carried out");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("IMG_0001.jpg").exists());
+    assert_eq!(RenamePlan::read_from_file(&plan_file).unwrap().renames.len(), 1);
+
+    let exit_code = apply(apply_options(&plan_file));
+
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    assert!(directory.join("IMG_0001.jpg").exists().not());
+    assert_eq!(std::fs::read_to_string(directory.join("2024-05-01__10-00-00.jpg")).unwrap(), "content");
+}
+
+#[test]
+fn reports_the_files_that_vanished_after_the_plan_was_created() {
+    let directory = temporary_directory("plan-vanished");
+    std::fs::write(directory.join("IMG_0001.jpg"), "content").unwrap();
+    let plan_file = directory.join("plan.json");
+
+    run(options_for(&directory)
+        .extractor_cmd(extractor_printing("2024-05-01T10:00:00+02:00"))
+        .plan_file(plan_file.clone())
+        .build())
+    .expect("the run can be carried out");
+    std::fs::remove_file(directory.join("IMG_0001.jpg")).unwrap();
+
+    let exit_code = apply(ApplyOptions {
+        fail_on_vanished: true,
+        ..apply_options(&plan_file)
+    });
+
+    assert_eq!(exit_code, ExitCode::FAILURE);
+    assert!(directory.join("2024-05-01__10-00-00.jpg").exists().not());
+}
+
+#[test]
+fn applies_a_plan_in_which_two_files_swap_their_names() {
+    let directory = temporary_directory("plan-swap");
+    std::fs::write(directory.join("a.jpg"), "first").unwrap();
+    std::fs::write(directory.join("b.jpg"), "second").unwrap();
+    let plan_file = directory.join("plan.json");
+    RenamePlan {
+        copy: false,
+        renames: vec![
+            PlannedRename {
+                source: directory.join("a.jpg"),
+                target: directory.join("b.jpg"),
+            },
+            PlannedRename {
+                source: directory.join("b.jpg"),
+                target: directory.join("a.jpg"),
+            },
+        ],
+    }
+    .write_to_file(&plan_file)
+    .unwrap();
+
+    let exit_code = apply(apply_options(&plan_file));
+
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    assert_eq!(std::fs::read_to_string(directory.join("a.jpg")).unwrap(), "second");
+    assert_eq!(std::fs::read_to_string(directory.join("b.jpg")).unwrap(), "first");
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 3);
+}
@@ -0,0 +1,40 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{extractor_printing, options_for, temporary_directory};
+use rambo::{RenameAction, run};
+use std::os::unix::fs::PermissionsExt;
+
+const EXTENDED_ATTRIBUTE: &str = "user.rambo.test";
+
+#[test]
+fn copies_keep_the_permissions_and_the_extended_attributes() {
+    let directory = temporary_directory("preserved-metadata");
+    let file_path = directory.join("IMG_0001.jpg");
+    std::fs::write(&file_path, b"no metadata").expect("the file can be created");
+    std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o640)).expect("the permissions can be set");
+    // Not every file system of the temporary directory supports extended attributes.
+    let has_extended_attribute = xattr::set(&file_path, EXTENDED_ATTRIBUTE, b"value").is_ok();
+
+    let copy_directory = directory.join("copies");
+    let report = run(options_for(&directory)
+        .extractor_cmd(extractor_printing("2024-05-01T10:00:00+02:00"))
+        .copy_to(copy_directory.clone())
+        .build())
+    .expect("the run can be carried out");
+
+    assert_eq!(report.file_outcomes.len(), 1);
+    let file_outcome = &report.file_outcomes[0];
+    assert_eq!(file_outcome.action, RenameAction::Copied);
+    let copy_path = file_outcome.new_path.as_ref().expect("the copied file has a new path");
+    assert_eq!(*copy_path, copy_directory.join("2024-05-01__10-00-00.jpg"));
+
+    let copy_metadata = std::fs::metadata(copy_path).expect("the copy exists");
+    assert_eq!(copy_metadata.permissions().mode() & 0o777, 0o640);
+    if has_extended_attribute {
+        let value = xattr::get(copy_path, EXTENDED_ATTRIBUTE).expect("the extended attributes of the copy can be read");
+        assert_eq!(value.as_deref(), Some(b"value".as_slice()));
+    }
+    assert!(file_path.is_file());
+}
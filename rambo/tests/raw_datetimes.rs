@@ -0,0 +1,47 @@
+use chrono::{DateTime, Timelike};
+use rambo::{DatetimeSource, extract_creation_datetime};
+use std::path::Path;
+
+/// The fixtures only contain the headers and the metadata of each RAW format, without any image data.
+fn assert_creation_datetime(fixture: &str, expected_datetime: &str, expected_nanoseconds: u32, expected_tag: &'static str) {
+    let fixture_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(fixture);
+
+    let extracted_datetime = extract_creation_datetime(&fixture_path).expect("the creation datetime can be extracted");
+
+    let expected_datetime = DateTime::parse_from_rfc3339(expected_datetime).unwrap();
+    assert_eq!(extracted_datetime.datetime, expected_datetime);
+    assert_eq!(extracted_datetime.datetime.offset(), expected_datetime.offset());
+    assert_eq!(extracted_datetime.datetime.nanosecond(), expected_nanoseconds);
+    assert_eq!(extracted_datetime.source, DatetimeSource::ExifTag(expected_tag));
+}
+
+#[test]
+fn reads_the_creation_datetime_of_a_cr2_file() {
+    assert_creation_datetime("canon.cr2", "2021-03-14T15:09:26.53+01:00", 530_000_000, "DateTimeOriginal");
+}
+
+#[test]
+fn reads_the_creation_datetime_of_a_cr3_file() {
+    assert_creation_datetime("canon.cr3", "2024-06-01T12:34:56.25+02:00", 250_000_000, "DateTimeOriginal");
+}
+
+#[test]
+fn reads_the_creation_datetime_of_a_nef_file() {
+    assert_creation_datetime("nikon.nef", "2020-07-04T18:30:12.123456-05:00", 123_456_000, "DateTimeOriginal");
+}
+
+#[test]
+fn reads_the_creation_datetime_of_an_arw_file() {
+    assert_creation_datetime("sony.arw", "2019-12-31T23:59:59.999+09:00", 999_000_000, "DateTimeOriginal");
+}
+
+/// The DNG fixture has no `DateTimeOriginal`, so `CreateDate` is taken instead of the newer `ModifyDate`.
+#[test]
+fn reads_the_creation_datetime_of_a_dng_file() {
+    assert_creation_datetime("adobe.dng", "2023-05-01T10:00:00.5+05:30", 500_000_000, "CreateDate");
+}
+
+#[test]
+fn reads_the_creation_datetime_of_a_raf_file() {
+    assert_creation_datetime("fujifilm.raf", "2018-02-28T06:07:08.07+00:00", 70_000_000, "DateTimeOriginal");
+}
@@ -0,0 +1,27 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{extractor_printing, options_for, temporary_directory};
+use rambo::{InputSource, run};
+use std::ops::Not;
+
+#[test]
+fn a_hard_link_of_the_new_name_keeps_other_files_with_the_temporary_name() {
+    let directory = temporary_directory("renamed-to-itself");
+    std::fs::write(directory.join("IMG_0001.jpg"), "content").unwrap();
+    std::fs::hard_link(directory.join("IMG_0001.jpg"), directory.join("2024-05-01__10-00-00.jpg")).unwrap();
+    std::fs::write(directory.join("IMG_0001.jpg.rambo-rename"), "another file").unwrap();
+
+    // Only the file itself is renamed, so that its new name is not claimed by its other name in the run.
+    let report = run(options_for(&directory)
+        .input(InputSource::GlobPatterns(vec![directory.join("IMG_0001.jpg").display().to_string()]))
+        .extractor_cmd(extractor_printing("2024-05-01T10:00:00+02:00"))
+        .build())
+    .expect("the run can be carried out");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join("IMG_0001.jpg").exists().not());
+    assert_eq!(std::fs::read_to_string(directory.join("2024-05-01__10-00-00.jpg")).unwrap(), "content");
+    assert_eq!(std::fs::read_to_string(directory.join("IMG_0001.jpg.rambo-rename")).unwrap(), "another file");
+}
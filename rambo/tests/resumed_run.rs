@@ -0,0 +1,45 @@
+#![cfg(unix)]
+//! The state file of a run is written into the current working directory, so this test has a test binary of its own.
+
+mod common;
+
+use common::{EXTRACTOR_READING_THE_CONTENT, options_for, temporary_directory};
+use rambo::{RESUME_STATE_FILE_NAME, run};
+use std::ops::Not;
+
+#[test]
+fn a_resumed_run_skips_the_files_which_the_interrupted_run_handled() {
+    let directory = temporary_directory("resumed-run");
+    std::env::set_current_dir(&directory).expect("the temporary directory can be the current working directory");
+
+    // The interrupted run renamed the first file and was killed before the second one.
+    // The first file claims another datetime now, so it would be renamed again if it was processed.
+    std::fs::write(directory.join("2024-05-01__10-00-00.jpg"), "2024-05-01T12:00:00+02:00\nfirst").unwrap();
+    std::fs::write(directory.join("IMG_0002.jpg"), "2024-05-01T11:00:00+02:00\nsecond").unwrap();
+    std::fs::write(
+        directory.join(RESUME_STATE_FILE_NAME),
+        format!(
+            "{{\"type\":\"handled\",\"path\":\"{}\",\"new_path\":\"{}\"}}\n",
+            directory.join("IMG_0001.jpg").display(),
+            directory.join("2024-05-01__10-00-00.jpg").display()
+        ),
+    )
+    .unwrap();
+
+    let report = run(options_for(&directory)
+        .extractor_cmd(EXTRACTOR_READING_THE_CONTENT.to_string())
+        .resume(true)
+        .build())
+    .expect("the run can be carried out");
+
+    assert!(report.has_failures(false).not());
+    assert_eq!(report.statistics.resumed_files, 1);
+    assert_eq!(report.statistics.renamed_files, 1);
+    assert!(directory.join("2024-05-01__10-00-00.jpg").exists());
+    assert!(directory.join("2024-05-01__11-00-00.jpg").exists());
+    assert!(directory.join("2024-05-01__12-00-00.jpg").exists().not());
+    assert!(
+        directory.join(RESUME_STATE_FILE_NAME).exists().not(),
+        "the state file is removed once the run is completed"
+    );
+}
@@ -0,0 +1,60 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{EXTRACTOR_READING_THE_CONTENT, options_for, temporary_directory};
+use rambo::{UndoOptions, run, undo};
+use std::ops::Not;
+use std::process::ExitCode;
+
+#[test]
+fn files_which_swap_their_names_are_renamed_via_temporary_names() {
+    let directory = temporary_directory("swapped-names");
+    std::fs::write(directory.join("2024-05-01__10-00-00.jpg"), "2024-05-01T11:00:00+02:00\nfirst").unwrap();
+    std::fs::write(directory.join("2024-05-01__11-00-00.jpg"), "2024-05-01T10:00:00+02:00\nsecond").unwrap();
+
+    let report = run(options_for(&directory).extractor_cmd(EXTRACTOR_READING_THE_CONTENT.to_string()).build()).expect("the run can be carried out");
+
+    assert!(report.has_failures(false).not());
+    assert_eq!(
+        std::fs::read_to_string(directory.join("2024-05-01__11-00-00.jpg")).unwrap(),
+        "2024-05-01T11:00:00+02:00\nfirst"
+    );
+    assert_eq!(
+        std::fs::read_to_string(directory.join("2024-05-01__10-00-00.jpg")).unwrap(),
+        "2024-05-01T10:00:00+02:00\nsecond"
+    );
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 2);
+}
+
+#[test]
+fn undoing_a_swap_gives_the_files_their_names_back() {
+    let directory = temporary_directory("swapped-names-undo");
+    std::fs::write(directory.join("2024-05-01__10-00-00.jpg"), "2024-05-01T11:00:00+02:00\nfirst").unwrap();
+    std::fs::write(directory.join("2024-05-01__11-00-00.jpg"), "2024-05-01T10:00:00+02:00\nsecond").unwrap();
+    let rename_map = directory.join("renames.csv");
+
+    let report = run(options_for(&directory)
+        .extractor_cmd(EXTRACTOR_READING_THE_CONTENT.to_string())
+        .rename_map(rename_map.clone())
+        .build())
+    .expect("the run can be carried out");
+    assert!(report.has_failures(false).not());
+
+    let exit_code = undo(UndoOptions {
+        rename_map,
+        no_dry_run: true,
+        fail_on_vanished: false,
+    });
+
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    assert_eq!(
+        std::fs::read_to_string(directory.join("2024-05-01__10-00-00.jpg")).unwrap(),
+        "2024-05-01T11:00:00+02:00\nfirst"
+    );
+    assert_eq!(
+        std::fs::read_to_string(directory.join("2024-05-01__11-00-00.jpg")).unwrap(),
+        "2024-05-01T10:00:00+02:00\nsecond"
+    );
+    assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 3);
+}
@@ -0,0 +1,102 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{extractor_printing, options_for, temporary_directory};
+use rambo::{UndoOptions, run, undo};
+use std::ops::Not;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const RENAMED_FILE_NAME: &str = "2024-05-01__10-00-00.jpg";
+
+/// Renames `IMG_0001.jpg` and returns the directory and the rename map of the run.
+fn rename_with_rename_map(name: &str) -> (PathBuf, PathBuf) {
+    let directory = temporary_directory(name);
+    std::fs::write(directory.join("IMG_0001.jpg"), "content").unwrap();
+    let rename_map = directory.join("renames.csv");
+
+    let report = run(options_for(&directory)
+        .extractor_cmd(extractor_printing("2024-05-01T10:00:00+02:00"))
+        .rename_map(rename_map.clone())
+        .build())
+    .expect("the run can be carried out");
+
+    assert!(report.has_failures(false).not());
+    assert!(directory.join(RENAMED_FILE_NAME).exists());
+
+    (directory, rename_map)
+}
+
+#[test]
+fn renames_the_files_back_to_their_old_names() {
+    let (directory, rename_map) = rename_with_rename_map("undo-renames-back");
+
+    let exit_code = undo(UndoOptions {
+        rename_map,
+        no_dry_run: true,
+        fail_on_vanished: false,
+    });
+
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    assert!(directory.join("IMG_0001.jpg").exists());
+    assert!(directory.join(RENAMED_FILE_NAME).exists().not());
+}
+
+#[test]
+fn a_dry_run_renames_nothing_back() {
+    let (directory, rename_map) = rename_with_rename_map("undo-dry-run");
+
+    let exit_code = undo(UndoOptions {
+        rename_map,
+        no_dry_run: false,
+        fail_on_vanished: false,
+    });
+
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    assert!(directory.join("IMG_0001.jpg").exists().not());
+    assert!(directory.join(RENAMED_FILE_NAME).exists());
+}
+
+#[test]
+fn fails_when_the_old_name_was_taken_in_the_meantime() {
+    let (directory, rename_map) = rename_with_rename_map("undo-old-name-taken");
+    std::fs::write(directory.join("IMG_0001.jpg"), "another file").unwrap();
+
+    let exit_code = undo(UndoOptions {
+        rename_map,
+        no_dry_run: true,
+        fail_on_vanished: false,
+    });
+
+    assert_eq!(exit_code, ExitCode::FAILURE);
+    assert_eq!(std::fs::read_to_string(directory.join("IMG_0001.jpg")).unwrap(), "another file");
+    assert_eq!(std::fs::read_to_string(directory.join(RENAMED_FILE_NAME)).unwrap(), "content");
+}
+
+#[test]
+fn removes_the_copies_and_keeps_their_originals() {
+    let directory = temporary_directory("undo-copies");
+    std::fs::write(directory.join("IMG_0001.jpg"), "content").unwrap();
+    let copy_directory = directory.join("copies");
+    let rename_map = directory.join("renames.csv");
+
+    let report = run(options_for(&directory)
+        .extractor_cmd(extractor_printing("2024-05-01T10:00:00+02:00"))
+        .copy_to(copy_directory.clone())
+        .rename_map(rename_map.clone())
+        .build())
+    .expect("the run can be carried out");
+    assert!(report.has_failures(false).not());
+    assert!(copy_directory.join(RENAMED_FILE_NAME).exists());
+
+    let exit_code = undo(UndoOptions {
+        rename_map,
+        no_dry_run: true,
+        fail_on_vanished: false,
+    });
+
+    assert_eq!(exit_code, ExitCode::SUCCESS);
+    assert!(copy_directory.join(RENAMED_FILE_NAME).exists().not());
+    assert_eq!(std::fs::read_to_string(directory.join("IMG_0001.jpg")).unwrap(), "content");
+}